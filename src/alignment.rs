@@ -0,0 +1,176 @@
+use std::path::PathBuf;
+
+use eframe::egui;
+
+use crate::feature_matching::{DistanceField, ANALYSIS_SIZE};
+use crate::graticule::LineStyle;
+use crate::palettes;
+use crate::settings;
+use crate::vectors::{OverlaySettings, VectorOverlay};
+use crate::ProjectionData;
+
+/// The file names the coastline is looked for under, e.g. Natural Earth's
+/// coastline renamed or converted.
+const COASTLINE_FILES: [&str; 3] = [
+    "coastline.geojson",
+    "coastline.shp",
+    "ne_110m_coastline.shp",
+];
+/// How close (in projected pixels) a coastline point must be to an edge of
+/// the raster to count as aligned.
+const ALIGNED_DISTANCE: f64 = 2.;
+
+/// Draws a world coastline over the projected map and measures how well it
+/// lines up with the map's own coastlines, to confirm the source
+/// projection's parameters before a large export.
+#[derive(Default)]
+pub struct AlignmentCheck {
+    coastline: Option<VectorOverlay>,
+    pub result: Option<Alignment>,
+    pub error: Option<String>,
+}
+
+pub struct Alignment {
+    /// Mean distance from the coastline to the nearest raster edge, in projected pixels.
+    pub mean_distance: f64,
+    /// Share of the coastline within `ALIGNED_DISTANCE` of a raster edge.
+    pub aligned: f64,
+}
+
+/// A coastline installed in the configuration directory, or in `data` next
+/// to the executable.
+fn bundled_coastline() -> Option<PathBuf> {
+    let executable_directory = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(|directory| directory.join("data")));
+    [settings::config_directory(), executable_directory]
+        .into_iter()
+        .flatten()
+        .flat_map(|directory| COASTLINE_FILES.map(|name| directory.join(name)))
+        .find(|path| path.is_file())
+}
+
+impl AlignmentCheck {
+    pub fn coastline(&mut self) -> Option<&mut VectorOverlay> {
+        self.coastline.as_mut()
+    }
+
+    pub fn load_coastline(&mut self, path: PathBuf) {
+        let settings = OverlaySettings {
+            path,
+            style: LineStyle {
+                color: palettes::CATEGORICAL[6],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        match VectorOverlay::load(settings) {
+            Ok(coastline) => {
+                self.coastline = Some(coastline);
+                self.result = None;
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        }
+    }
+
+    fn load_bundled_coastline(&mut self) {
+        match bundled_coastline() {
+            Some(path) => self.load_coastline(path),
+            None => {
+                let directory = settings::config_directory()
+                    .map(|directory| directory.display().to_string())
+                    .unwrap_or_default();
+                self.error = Some(format!(
+                    "No coastline installed. Put one named {} into {}, \
+                     or choose a file.",
+                    COASTLINE_FILES[0], directory
+                ));
+            }
+        }
+    }
+
+    /// Compares the coastline, placed by `projection`, with the edges of
+    /// `projected`, the map reprojected into it.
+    pub fn measure(&mut self, projection: &ProjectionData, projected: &image::DynamicImage) {
+        let Some(coastline) = &self.coastline else {
+            return;
+        };
+        let dimensions = (projected.width(), projected.height());
+        let vertices = coastline.vertices(projection, dimensions);
+        if vertices.is_empty() {
+            self.error = Some("The coastline lies outside the projected map.".to_string());
+            self.result = None;
+            return;
+        }
+        let analysed = projected.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+        let field = DistanceField::from_edges(&analysed);
+        // Pixels of the projected map per cell of the distance field.
+        let scale_x = dimensions.0 as f64 / field.width() as f64;
+        let scale_y = dimensions.1 as f64 / field.height() as f64;
+        let distances: Vec<f64> = vertices
+            .iter()
+            .map(|(x, y)| field.distance_at_cell(x / scale_x, y / scale_y) * scale_x)
+            .collect();
+        let aligned = distances
+            .iter()
+            .filter(|&&distance| distance <= ALIGNED_DISTANCE.max(scale_x))
+            .count();
+        self.result = Some(Alignment {
+            mean_distance: distances.iter().sum::<f64>() / distances.len() as f64,
+            aligned: aligned as f64 / distances.len() as f64,
+        });
+        self.error = None;
+    }
+}
+
+pub fn alignment_ui(
+    ui: &mut egui::Ui,
+    check: &mut AlignmentCheck,
+    projection: &ProjectionData,
+    projected: Option<&image::DynamicImage>,
+) {
+    ui.collapsing("Alignment check", |ui| {
+        ui.label(
+            "Draws a world coastline over the projected map and scores how \
+             closely the map's own coastlines follow it.",
+        );
+        ui.horizontal(|ui| {
+            if check.coastline.is_none() && ui.button("Use installed coastline").clicked() {
+                check.load_bundled_coastline();
+            }
+            if ui.button("Choose coastline...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Vector data", &["geojson", "json", "shp"])
+                    .pick_file()
+                {
+                    check.load_coastline(path);
+                }
+            }
+        });
+        if let Some(coastline) = &mut check.coastline {
+            ui.checkbox(&mut coastline.settings.visible, "Show coastline");
+        }
+        match projected {
+            Some(projected) if check.coastline.is_some() => {
+                if ui.button("Measure alignment").clicked() {
+                    check.measure(projection, projected);
+                }
+            }
+            Some(_) => {}
+            None => {
+                ui.label("Project the map first.");
+            }
+        }
+        if let Some(result) = &check.result {
+            ui.label(format!(
+                "Mean coastline distance: {:.1} px, {:.0}% aligned",
+                result.mean_distance,
+                result.aligned * 100.
+            ));
+        }
+        if let Some(error) = &check.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    });
+}
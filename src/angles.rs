@@ -0,0 +1,139 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::expressions;
+
+/// How angles are shown and entered. They are always stored in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AngleUnit {
+    #[default]
+    Degrees,
+    /// Degrees, minutes and seconds.
+    Dms,
+    Radians,
+    Grads,
+}
+
+impl AngleUnit {
+    pub const ALL: [AngleUnit; 4] = [
+        AngleUnit::Degrees,
+        AngleUnit::Dms,
+        AngleUnit::Radians,
+        AngleUnit::Grads,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AngleUnit::Degrees => "Decimal degrees",
+            AngleUnit::Dms => "Degrees, minutes, seconds",
+            AngleUnit::Radians => "Radians",
+            AngleUnit::Grads => "Grads",
+        }
+    }
+
+    /// Formats an angle given in degrees, about as precisely as four decimal places of a degree.
+    pub fn format(&self, degrees: f64) -> String {
+        match self {
+            AngleUnit::Degrees => format!("{:.4}°", degrees),
+            AngleUnit::Dms => {
+                let sign = if degrees < 0. { "-" } else { "" };
+                // Round first, so that 59.96″ doesn't show up as 60.0″.
+                let tenths = (degrees.abs() * 36000.).round() as u64;
+                format!(
+                    "{}{}°{:02}′{:04.1}″",
+                    sign,
+                    tenths / 36000,
+                    tenths / 600 % 60,
+                    (tenths % 600) as f64 / 10.
+                )
+            }
+            AngleUnit::Radians => format!("{:.6} rad", degrees.to_radians()),
+            AngleUnit::Grads => format!("{:.4} gon", degrees / 0.9),
+        }
+    }
+
+    /// Reads an angle in this unit back into degrees. Units written out with
+    /// the number are understood, whatever the setting, and so are
+    /// expressions such as `-(75+30/60)`.
+    pub fn parse(&self, text: &str) -> Option<f64> {
+        let text = text.trim();
+        if let Some(radians) = text.strip_suffix("rad") {
+            return expressions::evaluate(radians).map(f64::to_degrees);
+        }
+        if let Some(grads) = text.strip_suffix("gon") {
+            return expressions::evaluate(grads).map(|grads| grads * 0.9);
+        }
+        match self {
+            AngleUnit::Radians => expressions::evaluate(text).map(f64::to_degrees),
+            AngleUnit::Grads => expressions::evaluate(text).map(|grads| grads * 0.9),
+            AngleUnit::Degrees | AngleUnit::Dms => {
+                parse_dms(text).or_else(|| expressions::evaluate(text))
+            }
+        }
+    }
+}
+
+/// Reads e.g. `-12°34′56.7″`, `12 34 56.7 W`, `12:34.5` or `12.5°`.
+fn parse_dms(text: &str) -> Option<f64> {
+    let (text, hemisphere_sign) = match text.chars().last()? {
+        'N' | 'n' | 'E' | 'e' => (&text[..text.len() - 1], 1.),
+        'S' | 's' | 'W' | 'w' => (&text[..text.len() - 1], -1.),
+        _ => (text, 1.),
+    };
+    let text = text.trim();
+    let (text, sign) = match text.strip_prefix('-').or_else(|| text.strip_prefix('−')) {
+        Some(rest) => (rest, -1.),
+        None => (text, 1.),
+    };
+    let parts: Vec<f64> = text
+        .split(|c: char| "°′″'\": ".contains(c))
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let degrees: f64 = parts
+        .iter()
+        .zip([1., 60., 3600.])
+        .map(|(part, divisor)| part / divisor)
+        .sum();
+    Some(sign * hemisphere_sign * degrees)
+}
+
+/// Makes a slider or drag value show and take angles in a unit, in place of a `°` suffix.
+pub trait AngleWidget: Sized {
+    fn angle(self, unit: AngleUnit) -> Self;
+}
+
+impl<'a> AngleWidget for egui::Slider<'a> {
+    fn angle(self, unit: AngleUnit) -> Self {
+        match unit {
+            AngleUnit::Degrees => self.suffix("°").custom_parser(move |text| unit.parse(text)),
+            _ => self
+                .custom_formatter(move |degrees, _| unit.format(degrees))
+                .custom_parser(move |text| unit.parse(text)),
+        }
+    }
+}
+
+impl<'a> AngleWidget for egui::DragValue<'a> {
+    fn angle(self, unit: AngleUnit) -> Self {
+        match unit {
+            AngleUnit::Degrees => self.suffix("°").custom_parser(move |text| unit.parse(text)),
+            _ => self
+                .custom_formatter(move |degrees, _| unit.format(degrees))
+                .custom_parser(move |text| unit.parse(text)),
+        }
+    }
+}
+
+pub fn angle_unit_ui(ui: &mut egui::Ui, unit: &mut AngleUnit) {
+    egui::ComboBox::new("Angle unit", "Angles")
+        .selected_text(unit.name())
+        .show_ui(ui, |ui| {
+            for option in AngleUnit::ALL {
+                ui.selectable_value(unit, option, option.name());
+            }
+        });
+}
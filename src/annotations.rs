@@ -0,0 +1,73 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::labels::{Labels, Priority};
+
+pub const DEFAULT_COLOR: [u8; 3] = [255, 160, 0];
+
+/// A note pinned to a spot on the source image.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub pixel: (f64, f64),
+    pub text: String,
+    #[serde(default = "default_color")]
+    pub color: [u8; 3],
+}
+
+fn default_color() -> [u8; 3] {
+    DEFAULT_COLOR
+}
+
+pub fn annotations_ui(ui: &mut egui::Ui, annotations: &mut Vec<Annotation>) {
+    ui.collapsing("Annotations", |ui| {
+        if annotations.is_empty() {
+            ui.label("Place notes with the annotate tool.");
+        }
+        let mut removed = None;
+        for (index, annotation) in annotations.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("{}:", index + 1));
+                ui.color_edit_button_srgb(&mut annotation.color);
+                ui.text_edit_singleline(&mut annotation.text);
+                if ui.small_button("✖").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            annotations.remove(index);
+        }
+    });
+}
+
+pub fn paint_annotations(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    annotations: &[Annotation],
+    labels: &mut Labels,
+) {
+    for (index, annotation) in annotations.iter().enumerate() {
+        let [r, g, b] = annotation.color;
+        let color = egui::Color32::from_rgb(r, g, b);
+        let position = rect.min
+            + egui::vec2(
+                annotation.pixel.0 as f32 / image_dimensions.0 as f32 * rect.width(),
+                annotation.pixel.1 as f32 / image_dimensions.1 as f32 * rect.height(),
+            );
+        painter.circle_filled(position, 4., color);
+        labels.avoid(egui::Rect::from_center_size(position, egui::vec2(8., 8.)));
+        let text = match annotation.text.as_str() {
+            "" => (index + 1).to_string(),
+            text => text.to_string(),
+        };
+        labels.add(
+            position,
+            (egui::Align2::LEFT_CENTER, egui::vec2(6., 0.)),
+            text,
+            egui::FontId::default(),
+            color,
+            Priority::Annotation,
+        );
+    }
+}
@@ -0,0 +1,76 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Accessibility options for the look of the interface, beyond the scaling
+/// the operating system applies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Appearance {
+    /// White on black, with thick outlines and a bright focus color.
+    pub high_contrast: bool,
+    /// Larger buttons, sliders, checkboxes and resize handles.
+    pub large_targets: bool,
+}
+
+impl Appearance {
+    pub fn style(&self) -> egui::Style {
+        let mut style = egui::Style::default();
+        if self.high_contrast {
+            style.visuals = high_contrast_visuals();
+        }
+        if self.large_targets {
+            let spacing = &mut style.spacing;
+            spacing.item_spacing = egui::vec2(10., 6.);
+            spacing.button_padding = egui::vec2(8., 5.);
+            spacing.interact_size = egui::vec2(56., 30.);
+            spacing.slider_width = 200.;
+            spacing.combo_width = 160.;
+            spacing.icon_width = 22.;
+            spacing.icon_width_inner = 14.;
+            spacing.scroll_bar_width = 14.;
+            style.interaction.resize_grab_radius_side = 10.;
+            style.interaction.resize_grab_radius_corner = 16.;
+        }
+        style
+    }
+}
+
+fn high_contrast_visuals() -> egui::Visuals {
+    use egui::{Color32, Stroke};
+    let focus = Color32::from_rgb(255, 210, 0);
+    let mut visuals = egui::Visuals::dark();
+    visuals.panel_fill = Color32::BLACK;
+    visuals.window_fill = Color32::BLACK;
+    visuals.window_stroke = Stroke::new(2., Color32::WHITE);
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(24);
+    visuals.code_bg_color = Color32::from_gray(24);
+    visuals.hyperlink_color = Color32::from_rgb(120, 220, 255);
+    visuals.warn_fg_color = focus;
+    visuals.error_fg_color = Color32::from_rgb(255, 110, 110);
+    visuals.selection.bg_fill = focus;
+    visuals.selection.stroke = Stroke::new(2., Color32::BLACK);
+    let widgets = &mut visuals.widgets;
+    widgets.noninteractive.bg_fill = Color32::BLACK;
+    widgets.noninteractive.bg_stroke = Stroke::new(1., Color32::WHITE);
+    widgets.noninteractive.fg_stroke = Stroke::new(1., Color32::WHITE);
+    for state in [
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+    ] {
+        state.bg_fill = Color32::from_gray(32);
+        state.weak_bg_fill = Color32::from_gray(32);
+        state.fg_stroke = Stroke::new(1.5, Color32::WHITE);
+    }
+    widgets.inactive.bg_stroke = Stroke::new(1.5, Color32::WHITE);
+    widgets.hovered.bg_stroke = Stroke::new(2.5, focus);
+    widgets.active.bg_stroke = Stroke::new(3., focus);
+    widgets.open.bg_stroke = Stroke::new(2.5, focus);
+    visuals
+}
+
+pub fn appearance_ui(ui: &mut egui::Ui, appearance: &mut Appearance) {
+    ui.checkbox(&mut appearance.high_contrast, "High contrast");
+    ui.checkbox(&mut appearance.large_targets, "Larger buttons and sliders");
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use eframe::egui;
+use egui::epaint::text::{FontDefinitions, Fonts};
+use serde::{Deserialize, Serialize};
+
+use crate::remote::{self, RangeReader};
+
+/// The credit the source's license asks for, and whether exports carry it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attribution {
+    pub text: String,
+    /// Draw the text into the corner of exported maps.
+    pub stamp: bool,
+}
+
+impl Attribution {
+    /// The text to stamp onto exports, if any.
+    pub fn stamped(&self) -> Option<&str> {
+        match self.stamp && !self.text.trim().is_empty() {
+            true => Some(self.text.trim()),
+            false => None,
+        }
+    }
+}
+
+/// The copyright a TIFF states, whether local or remote. Remote headers come
+/// from the download cache once the image has been read.
+pub fn from_file(path: &Path) -> Option<String> {
+    let text = match remote::url(path) {
+        Some(url) => copyright(RangeReader::open(url).ok()?),
+        None => copyright(std::io::BufReader::new(std::fs::File::open(path).ok()?)),
+    }?;
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    match text.is_empty() {
+        true => None,
+        false => Some(text.to_string()),
+    }
+}
+
+fn copyright(reader: impl std::io::Read + std::io::Seek) -> Option<String> {
+    tiff::decoder::Decoder::new(reader)
+        .ok()?
+        .get_tag_ascii_string(tiff::tags::Tag::Copyright)
+        .ok()
+}
+
+/// Draws `text` in the bottom right corner of `image`, dark on a light box,
+/// at a size that grows with the image.
+pub fn stamp(mut image: image::DynamicImage, text: &str) -> image::DynamicImage {
+    use image::{GenericImage, GenericImageView};
+    let size = (image.height() as f32 / 50.).clamp(10., 48.);
+    // At one pixel per point, the glyphs in the font atlas are the size they are drawn.
+    let fonts = Fonts::new(1., 8192, FontDefinitions::default());
+    let galley = fonts.layout_no_wrap(
+        text.to_string(),
+        egui::FontId::proportional(size),
+        egui::Color32::BLACK,
+    );
+    let Some(egui::ImageData::Font(atlas)) = fonts.font_image_delta().map(|delta| delta.image)
+    else {
+        return image;
+    };
+    let margin = (size / 3.).round() as i64;
+    let box_width = galley.size().x.ceil() as i64 + 2 * margin;
+    let box_height = galley.size().y.ceil() as i64 + 2 * margin;
+    let (left, top) = (
+        image.width() as i64 - box_width,
+        image.height() as i64 - box_height,
+    );
+    let mut blend = |x: i64, y: i64, value: f32, amount: f32| {
+        if x < 0 || y < 0 || x >= image.width() as i64 || y >= image.height() as i64 {
+            return;
+        }
+        let (x, y) = (x as u32, y as u32);
+        let mut pixel = image.get_pixel(x, y);
+        // Drawn over what is there, so transparent maps get a light box too.
+        let below = pixel.0[3] as f32 / 255. * (1. - amount);
+        let alpha = below + amount;
+        for channel in &mut pixel.0[..3] {
+            *channel = ((*channel as f32 * below + value * amount) / alpha).round() as u8;
+        }
+        pixel.0[3] = (alpha * 255.).round() as u8;
+        image.put_pixel(x, y, pixel);
+    };
+    for y in top..top + box_height {
+        for x in left..left + box_width {
+            blend(x, y, 255., 0.75);
+        }
+    }
+    for glyph in galley.rows.iter().flat_map(|row| &row.glyphs) {
+        let uv = glyph.uv_rect;
+        let x0 = left + margin + (glyph.pos.x + uv.offset.x).round() as i64;
+        let y0 = top + margin + (glyph.pos.y + uv.offset.y).round() as i64;
+        for v in uv.min[1]..uv.max[1] {
+            for u in uv.min[0]..uv.max[0] {
+                let coverage = atlas.pixels[v as usize * atlas.size[0] + u as usize];
+                blend(
+                    x0 + (u - uv.min[0]) as i64,
+                    y0 + (v - uv.min[1]) as i64,
+                    0.,
+                    coverage,
+                );
+            }
+        }
+    }
+    image
+}
+
+pub fn attribution_ui(ui: &mut egui::Ui, attribution: &mut Attribution) {
+    ui.collapsing("Attribution", |ui| {
+        ui.add(
+            egui::TextEdit::singleline(&mut attribution.text)
+                .hint_text("© OpenStreetMap contributors"),
+        );
+        ui.add_enabled(
+            !attribution.text.trim().is_empty(),
+            egui::Checkbox::new(&mut attribution.stamp, "Stamp onto exports"),
+        );
+    });
+}
@@ -0,0 +1,914 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use image::GenericImageView;
+use serde::{Deserialize, Serialize};
+
+use crate::codecs::Codec;
+use crate::collisions::{self, Collision};
+use crate::jobs::{Job, Progress};
+use crate::notifications::Log;
+use crate::sidecar::Sidecar;
+use crate::{
+    attribution, color_keys, color_types, engine, feature_matching, fits, gridded, layered,
+    loading, remote, ProjectionData, ProjectionKind,
+};
+
+/// Written into new manifests. Manifests from later versions are refused
+/// rather than half understood.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// A projection as manifests and commands name it: its `ProjectionKind::id`
+/// and its parameters in degrees, by `feature_matching::parameter_names`.
+/// Parameters left out keep their defaults.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProjectionSpec {
+    pub kind: String,
+    #[serde(default)]
+    pub parameters: BTreeMap<String, f64>,
+}
+
+impl ProjectionSpec {
+    pub fn of(projection: &ProjectionData) -> Self {
+        let names = feature_matching::parameter_names(projection).unwrap_or_default();
+        let values = feature_matching::parameters(projection).unwrap_or_default();
+        ProjectionSpec {
+            kind: projection.kind().id().to_string(),
+            parameters: names
+                .iter()
+                .map(|name| name.to_string())
+                .zip(values)
+                .collect(),
+        }
+    }
+
+    /// The projection itself. A rubber sheet still has to be fitted.
+    pub fn build(&self) -> Result<ProjectionData, String> {
+        let kind = ProjectionKind::all()
+            .find(|candidate| candidate.id() == self.kind)
+            .ok_or(format!("Unknown projection: {}", self.kind))?;
+        let projection = kind.default_projection_data();
+        let names = feature_matching::parameter_names(&projection).unwrap_or_default();
+        let mut values = feature_matching::parameters(&projection).unwrap_or_default();
+        for (name, value) in &self.parameters {
+            let Some(index) = names.iter().position(|known| known == name) else {
+                return Err(match names.is_empty() {
+                    true => format!("{} takes no parameters", kind.id()),
+                    false => format!(
+                        "{} has no parameter {}, only {}",
+                        kind.id(),
+                        name,
+                        names.join(", ")
+                    ),
+                });
+            };
+            values[index] = *value;
+        }
+        Ok(feature_matching::with_parameters(&projection, &values))
+    }
+}
+
+impl Default for ProjectionSpec {
+    fn default() -> Self {
+        ProjectionSpec::of(&ProjectionKind::Equirectangular.default_projection_data())
+    }
+}
+
+/// One map to project.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Conversion {
+    pub input: PathBuf,
+    /// Its extension decides the format.
+    pub output: PathBuf,
+    pub source: ProjectionSpec,
+    pub target: ProjectionSpec,
+    /// Whether the corrections, mask and control points saved next to the
+    /// input are applied.
+    pub use_sidecar: bool,
+    /// Replaces the sidecar's export background.
+    pub background: Option<[u8; 3]>,
+    /// Read instead of the sidecar next to the input, as export bundles do.
+    pub sidecar: Option<PathBuf>,
+    /// Also save which pixels show something of the input, as `engine::mask_path` names it.
+    pub validity_mask: bool,
+}
+
+impl Default for Conversion {
+    fn default() -> Self {
+        Conversion {
+            input: PathBuf::new(),
+            output: PathBuf::new(),
+            source: Default::default(),
+            target: Default::default(),
+            use_sidecar: true,
+            background: None,
+            sidecar: None,
+            validity_mask: false,
+        }
+    }
+}
+
+impl Conversion {
+    /// Projects `input` into `map-projected.png` next to it.
+    pub fn for_input(input: PathBuf, source: &ProjectionData, target: &ProjectionData) -> Self {
+        let mut file_name = input.file_stem().unwrap_or_default().to_os_string();
+        file_name.push("-projected.png");
+        Conversion {
+            output: input.with_file_name(file_name),
+            input,
+            source: ProjectionSpec::of(source),
+            target: ProjectionSpec::of(target),
+            ..Default::default()
+        }
+    }
+
+    fn sidecar(&self) -> Result<Sidecar, String> {
+        match (self.use_sidecar, &self.sidecar) {
+            (false, _) => Ok(Sidecar::default()),
+            (true, Some(path)) => Sidecar::read(path),
+            (true, None) => Sidecar::load(&self.input).map(Option::unwrap_or_default),
+        }
+    }
+}
+
+/// A list of conversions, stored as JSON so that it can be kept under version
+/// control next to the maps. Relative paths are relative to the manifest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    pub version: u32,
+    pub conversions: Vec<Conversion>,
+    /// What to do with outputs that already exist.
+    pub on_existing: Collision,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            conversions: Vec::new(),
+            on_existing: Default::default(),
+        }
+    }
+}
+
+impl Manifest {
+    /// Reads a manifest, with its paths resolved.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let mut manifest: Manifest = serde_json::from_str(&contents)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(format!(
+                "{} is a version {} manifest, but only version {} is understood.",
+                path.display(),
+                manifest.version,
+                MANIFEST_VERSION
+            ));
+        }
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for conversion in &mut manifest.conversions {
+            conversion.input = directory.join(&conversion.input);
+            conversion.output = directory.join(&conversion.output);
+            if let Some(sidecar) = &mut conversion.sidecar {
+                *sidecar = directory.join(&*sidecar);
+            }
+        }
+        Ok(manifest)
+    }
+
+    /// Writes the manifest, with paths below its directory made relative so
+    /// that it still works when the directory is moved or checked out elsewhere.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let directory = path.parent().unwrap_or(Path::new(""));
+        let relative = |file: &PathBuf| file.strip_prefix(directory).unwrap_or(file).to_path_buf();
+        let mut manifest = self.clone();
+        manifest.version = MANIFEST_VERSION;
+        for conversion in &mut manifest.conversions {
+            conversion.input = relative(&conversion.input);
+            conversion.output = relative(&conversion.output);
+            conversion.sidecar = conversion.sidecar.as_ref().map(relative);
+        }
+        let contents = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+        std::fs::write(path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+/// What went wrong, which decides the exit code on the command line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The command line itself makes no sense.
+    Usage,
+    Manifest,
+    /// An input or its sidecar can't be read.
+    Input,
+    /// A projection doesn't exist, has wrong parameters or can't be fitted.
+    Projection,
+    Output,
+}
+
+impl FailureKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureKind::Usage => 2,
+            FailureKind::Manifest => 3,
+            FailureKind::Input => 4,
+            FailureKind::Projection => 5,
+            FailureKind::Output => 6,
+        }
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            FailureKind::Usage => "usage",
+            FailureKind::Manifest => "manifest",
+            FailureKind::Input => "input",
+            FailureKind::Projection => "projection",
+            FailureKind::Output => "output",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Failure {
+    pub kind: FailureKind,
+    pub message: String,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt.write_str(&self.message)
+    }
+}
+
+/// What a conversion did.
+#[derive(Clone, Debug)]
+pub enum Converted {
+    /// Saved a map of this size, to the output or, if that existed, next to it.
+    Written(PathBuf, (u32, u32)),
+    /// Left the existing output alone.
+    Skipped,
+}
+
+/// Turns an error message into a failure of `kind`, for `map_err`.
+fn failure(kind: FailureKind) -> impl Fn(String) -> Failure {
+    move |message| Failure { kind, message }
+}
+
+/// Carries out one conversion, doing what `on_existing` says if the output
+/// exists. Only the manifest and the input's sidecar go into the result, not
+/// how the app happens to be set up, so that every run gives the same maps.
+pub fn convert(
+    conversion: &Conversion,
+    on_existing: Collision,
+    progress: &Progress,
+) -> Result<Converted, Failure> {
+    use FailureKind::*;
+    let Some(output) = on_existing.resolve(&conversion.output) else {
+        return Ok(Converted::Skipped);
+    };
+    let input = &conversion.input;
+    let sidecar = conversion.sidecar().map_err(failure(Input))?;
+    let mut source = conversion.source.build().map_err(failure(Projection))?;
+    // Astronomical images say how they are projected.
+    if let Some(projection) = fits::sky(input).and_then(|sky| sky.projection) {
+        source = projection;
+    }
+    // Gridded data is drawn on an equirectangular map, whatever the manifest says.
+    let image = match gridded::Format::for_path(input) {
+        Some(_) => {
+            source = ProjectionKind::Equirectangular.default_projection_data();
+            loading::read_field(input)
+                .map_err(failure(Input))?
+                .render(&sidecar.data_coloring)
+        }
+        None => loading::decode(input, progress).map_err(failure(Input))?,
+    };
+    let image = match sidecar.dual_fisheye {
+        // Stitched panoramas are equirectangular, whatever the manifest says.
+        Some(fisheye) => {
+            source = ProjectionKind::Equirectangular.default_projection_data();
+            fisheye.stitch(&image)
+        }
+        None => image,
+    };
+    let dimensions = image.dimensions();
+    let prepared = engine::Corrections::from_sidecar(&sidecar).apply(&image, dimensions);
+    let control_points = &sidecar.control_points;
+    let source = engine::fit(source, control_points, dimensions).map_err(failure(Projection))?;
+    let target = conversion
+        .target
+        .build()
+        .and_then(|target| engine::fit(target, control_points, dimensions))
+        .map_err(failure(Projection))?;
+    let projected = engine::reproject(prepared, &source, &target, &sidecar.sampling)
+        .map_err(failure(Projection))?;
+    let projected_dimensions = projected.dimensions();
+    let background = conversion.background.or(sidecar.color_keys.background);
+    let output = &output;
+    if let Some(directory) = output
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        std::fs::create_dir_all(directory)
+            .map_err(|e| format!("Could not create {}: {}", directory.display(), e))
+            .map_err(failure(Output))?;
+    }
+    if conversion.validity_mask {
+        engine::save_validity_mask(&projected, output).map_err(failure(Output))?;
+    }
+    let projected = match sidecar.attribution.stamped() {
+        Some(text) => attribution::stamp(projected, text),
+        None => projected,
+    };
+    color_keys::for_export(projected, background, output)
+        .save(output)
+        .map_err(|e| format!("Could not write {}: {}", output.display(), e))
+        .map_err(failure(Output))?;
+    Ok(Converted::Written(output.clone(), projected_dimensions))
+}
+
+/// A rough rate at which maps are decoded and projected on a typical
+/// desktop, in source plus projected pixels per second. Only for estimates.
+const PIXELS_PER_SECOND: f64 = 20e6;
+
+/// What a conversion would do, found out without doing it.
+#[derive(Clone, Debug)]
+pub struct Plan {
+    pub input_dimensions: (u32, u32),
+    /// Assumes the projected map is as wide as the source.
+    pub output_dimensions: (u32, u32),
+    /// Held in memory at once while projecting, uncompressed.
+    pub memory_bytes: u64,
+    pub seconds: f64,
+    /// Things that won't stop the conversion but may not be intended.
+    pub warnings: Vec<String>,
+}
+
+impl std::fmt::Display for Plan {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{} × {} px to {} × {} px, about {:.0} MB and {:.1} s",
+            self.input_dimensions.0,
+            self.input_dimensions.1,
+            self.output_dimensions.0,
+            self.output_dimensions.1,
+            self.memory_bytes as f64 / 1e6,
+            self.seconds
+        )
+    }
+}
+
+/// Checks that a conversion can be carried out: the input can be read, the
+/// projections exist and fit, and the output can be written. Only the image
+/// header is read.
+pub fn plan(conversion: &Conversion, on_existing: Collision) -> Result<Plan, Failure> {
+    use FailureKind::*;
+    let (input, output) = (&conversion.input, &conversion.output);
+    let read_error = |e: &dyn std::fmt::Display| Failure {
+        kind: Input,
+        message: format!("Could not read {}: {}", input.display(), e),
+    };
+    let formats = (
+        layered::Format::for_path(input),
+        Codec::for_path(input),
+        gridded::Format::for_path(input),
+    );
+    // Remote files are read as cloud-optimized GeoTIFFs, whatever they are called.
+    let remote = remote::url(input).map(|url| remote::Request {
+        url: url.to_string(),
+        ..Default::default()
+    });
+    let mut input_dimensions = match (&remote, formats) {
+        (Some(request), _) => remote::dimensions(request).map_err(failure(Input))?,
+        (None, (Some(_), _, _)) => layered::dimensions(input).map_err(failure(Input))?,
+        (None, (None, Some(codec), _)) => codec.dimensions(input).map_err(failure(Input))?,
+        (None, (None, None, Some(_))) => loading::read_field(input)
+            .map_err(failure(Input))?
+            .canvas_dimensions(),
+        (None, (None, None, None)) if fits::is_fits(input) => {
+            fits::dimensions(input).map_err(failure(Input))?
+        }
+        (None, (None, None, None)) => {
+            let reader = image::io::Reader::open(input)
+                .map_err(|e| read_error(&e))?
+                .with_guessed_format()
+                .map_err(|e| read_error(&e))?;
+            if reader.format().is_none() {
+                return Err(read_error(&"not an image format that can be read"));
+            }
+            match reader.into_dimensions() {
+                Err(e @ image::ImageError::Unsupported(_)) => {
+                    color_types::tiff_dimensions(input).ok_or_else(|| read_error(&e))?
+                }
+                result => result.map_err(|e| read_error(&e))?,
+            }
+        }
+    };
+    let sidecar = conversion.sidecar().map_err(failure(Input))?;
+    let mut source = conversion.source.build().map_err(failure(Projection))?;
+    // Astronomical images say how they are projected.
+    if let Some(projection) = fits::sky(input).and_then(|sky| sky.projection) {
+        source = projection;
+    }
+    if let Some(fisheye) = sidecar.dual_fisheye {
+        source = ProjectionKind::Equirectangular.default_projection_data();
+        input_dimensions = fisheye.panorama_dimensions(input_dimensions);
+    }
+    if formats.2.is_some() {
+        source = ProjectionKind::Equirectangular.default_projection_data();
+    }
+    let control_points = &sidecar.control_points;
+    engine::fit(source, control_points, input_dimensions).map_err(failure(Projection))?;
+    let target = conversion
+        .target
+        .build()
+        .and_then(|target| engine::fit(target, control_points, input_dimensions))
+        .map_err(failure(Projection))?;
+
+    let output_error = failure(Output);
+    let format = image::ImageFormat::from_path(output)
+        .map_err(|_| output_error(format!("{} has no known image extension", output.display())))?;
+    if !format.can_write() {
+        return Err(output_error(format!("{:?} files can't be written", format)));
+    }
+    if remote.is_none() && output == input {
+        return Err(output_error(format!(
+            "{} would overwrite the input",
+            output.display()
+        )));
+    }
+    let mut warnings: Vec<String> = color_types::inspect(input).into_iter().collect();
+    if output.exists() {
+        warnings.push(match on_existing {
+            Collision::Increment => format!(
+                "{} exists, so {} is written instead",
+                output.display(),
+                collisions::numbered(output).display()
+            ),
+            Collision::Overwrite => format!("{} will be overwritten", output.display()),
+            Collision::Skip => format!("{} exists and is skipped", output.display()),
+        });
+    }
+    // The directory is created if needed, so whatever part of it exists has to be writable.
+    let directory = output
+        .ancestors()
+        .skip(1)
+        .map(|directory| match directory.as_os_str().is_empty() {
+            true => Path::new("."),
+            false => directory,
+        })
+        .find(|directory| directory.exists());
+    if let Some(directory) = directory {
+        if !directory.is_dir() {
+            return Err(output_error(format!(
+                "{} is not a directory",
+                directory.display()
+            )));
+        }
+        let metadata = std::fs::metadata(directory).map_err(|e| output_error(e.to_string()))?;
+        if metadata.permissions().readonly() {
+            return Err(output_error(format!(
+                "{} is read-only",
+                directory.display()
+            )));
+        }
+    }
+
+    let output_dimensions = sidecar.sampling.dimensions(input_dimensions, &target);
+    let pixels = |(width, height): (u32, u32)| width as u64 * height as u64;
+    Ok(Plan {
+        input_dimensions,
+        output_dimensions,
+        // The decoded source, its prepared copy and the projected map, as RGBA.
+        memory_bytes: 4 * (2 * pixels(input_dimensions) + pixels(output_dimensions)),
+        seconds: (pixels(input_dimensions) + pixels(output_dimensions)) as f64 / PIXELS_PER_SECOND,
+        warnings,
+    })
+}
+
+/// Plans every conversion of a manifest, also noting conversions that write
+/// to the same file.
+pub fn plan_all(manifest: &Manifest) -> Vec<Result<Plan, Failure>> {
+    let mut plans: Vec<_> = manifest
+        .conversions
+        .iter()
+        .map(|conversion| plan(conversion, manifest.on_existing))
+        .collect();
+    for (index, conversion) in manifest.conversions.iter().enumerate() {
+        let first = manifest
+            .conversions
+            .iter()
+            .position(|other| other.output == conversion.output)
+            .unwrap_or(index);
+        if let (true, Ok(plan)) = (first < index, &mut plans[index]) {
+            let what = match manifest.on_existing {
+                Collision::Increment => "so this one adds a number",
+                Collision::Overwrite => "which this one replaces",
+                Collision::Skip => "so this one is skipped",
+            };
+            plan.warnings.push(format!(
+                "Conversion {} writes to the same file, {}",
+                first + 1,
+                what
+            ));
+        }
+    }
+    plans
+}
+
+/// Where the command line reports to: lines for people, or with `json` one
+/// JSON object per line on stdout for scripts. Each object has an `event`:
+/// `start`, then `planned`, `converted`, `skipped` or `failed` per conversion, and
+/// `finished` with the exit code.
+struct Report {
+    json: bool,
+}
+
+impl Report {
+    fn event(&self, event: serde_json::Value, text: impl FnOnce() -> String) {
+        match self.json {
+            true => println!("{}", event),
+            false => println!("{}", text()),
+        }
+    }
+
+    fn failed(&self, index: Option<usize>, conversion: Option<&Conversion>, failure: &Failure) {
+        match self.json {
+            true => println!(
+                "{}",
+                serde_json::json!({
+                    "event": "failed",
+                    "index": index,
+                    "input": conversion.map(|conversion| conversion.input.display().to_string()),
+                    "output": conversion.map(|conversion| conversion.output.display().to_string()),
+                    "class": failure.kind.id(),
+                    "message": failure.message,
+                })
+            ),
+            false => eprintln!("{}", failure),
+        }
+    }
+}
+
+/// Reports a failure that stops the command line before any conversion,
+/// and returns its exit code.
+pub fn fail_headless(failure: Failure, json: bool) -> i32 {
+    Report { json }.failed(None, None, &failure);
+    failure.kind.exit_code()
+}
+
+/// Runs a manifest from the command line, or with `dry_run` only checks it.
+/// Returns the exit code: 0 if every conversion worked, otherwise that of the
+/// first failure's kind.
+pub fn run_headless(path: &Path, dry_run: bool, json: bool) -> i32 {
+    let report = Report { json };
+    let manifest = match Manifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(e) => return fail_headless(failure(FailureKind::Manifest)(e), json),
+    };
+    let count = manifest.conversions.len();
+    report.event(
+        serde_json::json!({"event": "start", "conversions": count, "dry_run": dry_run}),
+        || format!("{} conversions in {}", count, path.display()),
+    );
+    let prefix = |index: usize| {
+        let conversion = &manifest.conversions[index];
+        format!(
+            "[{}/{}] {} -> {}",
+            index + 1,
+            count,
+            conversion.input.display(),
+            conversion.output.display()
+        )
+    };
+    let mut plans = match dry_run {
+        true => plan_all(&manifest).into_iter().map(Some).collect(),
+        false => vec![None; count],
+    };
+    let (mut first_failure, mut failures) = (None, 0);
+    let (mut memory, mut seconds) = (0, 0.);
+    for (index, conversion) in manifest.conversions.iter().enumerate() {
+        let (input, output) = (
+            conversion.input.display().to_string(),
+            conversion.output.display().to_string(),
+        );
+        let result = match plans[index].take() {
+            Some(plan) => plan.map(|plan| {
+                memory = plan.memory_bytes.max(memory);
+                seconds += plan.seconds;
+                let warnings: String = plan
+                    .warnings
+                    .iter()
+                    .map(|warning| format!("\n    warning: {}", warning))
+                    .collect();
+                let text = format!("{}: {}{}", prefix(index), plan, warnings);
+                let event = serde_json::json!({
+                    "event": "planned",
+                    "index": index,
+                    "input": input,
+                    "output": output,
+                    "input_size": [plan.input_dimensions.0, plan.input_dimensions.1],
+                    "output_size": [plan.output_dimensions.0, plan.output_dimensions.1],
+                    "memory_bytes": plan.memory_bytes,
+                    "seconds": plan.seconds,
+                    "warnings": plan.warnings,
+                });
+                (event, text)
+            }),
+            None => {
+                let started = std::time::Instant::now();
+                let converted = convert(conversion, manifest.on_existing, &Progress::default());
+                converted.map(|converted| match converted {
+                    Converted::Written(written, (width, height)) => {
+                        let event = serde_json::json!({
+                            "event": "converted",
+                            "index": index,
+                            "input": input,
+                            "output": written.display().to_string(),
+                            "size": [width, height],
+                            "seconds": started.elapsed().as_secs_f64(),
+                        });
+                        let renamed = match written == conversion.output {
+                            true => String::new(),
+                            false => format!(" in {}", written.display()),
+                        };
+                        (
+                            event,
+                            format!("{}: {} × {} px{}", prefix(index), width, height, renamed),
+                        )
+                    }
+                    Converted::Skipped => {
+                        let event = serde_json::json!({
+                            "event": "skipped",
+                            "index": index,
+                            "input": input,
+                            "output": output,
+                        });
+                        (event, format!("{}: skipped, as it exists", prefix(index)))
+                    }
+                })
+            }
+        };
+        match result {
+            Ok((event, text)) => report.event(event, || text),
+            Err(e) => {
+                failures += 1;
+                first_failure.get_or_insert(e.kind);
+                match json {
+                    true => report.failed(Some(index), Some(conversion), &e),
+                    false => eprintln!("{}: {}", prefix(index), e),
+                }
+            }
+        }
+    }
+    let exit_code = first_failure.map_or(0, |kind| kind.exit_code());
+    report.event(
+        serde_json::json!({
+            "event": "finished",
+            "succeeded": count - failures,
+            "failed": failures,
+            "exit_code": exit_code,
+        }),
+        || match dry_run {
+            true => format!(
+                "{} of {} conversions can run, needing about {:.0} MB and {:.1} s",
+                count - failures,
+                count,
+                memory as f64 / 1e6,
+                seconds
+            ),
+            false => format!("{} of {} conversions done", count - failures, count),
+        },
+    );
+    exit_code
+}
+
+type Outcome = Result<Converted, Failure>;
+
+/// The manifest edited in the batch panel, and its run.
+#[derive(Default)]
+pub struct Batch {
+    pub manifest: Manifest,
+    job: Option<Job<(usize, Outcome)>>,
+    /// How each conversion of the last run went, once it has.
+    outcomes: Vec<Option<Outcome>>,
+    /// What each conversion would do, from the last dry run.
+    plans: Vec<Result<Plan, Failure>>,
+}
+
+impl Batch {
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    fn start(&mut self, ctx: &egui::Context) {
+        let conversions = self.manifest.conversions.clone();
+        let on_existing = self.manifest.on_existing;
+        self.outcomes = vec![None; conversions.len()];
+        self.job = Some(Job::spawn(ctx, "Batch", move |send, progress| {
+            for (index, conversion) in conversions.iter().enumerate() {
+                send((
+                    index,
+                    convert(conversion, on_existing, &Progress::default()),
+                ));
+                progress.set((index + 1) as f32 / conversions.len() as f32);
+            }
+        }));
+    }
+
+    pub fn poll(&mut self, log: &mut Log) {
+        let Some(job) = &mut self.job else {
+            return;
+        };
+        for (index, outcome) in job.poll() {
+            let conversion = &self.manifest.conversions[index];
+            match &outcome {
+                Ok(Converted::Written(path, _)) => log.info(format!("Exported {}", path.display())),
+                Ok(Converted::Skipped) => log.info(format!(
+                    "Skipped {}, which exists",
+                    conversion.output.display()
+                )),
+                Err(e) => log.error(format!("{}: {}", conversion.input.display(), e)),
+            }
+            self.outcomes[index] = Some(outcome);
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+}
+
+pub enum BatchAction {
+    /// Add a conversion of the current map with the current projections.
+    AddCurrent,
+}
+
+pub fn batch_ui(
+    ui: &mut egui::Ui,
+    batch: &mut Batch,
+    log: &mut Log,
+    can_add: bool,
+) -> Option<BatchAction> {
+    let mut action = None;
+    ui.collapsing("Batch", |ui| {
+        ui.horizontal(|ui| {
+            if ui.button("Open manifest...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Manifest", &["json"])
+                    .pick_file()
+                {
+                    match Manifest::load(&path) {
+                        Ok(manifest) => {
+                            batch.manifest = manifest;
+                            batch.outcomes.clear();
+                            batch.plans.clear();
+                        }
+                        Err(e) => log.error(e),
+                    }
+                }
+            }
+            if ui.button("Save manifest...").clicked() {
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Manifest", &["json"])
+                    .save_file()
+                {
+                    if let Err(e) = batch.manifest.save(&path) {
+                        log.error(e);
+                    }
+                }
+            }
+        });
+        if ui
+            .add_enabled(can_add, egui::Button::new("Add current map"))
+            .on_disabled_hover_text("Open a source map first.")
+            .clicked()
+        {
+            action = Some(BatchAction::AddCurrent);
+        }
+        let mut removed = None;
+        for (index, conversion) in batch.manifest.conversions.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let name = |path: &Path| {
+                    path.file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .to_string()
+                };
+                ui.label(format!(
+                    "{} → {} ({} → {})",
+                    name(&conversion.input),
+                    name(&conversion.output),
+                    conversion.source.kind,
+                    conversion.target.kind
+                ))
+                .on_hover_text(conversion.input.display().to_string());
+                match batch.outcomes.get(index) {
+                    Some(Some(Ok(Converted::Written(path, _)))) => {
+                        ui.label("✔").on_hover_text(path.display().to_string());
+                    }
+                    Some(Some(Ok(Converted::Skipped))) => {
+                        ui.weak("skipped")
+                            .on_hover_text("The output exists and was left alone.");
+                    }
+                    Some(Some(Err(e))) => {
+                        ui.colored_label(ui.visuals().error_fg_color, "✖")
+                            .on_hover_text(e.to_string());
+                    }
+                    _ => match batch.plans.get(index) {
+                        Some(Ok(plan)) if plan.warnings.is_empty() => {
+                            ui.weak("ok").on_hover_text(plan.to_string());
+                        }
+                        Some(Ok(plan)) => {
+                            ui.colored_label(ui.visuals().warn_fg_color, "⚠")
+                                .on_hover_text(format!("{}\n{}", plan, plan.warnings.join("\n")));
+                        }
+                        Some(Err(e)) => {
+                            ui.colored_label(ui.visuals().error_fg_color, "✖")
+                                .on_hover_text(e.to_string());
+                        }
+                        None => {}
+                    },
+                }
+                if !batch.is_running() && ui.small_button("🗑").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            batch.manifest.conversions.remove(index);
+            batch.outcomes.clear();
+            batch.plans.clear();
+        }
+        ui.add_enabled_ui(!batch.is_running(), |ui| {
+            collisions::collision_ui(ui, "Batch collisions", &mut batch.manifest.on_existing);
+        });
+        match &batch.job {
+            Some(job) => {
+                ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+            }
+            None => {
+                let runnable = !batch.manifest.conversions.is_empty();
+                if ui
+                    .add_enabled(runnable, egui::Button::new("Run batch"))
+                    .clicked()
+                {
+                    batch.start(ui.ctx());
+                }
+            }
+        }
+    });
+    action
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_parameter_has_a_name() {
+        for kind in ProjectionKind::all() {
+            let projection = kind.default_projection_data();
+            assert_eq!(
+                feature_matching::parameter_names(&projection).map(|names| names.len()),
+                feature_matching::parameters(&projection).map(|values| values.len()),
+                "{}",
+                kind.id()
+            );
+        }
+    }
+
+    #[test]
+    fn parameters_are_written_and_read_by_name() {
+        let spec: ProjectionSpec = serde_json::from_str(
+            r#"{"kind": "azimuthal-equidistant", "parameters": {"center_lat": 50}}"#,
+        )
+        .unwrap();
+        let projection = spec.build().unwrap();
+        assert_eq!(
+            feature_matching::parameters(&projection),
+            Some(vec![0., 50., 0.])
+        );
+        let written = serde_json::to_value(ProjectionSpec::of(&projection)).unwrap();
+        assert_eq!(written["parameters"]["center_lat"], 50.);
+    }
+
+    #[test]
+    fn unknown_parameters_are_refused() {
+        for (kind, name) in [
+            ("mollweide", "center_lat"),
+            ("web-mercator", "central_long"),
+        ] {
+            let spec = ProjectionSpec {
+                kind: kind.to_string(),
+                parameters: [(name.to_string(), 10.)].into(),
+            };
+            assert!(spec.build().is_err(), "{} took {}", kind, name);
+        }
+    }
+}
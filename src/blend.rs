@@ -0,0 +1,298 @@
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+use eframe::egui_glow::{self, glow};
+use glow::HasContext;
+use serde::{Deserialize, Serialize};
+
+/// How many layers the compositor blends in one pass. Any beyond these are
+/// drawn over the result without their blend mode.
+pub const MAX_LAYERS: usize = 6;
+
+/// How a layer's colors combine with those below it, as in the W3C
+/// compositing specification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BlendMode {
+    #[default]
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    Difference,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 7] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::Difference,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "Normal",
+            BlendMode::Multiply => "Multiply",
+            BlendMode::Screen => "Screen",
+            BlendMode::Overlay => "Overlay",
+            BlendMode::Darken => "Darken",
+            BlendMode::Lighten => "Lighten",
+            BlendMode::Difference => "Difference",
+        }
+    }
+
+    /// One channel of the layer (`above`) blended with what is below, both
+    /// in 0..=1. `BLEND_GLSL` is the same for the shader.
+    pub fn blend(&self, below: f32, above: f32) -> f32 {
+        match self {
+            BlendMode::Normal => above,
+            BlendMode::Multiply => below * above,
+            BlendMode::Screen => below + above - below * above,
+            BlendMode::Overlay if below <= 0.5 => 2. * below * above,
+            BlendMode::Overlay => 1. - 2. * (1. - below) * (1. - above),
+            BlendMode::Darken => below.min(above),
+            BlendMode::Lighten => below.max(above),
+            BlendMode::Difference => (below - above).abs(),
+        }
+    }
+
+    /// Composites a non-premultiplied layer pixel over a non-premultiplied
+    /// pixel below, with the layer's alpha scaled by `opacity`.
+    pub fn composite(&self, below: [f32; 4], above: [f32; 4], opacity: f32) -> [f32; 4] {
+        let (below_alpha, above_alpha) = (below[3], above[3] * opacity);
+        let alpha = above_alpha + below_alpha * (1. - above_alpha);
+        if alpha <= 0. {
+            return [0.; 4];
+        }
+        let mut result = [0., 0., 0., alpha];
+        for channel in 0..3 {
+            let (b, a) = (below[channel], above[channel]);
+            let mixed = (1. - below_alpha) * a + below_alpha * self.blend(b, a);
+            result[channel] = (above_alpha * mixed + (1. - above_alpha) * below_alpha * b) / alpha;
+        }
+        result
+    }
+}
+
+const BLEND_GLSL: &str = r#"
+float blend_channel(int mode, float below, float above) {
+    if (mode == 1) return below * above;
+    if (mode == 2) return below + above - below * above;
+    if (mode == 3) {
+        return below <= 0.5 ? 2.0 * below * above : 1.0 - 2.0 * (1.0 - below) * (1.0 - above);
+    }
+    if (mode == 4) return min(below, above);
+    if (mode == 5) return max(below, above);
+    if (mode == 6) return abs(below - above);
+    return above;
+}
+
+// Both non-premultiplied, like BlendMode::composite.
+vec4 composite(vec4 below, vec4 above, float opacity, int mode) {
+    float above_alpha = above.a * opacity;
+    float alpha = above_alpha + below.a * (1.0 - above_alpha);
+    if (alpha <= 0.0) return vec4(0.0);
+    vec3 blended = vec3(
+        blend_channel(mode, below.r, above.r),
+        blend_channel(mode, below.g, above.g),
+        blend_channel(mode, below.b, above.b));
+    vec3 mixed = (1.0 - below.a) * above.rgb + below.a * blended;
+    return vec4((above_alpha * mixed + (1.0 - above_alpha) * below.a * below.rgb) / alpha, alpha);
+}
+
+vec3 srgb_gamma_from_linear(vec3 rgb) {
+    bvec3 cutoff = lessThan(rgb, vec3(0.0031308));
+    vec3 lower = rgb * vec3(12.92);
+    vec3 higher = vec3(1.055) * pow(rgb, vec3(1.0 / 2.4)) - vec3(0.055);
+    return mix(higher, lower, vec3(cutoff));
+}
+
+// egui's textures hold premultiplied colors, in linear space where sRGB
+// textures are supported. Blending happens in gamma space, like on export.
+vec4 straight(vec4 sampled) {
+#if SRGB_TEXTURES
+    sampled = vec4(srgb_gamma_from_linear(sampled.rgb), sampled.a);
+#endif
+    return sampled.a > 0.0 ? vec4(sampled.rgb / sampled.a, sampled.a) : vec4(0.0);
+}
+"#;
+
+/// Blends the projected map and its layers in a shader, so that changing a
+/// layer's opacity or blend mode only costs a redraw.
+pub struct Compositor {
+    program: glow::Program,
+    vertex_array: glow::VertexArray,
+}
+
+impl Compositor {
+    pub fn new(gl: &glow::Context) -> Result<Self, String> {
+        let shader_version = egui_glow::ShaderVersion::get(gl);
+        let srgb_textures = shader_version == egui_glow::ShaderVersion::Es300
+            || gl
+                .supported_extensions()
+                .iter()
+                .any(|extension| extension.contains("sRGB"));
+        let header = format!(
+            "{}\n#define NEW_SHADER_INTERFACE {}\n#define SRGB_TEXTURES {}\n\
+             #ifdef GL_ES\nprecision mediump float;\n#endif\n",
+            shader_version.version_declaration(),
+            shader_version.is_new_shader_interface() as i32,
+            srgb_textures as i32,
+        );
+        let vertex_source = format!(
+            "{}{}",
+            header,
+            r#"
+#if NEW_SHADER_INTERFACE
+    out vec2 v_tc;
+#else
+    varying vec2 v_tc;
+#endif
+void main() {
+    // A quad covering the viewport, from the vertex index alone.
+    vec2 corner = vec2(float(gl_VertexID & 1), float((gl_VertexID >> 1) & 1));
+    v_tc = vec2(corner.x, 1.0 - corner.y);
+    gl_Position = vec4(corner * 2.0 - 1.0, 0.0, 1.0);
+}
+"#
+        );
+        let mut samplers = String::from("uniform sampler2D u_base;\n");
+        let mut steps = String::new();
+        for index in 0..MAX_LAYERS {
+            samplers += &format!("uniform sampler2D u_layer{};\n", index);
+            steps += &format!(
+                "    if ({index} < u_count) color = composite(color, \
+                 straight(texture2D(u_layer{index}, v_tc)), u_opacity[{index}], u_mode[{index}]);\n"
+            );
+        }
+        let fragment_source = format!(
+            r#"{header}
+#if NEW_SHADER_INTERFACE
+    in vec2 v_tc;
+    out vec4 f_color;
+    #define gl_FragColor f_color
+    #define texture2D texture
+#else
+    varying vec2 v_tc;
+#endif
+{samplers}
+uniform int u_count;
+uniform float u_opacity[{MAX_LAYERS}];
+uniform int u_mode[{MAX_LAYERS}];
+{BLEND_GLSL}
+void main() {{
+    vec4 color = straight(texture2D(u_base, v_tc));
+{steps}
+    // egui blends premultiplied colors.
+    gl_FragColor = vec4(color.rgb * color.a, color.a);
+}}
+"#
+        );
+        unsafe {
+            let program = gl.create_program()?;
+            let mut shaders = Vec::new();
+            for (kind, source) in [
+                (glow::VERTEX_SHADER, vertex_source),
+                (glow::FRAGMENT_SHADER, fragment_source),
+            ] {
+                let shader = gl.create_shader(kind)?;
+                gl.shader_source(shader, &source);
+                gl.compile_shader(shader);
+                if !gl.get_shader_compile_status(shader) {
+                    return Err(gl.get_shader_info_log(shader));
+                }
+                gl.attach_shader(program, shader);
+                shaders.push(shader);
+            }
+            gl.link_program(program);
+            if !gl.get_program_link_status(program) {
+                return Err(gl.get_program_info_log(program));
+            }
+            for shader in shaders {
+                gl.detach_shader(program, shader);
+                gl.delete_shader(shader);
+            }
+            let vertex_array = gl.create_vertex_array()?;
+            Ok(Compositor {
+                program,
+                vertex_array,
+            })
+        }
+    }
+
+    pub fn destroy(&self, gl: &glow::Context) {
+        unsafe {
+            gl.delete_program(self.program);
+            gl.delete_vertex_array(self.vertex_array);
+        }
+    }
+
+    fn paint(
+        &self,
+        gl: &glow::Context,
+        base: glow::Texture,
+        layers: &[(glow::Texture, f32, BlendMode)],
+    ) {
+        unsafe {
+            gl.use_program(Some(self.program));
+            let location = |name: &str| gl.get_uniform_location(self.program, name);
+            gl.active_texture(glow::TEXTURE0);
+            gl.bind_texture(glow::TEXTURE_2D, Some(base));
+            gl.uniform_1_i32(location("u_base").as_ref(), 0);
+            for (index, (texture, opacity, mode)) in layers.iter().enumerate() {
+                let unit = index as u32 + 1;
+                gl.active_texture(glow::TEXTURE0 + unit);
+                gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+                gl.uniform_1_i32(location(&format!("u_layer{}", index)).as_ref(), unit as i32);
+                gl.uniform_1_f32(
+                    location(&format!("u_opacity[{}]", index)).as_ref(),
+                    *opacity,
+                );
+                gl.uniform_1_i32(
+                    location(&format!("u_mode[{}]", index)).as_ref(),
+                    *mode as i32,
+                );
+            }
+            gl.uniform_1_i32(location("u_count").as_ref(), layers.len() as i32);
+            gl.bind_vertex_array(Some(self.vertex_array));
+            gl.draw_arrays(glow::TRIANGLE_STRIP, 0, 4);
+            gl.active_texture(glow::TEXTURE0);
+        }
+    }
+}
+
+/// Draws `base` with `layers` (texture, opacity and blend mode, bottom first)
+/// blended over it into `rect`. Returns the layers the compositor had no
+/// room for.
+pub fn paint_composite(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    compositor: &Arc<Mutex<Compositor>>,
+    base: egui::TextureId,
+    mut layers: Vec<(egui::TextureId, f32, BlendMode)>,
+) -> Vec<(egui::TextureId, f32, BlendMode)> {
+    let rest = layers.split_off(layers.len().min(MAX_LAYERS));
+    let compositor = compositor.clone();
+    let callback = egui_glow::CallbackFn::new(move |_info, painter| {
+        let textures: Option<Vec<_>> = layers
+            .iter()
+            .map(|(id, opacity, mode)| Some((painter.texture(*id)?, *opacity, *mode)))
+            .collect();
+        if let (Some(base), Some(textures)) = (painter.texture(base), textures) {
+            compositor
+                .lock()
+                .unwrap()
+                .paint(painter.gl(), base, &textures);
+        }
+    });
+    painter.add(egui::PaintCallback {
+        rect,
+        callback: Arc::new(callback),
+    });
+    rest
+}
@@ -0,0 +1,193 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Preset {
+    #[default]
+    Earth,
+    Moon,
+    Mars,
+    Custom,
+}
+
+impl Preset {
+    const ALL: [Preset; 4] = [Preset::Earth, Preset::Moon, Preset::Mars, Preset::Custom];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Preset::Earth => "Earth",
+            Preset::Moon => "Moon",
+            Preset::Mars => "Mars",
+            Preset::Custom => "Custom",
+        }
+    }
+
+    /// Equatorial and polar radius in km (WGS 84 and the IAU reference ellipsoids).
+    fn radii(&self) -> Option<(f64, f64)> {
+        match self {
+            Preset::Earth => Some((6378.137, 6356.752)),
+            Preset::Moon => Some((1737.4, 1737.4)),
+            Preset::Mars => Some((3396.19, 3376.2)),
+            Preset::Custom => None,
+        }
+    }
+
+    /// Obliquity of the equator to the orbit in degrees, which places the tropics and polar circles.
+    fn axial_tilt(&self) -> f64 {
+        match self {
+            Preset::Earth => 23.4393,
+            Preset::Moon => 1.5424,
+            Preset::Mars => 25.19,
+            Preset::Custom => 0.,
+        }
+    }
+}
+
+/// The planet or moon a map shows, for distances and scale bars.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Body {
+    pub preset: Preset,
+    /// In km.
+    pub equatorial_radius: f64,
+    pub polar_radius: f64,
+    /// In degrees.
+    pub axial_tilt: f64,
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Body::from_preset(Preset::Earth)
+    }
+}
+
+impl Body {
+    pub fn from_preset(preset: Preset) -> Self {
+        let (equatorial_radius, polar_radius) = preset.radii().unwrap_or((1000., 1000.));
+        Body {
+            preset,
+            equatorial_radius,
+            polar_radius,
+            axial_tilt: preset.axial_tilt(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.preset.name()
+    }
+
+    pub fn flattening(&self) -> f64 {
+        1. - self.polar_radius / self.equatorial_radius
+    }
+
+    /// Distance in km along the surface, with Lambert's formula for long lines
+    /// on an ellipsoid (good to some meters on Earth).
+    pub fn distance(&self, a: &submaptive::Point, b: &submaptive::Point) -> f64 {
+        let f = self.flattening();
+        // Reduced latitudes, on the sphere the ellipsoid is squashed from.
+        let reduced = |lat: f64| ((1. - f) * lat.to_radians().tan()).atan();
+        let (lat_a, lat_b) = (reduced(a.lat()), reduced(b.lat()));
+        let d_lat = lat_b - lat_a;
+        let d_long = (b.long() - a.long()).to_radians();
+        let h =
+            (d_lat / 2.).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_long / 2.).sin().powi(2);
+        let sigma = 2. * h.sqrt().min(1.).asin();
+        if f == 0. || sigma < 1e-12 {
+            return self.equatorial_radius * sigma;
+        }
+        let p = (lat_a + lat_b) / 2.;
+        let q = (lat_b - lat_a) / 2.;
+        let x = (sigma - sigma.sin()) * (p.sin() * q.cos()).powi(2) / (sigma / 2.).cos().powi(2);
+        let y = (sigma + sigma.sin()) * (p.cos() * q.sin()).powi(2) / (sigma / 2.).sin().powi(2);
+        self.equatorial_radius * (sigma - f / 2. * (x + y))
+    }
+}
+
+pub fn body_ui(ui: &mut egui::Ui, body: &mut Body, unit: AngleUnit) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::new("Body", "Body")
+            .selected_text(body.name())
+            .show_ui(ui, |ui| {
+                for preset in Preset::ALL {
+                    if ui
+                        .selectable_label(body.preset == preset, preset.name())
+                        .clicked()
+                    {
+                        *body = match preset {
+                            // Start from whatever was chosen before.
+                            Preset::Custom => Body { preset, ..*body },
+                            _ => Body::from_preset(preset),
+                        };
+                    }
+                }
+            });
+    });
+    if body.preset == Preset::Custom {
+        ui.add(
+            egui::DragValue::new(&mut body.equatorial_radius)
+                .clamp_range(0.001..=1e6)
+                .prefix("Equatorial radius ")
+                .suffix(" km"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut body.polar_radius)
+                .clamp_range(0.001..=1e6)
+                .prefix("Polar radius ")
+                .suffix(" km"),
+        );
+        ui.add(
+            egui::DragValue::new(&mut body.axial_tilt)
+                .clamp_range(0.0..=90.)
+                .speed(0.1)
+                .angle(unit)
+                .prefix("Axial tilt "),
+        );
+    }
+}
+
+/// A length of about a fifth of `max_km` that is 1, 2 or 5 times a power of ten.
+fn round_length(max_km: f64) -> f64 {
+    let target = max_km / 5.;
+    let magnitude = 10f64.powf(target.log10().floor());
+    [5., 2., 1.]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|length| *length <= target)
+        .unwrap_or(magnitude)
+}
+
+pub fn format_length(km: f64) -> String {
+    if km < 1. {
+        format!("{} m", (km * 1000.).round())
+    } else {
+        format!("{} km", km)
+    }
+}
+
+/// Draws a scale bar into the bottom left of `rect`, for a view whose scale
+/// at its center is `km_per_point`.
+pub fn paint_scale_bar(painter: &egui::Painter, rect: egui::Rect, km_per_point: f64) {
+    if !km_per_point.is_finite() || km_per_point <= 0. {
+        return;
+    }
+    let length = round_length(km_per_point * rect.width() as f64);
+    let width = (length / km_per_point) as f32;
+    let start = rect.left_bottom() + egui::vec2(12., -12.);
+    let end = start + egui::vec2(width, 0.);
+    let stroke = (2., egui::Color32::WHITE);
+    let shadow = (4., egui::Color32::from_black_alpha(160));
+    for stroke in [shadow, stroke] {
+        painter.line_segment([start, end], stroke);
+        painter.line_segment([start, start - egui::vec2(0., 6.)], stroke);
+        painter.line_segment([end, end - egui::vec2(0., 6.)], stroke);
+    }
+    painter.text(
+        start + egui::vec2(width / 2., -8.),
+        egui::Align2::CENTER_BOTTOM,
+        format_length(length),
+        egui::FontId::default(),
+        egui::Color32::WHITE,
+    );
+}
@@ -0,0 +1,146 @@
+use std::path::{Path, PathBuf};
+
+use crate::batch::{Conversion, Manifest, ProjectionSpec};
+use crate::sidecar::Sidecar;
+use crate::ProjectionData;
+
+/// Names inside a bundle. Unzipped, the manifest runs as it is with
+/// `--batch manifest.json` and writes `REGENERATED` next to `IMAGE`.
+const IMAGE: &str = "map.png";
+const REGENERATED: &str = "regenerated.png";
+const MANIFEST: &str = "manifest.json";
+const SETTINGS: &str = "settings.json";
+const OVERLAYS: &str = "overlays";
+const SOURCE: &str = "source";
+
+/// Writes the exported map into a zip archive at `path`, together with
+/// `input`, a manifest that makes the map again from it and the settings and
+/// vector overlays that went into it.
+pub fn write(
+    path: &Path,
+    image: &image::DynamicImage,
+    input: &Path,
+    source: &ProjectionData,
+    target: &ProjectionData,
+    sidecar: &Sidecar,
+) -> Result<(), String> {
+    let mut encoded = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut encoded),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| e.to_string())?;
+    let source_file =
+        std::fs::read(input).map_err(|e| format!("Could not read {}: {}", input.display(), e))?;
+    let source_name = format!(
+        "{}/{}",
+        SOURCE,
+        input.file_name().unwrap_or_default().to_string_lossy()
+    );
+    let mut files = vec![
+        (IMAGE.to_string(), encoded),
+        (source_name.clone(), source_file),
+    ];
+    let mut settings = sidecar.clone();
+    for overlay in &mut settings.vector_overlays {
+        let contents = std::fs::read(&overlay.path)
+            .map_err(|e| format!("Could not read {}: {}", overlay.path.display(), e))?;
+        let file_name = overlay
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy();
+        let mut name = format!("{}/{}", OVERLAYS, file_name);
+        if files.iter().any(|(existing, _)| *existing == name) {
+            name = format!("{}/{}-{}", OVERLAYS, files.len(), file_name);
+        }
+        overlay.path = PathBuf::from(&name);
+        files.push((name, contents));
+    }
+    let manifest = Manifest {
+        conversions: vec![Conversion {
+            input: source_name.into(),
+            output: REGENERATED.into(),
+            source: ProjectionSpec::of(source),
+            target: ProjectionSpec::of(target),
+            sidecar: Some(SETTINGS.into()),
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    for (name, value) in [
+        (MANIFEST, serde_json::to_vec_pretty(&manifest)),
+        (SETTINGS, serde_json::to_vec_pretty(&settings)),
+    ] {
+        files.push((name.to_string(), value.map_err(|e| e.to_string())?));
+    }
+    let files: Vec<_> = files
+        .iter()
+        .map(|(name, contents)| (name.as_str(), contents.as_slice()))
+        .collect();
+    std::fs::write(path, zip(&files)?)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+/// An uncompressed zip archive. Names that aren't ASCII are marked as UTF-8.
+/// Archives that would need the ZIP64 extension, with more than 65534 files
+/// or 4 GiB or more in all, are refused.
+pub fn zip(files: &[(&str, &[u8])]) -> Result<Vec<u8>, String> {
+    // All ones in a field says that the value is in a ZIP64 field instead.
+    let field = |value: usize| match value < u32::MAX as usize {
+        true => Ok((value as u32).to_le_bytes()),
+        false => Err(
+            "The archive would be larger than 4 GiB, which zip archives without ZIP64 can't be."
+                .to_string(),
+        ),
+    };
+    if files.len() >= u16::MAX as usize {
+        return Err(format!(
+            "{} files are more than a zip archive without ZIP64 can hold.",
+            files.len()
+        ));
+    }
+    let (mut archive, mut directory) = (Vec::new(), Vec::new());
+    for (name, contents) in files {
+        let name_length = u16::try_from(name.len())
+            .map_err(|_| format!("{} is too long a name for a zip archive.", name))?;
+        let mut crc = flate2::Crc::new();
+        crc.update(contents);
+        let offset = field(archive.len())?;
+        // Version, flags, method, time and date, the checksum and both sizes.
+        let flags: u16 = match name.is_ascii() {
+            true => 0,
+            false => 1 << 11,
+        };
+        let mut common = vec![20, 0];
+        common.extend(flags.to_le_bytes());
+        common.extend([0; 6]);
+        common.extend(crc.sum().to_le_bytes());
+        common.extend(field(contents.len())?);
+        common.extend(field(contents.len())?);
+        common.extend(name_length.to_le_bytes());
+        archive.extend(b"PK\x03\x04");
+        archive.extend(&common);
+        archive.extend([0, 0]);
+        archive.extend(name.as_bytes());
+        archive.extend(*contents);
+        directory.extend(b"PK\x01\x02\x14\0");
+        directory.extend(&common);
+        // No extra field, comment or attributes, on the first disk.
+        directory.extend([0; 12]);
+        directory.extend(offset);
+        directory.extend(name.as_bytes());
+    }
+    let offset = field(archive.len())?;
+    let size = field(directory.len())?;
+    let count = (files.len() as u16).to_le_bytes();
+    archive.extend(&directory);
+    archive.extend(b"PK\x05\x06\0\0\0\0");
+    archive.extend(count);
+    archive.extend(count);
+    archive.extend(size);
+    archive.extend(offset);
+    archive.extend([0, 0]);
+    Ok(archive)
+}
@@ -0,0 +1,107 @@
+use std::path::PathBuf;
+
+/// A directory of expensive intermediate results, keyed by a hash of what they
+/// were made from and trimmed to a size limit by evicting the least recently used.
+#[derive(Clone)]
+pub struct Cache {
+    directory: PathBuf,
+    limit_bytes: u64,
+}
+
+impl Cache {
+    pub fn new(directory: PathBuf, limit_bytes: u64) -> Self {
+        Cache {
+            directory,
+            limit_bytes,
+        }
+    }
+
+    pub fn set_limit(&mut self, limit_bytes: u64) {
+        self.limit_bytes = limit_bytes;
+    }
+
+    /// A key for something of the given kind derived from `inputs`.
+    pub fn key(kind: &str, inputs: &[&[u8]]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for input in inputs {
+            hasher.update(&(input.len() as u64).to_le_bytes());
+            hasher.update(input);
+        }
+        format!("{}-{}", kind, hasher.finalize().to_hex())
+    }
+
+    /// The content hash of a file, read in chunks so it can be huge.
+    pub fn hash_file(path: &std::path::Path) -> std::io::Result<String> {
+        let mut hasher = blake3::Hasher::new();
+        std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+        Ok(hasher.finalize().to_hex().to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.directory.join(key);
+        let data = std::fs::read(&path).ok()?;
+        // Refresh the modification time so eviction sees the entry as recently used.
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(std::time::SystemTime::now());
+        }
+        Some(data)
+    }
+
+    pub fn put(&self, key: &str, data: &[u8]) -> std::io::Result<()> {
+        if data.len() as u64 > self.limit_bytes {
+            return Ok(());
+        }
+        std::fs::create_dir_all(&self.directory)?;
+        // Write under a temporary name first so a crash can't leave a truncated entry.
+        let temporary = self.directory.join(format!("{}.partial", key));
+        std::fs::write(&temporary, data)?;
+        std::fs::rename(&temporary, self.directory.join(key))?;
+        self.evict()
+    }
+
+    /// The entries with their sizes and last use, oldest first.
+    fn entries(&self) -> Vec<(PathBuf, u64, std::time::SystemTime)> {
+        let Ok(directory) = std::fs::read_dir(&self.directory) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<_> = directory
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let metadata = entry.metadata().ok()?;
+                metadata.is_file().then(|| {
+                    (
+                        entry.path(),
+                        metadata.len(),
+                        metadata.modified().unwrap_or(std::time::UNIX_EPOCH),
+                    )
+                })
+            })
+            .collect();
+        entries.sort_by_key(|(_, _, modified)| *modified);
+        entries
+    }
+
+    pub fn size(&self) -> u64 {
+        self.entries().iter().map(|(_, size, _)| size).sum()
+    }
+
+    fn evict(&self) -> std::io::Result<()> {
+        let entries = self.entries();
+        let mut size: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (path, entry_size, _) in entries {
+            if size <= self.limit_bytes {
+                break;
+            }
+            std::fs::remove_file(path)?;
+            size -= entry_size;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) -> std::io::Result<()> {
+        for (path, _, _) in self.entries() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
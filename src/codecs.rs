@@ -0,0 +1,178 @@
+use std::path::Path;
+
+/// Formats the `image` crate can't read, decoded by optional dependencies
+/// when the app is built with the feature of the same name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Codec {
+    /// HEIC and HEIF, as phones take photos in.
+    Heic,
+    /// JPEG XL.
+    Jxl,
+}
+
+impl Codec {
+    /// The codec for a file by its extension, whether or not it was built in.
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "heic" | "heif" => Some(Codec::Heic),
+            "jxl" => Some(Codec::Jxl),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Codec::Heic => "HEIC",
+            Codec::Jxl => "JPEG XL",
+        }
+    }
+
+    fn feature(&self) -> &'static str {
+        match self {
+            Codec::Heic => "heic",
+            Codec::Jxl => "jxl",
+        }
+    }
+
+    /// The size of the image, from the header only.
+    pub fn dimensions(&self, path: &Path) -> Result<(u32, u32), String> {
+        #[cfg(feature = "heic")]
+        if *self == Codec::Heic {
+            return heic::dimensions(path);
+        }
+        #[cfg(feature = "jxl")]
+        if *self == Codec::Jxl {
+            return jxl::dimensions(path);
+        }
+        Err(self.not_built_in(path))
+    }
+
+    /// The color type images are decoded to, which is all the header needs
+    /// to tell for the memory they take.
+    pub fn color_type(&self) -> image::ColorType {
+        match self {
+            Codec::Heic => image::ColorType::Rgba8,
+            Codec::Jxl => image::ColorType::Rgba16,
+        }
+    }
+
+    pub fn decode(&self, path: &Path) -> Result<image::DynamicImage, String> {
+        #[cfg(feature = "heic")]
+        if *self == Codec::Heic {
+            return heic::decode(path);
+        }
+        #[cfg(feature = "jxl")]
+        if *self == Codec::Jxl {
+            return jxl::decode(path);
+        }
+        Err(self.not_built_in(path))
+    }
+
+    fn not_built_in(&self, path: &Path) -> String {
+        format!(
+            "{} is a {} image, which this build can't open. Build with `--features {}` to open it.",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            self.name(),
+            self.feature()
+        )
+    }
+}
+
+#[cfg(feature = "heic")]
+mod heic {
+    use std::path::Path;
+
+    use libheif_rs::{ColorSpace, HeifContext, ImageHandle, LibHeif, RgbChroma};
+
+    fn primary_image(path: &Path) -> Result<ImageHandle, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        HeifContext::read_from_file(&path.to_string_lossy())
+            .and_then(|context| context.primary_image_handle())
+            .map_err(|e| format!("{} could not be read: {}", name, e))
+    }
+
+    pub fn dimensions(path: &Path) -> Result<(u32, u32), String> {
+        let handle = primary_image(path)?;
+        Ok((handle.width(), handle.height()))
+    }
+
+    pub fn decode(path: &Path) -> Result<image::DynamicImage, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let handle = primary_image(path)?;
+        let image = LibHeif::new()
+            .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgba), None)
+            .map_err(|e| format!("{} is damaged: {}", name, e))?;
+        let plane = image
+            .planes()
+            .interleaved
+            .ok_or_else(|| format!("{} decoded to no image data.", name))?;
+        // Rows may be padded beyond the four bytes per pixel.
+        let row = plane.width as usize * 4;
+        let data = plane
+            .data
+            .chunks(plane.stride)
+            .take(plane.height as usize)
+            .flat_map(|line| &line[..row])
+            .copied()
+            .collect();
+        image::RgbaImage::from_raw(plane.width, plane.height, data)
+            .map(image::DynamicImage::ImageRgba8)
+            .ok_or_else(|| format!("{} decoded to too little image data.", name))
+    }
+}
+
+#[cfg(feature = "jxl")]
+mod jxl {
+    use std::path::Path;
+
+    use jxl_oxide::JxlImage;
+
+    fn open(path: &Path) -> Result<JxlImage, String> {
+        JxlImage::builder().open(path).map_err(|e| {
+            format!(
+                "{} could not be read: {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                e
+            )
+        })
+    }
+
+    pub fn dimensions(path: &Path) -> Result<(u32, u32), String> {
+        let image = open(path)?;
+        Ok((image.width(), image.height()))
+    }
+
+    /// Decodes at 16 bits per channel, as archival scans often have more than 8.
+    pub fn decode(path: &Path) -> Result<image::DynamicImage, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let image = open(path)?;
+        let render = image
+            .render_frame(0)
+            .map_err(|e| format!("{} is damaged: {}", name, e))?;
+        let buffer = render.image_all_channels();
+        let (width, height) = (buffer.width() as u32, buffer.height() as u32);
+        let samples: Vec<u16> = buffer
+            .buf()
+            .iter()
+            .map(|sample| (sample.clamp(0., 1.) * 65535. + 0.5) as u16)
+            .collect();
+        let image = match buffer.channels() {
+            1 => image::ImageBuffer::from_raw(width, height, samples)
+                .map(image::DynamicImage::ImageLuma16),
+            2 => image::ImageBuffer::from_raw(width, height, samples)
+                .map(image::DynamicImage::ImageLumaA16),
+            3 => image::ImageBuffer::from_raw(width, height, samples)
+                .map(image::DynamicImage::ImageRgb16),
+            4 => image::ImageBuffer::from_raw(width, height, samples)
+                .map(image::DynamicImage::ImageRgba16),
+            channels => {
+                return Err(format!(
+                    "{} has {} channels, which can't be shown.",
+                    name, channels
+                ))
+            }
+        };
+        image.ok_or_else(|| format!("{} decoded to too little image data.", name))
+    }
+}
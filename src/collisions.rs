@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// What to do when a map is saved where a file already exists.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Collision {
+    /// Save next to it with a number added, as `map-2.png`.
+    #[default]
+    Increment,
+    Overwrite,
+    /// Keep the existing file and save nothing.
+    Skip,
+}
+
+impl Collision {
+    pub const ALL: [Collision; 3] = [Collision::Increment, Collision::Overwrite, Collision::Skip];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Collision::Increment => "Add a number",
+            Collision::Overwrite => "Overwrite",
+            Collision::Skip => "Skip",
+        }
+    }
+
+    /// Where to save what was meant for `path`, or `None` to save nothing.
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        match (path.exists(), self) {
+            (false, _) | (true, Collision::Overwrite) => Some(path.to_path_buf()),
+            (true, Collision::Increment) => Some(numbered(path)),
+            (true, Collision::Skip) => None,
+        }
+    }
+}
+
+/// The first of `map-2.png`, `map-3.png` and so on that doesn't exist yet.
+/// A number already at the end of the name is counted on from, so that
+/// saving over `map-2.png` gives `map-3.png` rather than `map-2-2.png`.
+pub fn numbered(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let (stem, first) = match stem.rsplit_once('-') {
+        Some((stem, number)) if !stem.is_empty() => match number.parse::<u32>() {
+            Ok(number) => (stem.to_string(), number + 1),
+            Err(_) => (stem.to_string() + "-" + number, 2),
+        },
+        _ => (stem.to_string(), 2),
+    };
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (first..)
+        .map(|number| path.with_file_name(format!("{}-{}{}", stem, number, extension)))
+        .find(|path| !path.exists())
+        .unwrap()
+}
+
+pub fn collision_ui(ui: &mut egui::Ui, id: &str, collision: &mut Collision) {
+    egui::ComboBox::new(id, "If the file exists")
+        .selected_text(collision.name())
+        .show_ui(ui, |ui| {
+            for option in Collision::ALL {
+                ui.selectable_value(collision, option, option.name());
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory of its own for each test holding empty files of `names`.
+    fn directory(test: &str, names: &[&str]) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join("submaptive-collision-tests")
+            .join(test);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for name in names {
+            std::fs::write(directory.join(name), []).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn numbers_count_on_from_one_already_there() {
+        let directory = directory(
+            "increment",
+            &[
+                "map.png",
+                "map-2.png",
+                "map-3.png",
+                "v-1-x.png",
+                "old-7.tif",
+            ],
+        );
+        for (name, expected) in [
+            ("map.png", "map-4.png"),
+            ("map-2.png", "map-4.png"),
+            ("v-1-x.png", "v-1-x-2.png"),
+            ("old-7.tif", "old-8.tif"),
+            ("new.png", "new.png"),
+        ] {
+            assert_eq!(
+                Collision::Increment.resolve(&directory.join(name)),
+                Some(directory.join(expected)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn existing_files_are_skipped_or_overwritten() {
+        let directory = directory("skip", &["map.png"]);
+        let path = directory.join("map.png");
+        assert_eq!(Collision::Skip.resolve(&path), None);
+        assert_eq!(Collision::Overwrite.resolve(&path), Some(path));
+    }
+}
@@ -0,0 +1,106 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Colors with a special meaning: one that marks missing data in the source,
+/// and one to fill in behind the transparent parts of exported maps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColorKeys {
+    /// Source pixels of this color, give or take `tolerance` per channel, are
+    /// made transparent before projecting.
+    pub no_data: Option<[u8; 3]>,
+    pub tolerance: u8,
+    /// Exports keep their transparency without one, where the format allows.
+    pub background: Option<[u8; 3]>,
+}
+
+impl ColorKeys {
+    pub fn key_out(&self, image: image::DynamicImage) -> image::DynamicImage {
+        let Some(key) = self.no_data else {
+            return image;
+        };
+        let mut image = image.to_rgba8();
+        for pixel in image.pixels_mut() {
+            let matches = (0..3).all(|i| pixel.0[i].abs_diff(key[i]) <= self.tolerance);
+            if matches {
+                pixel.0[3] = 0;
+            }
+        }
+        image::DynamicImage::ImageRgba8(image)
+    }
+
+    pub fn fill_background(&self, image: image::DynamicImage) -> image::DynamicImage {
+        let Some(background) = self.background else {
+            return image;
+        };
+        let mut image = image.to_rgba8();
+        for pixel in image.pixels_mut() {
+            let alpha = pixel.0[3] as u32;
+            for (channel, background) in pixel.0.iter_mut().zip(background) {
+                *channel =
+                    ((*channel as u32 * alpha + background as u32 * (255 - alpha)) / 255) as u8;
+            }
+            pixel.0[3] = 255;
+        }
+        image::DynamicImage::ImageRgba8(image)
+    }
+}
+
+/// Prepares a projected map for saving to `path`: fills in `background`
+/// behind it, or white if the format has no transparency.
+pub fn for_export(
+    image: image::DynamicImage,
+    background: Option<[u8; 3]>,
+    path: &std::path::Path,
+) -> image::DynamicImage {
+    let is_jpeg = path.extension().is_some_and(|extension| {
+        ["jpg", "jpeg"].contains(&extension.to_string_lossy().to_lowercase().as_str())
+    });
+    let keys = ColorKeys {
+        background: match is_jpeg {
+            true => Some(background.unwrap_or([255, 255, 255])),
+            false => background,
+        },
+        ..Default::default()
+    };
+    match is_jpeg {
+        true => image::DynamicImage::ImageRgb8(keys.fill_background(image).to_rgb8()),
+        false => keys.fill_background(image),
+    }
+}
+
+/// `#rrggbb` and `rgb(r, g, b)`, as shown for sampled colors.
+pub fn describe(color: [u8; 3]) -> String {
+    format!(
+        "#{:02x}{:02x}{:02x}  rgb({}, {}, {})",
+        color[0], color[1], color[2], color[0], color[1], color[2]
+    )
+}
+
+fn optional_color(ui: &mut egui::Ui, color: &mut Option<[u8; 3]>, label: &str) {
+    ui.horizontal(|ui| {
+        let mut enabled = color.is_some();
+        ui.checkbox(&mut enabled, label);
+        match (enabled, color.as_mut()) {
+            (true, Some(color)) => {
+                ui.color_edit_button_srgb(color);
+            }
+            (true, None) => *color = Some([0, 0, 0]),
+            (false, _) => *color = None,
+        }
+    });
+}
+
+pub fn color_keys_ui(ui: &mut egui::Ui, keys: &mut ColorKeys) {
+    ui.collapsing("No-data and background", |ui| {
+        optional_color(ui, &mut keys.no_data, "No-data color");
+        if keys.no_data.is_some() {
+            ui.add(egui::Slider::new(&mut keys.tolerance, 0..=64).text("Tolerance"));
+        }
+        optional_color(ui, &mut keys.background, "Export background");
+        if keys.background.is_none() {
+            ui.weak("Exports are transparent where there is no data, except as JPEG.");
+        }
+        ui.weak("Either color can be sampled from a view with the eyedropper tool.");
+    });
+}
@@ -0,0 +1,495 @@
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+
+/// A TIFF in a color type the `image` crate refuses, which is converted here.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TiffConversion {
+    /// Fewer than 8 bits of gray per pixel, such as bilevel scans.
+    Gray { bits: u16, white_is_zero: bool },
+    /// Indices into a color map.
+    Palette { bits: u16 },
+    /// 8 bits each of cyan, magenta, yellow and black.
+    Cmyk,
+}
+
+impl TiffConversion {
+    fn of<R: Read + Seek>(decoder: &mut Decoder<R>) -> Option<Self> {
+        let photometric = decoder
+            .get_tag_unsigned::<u16>(Tag::PhotometricInterpretation)
+            .ok()?;
+        let samples = decoder
+            .find_tag_unsigned::<u16>(Tag::SamplesPerPixel)
+            .ok()?
+            .unwrap_or(1);
+        let bits = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::BitsPerSample)
+            .ok()?
+            .and_then(|bits| bits.first().copied())
+            .unwrap_or(1);
+        let formats = decoder
+            .find_tag_unsigned_vec::<u16>(Tag::SampleFormat)
+            .ok()?;
+        if let Some(formats) = formats {
+            // Anything but unsigned integers.
+            if formats.iter().any(|format| *format != 1) {
+                return None;
+            }
+        }
+        match (photometric, samples, bits) {
+            (0 | 1, 1, 1 | 2 | 4) => Some(TiffConversion::Gray {
+                bits,
+                white_is_zero: photometric == 0,
+            }),
+            (3, 1, 1 | 2 | 4 | 8) => Some(TiffConversion::Palette { bits }),
+            (5, 4, 8) => Some(TiffConversion::Cmyk),
+            _ => None,
+        }
+    }
+
+    fn description(&self) -> String {
+        match self {
+            TiffConversion::Gray { bits: 1, .. } => {
+                "is black and white at 1 bit per pixel, and is opened as 8-bit grayscale.".into()
+            }
+            TiffConversion::Gray { bits, .. } => format!(
+                "has {} bits of gray per pixel, and is opened as 8-bit grayscale.",
+                bits
+            ),
+            TiffConversion::Palette { .. } => {
+                "uses a color palette, which is expanded to RGB.".into()
+            }
+            TiffConversion::Cmyk => CMYK.into(),
+        }
+    }
+}
+
+const CMYK: &str = "is in CMYK, which is converted to RGB; colors may differ slightly from print.";
+
+/// Says how an image is converted to a color type the app works in when it
+/// is opened, or `None` if it is used as it is. Only reads the header.
+pub fn inspect(path: &Path) -> Option<String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let description = match image::ImageFormat::from_path(path).ok()? {
+        image::ImageFormat::Jpeg => match jpeg_components(open(path)?) {
+            Some(4) => CMYK.to_string(),
+            _ => return None,
+        },
+        image::ImageFormat::Png => match png_palette_with_transparency(open(path)?) {
+            Some(true) => "has a palette with transparency, which is expanded to RGBA.".into(),
+            _ => return None,
+        },
+        image::ImageFormat::Tiff => {
+            let mut decoder = Decoder::new(open(path)?).ok()?;
+            TiffConversion::of(&mut decoder)?.description()
+        }
+        _ => return None,
+    };
+    Some(format!("{} {}", name, description))
+}
+
+fn open(path: &Path) -> Option<BufReader<std::fs::File>> {
+    std::fs::File::open(path).ok().map(BufReader::new)
+}
+
+/// The number of color components in the frame header of a JPEG.
+fn jpeg_components(mut reader: impl Read + Seek) -> Option<u8> {
+    let mut marker = [0; 2];
+    reader.read_exact(&mut marker).ok()?;
+    if marker != [0xff, 0xd8] {
+        return None;
+    }
+    loop {
+        let mut segment = [0; 4];
+        reader.read_exact(&mut segment).ok()?;
+        let [0xff, marker, length @ ..] = segment else {
+            return None;
+        };
+        match marker {
+            // Start of frame, but for the huffman tables, arithmetic coding
+            // conditions and an extension that share the range.
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                let mut frame = [0; 6];
+                reader.read_exact(&mut frame).ok()?;
+                return Some(frame[5]);
+            }
+            _ => {
+                let length = u16::from_be_bytes(length) as i64;
+                reader.seek(SeekFrom::Current(length - 2)).ok()?;
+            }
+        }
+    }
+}
+
+/// Whether a PNG has a palette and a tRNS chunk that makes some of it transparent.
+fn png_palette_with_transparency(mut reader: impl Read + Seek) -> Option<bool> {
+    let mut signature = [0; 8];
+    reader.read_exact(&mut signature).ok()?;
+    let mut palette = false;
+    loop {
+        let mut chunk = [0; 8];
+        reader.read_exact(&mut chunk).ok()?;
+        let length = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        match &chunk[4..] {
+            b"IHDR" => {
+                let mut header = [0; 13];
+                reader.read_exact(&mut header).ok()?;
+                palette = header[9] == 3;
+                reader.seek(SeekFrom::Current(4)).ok()?;
+            }
+            b"tRNS" => return Some(palette),
+            // The tRNS chunk comes before the image data if at all.
+            b"IDAT" | b"IEND" => return Some(false),
+            _ => {
+                reader.seek(SeekFrom::Current(length as i64 + 4)).ok()?;
+            }
+        }
+    }
+}
+
+/// The size of a TIFF in one of the color types converted here, which the
+/// `image` crate can't even read the header of.
+pub fn tiff_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut decoder = Decoder::new(open(path)?).ok()?;
+    TiffConversion::of(&mut decoder)?;
+    decoder.dimensions().ok()
+}
+
+/// Decodes a TIFF the `image` crate refused because of its color type, or
+/// returns `None` if it is not one of the color types converted here.
+pub fn decode_tiff(path: &Path) -> Result<Option<image::DynamicImage>, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    // Strips are read from a reader of their own, as `Decoder` doesn't give
+    // out the one it has.
+    let open_file = || open(path).ok_or_else(|| format!("{} could not be read.", name));
+    let mut decoder = Decoder::new(open_file()?)
+        .map_err(|e| format!("{} is damaged: {}", name, e))?
+        .with_limits(tiff::decoder::Limits::unlimited());
+    let Some(conversion) = TiffConversion::of(&mut decoder) else {
+        return Ok(None);
+    };
+    let (width, height) = decoder
+        .dimensions()
+        .map_err(|e| format!("{} is damaged: {}", name, e))?;
+    let image = match conversion {
+        TiffConversion::Cmyk => match decoder.read_image() {
+            Ok(DecodingResult::U8(data)) => {
+                image::RgbImage::from_raw(width, height, cmyk_to_rgb(&data))
+                    .map(image::DynamicImage::ImageRgb8)
+            }
+            Ok(_) => None,
+            Err(e) => return Err(format!("{} is damaged: {}", name, e)),
+        },
+        TiffConversion::Gray {
+            bits,
+            white_is_zero,
+        } => {
+            let max = (1u16 << bits) - 1;
+            let gray = unpack(
+                &read_strips(&mut decoder, open_file()?, width, height, bits)?,
+                width,
+                bits,
+            )
+            .map(|value| {
+                let value = match white_is_zero {
+                    true => max - value,
+                    false => value,
+                };
+                (value as u32 * 255 / max as u32) as u8
+            })
+            .collect();
+            image::GrayImage::from_raw(width, height, gray).map(image::DynamicImage::ImageLuma8)
+        }
+        TiffConversion::Palette { bits } => {
+            // Red, then green, then blue entries, at 16 bits each.
+            let map = decoder
+                .get_tag_u16_vec(Tag::ColorMap)
+                .map_err(|_| format!("{} has a palette but no colors for it.", name))?;
+            let entries = 1usize << bits;
+            if map.len() < entries * 3 {
+                return Err(format!("{} has too few colors in its palette.", name));
+            }
+            let rgb = unpack(
+                &read_strips(&mut decoder, open_file()?, width, height, bits)?,
+                width,
+                bits,
+            )
+            .flat_map(|index| {
+                let index = index as usize;
+                [0, 1, 2].map(|channel| (map[channel * entries + index] >> 8) as u8)
+            })
+            .collect();
+            image::RgbImage::from_raw(width, height, rgb).map(image::DynamicImage::ImageRgb8)
+        }
+    };
+    image
+        .map(Some)
+        .ok_or_else(|| format!("{} has less image data than its size needs.", name))
+}
+
+/// Naive, as there is no color profile to go by.
+pub fn cmyk_to_rgb(cmyk: &[u8]) -> Vec<u8> {
+    cmyk.chunks_exact(4)
+        .flat_map(|pixel| {
+            let black = 255 - pixel[3] as u32;
+            [pixel[0], pixel[1], pixel[2]].map(|ink| ((255 - ink as u32) * black / 255) as u8)
+        })
+        .collect()
+}
+
+/// The samples of `width` pixels per row, where rows start on whole bytes.
+fn unpack(packed: &[u8], width: u32, bits: u16) -> impl Iterator<Item = u16> + '_ {
+    let row_bytes = (width as usize * bits as usize).div_ceil(8);
+    let mask = (1u16 << bits) - 1;
+    packed.chunks_exact(row_bytes).flat_map(move |row| {
+        (0..width as usize).map(move |x| {
+            let bit = x * bits as usize;
+            let shift = 8 - bits as usize - bit % 8;
+            (row[bit / 8] as u16 >> shift) & mask
+        })
+    })
+}
+
+/// Reads and decompresses the strips of an image with fewer than 8 bits per
+/// pixel, which the `tiff` crate doesn't unpack.
+fn read_strips<R: Read + Seek>(
+    decoder: &mut Decoder<R>,
+    mut reader: impl Read + Seek,
+    width: u32,
+    height: u32,
+    bits: u16,
+) -> Result<Vec<u8>, String> {
+    let unreadable = |what: &str| format!("The TIFF's {} can't be read.", what);
+    if decoder.find_tag(Tag::TileWidth).ok().flatten().is_some() {
+        return Err("Tiled TIFFs of this color type can't be read.".into());
+    }
+    let predictor = decoder
+        .find_tag_unsigned::<u16>(Tag::Predictor)
+        .map_err(|_| unreadable("predictor"))?;
+    if predictor.unwrap_or(1) != 1 {
+        return Err("TIFFs of this color type with a predictor can't be read.".into());
+    }
+    let compression = decoder
+        .find_tag_unsigned::<u16>(Tag::Compression)
+        .map_err(|_| unreadable("compression"))?
+        .unwrap_or(1);
+    let offsets = decoder
+        .get_tag_u64_vec(Tag::StripOffsets)
+        .map_err(|_| unreadable("strip offsets"))?;
+    let counts = decoder
+        .get_tag_u64_vec(Tag::StripByteCounts)
+        .map_err(|_| unreadable("strip sizes"))?;
+    let row_bytes = (width as usize * bits as usize).div_ceil(8);
+    let size = row_bytes * height as usize;
+    let mut data = Vec::with_capacity(size);
+    for (offset, count) in offsets.into_iter().zip(counts) {
+        let mut strip = vec![0; count as usize];
+        reader
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| reader.read_exact(&mut strip))
+            .map_err(|_| "The TIFF ends early.".to_string())?;
+        match compression {
+            1 => data.extend(strip),
+            // LZW
+            5 => data.extend(
+                weezl::decode::Decoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8)
+                    .decode(&strip)
+                    .map_err(|_| unreadable("compressed data"))?,
+            ),
+            // Deflate, under the official and an older code.
+            8 | 32946 => {
+                flate2::read::ZlibDecoder::new(&strip[..])
+                    .read_to_end(&mut data)
+                    .map_err(|_| unreadable("compressed data"))?;
+            }
+            32773 => unpack_bits(&strip, &mut data),
+            other => {
+                return Err(format!(
+                    "TIFFs of this color type compressed with method {} can't be read.",
+                    other
+                ))
+            }
+        }
+    }
+    data.truncate(size);
+    Ok(data)
+}
+
+/// Decodes TIFF's PackBits run-length encoding.
+pub fn unpack_bits(mut packed: &[u8], unpacked: &mut Vec<u8>) {
+    while let [header, rest @ ..] = packed {
+        let header = *header as i8;
+        packed = match header {
+            0.. => {
+                let literal = rest.len().min(header as usize + 1);
+                unpacked.extend(&rest[..literal]);
+                &rest[literal..]
+            }
+            // A no-op.
+            -128 => rest,
+            _ => match rest {
+                [value, rest @ ..] => {
+                    let run = (1 - header as isize) as usize;
+                    unpacked.resize(unpacked.len() + run, *value);
+                    rest
+                }
+                [] => rest,
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::inspect;
+    use crate::jobs::Progress;
+    use crate::loading;
+
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const BLACK: [u8; 4] = [0, 0, 0, 255];
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    /// Ten pixels per row, so that rows are padded to whole bytes.
+    const BILEVEL: [u8; 4] = [0b1000_0000, 0b0100_0000, 0, 0];
+
+    /// A little-endian TIFF of one uncompressed strip with one sample per pixel,
+    /// and a color map unless it is empty.
+    fn tiff(
+        width: u32,
+        height: u32,
+        bits: u16,
+        photometric: u16,
+        strip: &[u8],
+        map: &[u16],
+    ) -> Vec<u8> {
+        const SHORT: u16 = 3;
+        const LONG: u16 = 4;
+        let count = 9 + !map.is_empty() as u32;
+        let map_offset = 8 + 2 + count * 12 + 4;
+        let strip_offset = map_offset + map.len() as u32 * 2;
+        // Tag, type, number of values and the value, or where the values are.
+        let mut entries: Vec<(u16, u16, u32, u32)> = vec![
+            (256, LONG, 1, width),
+            (257, LONG, 1, height),
+            (258, SHORT, 1, bits as u32),
+            (259, SHORT, 1, 1),
+            (262, SHORT, 1, photometric as u32),
+            (273, LONG, 1, strip_offset),
+            (277, SHORT, 1, 1),
+            (278, LONG, 1, height),
+            (279, LONG, 1, strip.len() as u32),
+        ];
+        if !map.is_empty() {
+            entries.push((320, SHORT, map.len() as u32, map_offset));
+        }
+        let mut file = b"II*\0".to_vec();
+        file.extend(8u32.to_le_bytes());
+        file.extend((entries.len() as u16).to_le_bytes());
+        for (tag, kind, values, value) in entries {
+            file.extend(tag.to_le_bytes());
+            file.extend(kind.to_le_bytes());
+            file.extend(values.to_le_bytes());
+            file.extend(value.to_le_bytes());
+        }
+        file.extend(0u32.to_le_bytes());
+        file.extend(map.iter().flat_map(|entry| entry.to_le_bytes()));
+        file.extend(strip);
+        file
+    }
+
+    /// Writes a made up file where the decoders can read it from.
+    fn write(name: &str, data: &[u8]) -> PathBuf {
+        let directory = std::env::temp_dir().join("submaptive-color-type-tests");
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join(name);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    /// Checks that the file is recognized as needing a conversion, and that
+    /// it opens with the given pixels.
+    fn assert_converts(name: &str, data: &[u8], pixels: &[((u32, u32), [u8; 4])]) {
+        let path = write(name, data);
+        assert!(inspect(&path).is_some(), "{} isn't recognized", name);
+        let image = loading::decode(&path, &Progress::default())
+            .unwrap()
+            .to_rgba8();
+        for &((x, y), expected) in pixels {
+            assert_eq!(
+                image.get_pixel(x, y).0,
+                expected,
+                "{} at ({}, {})",
+                name,
+                x,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn bilevel_tiffs_are_black_on_white() {
+        let pixels = [
+            ((0, 0), WHITE),
+            ((1, 0), BLACK),
+            ((9, 0), WHITE),
+            ((0, 1), BLACK),
+        ];
+        assert_converts("bilevel.tif", &tiff(10, 2, 1, 1, &BILEVEL, &[]), &pixels);
+    }
+
+    #[test]
+    fn bilevel_tiffs_can_have_white_as_zero() {
+        let pixels = [((0, 0), BLACK), ((1, 0), WHITE), ((9, 0), BLACK)];
+        let data = tiff(10, 2, 1, 0, &BILEVEL, &[]);
+        assert_converts("bilevel-white-is-zero.tif", &data, &pixels);
+    }
+
+    #[test]
+    fn sub_byte_palette_tiffs_are_expanded() {
+        // Red for 0 and green for 1 of a 4-bit palette.
+        let mut map = vec![0; 3 * 16];
+        map[0] = 0xffff;
+        map[16 + 1] = 0xffff;
+        let pixels = [((0, 0), RED), ((1, 0), [0, 255, 0, 255])];
+        assert_converts("palette.tif", &tiff(2, 1, 4, 3, &[0x01], &map), &pixels);
+    }
+
+    #[test]
+    fn cmyk_tiffs_are_converted_to_rgb() {
+        let mut cmyk = Vec::new();
+        let mut encoder = tiff::encoder::TiffEncoder::new(std::io::Cursor::new(&mut cmyk)).unwrap();
+        encoder
+            .write_image::<tiff::encoder::colortype::CMYK8>(2, 1, &[0, 255, 255, 0, 0, 0, 0, 255])
+            .unwrap();
+        assert_converts("cmyk.tif", &cmyk, &[((0, 0), RED), ((1, 0), BLACK)]);
+    }
+
+    #[test]
+    fn palette_pngs_keep_their_transparency() {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_palette(vec![255, 0, 0, 0, 0, 255]);
+        encoder.set_trns(vec![255, 0]);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&[0, 1]))
+            .unwrap();
+        let pixels = [((0, 0), RED), ((1, 0), [0, 0, 255, 0])];
+        assert_converts("palette-with-transparency.png", &png, &pixels);
+    }
+
+    #[test]
+    fn cmyk_jpegs_are_recognized() {
+        // `image` converts CMYK JPEGs itself, so only the header is made up,
+        // to check that it is recognized: a JFIF segment to skip, then the frame.
+        let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe0, 0, 16];
+        jpeg.extend(b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+        jpeg.extend([0xff, 0xc0, 0, 20, 8, 0, 1, 0, 1, 4]);
+        jpeg.extend([1, 0x11, 0, 2, 0x11, 0, 3, 0x11, 0, 4, 0x11, 0, 0xff, 0xd9]);
+        assert!(inspect(&write("cmyk.jpg", &jpeg)).is_some());
+    }
+}
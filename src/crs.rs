@@ -0,0 +1,300 @@
+use std::f64::consts::FRAC_PI_2;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ellipsoid {
+    /// Semi-major axis, in meters.
+    pub a: f64,
+    pub flattening: f64,
+}
+
+impl Ellipsoid {
+    pub const WGS84: Ellipsoid = Ellipsoid {
+        a: 6378137.,
+        flattening: 1. / 298.257223563,
+    };
+    pub const GRS80: Ellipsoid = Ellipsoid {
+        a: 6378137.,
+        flattening: 1. / 298.257222101,
+    };
+
+    fn sphere(radius: f64) -> Self {
+        Ellipsoid {
+            a: radius,
+            flattening: 0.,
+        }
+    }
+
+    fn eccentricity_squared(&self) -> f64 {
+        self.flattening * (2. - self.flattening)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProjectedCrs {
+    pub ellipsoid: Ellipsoid,
+    /// In degrees.
+    pub central_meridian: f64,
+    pub latitude_of_origin: f64,
+    pub scale_factor: f64,
+    /// In the CRS's unit.
+    pub false_easting: f64,
+    pub false_northing: f64,
+    /// Meters per unit of the coordinates.
+    pub unit: f64,
+}
+
+/// A coordinate reference system vector overlays can come in. Only the most
+/// common ones are known; anything else is reported rather than drawn in the
+/// wrong place.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Crs {
+    /// Longitude and latitude in degrees, on any datum (the differences are
+    /// far below what an overlay shows).
+    Geographic,
+    Mercator(ProjectedCrs),
+    TransverseMercator(ProjectedCrs),
+}
+
+impl Crs {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Crs::Geographic => "Longitude/latitude",
+            Crs::Mercator(_) => "Mercator",
+            Crs::TransverseMercator(_) => "Transverse Mercator",
+        }
+    }
+
+    pub fn from_epsg(code: u32) -> Result<Self, String> {
+        let utm = |zone: u32, south: bool, ellipsoid: Ellipsoid| {
+            Crs::TransverseMercator(ProjectedCrs {
+                ellipsoid,
+                central_meridian: zone as f64 * 6. - 183.,
+                latitude_of_origin: 0.,
+                scale_factor: 0.9996,
+                false_easting: 500000.,
+                false_northing: if south { 10000000. } else { 0. },
+                unit: 1.,
+            })
+        };
+        Ok(match code {
+            4326 | 4258 | 4269 | 4230 | 4267 | 4283 => Crs::Geographic,
+            3857 | 3785 | 900913 | 102100 | 102113 => Crs::Mercator(ProjectedCrs {
+                ellipsoid: Ellipsoid::sphere(Ellipsoid::WGS84.a),
+                central_meridian: 0.,
+                latitude_of_origin: 0.,
+                scale_factor: 1.,
+                false_easting: 0.,
+                false_northing: 0.,
+                unit: 1.,
+            }),
+            3395 => Crs::Mercator(ProjectedCrs {
+                ellipsoid: Ellipsoid::WGS84,
+                central_meridian: 0.,
+                latitude_of_origin: 0.,
+                scale_factor: 1.,
+                false_easting: 0.,
+                false_northing: 0.,
+                unit: 1.,
+            }),
+            32601..=32660 => utm(code - 32600, false, Ellipsoid::WGS84),
+            32701..=32760 => utm(code - 32700, true, Ellipsoid::WGS84),
+            25828..=25838 => utm(code - 25800, false, Ellipsoid::GRS80),
+            26901..=26923 => utm(code - 26900, false, Ellipsoid::GRS80),
+            _ => {
+                return Err(format!(
+                    "The coordinate system EPSG:{} isn't supported.",
+                    code
+                ))
+            }
+        })
+    }
+
+    /// Reads names like `EPSG:3857`, `urn:ogc:def:crs:EPSG::32633` or
+    /// `urn:ogc:def:crs:OGC:1.3:CRS84`, as GeoJSON files name their CRS.
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        if name.ends_with("CRS84") || name.ends_with("CRS83") {
+            return Ok(Crs::Geographic);
+        }
+        let code = name
+            .contains("EPSG")
+            .then(|| name.rsplit(':').next())
+            .flatten()
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| format!("Unknown coordinate system \"{}\".", name))?;
+        Self::from_epsg(code)
+    }
+
+    /// Reads the well-known text of a shapefile's `.prj`.
+    pub fn from_wkt(wkt: &str) -> Result<Self, String> {
+        if !wkt.contains("PROJCS[") {
+            return match wkt.contains("GEOGCS[") {
+                true => Ok(Crs::Geographic),
+                false => Err("Unreadable coordinate system (.prj).".to_string()),
+            };
+        }
+        let projection = quoted_after(wkt, "PROJECTION[").unwrap_or_default();
+        let parameter = |name: &str| {
+            let mut rest = wkt;
+            while let Some(start) = rest.find("PARAMETER[") {
+                rest = &rest[start + "PARAMETER[".len()..];
+                let mut fields = rest.split([',', ']']);
+                let key = fields.next()?.trim().trim_matches('"');
+                if key.eq_ignore_ascii_case(name) {
+                    return fields.next()?.trim().parse::<f64>().ok();
+                }
+            }
+            None
+        };
+        let spheroid = numbers_after(wkt, "SPHEROID[").or_else(|| numbers_after(wkt, "ELLIPSOID["));
+        let mut ellipsoid = match spheroid.as_deref() {
+            Some([a, inverse_flattening, ..]) => Ellipsoid {
+                a: *a,
+                flattening: if *inverse_flattening == 0. {
+                    0.
+                } else {
+                    1. / inverse_flattening
+                },
+            },
+            _ => Ellipsoid::WGS84,
+        };
+        // The linear unit is the projected system's own, which comes last.
+        let unit = wkt
+            .rfind("UNIT[")
+            .and_then(|start| numbers_after(&wkt[start..], "UNIT["))
+            .and_then(|numbers| numbers.first().copied())
+            .unwrap_or(1.);
+        let mut crs = ProjectedCrs {
+            ellipsoid,
+            central_meridian: parameter("central_meridian")
+                .or_else(|| parameter("longitude_of_center"))
+                .unwrap_or(0.),
+            latitude_of_origin: parameter("latitude_of_origin").unwrap_or(0.),
+            scale_factor: parameter("scale_factor").unwrap_or(1.),
+            false_easting: parameter("false_easting").unwrap_or(0.),
+            false_northing: parameter("false_northing").unwrap_or(0.),
+            unit,
+        };
+        let name = projection.to_lowercase();
+        if name.contains("transverse_mercator") || name.contains("transverse mercator") {
+            Ok(Crs::TransverseMercator(crs))
+        } else if name.contains("mercator") {
+            if name.contains("auxiliary_sphere") || name.contains("pseudo") {
+                ellipsoid = Ellipsoid::sphere(ellipsoid.a);
+                crs.ellipsoid = ellipsoid;
+            }
+            if let Some(parallel) = parameter("standard_parallel_1") {
+                let phi = parallel.to_radians();
+                let e2 = ellipsoid.eccentricity_squared();
+                crs.scale_factor = phi.cos() / (1. - e2 * phi.sin().powi(2)).sqrt();
+            }
+            Ok(Crs::Mercator(crs))
+        } else {
+            Err(format!(
+                "The projection \"{}\" isn't supported.",
+                projection
+            ))
+        }
+    }
+
+    /// Longitude and latitude in degrees for coordinates in this system.
+    pub fn to_geographic(self, (x, y): (f64, f64)) -> (f64, f64) {
+        match self {
+            Crs::Geographic => (x, y),
+            Crs::Mercator(crs) => crs.inverse_mercator(x, y),
+            Crs::TransverseMercator(crs) => crs.inverse_transverse_mercator(x, y),
+        }
+    }
+}
+
+impl ProjectedCrs {
+    /// Meters east and north of the projection's origin.
+    fn meters(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (x - self.false_easting) * self.unit,
+            (y - self.false_northing) * self.unit,
+        )
+    }
+
+    fn inverse_mercator(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.meters(x, y);
+        let a = self.ellipsoid.a * self.scale_factor;
+        let e = self.ellipsoid.eccentricity_squared().sqrt();
+        let t = (-y / a).exp();
+        let mut lat = FRAC_PI_2 - 2. * t.atan();
+        for _ in 0..8 {
+            let factor = ((1. - e * lat.sin()) / (1. + e * lat.sin())).powf(e / 2.);
+            lat = FRAC_PI_2 - 2. * (t * factor).atan();
+        }
+        (
+            self.central_meridian + (x / a).to_degrees(),
+            lat.to_degrees(),
+        )
+    }
+
+    /// The distance along the meridian from the equator to `lat` (radians).
+    fn meridian_arc(&self, lat: f64) -> f64 {
+        let e2 = self.ellipsoid.eccentricity_squared();
+        let (e4, e6) = (e2 * e2, e2 * e2 * e2);
+        self.ellipsoid.a
+            * ((1. - e2 / 4. - 3. * e4 / 64. - 5. * e6 / 256.) * lat
+                - (3. * e2 / 8. + 3. * e4 / 32. + 45. * e6 / 1024.) * (2. * lat).sin()
+                + (15. * e4 / 256. + 45. * e6 / 1024.) * (4. * lat).sin()
+                - (35. * e6 / 3072.) * (6. * lat).sin())
+    }
+
+    /// Snyder's series (Map Projections: A Working Manual, p. 63), good to
+    /// well under a meter within a UTM zone.
+    fn inverse_transverse_mercator(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.meters(x, y);
+        let a = self.ellipsoid.a;
+        let k0 = self.scale_factor;
+        let e2 = self.ellipsoid.eccentricity_squared();
+        let ep2 = e2 / (1. - e2);
+        let m = self.meridian_arc(self.latitude_of_origin.to_radians()) + y / k0;
+        let mu = m / (a * (1. - e2 / 4. - 3. * e2 * e2 / 64. - 5. * e2 * e2 * e2 / 256.));
+        let e1 = (1. - (1. - e2).sqrt()) / (1. + (1. - e2).sqrt());
+        let phi1 = mu
+            + (3. * e1 / 2. - 27. * e1.powi(3) / 32.) * (2. * mu).sin()
+            + (21. * e1 * e1 / 16. - 55. * e1.powi(4) / 32.) * (4. * mu).sin()
+            + (151. * e1.powi(3) / 96.) * (6. * mu).sin()
+            + (1097. * e1.powi(4) / 512.) * (8. * mu).sin();
+        let (sin, cos, tan) = (phi1.sin(), phi1.cos(), phi1.tan());
+        let c1 = ep2 * cos * cos;
+        let t1 = tan * tan;
+        let n1 = a / (1. - e2 * sin * sin).sqrt();
+        let r1 = a * (1. - e2) / (1. - e2 * sin * sin).powf(1.5);
+        let d = x / (n1 * k0);
+        let lat = phi1
+            - (n1 * tan / r1)
+                * (d * d / 2.
+                    - (5. + 3. * t1 + 10. * c1 - 4. * c1 * c1 - 9. * ep2) * d.powi(4) / 24.
+                    + (61. + 90. * t1 + 298. * c1 + 45. * t1 * t1 - 252. * ep2 - 3. * c1 * c1)
+                        * d.powi(6)
+                        / 720.);
+        let long = (d - (1. + 2. * t1 + c1) * d.powi(3) / 6.
+            + (5. - 2. * c1 + 28. * t1 - 3. * c1 * c1 + 8. * ep2 + 24. * t1 * t1) * d.powi(5)
+                / 120.)
+            / cos;
+        (self.central_meridian + long.to_degrees(), lat.to_degrees())
+    }
+}
+
+/// The quoted name right after `keyword`, as in `PROJECTION["Mercator"]`.
+fn quoted_after<'a>(wkt: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = &wkt[wkt.find(keyword)? + keyword.len()..];
+    let rest = &rest[rest.find('"')? + 1..];
+    Some(&rest[..rest.find('"')?])
+}
+
+/// The numbers after the name in an element like `SPHEROID["WGS_84",6378137,298.25]`.
+fn numbers_after(wkt: &str, keyword: &str) -> Option<Vec<f64>> {
+    let rest = &wkt[wkt.find(keyword)? + keyword.len()..];
+    let element = &rest[..rest.find(']')?];
+    Some(
+        element
+            .split(',')
+            .skip(1)
+            .filter_map(|field| field.trim().parse().ok())
+            .collect(),
+    )
+}
@@ -0,0 +1,134 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui_glow::glow;
+use glow::HasContext;
+use serde::Serialize;
+
+use crate::batch::ProjectionSpec;
+use crate::bundle;
+use crate::notifications::Log;
+use crate::settings::{self, Settings};
+use crate::sidecar::Sidecar;
+use crate::viewer::ViewerState;
+
+/// Images in a report are scaled down to fit in this many pixels square.
+const PREVIEW_SIZE: u32 = 512;
+
+/// The graphics driver, as OpenGL names it.
+pub fn gpu_info(gl: &glow::Context) -> String {
+    let [vendor, renderer, version] = [glow::VENDOR, glow::RENDERER, glow::VERSION]
+        .map(|parameter| unsafe { gl.get_parameter_string(parameter) });
+    format!("{} {}, OpenGL {}", vendor, renderer, version)
+}
+
+/// What was open when the report was made, without the images.
+#[derive(Serialize)]
+pub struct Session {
+    pub source_path: Option<PathBuf>,
+    pub source_dimensions: Option<(u32, u32)>,
+    pub source_projection: ProjectionSpec,
+    pub target_projection: ProjectionSpec,
+    pub viewer: ViewerState,
+    pub sidecar: Sidecar,
+}
+
+/// The machine the app runs on. `gpu` is missing without the OpenGL backend.
+pub struct System<'a> {
+    pub gpu: Option<&'a str>,
+    pub max_texture_side: usize,
+}
+
+impl System<'_> {
+    fn text(&self) -> String {
+        let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+        let directory = |directory: Option<PathBuf>| match directory {
+            Some(directory) => directory.display().to_string(),
+            None => "none".to_string(),
+        };
+        [
+            format!("Version: submaptive-ui {}", env!("CARGO_PKG_VERSION")),
+            format!(
+                "System: {} {}",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+            format!("CPU cores: {}", cores),
+            format!("GPU: {}", self.gpu.unwrap_or("unknown")),
+            format!("Largest texture: {} px", self.max_texture_side),
+            format!(
+                "Configuration directory: {}",
+                directory(settings::config_directory())
+            ),
+            format!(
+                "Cache directory: {}",
+                directory(Some(settings::cache_directory()))
+            ),
+        ]
+        .map(|line| line + "\n")
+        .concat()
+    }
+}
+
+/// The settings with whoever connects through the proxy left out. Passwords
+/// and tokens are kept apart from the settings anyway.
+fn redacted(settings: &Settings) -> Settings {
+    let mut settings = settings.clone();
+    settings.proxy.user.clear();
+    let url = &mut settings.proxy.url;
+    if let (Some(scheme), Some(at)) = (url.find("://"), url.rfind('@')) {
+        if scheme < at {
+            url.replace_range(scheme + 3..=at, "");
+        }
+    }
+    settings
+}
+
+fn png(image: &image::DynamicImage) -> Result<Vec<u8>, String> {
+    let image = match image.width().max(image.height()) > PREVIEW_SIZE {
+        true => image.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE),
+        false => image.clone(),
+    };
+    let mut encoded = Vec::new();
+    image
+        .write_to(
+            &mut std::io::Cursor::new(&mut encoded),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(encoded)
+}
+
+/// Writes everything needed to look into a problem into a zip archive at
+/// `path`, to attach to a bug report: the log, the settings, what the app
+/// runs on, and the session with downscaled copies of `images` by name.
+pub fn write(
+    path: &Path,
+    log: &Log,
+    settings: &Settings,
+    system: &System,
+    session: &Session,
+    images: &[(&str, &image::DynamicImage)],
+) -> Result<(), String> {
+    let json = |value: Result<Vec<u8>, serde_json::Error>| value.map_err(|e| e.to_string());
+    let mut files = vec![
+        ("log.txt".to_string(), log.text().into_bytes()),
+        ("system.txt".to_string(), system.text().into_bytes()),
+        (
+            "settings.json".to_string(),
+            json(serde_json::to_vec_pretty(&redacted(settings)))?,
+        ),
+        (
+            "session.json".to_string(),
+            json(serde_json::to_vec_pretty(session))?,
+        ),
+    ];
+    for (name, image) in images {
+        files.push((format!("{}.png", name), png(image)?));
+    }
+    let files: Vec<_> = files
+        .iter()
+        .map(|(name, contents)| (name.as_str(), contents.as_slice()))
+        .collect();
+    std::fs::write(path, bundle::zip(&files)?)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
@@ -0,0 +1,211 @@
+use eframe::egui;
+
+use crate::palettes::{self, Ramp};
+use crate::ProjectionData;
+
+/// Columns of the grid distortion is sampled on; rows follow the aspect ratio.
+const GRID_COLUMNS: usize = 96;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measure {
+    /// How much larger areas are drawn than where they are drawn smallest.
+    Area,
+    /// How much the map stretches in one direction more than in the other.
+    Shape,
+}
+
+impl Measure {
+    fn name(&self) -> &'static str {
+        match self {
+            Measure::Area => "Area",
+            Measure::Shape => "Shape",
+        }
+    }
+}
+
+/// A heat map of how much the target projection distorts the globe.
+pub struct Distortion {
+    pub visible: bool,
+    pub measure: Measure,
+    pub ramp: Ramp,
+    pub opacity: f32,
+    /// The sampled grid and what it was sampled for.
+    cache: Option<(String, Grid)>,
+}
+
+impl Default for Distortion {
+    fn default() -> Self {
+        Distortion {
+            visible: false,
+            measure: Measure::Area,
+            ramp: Ramp::default(),
+            opacity: 0.6,
+            cache: None,
+        }
+    }
+}
+
+struct Grid {
+    columns: usize,
+    rows: usize,
+    /// Each cell's distortion as a power of two, where the projection shows anything.
+    values: Vec<Option<f64>>,
+    max: f64,
+}
+
+/// The semi-axes of the ellipse a pixel-sized circle at `pixel` covers on the
+/// unit sphere, i.e. the singular values of the map's local Jacobian.
+fn local_axes(
+    projection: &ProjectionData,
+    pixel: (f64, f64),
+    image_dimensions: (u32, u32),
+) -> Option<(f64, f64)> {
+    let center = projection.point_at_pixel(pixel, image_dimensions)?;
+    let right = projection.point_at_pixel((pixel.0 + 1., pixel.1), image_dimensions)?;
+    let down = projection.point_at_pixel((pixel.0, pixel.1 + 1.), image_dimensions)?;
+    let offset = |point: &submaptive::Point| {
+        let long = (point.long() - center.long() + 540.).rem_euclid(360.) - 180.;
+        (
+            long.to_radians() * center.lat().to_radians().cos(),
+            (point.lat() - center.lat()).to_radians(),
+        )
+    };
+    let ((east_x, north_x), (east_y, north_y)) = (offset(&right), offset(&down));
+    let squares = east_x * east_x + north_x * north_x + east_y * east_y + north_y * north_y;
+    let determinant = (east_x * north_y - east_y * north_x).abs();
+    if determinant < 1e-18 {
+        return None;
+    }
+    let root = (squares * squares - 4. * determinant * determinant)
+        .max(0.)
+        .sqrt();
+    Some((
+        ((squares + root) / 2.).sqrt(),
+        ((squares - root) / 2.).sqrt().max(1e-12),
+    ))
+}
+
+fn sample(projection: &ProjectionData, image_dimensions: (u32, u32), measure: Measure) -> Grid {
+    let columns = GRID_COLUMNS;
+    let rows = ((columns as f64 * image_dimensions.1 as f64 / image_dimensions.0 as f64).round()
+        as usize)
+        .max(1);
+    let cell = (
+        image_dimensions.0 as f64 / columns as f64,
+        image_dimensions.1 as f64 / rows as f64,
+    );
+    let raw: Vec<Option<f64>> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let pixel = ((column as f64 + 0.5) * cell.0, (row as f64 + 0.5) * cell.1);
+            let (major, minor) = local_axes(projection, pixel, image_dimensions)?;
+            Some(match measure {
+                // Pixels per unit of area on the globe.
+                Measure::Area => -(major * minor).log2(),
+                Measure::Shape => (major / minor).log2(),
+            })
+        })
+        .collect();
+    let min = match measure {
+        Measure::Area => raw.iter().flatten().copied().fold(f64::INFINITY, f64::min),
+        Measure::Shape => 0.,
+    };
+    let values: Vec<Option<f64>> = raw
+        .into_iter()
+        .map(|value| value.map(|value| value - min))
+        .collect();
+    let max = values.iter().flatten().copied().fold(0., f64::max);
+    Grid {
+        columns,
+        rows,
+        values,
+        max,
+    }
+}
+
+/// Draws the heat map over a map in `projection` shown in `rect`, with a
+/// legend. `key` identifies the projection and its parameters.
+pub fn paint_distortion(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    projection: &ProjectionData,
+    key: String,
+    distortion: &mut Distortion,
+) {
+    if !distortion.visible {
+        return;
+    }
+    let key = format!("{} {:?} {:?}", key, image_dimensions, distortion.measure);
+    if !matches!(&distortion.cache, Some((cached, _)) if *cached == key) {
+        let grid = sample(projection, image_dimensions, distortion.measure);
+        distortion.cache = Some((key, grid));
+    }
+    let Some((_, grid)) = &distortion.cache else {
+        return;
+    };
+    let cell = egui::vec2(
+        rect.width() / grid.columns as f32,
+        rect.height() / grid.rows as f32,
+    );
+    let mut mesh = egui::Mesh::default();
+    for (index, value) in grid.values.iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        let t = match grid.max > 1e-6 {
+            true => value / grid.max,
+            false => 0.,
+        };
+        let min = rect.min
+            + egui::vec2(
+                (index % grid.columns) as f32 * cell.x,
+                (index / grid.columns) as f32 * cell.y,
+            );
+        let color = distortion
+            .ramp
+            .color_at(t)
+            .linear_multiply(distortion.opacity);
+        mesh.add_colored_rect(egui::Rect::from_min_size(min, cell), color);
+    }
+    painter.add(mesh);
+    let legend = egui::Rect::from_min_size(
+        rect.left_bottom() + egui::vec2(10., -22.),
+        egui::vec2(120., 10.),
+    );
+    palettes::paint_ramp(painter, legend, distortion.ramp);
+    let font = egui::FontId::proportional(11.);
+    for (align, position, text) in [
+        (
+            egui::Align2::LEFT_BOTTOM,
+            legend.left_top(),
+            "×1".to_string(),
+        ),
+        (
+            egui::Align2::RIGHT_BOTTOM,
+            legend.right_top(),
+            format!("×{:.1}", 2f64.powf(grid.max)),
+        ),
+    ] {
+        painter.text(
+            position - egui::vec2(0., 2.),
+            align,
+            text,
+            font.clone(),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+pub fn distortion_ui(ui: &mut egui::Ui, distortion: &mut Distortion) {
+    ui.collapsing("Distortion", |ui| {
+        ui.checkbox(&mut distortion.visible, "Show on projected map");
+        ui.horizontal(|ui| {
+            for measure in [Measure::Area, Measure::Shape] {
+                ui.selectable_value(&mut distortion.measure, measure, measure.name());
+            }
+        });
+        palettes::ramp_ui(ui, &mut distortion.ramp, "Colors");
+        ui.add(egui::Slider::new(&mut distortion.opacity, 0.0..=1.).text("Opacity"));
+    });
+}
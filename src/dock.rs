@@ -0,0 +1,292 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// The panels of the main window, each of which can be docked to a side,
+/// floated in a window of its own, collapsed or closed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PanelKind {
+    /// Opening the source and everything about how it is projected.
+    Controls,
+    Layers,
+    /// Loading and batch conversions.
+    Tasks,
+    /// What lies under the cursor.
+    Inspector,
+    Log,
+}
+
+impl PanelKind {
+    pub const ALL: [PanelKind; 5] = [
+        PanelKind::Controls,
+        PanelKind::Layers,
+        PanelKind::Tasks,
+        PanelKind::Inspector,
+        PanelKind::Log,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            PanelKind::Controls => "Projection",
+            PanelKind::Layers => "Layers and overlays",
+            PanelKind::Tasks => "Tasks",
+            PanelKind::Inspector => "Inspector",
+            PanelKind::Log => "Log",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dock {
+    Left,
+    Right,
+    Bottom,
+    Floating,
+}
+
+impl Dock {
+    /// The id of the side panel for the dock, whose size egui keeps under it.
+    pub fn id(&self) -> Option<&'static str> {
+        match self {
+            Dock::Left => Some("Dock left"),
+            Dock::Right => Some("Dock right"),
+            Dock::Bottom => Some("Dock bottom"),
+            Dock::Floating => None,
+        }
+    }
+
+    const ALL: [Dock; 4] = [Dock::Left, Dock::Right, Dock::Bottom, Dock::Floating];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Dock::Left => "Dock left",
+            Dock::Right => "Dock right",
+            Dock::Bottom => "Dock at the bottom",
+            Dock::Floating => "Float",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Panel {
+    pub kind: PanelKind,
+    pub dock: Dock,
+    pub collapsed: bool,
+    pub open: bool,
+}
+
+/// Where the panels are, in the order they are stacked within a dock.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DockLayout {
+    pub panels: Vec<Panel>,
+}
+
+impl Default for DockLayout {
+    fn default() -> Self {
+        let panel = |kind, dock, open| Panel {
+            kind,
+            dock,
+            collapsed: false,
+            open,
+        };
+        DockLayout {
+            panels: vec![
+                panel(PanelKind::Controls, Dock::Left, true),
+                panel(PanelKind::Layers, Dock::Left, true),
+                panel(PanelKind::Tasks, Dock::Left, true),
+                panel(PanelKind::Inspector, Dock::Right, false),
+                panel(PanelKind::Log, Dock::Bottom, false),
+            ],
+        }
+    }
+}
+
+impl DockLayout {
+    /// Layouts from older versions may lack panels added since.
+    fn panel_mut(&mut self, kind: PanelKind) -> &mut Panel {
+        match self.panels.iter().position(|panel| panel.kind == kind) {
+            Some(index) => &mut self.panels[index],
+            None => {
+                let panel = DockLayout::default()
+                    .panels
+                    .into_iter()
+                    .find(|panel| panel.kind == kind)
+                    .unwrap();
+                self.panels.push(panel);
+                self.panels.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub fn is_open(&self, kind: PanelKind) -> bool {
+        self.panels
+            .iter()
+            .any(|panel| panel.kind == kind && panel.open)
+    }
+
+    /// Opens a closed panel, expanded, and closes an open one.
+    pub fn toggle(&mut self, kind: PanelKind) {
+        let panel = self.panel_mut(kind);
+        panel.open = !panel.open;
+        panel.collapsed = false;
+    }
+
+    fn apply(&mut self, index: usize, action: Action) {
+        match action {
+            Action::Collapse => self.panels[index].collapsed = !self.panels[index].collapsed,
+            Action::Close => self.panels[index].open = false,
+            Action::Dock(dock) => {
+                // Moved to the end of its new dock.
+                let mut panel = self.panels.remove(index);
+                panel.dock = dock;
+                self.panels.push(panel);
+            }
+            Action::Shift(up) => {
+                let dock = self.panels[index].dock;
+                let neighbor = match up {
+                    true => (0..index)
+                        .rev()
+                        .find(|&other| self.panels[other].dock == dock),
+                    false => (index + 1..self.panels.len())
+                        .find(|&other| self.panels[other].dock == dock),
+                };
+                if let Some(neighbor) = neighbor {
+                    self.panels.swap(index, neighbor);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Action {
+    Collapse,
+    Close,
+    Dock(Dock),
+    /// Towards the top (or left, at the bottom) if true.
+    Shift(bool),
+}
+
+/// The title bar of a docked panel, and the menu that moves it.
+fn header(ui: &mut egui::Ui, panel: &Panel, title: bool) -> Option<Action> {
+    let mut action = None;
+    ui.horizontal(|ui| {
+        if title {
+            let arrow = match panel.collapsed {
+                true => "⏵",
+                false => "⏷",
+            };
+            if ui.small_button(arrow).clicked() {
+                action = Some(Action::Collapse);
+            }
+            ui.strong(panel.kind.title());
+        }
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            // Floating windows have a close button of their own.
+            if title && ui.small_button("✖").clicked() {
+                action = Some(Action::Close);
+            }
+            ui.menu_button("☰", |ui| {
+                for dock in Dock::ALL.into_iter().filter(|dock| *dock != panel.dock) {
+                    if ui.button(dock.name()).clicked() {
+                        action = Some(Action::Dock(dock));
+                        ui.close_menu();
+                    }
+                }
+                if panel.dock != Dock::Floating {
+                    ui.separator();
+                    for (text, up) in [("Move up", true), ("Move down", false)] {
+                        if ui.button(text).clicked() {
+                            action = Some(Action::Shift(up));
+                            ui.close_menu();
+                        }
+                    }
+                }
+            });
+        });
+    });
+    action
+}
+
+/// Shows the open panels where they are docked, each filled in by
+/// `contents`. Must come before the central panel, which takes what is left.
+pub fn show(
+    ctx: &egui::Context,
+    layout: &mut DockLayout,
+    mut contents: impl FnMut(&mut egui::Ui, PanelKind),
+) {
+    let mut actions = Vec::new();
+    let docked = |layout: &DockLayout, dock| -> Vec<usize> {
+        (0..layout.panels.len())
+            .filter(|&index| layout.panels[index].open && layout.panels[index].dock == dock)
+            .collect()
+    };
+    let mut stack = |ui: &mut egui::Ui, index: usize, panel: &Panel| {
+        ui.push_id(panel.kind, |ui| {
+            if let Some(action) = header(ui, panel, true) {
+                actions.push((index, action));
+            }
+            if !panel.collapsed {
+                contents(ui, panel.kind);
+            }
+        });
+    };
+    let bottom = docked(layout, Dock::Bottom);
+    if !bottom.is_empty() {
+        egui::TopBottomPanel::bottom(Dock::Bottom.id().unwrap())
+            .resizable(true)
+            .height_range(60.0..=800.0)
+            .show(ctx, |ui| {
+                ui.columns(bottom.len(), |columns| {
+                    for (ui, &index) in columns.iter_mut().zip(&bottom) {
+                        egui::ScrollArea::vertical()
+                            .id_source(index)
+                            .show(ui, |ui| stack(ui, index, &layout.panels[index]));
+                    }
+                });
+            });
+    }
+    for dock in [Dock::Left, Dock::Right] {
+        let indices = docked(layout, dock);
+        if indices.is_empty() {
+            continue;
+        }
+        let side = match dock {
+            Dock::Left => egui::panel::Side::Left,
+            _ => egui::panel::Side::Right,
+        };
+        egui::SidePanel::new(side, dock.id().unwrap())
+            .width_range(100.0..=1000.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (position, &index) in indices.iter().enumerate() {
+                        if position > 0 {
+                            ui.separator();
+                        }
+                        stack(ui, index, &layout.panels[index]);
+                    }
+                });
+            });
+    }
+    for index in docked(layout, Dock::Floating) {
+        let panel = layout.panels[index];
+        let mut open = true;
+        egui::Window::new(panel.kind.title())
+            .id(egui::Id::new(("Floating", panel.kind)))
+            .open(&mut open)
+            .vscroll(true)
+            .show(ctx, |ui| {
+                if let Some(action) = header(ui, &panel, false) {
+                    actions.push((index, action));
+                }
+                contents(ui, panel.kind);
+            });
+        if !open {
+            actions.push((index, Action::Close));
+        }
+    }
+    // Applied after drawing, so that indices stay valid.
+    if let Some(&(index, action)) = actions.first() {
+        layout.apply(index, action);
+    }
+}
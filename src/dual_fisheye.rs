@@ -0,0 +1,128 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::leveling::direction;
+use crate::preprocess::sample_bilinear;
+
+/// One of the two circular images, in fractions of its half of the frame.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lens {
+    pub center: (f64, f64),
+    /// Radius of the image circle, relative to the height of the frame.
+    pub radius: f64,
+}
+
+impl Default for Lens {
+    fn default() -> Self {
+        Lens {
+            center: (0.5, 0.5),
+            radius: 0.5,
+        }
+    }
+}
+
+/// Side-by-side images of two opposite (equidistant) fisheye lenses, as
+/// consumer 360° cameras record them. The left lens looks forward.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DualFisheye {
+    /// The field of view of each lens in degrees, a little over 180 so the images overlap.
+    pub fov: f64,
+    pub front: Lens,
+    pub back: Lens,
+}
+
+impl Default for DualFisheye {
+    fn default() -> Self {
+        DualFisheye {
+            fov: 190.,
+            front: Default::default(),
+            back: Default::default(),
+        }
+    }
+}
+
+impl DualFisheye {
+    /// The size of the panorama stitched from a frame of `frame_dimensions`.
+    pub fn panorama_dimensions(&self, frame_dimensions: (u32, u32)) -> (u32, u32) {
+        (2 * frame_dimensions.1, frame_dimensions.1)
+    }
+
+    /// Stitches the two images into an equirectangular panorama as high as the frame.
+    pub fn stitch(&self, image: &image::DynamicImage) -> image::DynamicImage {
+        let frame = image.to_rgba8();
+        let (width, height) = frame.dimensions();
+        let half = width as f64 / 2.;
+        let max_angle = self.fov.to_radians() / 2.;
+        let output = self.panorama_dimensions((width, height));
+        let sample = |lens: &Lens, offset: f64, angle: f64, azimuth: f64| {
+            let r = angle / max_angle * lens.radius * height as f64;
+            let x = offset + lens.center.0 * half + r * azimuth.cos();
+            let y = lens.center.1 * height as f64 - r * azimuth.sin();
+            sample_bilinear(&frame, x - 0.5, y - 0.5)
+                .0
+                .map(|channel| channel as f64)
+        };
+        let panorama = image::RgbaImage::from_fn(output.0, output.1, |x, y| {
+            let [dx, dy, dz] = direction((x as f64 + 0.5, y as f64 + 0.5), output);
+            let (front_angle, back_angle) = (dx.clamp(-1., 1.).acos(), (-dx).clamp(-1., 1.).acos());
+            // Seen through the back lens, left and right are swapped.
+            let front = (front_angle < max_angle)
+                .then(|| sample(&self.front, 0., front_angle, dz.atan2(dy)));
+            let back = (back_angle < max_angle)
+                .then(|| sample(&self.back, half, back_angle, dz.atan2(-dy)));
+            let value = match (front, back) {
+                (Some(front), Some(back)) => {
+                    // Cross-fade over the overlap, by how far each is from its image's edge.
+                    let (a, b) = (max_angle - front_angle, max_angle - back_angle);
+                    let t = b / (a + b);
+                    std::array::from_fn(|i| front[i] * (1. - t) + back[i] * t)
+                }
+                (Some(only), None) | (None, Some(only)) => only,
+                (None, None) => [0.; 4],
+            };
+            image::Rgba(value.map(|channel| channel.round() as u8))
+        });
+        image::DynamicImage::ImageRgba8(panorama)
+    }
+}
+
+fn lens_ui(ui: &mut egui::Ui, lens: &mut Lens, label: &str) {
+    ui.label(label);
+    ui.add(egui::Slider::new(&mut lens.center.0, 0.3..=0.7).text("Center x"));
+    ui.add(egui::Slider::new(&mut lens.center.1, 0.3..=0.7).text("Center y"));
+    ui.add(egui::Slider::new(&mut lens.radius, 0.3..=0.7).text("Radius"));
+}
+
+/// Returns whether the source should be (re)converted with the settings.
+pub fn dual_fisheye_ui(
+    ui: &mut egui::Ui,
+    fisheye: &mut Option<DualFisheye>,
+    unit: AngleUnit,
+) -> bool {
+    let mut convert = false;
+    ui.collapsing("Dual fisheye", |ui| {
+        let mut enabled = fisheye.is_some();
+        if ui
+            .checkbox(&mut enabled, "Source is a dual-fisheye image")
+            .changed()
+        {
+            *fisheye = enabled.then(Default::default);
+            convert = true;
+        }
+        if let Some(fisheye) = fisheye {
+            ui.add(
+                egui::Slider::new(&mut fisheye.fov, 180.0..=230.)
+                    .angle(unit)
+                    .text("Lens field of view"),
+            );
+            lens_ui(ui, &mut fisheye.front, "Front (left) lens");
+            lens_ui(ui, &mut fisheye.back, "Back (right) lens");
+            if ui.button("Convert to equirectangular").clicked() {
+                convert = true;
+            }
+        }
+    });
+    convert
+}
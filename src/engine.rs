@@ -0,0 +1,294 @@
+use crate::cache::Cache;
+use crate::color_keys::ColorKeys;
+use crate::georeference::{self, ControlPoint, Georeference};
+use crate::leveling::Orientation;
+use crate::mask::Mask;
+use crate::polar_fill::PolarFill;
+use crate::preprocess::Preprocessing;
+use crate::sampling::{self, Sampling};
+use crate::sidecar::Sidecar;
+use crate::ProjectionData;
+
+/// What is done to a source map before it is projected, in the order it is
+/// done: photo corrections, the mask, keying out the no-data color, leveling
+/// and filling in the poles.
+#[derive(Clone, Default)]
+pub struct Corrections {
+    /// Lens distortion and keystone, undone first, as everything else is
+    /// placed on the corrected source.
+    pub preprocessing: Preprocessing,
+    /// Areas drawn on the corrected source to leave out.
+    pub mask: Mask,
+    /// Only the no-data color is used here; the background is for exports.
+    pub color_keys: ColorKeys,
+    /// Turns a panorama taken with a tilted camera level.
+    pub orientation: Orientation,
+    /// Fills in the caps of the leveled panorama.
+    pub polar_fill: PolarFill,
+}
+
+impl Corrections {
+    /// The corrections saved with a source map.
+    pub fn from_sidecar(sidecar: &Sidecar) -> Self {
+        Corrections {
+            preprocessing: sidecar.preprocessing,
+            mask: sidecar.mask.clone(),
+            color_keys: sidecar.color_keys,
+            orientation: sidecar.orientation,
+            polar_fill: sidecar.polar_fill,
+        }
+    }
+
+    /// Applies the corrections to `image`, which may be a reduced copy of the
+    /// source of `full_dimensions` the mask was drawn on.
+    pub fn apply(
+        &self,
+        image: &image::DynamicImage,
+        full_dimensions: (u32, u32),
+    ) -> image::DynamicImage {
+        self.apply_after_preprocessing(self.preprocessing.apply(image), full_dimensions)
+    }
+
+    /// Like `apply` for the full source, reusing the photo-corrected copy from
+    /// the cache when `content_key` identifies the source.
+    pub fn apply_cached(
+        &self,
+        image: &image::DynamicImage,
+        cache: &Cache,
+        content_key: Option<&str>,
+    ) -> image::DynamicImage {
+        let full_dimensions = (image.width(), image.height());
+        let corrected = self.preprocessing.apply_cached(image, cache, content_key);
+        self.apply_after_preprocessing(corrected, full_dimensions)
+    }
+
+    fn apply_after_preprocessing(
+        &self,
+        image: image::DynamicImage,
+        full_dimensions: (u32, u32),
+    ) -> image::DynamicImage {
+        let image = self.mask.apply(image, full_dimensions);
+        let image = self.orientation.apply(self.color_keys.key_out(image));
+        self.polar_fill.apply(image)
+    }
+}
+
+/// Fits a rubber sheet to the control points of a source of `dimensions`;
+/// other projections are returned as they are.
+pub fn fit(
+    projection: ProjectionData,
+    control_points: &[ControlPoint],
+    dimensions: (u32, u32),
+) -> Result<ProjectionData, String> {
+    let ProjectionData::RubberSheet(_) = projection else {
+        return Ok(projection);
+    };
+    let mut georeference = Georeference::default();
+    georeference.control_points = control_points.to_vec();
+    let sheet = georeference.rubber_sheet(dimensions);
+    match sheet.is_fitted() {
+        true => Ok(ProjectionData::RubberSheet(sheet)),
+        false => Err(georeference::NOT_FITTED.to_string()),
+    }
+}
+
+/// Projects a prepared source from `source` into `target`, as wide as
+/// `sampling` says. At the width of the source, sampling and interpolation
+/// are up to `submaptive`. Pixels that show nothing, such as keyed out or
+/// masked ones, stay transparent.
+pub fn reproject(
+    prepared: image::DynamicImage,
+    source: &ProjectionData,
+    target: &ProjectionData,
+    sampling: &Sampling,
+) -> Result<image::DynamicImage, String> {
+    let unfitted = [source, target].into_iter().any(
+        |projection| matches!(projection, ProjectionData::RubberSheet(sheet) if !sheet.is_fitted()),
+    );
+    if unfitted {
+        return Err(georeference::NOT_FITTED.to_string());
+    }
+    if !sampling.is_direct(prepared.width()) {
+        let dimensions = sampling.dimensions((prepared.width(), prepared.height()), target);
+        return Ok(sampling::resample(
+            &prepared, source, target, dimensions, sampling,
+        ));
+    }
+    Ok(submaptive::Map::new(prepared, source.clone())
+        .convert_to(target.clone())
+        .to_image())
+}
+
+/// Which pixels of a projected map show something of the source: white where
+/// they do and black where they stayed transparent.
+pub fn validity_mask(projected: &image::DynamicImage) -> image::GrayImage {
+    let projected = projected.to_rgba8();
+    image::GrayImage::from_fn(projected.width(), projected.height(), |x, y| {
+        match projected.get_pixel(x, y)[3] > 0 {
+            true => image::Luma([255]),
+            false => image::Luma([0]),
+        }
+    })
+}
+
+/// `map.jpg` keeps its validity mask in `map-mask.png`. Always a PNG, so that
+/// it stays exact.
+pub fn mask_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut file_name = path.file_stem().unwrap_or_default().to_os_string();
+    file_name.push("-mask.png");
+    path.with_file_name(file_name)
+}
+
+/// Saves the validity mask of `projected` next to where the map goes.
+pub fn save_validity_mask(
+    projected: &image::DynamicImage,
+    path: &std::path::Path,
+) -> Result<(), String> {
+    let path = mask_path(path);
+    validity_mask(projected)
+        .save(&path)
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProjectionKind;
+
+    fn control_point(pixel: (f64, f64), long: f64, lat: f64) -> ControlPoint {
+        ControlPoint {
+            pixel,
+            long,
+            lat,
+            enabled: true,
+        }
+    }
+
+    fn rubber_sheet() -> ProjectionData {
+        ProjectionKind::RubberSheet.default_projection_data()
+    }
+
+    /// `width` × `height` pixels of one opaque color.
+    fn plain(width: u32, height: u32, color: [u8; 3]) -> image::DynamicImage {
+        let [red, green, blue] = color;
+        let pixel = image::Rgba([red, green, blue, 255]);
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(width, height, pixel))
+    }
+
+    fn alpha(image: &image::DynamicImage, x: u32, y: u32) -> u8 {
+        image.to_rgba8().get_pixel(x, y)[3]
+    }
+
+    #[test]
+    fn fit_leaves_other_projections_alone() {
+        let projection = ProjectionKind::Mollweide.default_projection_data();
+        let fitted = fit(projection, &[], (64, 32)).unwrap();
+        assert!(matches!(fitted, ProjectionData::Mollweide(_)));
+    }
+
+    #[test]
+    fn fit_fits_a_rubber_sheet_to_enough_control_points() {
+        let points = [
+            control_point((0., 0.), -180., 90.),
+            control_point((64., 0.), 180., 90.),
+            control_point((0., 32.), -180., -90.),
+            control_point((64., 32.), 180., -90.),
+        ];
+        match fit(rubber_sheet(), &points, (64, 32)) {
+            Ok(ProjectionData::RubberSheet(sheet)) => assert!(sheet.is_fitted()),
+            Ok(_) => panic!("not a rubber sheet"),
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    #[test]
+    fn fit_refuses_too_few_or_collinear_control_points() {
+        let two = [
+            control_point((0., 0.), -180., 90.),
+            control_point((64., 32.), 180., -90.),
+        ];
+        let collinear = [
+            control_point((0., 0.), -180., 90.),
+            control_point((32., 16.), 0., 0.),
+            control_point((64., 32.), 180., -90.),
+        ];
+        // Only the enabled points count, and without the fourth these lie on a line.
+        let mut disabled = collinear.to_vec();
+        disabled.push(ControlPoint {
+            enabled: false,
+            ..control_point((64., 0.), 180., 90.)
+        });
+        for points in [&two[..], &collinear, &disabled] {
+            let error = fit(rubber_sheet(), points, (64, 32)).err();
+            assert_eq!(error.as_deref(), Some(georeference::NOT_FITTED));
+        }
+    }
+
+    #[test]
+    fn reproject_refuses_an_unfitted_rubber_sheet() {
+        let equirectangular = ProjectionKind::Equirectangular.default_projection_data();
+        let image = plain(64, 32, [0, 0, 255]);
+        let sampling = Sampling::default();
+        for (source, target) in [
+            (&rubber_sheet(), &equirectangular),
+            (&equirectangular, &rubber_sheet()),
+        ] {
+            let error = reproject(image.clone(), source, target, &sampling).err();
+            assert_eq!(error.as_deref(), Some(georeference::NOT_FITTED));
+        }
+    }
+
+    #[test]
+    fn mask_and_no_data_are_applied_before_leveling() {
+        // Red on the left, so that it is keyed out, and the quarter next to
+        // it masked.
+        let mut image = plain(64, 32, [0, 0, 255]).to_rgba8();
+        for (x, y) in (0..16).flat_map(|x| (0..32).map(move |y| (x, y))) {
+            image.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+        }
+        let corrections = Corrections {
+            mask: Mask {
+                polygons: vec![vec![(16., 0.), (32., 0.), (32., 32.), (16., 32.)]],
+            },
+            color_keys: ColorKeys {
+                no_data: Some([255, 0, 0]),
+                ..Default::default()
+            },
+            // Half way round, which moves the left half of the source to the right.
+            orientation: Orientation {
+                yaw: 180.,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let corrected = corrections.apply(&image::DynamicImage::ImageRgba8(image), (64, 32));
+        for x in [4, 12, 20, 28] {
+            assert_eq!(alpha(&corrected, x, 16), 255, "at {}", x);
+            assert_eq!(alpha(&corrected, x + 32, 16), 0, "at {}", x + 32);
+        }
+    }
+
+    #[test]
+    fn poles_are_filled_after_leveling() {
+        // A missing zenith, turned down to the equator by the leveling, where
+        // the fill doesn't reach it.
+        let mut image = plain(64, 32, [0, 0, 255]).to_rgba8();
+        for (x, y) in (0..64).flat_map(|x| (0..4).map(move |y| (x, y))) {
+            image.put_pixel(x, y, image::Rgba([0, 0, 0, 0]));
+        }
+        let corrections = Corrections {
+            orientation: Orientation {
+                pitch: 90.,
+                ..Default::default()
+            },
+            polar_fill: PolarFill {
+                zenith: 45.,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let corrected = corrections.apply(&image::DynamicImage::ImageRgba8(image), (64, 32));
+        assert!((0..64).all(|x| alpha(&corrected, x, 0) == 255));
+        assert!(corrected.to_rgba8().pixels().any(|pixel| pixel[3] == 0));
+    }
+}
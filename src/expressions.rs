@@ -0,0 +1,215 @@
+/// Evaluates a simple arithmetic expression as typed into a parameter field,
+/// such as `-(75+30/60)` or `mean(20, 60)`: numbers, `+ - * / ^`, brackets,
+/// `pi` and the functions `mean`, `min`, `max`, `abs` and `sqrt`. Anything
+/// else, or a result that isn't a finite number, gives `None`.
+pub fn evaluate(text: &str) -> Option<f64> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        position: 0,
+    };
+    let value = parser.sum()?;
+    match parser.peek().is_none() && value.is_finite() {
+        true => Some(value),
+        false => None,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl Parser {
+    /// The next character, within a number or name.
+    fn current(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    /// The next character after any space, which only separates numbers,
+    /// names and operators.
+    fn peek(&mut self) -> Option<char> {
+        while self.current().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+        self.current()
+    }
+
+    /// Takes `c` if it comes next.
+    fn eat(&mut self, c: char) -> bool {
+        let next = self.peek() == Some(c);
+        self.position += next as usize;
+        next
+    }
+
+    fn sum(&mut self) -> Option<f64> {
+        let mut value = self.product()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.position += 1;
+                    value += self.product()?;
+                }
+                Some('-' | '−') => {
+                    self.position += 1;
+                    value -= self.product()?;
+                }
+                _ => return Some(value),
+            }
+        }
+    }
+
+    fn product(&mut self) -> Option<f64> {
+        let mut value = self.factor()?;
+        loop {
+            match self.peek() {
+                Some('*' | '×') => {
+                    self.position += 1;
+                    value *= self.factor()?;
+                }
+                Some('/' | '÷') => {
+                    self.position += 1;
+                    value /= self.factor()?;
+                }
+                _ => return Some(value),
+            }
+        }
+    }
+
+    /// A signed power. `-2^2` is -4, as written in the literature.
+    fn factor(&mut self) -> Option<f64> {
+        match self.peek()? {
+            '-' | '−' => {
+                self.position += 1;
+                self.factor().map(|value| -value)
+            }
+            '+' => {
+                self.position += 1;
+                self.factor()
+            }
+            _ => {
+                let base = self.atom()?;
+                match self.eat('^') {
+                    true => Some(base.powf(self.factor()?)),
+                    false => Some(base),
+                }
+            }
+        }
+    }
+
+    fn atom(&mut self) -> Option<f64> {
+        let next = self.peek()?;
+        if self.eat('(') {
+            let value = self.sum()?;
+            return self.eat(')').then_some(value);
+        }
+        if next.is_ascii_digit() || next == '.' {
+            return self.number();
+        }
+        let start = self.position;
+        while self.current().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.position += 1;
+        }
+        let name: String = self.chars[start..self.position].iter().collect();
+        if name == "pi" {
+            return Some(std::f64::consts::PI);
+        }
+        if !self.eat('(') {
+            return None;
+        }
+        let mut arguments = vec![self.sum()?];
+        while self.eat(',') {
+            arguments.push(self.sum()?);
+        }
+        if !self.eat(')') {
+            return None;
+        }
+        let only = |arguments: &[f64]| match arguments {
+            [argument] => Some(*argument),
+            _ => None,
+        };
+        match name.as_str() {
+            "mean" => Some(arguments.iter().sum::<f64>() / arguments.len() as f64),
+            "min" => arguments.into_iter().reduce(f64::min),
+            "max" => arguments.into_iter().reduce(f64::max),
+            "abs" => only(&arguments).map(f64::abs),
+            "sqrt" => only(&arguments).map(f64::sqrt),
+            _ => None,
+        }
+    }
+
+    /// Digits with an optional fraction and exponent, such as `1.5e-3`.
+    fn number(&mut self) -> Option<f64> {
+        let start = self.position;
+        let digits = |parser: &mut Parser| {
+            while parser.current().is_some_and(|c| c.is_ascii_digit()) {
+                parser.position += 1;
+            }
+        };
+        digits(self);
+        if self.current() == Some('.') {
+            self.position += 1;
+            digits(self);
+        }
+        if matches!(self.current(), Some('e' | 'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if matches!(self.current(), Some('-' | '+')) {
+                self.position += 1;
+            }
+            let exponent_start = self.position;
+            digits(self);
+            // An `e` without digits isn't part of the number.
+            if self.position == exponent_start {
+                self.position = mantissa_end;
+            }
+        }
+        let text: String = self.chars[start..self.position].iter().collect();
+        text.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::evaluate;
+    use crate::angles::AngleUnit;
+
+    #[test]
+    fn parameters_are_read_as_the_literature_gives_them() {
+        for (text, expected) in [
+            ("-(75+30/60)", -75.5),
+            ("mean(20,60)", 40.),
+            ("2^3^2 / 64", 8.),
+            ("-2^2", -4.),
+            ("max(1, 2.5e1, -3)", 25.),
+            ("sqrt(2) * sqrt(2)", 2.),
+        ] {
+            match evaluate(text) {
+                Some(value) => assert!((value - expected).abs() < 1e-12, "{} is {}", text, value),
+                None => panic!("{} isn't read", text),
+            }
+        }
+    }
+
+    #[test]
+    fn text_that_isnt_an_expression_has_no_value() {
+        for text in ["1/0", "(1 + 2", "abs(1, 2)", "3 4"] {
+            assert_eq!(evaluate(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn angles_are_still_read_as_degrees_minutes_and_seconds_first() {
+        for (unit, text, expected) in [
+            (AngleUnit::Dms, "12 30 W", -12.5),
+            (AngleUnit::Degrees, "-(75+30/60)", -75.5),
+            (AngleUnit::Radians, "pi/2", 90.),
+        ] {
+            match unit.parse(text) {
+                Some(degrees) => {
+                    assert!((degrees - expected).abs() < 1e-9, "{} is {}", text, degrees)
+                }
+                None => panic!("{} isn't read", text),
+            }
+        }
+    }
+}
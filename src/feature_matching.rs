@@ -0,0 +1,625 @@
+use eframe::egui;
+use submaptive::Projection;
+
+use std::sync::Arc;
+
+use crate::angles::AngleUnit;
+use crate::jobs::{Job, Progress};
+use crate::{projections, ProjectionData};
+
+/// Resolution of the geographic grid the reference edges are rasterized to.
+const REFERENCE_WIDTH: u32 = 720;
+const REFERENCE_HEIGHT: u32 = 360;
+/// The side length edges are detected at, and how many of them are matched.
+pub const ANALYSIS_SIZE: u32 = 1024;
+const MAX_EDGE_SAMPLES: usize = 4000;
+/// Share of the strongest gradients considered to be edges.
+const EDGE_FRACTION: f64 = 0.08;
+/// The pattern search starts with steps of this many degrees and halves them
+/// until they are smaller than the last.
+const FIRST_STEP: f64 = 8.;
+const LAST_STEP: f64 = 0.05;
+
+/// Estimates the source projection's parameters by aligning edges (mostly
+/// coastlines) of the scan with the edges of an equirectangular reference map.
+#[derive(Default)]
+pub struct FeatureMatching {
+    reference: Option<Arc<DistanceField>>,
+    pub score: Option<f64>,
+    pub error: Option<String>,
+    /// The refinement running in the background.
+    job: Option<Job<Result<(ProjectionData, f64), String>>>,
+}
+
+/// For every cell of a grid, the distance (in cells) to the nearest edge.
+pub struct DistanceField {
+    width: usize,
+    height: usize,
+    distances: Vec<f32>,
+}
+
+impl DistanceField {
+    pub fn from_edges(image: &image::GrayImage) -> Self {
+        let edges = edge_map(image);
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut distances: Vec<f32> = edges
+            .iter()
+            .map(|&edge| if edge { 0. } else { f32::INFINITY })
+            .collect();
+        // Two-pass 3-4 chamfer distance transform.
+        let neighbours_forward = [(-1, 0, 3.), (-1, -1, 4.), (0, -1, 3.), (1, -1, 4.)];
+        let neighbours_backward = [(1, 0, 3.), (1, 1, 4.), (0, 1, 3.), (-1, 1, 4.)];
+        relax(
+            &mut distances,
+            width,
+            0..width * height,
+            &neighbours_forward,
+        );
+        relax(
+            &mut distances,
+            width,
+            (0..width * height).rev(),
+            &neighbours_backward,
+        );
+        DistanceField {
+            width,
+            height,
+            distances: distances.into_iter().map(|d| d / 3.).collect(),
+        }
+    }
+
+    /// The reference edges of a global equirectangular map.
+    fn from_equirectangular(image: &image::DynamicImage) -> Self {
+        let image = image.resize_exact(
+            REFERENCE_WIDTH,
+            REFERENCE_HEIGHT,
+            image::imageops::FilterType::Triangle,
+        );
+        Self::from_edges(&image.to_luma8())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The distance at a position given in cells, clamped to the grid.
+    pub fn distance_at_cell(&self, x: f64, y: f64) -> f64 {
+        let x = (x.max(0.) as usize).min(self.width - 1);
+        let y = (y.max(0.) as usize).min(self.height - 1);
+        self.distances[y * self.width + x] as f64
+    }
+
+    fn distance_at(&self, point: &submaptive::Point) -> f64 {
+        self.distance_at_cell(
+            (point.long() + 180.) / 360. * self.width as f64,
+            (90. - point.lat()) / 180. * self.height as f64,
+        )
+    }
+}
+
+/// One pass of the chamfer distance transform over the cells in `order`.
+fn relax(
+    distances: &mut [f32],
+    width: usize,
+    order: impl Iterator<Item = usize>,
+    offsets: &[(isize, isize, f32)],
+) {
+    let height = distances.len() / width;
+    for index in order {
+        let (x, y) = ((index % width) as isize, (index / width) as isize);
+        for &(dx, dy, cost) in offsets {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                continue;
+            }
+            let neighbour = distances[ny as usize * width + nx as usize] + cost;
+            if neighbour < distances[index] {
+                distances[index] = neighbour;
+            }
+        }
+    }
+}
+
+/// Marks the pixels with the strongest Sobel gradient magnitude.
+fn edge_map(luma: &image::GrayImage) -> Vec<bool> {
+    let (width, height) = (luma.width() as i64, luma.height() as i64);
+    let at = |x: i64, y: i64| {
+        luma.get_pixel(x.clamp(0, width - 1) as u32, y.clamp(0, height - 1) as u32)
+            .0[0] as f64
+    };
+    let magnitudes: Vec<f64> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let gx = at(x + 1, y - 1) + 2. * at(x + 1, y) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2. * at(x - 1, y)
+                - at(x - 1, y + 1);
+            let gy = at(x - 1, y + 1) + 2. * at(x, y + 1) + at(x + 1, y + 1)
+                - at(x - 1, y - 1)
+                - 2. * at(x, y - 1)
+                - at(x + 1, y - 1);
+            (gx * gx + gy * gy).sqrt()
+        })
+        .collect();
+    let mut sorted = magnitudes.clone();
+    sorted.sort_unstable_by(f64::total_cmp);
+    let threshold = sorted[((1. - EDGE_FRACTION) * (sorted.len() - 1) as f64) as usize].max(1.);
+    magnitudes.into_iter().map(|m| m > threshold).collect()
+}
+
+/// Evenly thinned out edge pixels of the image, in full-resolution pixel coordinates.
+fn edge_pixels(image: &image::DynamicImage) -> Vec<(f64, f64)> {
+    let analysed = image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+    let scale_x = image.width() as f64 / analysed.width() as f64;
+    let scale_y = image.height() as f64 / analysed.height() as f64;
+    let width = analysed.width() as usize;
+    let edges: Vec<_> = edge_map(&analysed)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, edge)| *edge)
+        .map(|(index, _)| {
+            (
+                ((index % width) as f64 + 0.5) * scale_x,
+                ((index / width) as f64 + 0.5) * scale_y,
+            )
+        })
+        .collect();
+    let step = (edges.len() / MAX_EDGE_SAMPLES).max(1);
+    edges.into_iter().step_by(step).collect()
+}
+
+/// Mean distance between the scan's edges, placed on the globe by
+/// `projection`, and the nearest reference edge. Lower is better.
+fn score(
+    projection: &ProjectionData,
+    edges: &[(f64, f64)],
+    image_dimensions: (u32, u32),
+    reference: &DistanceField,
+) -> f64 {
+    let dimensions = projection.dimensions();
+    let (width, height) = (image_dimensions.0 as f64, image_dimensions.1 as f64);
+    let mut total = 0.;
+    let mut count = 0;
+    for (x, y) in edges {
+        let projected = (
+            (x / width - 0.5) * dimensions.width(),
+            (0.5 - y / height) * dimensions.height(),
+        );
+        if !projection.projected_point_within_bounds(projected) {
+            continue;
+        }
+        total += reference.distance_at(&projection.invert(projected));
+        count += 1;
+    }
+    if count < edges.len() / 4 {
+        f64::INFINITY
+    } else {
+        total / count as f64
+    }
+}
+
+/// The parameters the search may adjust, in degrees.
+pub fn parameters(projection: &ProjectionData) -> Option<Vec<f64>> {
+    match projection {
+        ProjectionData::Equirectangular(data) => {
+            Some(vec![data.central_long(), data.true_scale_lat()])
+        }
+        ProjectionData::AzimuthalEquidistant(data) => Some(vec![
+            data.center().long(),
+            data.center().lat(),
+            data.central_long(),
+        ]),
+        ProjectionData::Mercator(data) => Some(vec![data.central_long, data.max_lat]),
+        ProjectionData::TransverseMercator(data) => {
+            Some(vec![data.central_long, data.origin_lat, data.scale_factor])
+        }
+        ProjectionData::WebMercator(_) => Some(Vec::new()),
+        ProjectionData::Mollweide(data) => Some(vec![data.central_long]),
+        ProjectionData::Robinson(data) => Some(vec![data.central_long]),
+        ProjectionData::WinkelTripel(data) => Some(vec![data.central_long, data.standard_parallel]),
+        ProjectionData::Sinusoidal(data) => Some(vec![data.central_long]),
+        ProjectionData::CylindricalEqualArea(data) => {
+            Some(vec![data.central_long, data.standard_parallel])
+        }
+        ProjectionData::LambertConformalConic(data) => Some(vec![
+            data.central_long,
+            data.origin_lat,
+            data.standard_parallels[0],
+            data.standard_parallels[1],
+        ]),
+        ProjectionData::AlbersEqualArea(data) => Some(vec![
+            data.central_long,
+            data.standard_parallels[0],
+            data.standard_parallels[1],
+        ]),
+        ProjectionData::EquidistantConic(data) => Some(vec![
+            data.central_long,
+            data.standard_parallels[0],
+            data.standard_parallels[1],
+        ]),
+        ProjectionData::Orthographic(data) => Some(vec![data.central_long, data.center_lat]),
+        ProjectionData::Stereographic(data) => Some(vec![data.central_long, data.center_lat]),
+        ProjectionData::Gnomonic(data) => Some(vec![data.central_long, data.center_lat, data.clip]),
+        ProjectionData::LambertAzimuthalEqualArea(data) => {
+            Some(vec![data.central_long, data.center_lat])
+        }
+        ProjectionData::Hammer(data) => Some(vec![data.central_long]),
+        ProjectionData::Aitoff(data) => Some(vec![data.central_long]),
+        ProjectionData::EckertIV(data) => Some(vec![data.central_long]),
+        ProjectionData::EckertVI(data) => Some(vec![data.central_long]),
+        ProjectionData::GoodeHomolosine(data) => {
+            Some(vec![data.central_long, data.interruptions.parameter()])
+        }
+        ProjectionData::Bonne(data) => Some(vec![data.central_long, data.standard_parallel]),
+        ProjectionData::Werner(data) => Some(vec![data.central_long]),
+        ProjectionData::VanDerGrinten(data) => Some(vec![data.central_long]),
+        ProjectionData::Miller(data) => Some(vec![data.central_long]),
+        ProjectionData::Cassini(data) => Some(vec![data.central_long]),
+        ProjectionData::Polyconic(data) => Some(vec![data.central_long, data.origin_lat]),
+        ProjectionData::NaturalEarth(data) => Some(vec![data.central_long]),
+        ProjectionData::KavrayskiyVII(data) => Some(vec![data.central_long]),
+        ProjectionData::RubberSheet(_) => None,
+    }
+}
+
+/// What manifests and commands call the `parameters`, in the same order.
+pub fn parameter_names(projection: &ProjectionData) -> Option<&'static [&'static str]> {
+    const CENTRAL_LONG: &[&str] = &["central_long"];
+    const STANDARD_PARALLEL: &[&str] = &["central_long", "standard_parallel"];
+    const STANDARD_PARALLELS: &[&str] =
+        &["central_long", "standard_parallel_1", "standard_parallel_2"];
+    const CENTER: &[&str] = &["central_long", "center_lat"];
+    const ORIGIN: &[&str] = &["central_long", "origin_lat"];
+    Some(match projection {
+        ProjectionData::Equirectangular(_) => &["central_long", "true_scale_lat"],
+        ProjectionData::AzimuthalEquidistant(_) => &["center_long", "center_lat", "central_long"],
+        ProjectionData::Mercator(_) => &["central_long", "max_lat"],
+        ProjectionData::TransverseMercator(_) => &["central_long", "origin_lat", "scale_factor"],
+        ProjectionData::WebMercator(_) => &[],
+        ProjectionData::WinkelTripel(_)
+        | ProjectionData::CylindricalEqualArea(_)
+        | ProjectionData::Bonne(_) => STANDARD_PARALLEL,
+        ProjectionData::LambertConformalConic(_) => &[
+            "central_long",
+            "origin_lat",
+            "standard_parallel_1",
+            "standard_parallel_2",
+        ],
+        ProjectionData::AlbersEqualArea(_) | ProjectionData::EquidistantConic(_) => {
+            STANDARD_PARALLELS
+        }
+        ProjectionData::Orthographic(_)
+        | ProjectionData::Stereographic(_)
+        | ProjectionData::LambertAzimuthalEqualArea(_) => CENTER,
+        ProjectionData::Gnomonic(_) => &["central_long", "center_lat", "clip"],
+        ProjectionData::GoodeHomolosine(_) => &["central_long", "interruptions"],
+        ProjectionData::Polyconic(_) => ORIGIN,
+        ProjectionData::Mollweide(_)
+        | ProjectionData::Robinson(_)
+        | ProjectionData::Sinusoidal(_)
+        | ProjectionData::Hammer(_)
+        | ProjectionData::Aitoff(_)
+        | ProjectionData::EckertIV(_)
+        | ProjectionData::EckertVI(_)
+        | ProjectionData::Werner(_)
+        | ProjectionData::VanDerGrinten(_)
+        | ProjectionData::Miller(_)
+        | ProjectionData::Cassini(_)
+        | ProjectionData::NaturalEarth(_)
+        | ProjectionData::KavrayskiyVII(_) => CENTRAL_LONG,
+        ProjectionData::RubberSheet(_) => return None,
+    })
+}
+
+pub fn with_parameters(projection: &ProjectionData, parameters: &[f64]) -> ProjectionData {
+    let long = |value: f64| value.clamp(-180., 180.);
+    let lat = |value: f64| value.clamp(-90., 90.);
+    match projection {
+        ProjectionData::Equirectangular(_) => ProjectionData::Equirectangular(
+            submaptive::Equirectangular::new()
+                .central_long(long(parameters[0]))
+                .true_scale_lat(lat(parameters[1]).clamp(-85., 85.))
+                .build(),
+        ),
+        ProjectionData::AzimuthalEquidistant(_) => ProjectionData::AzimuthalEquidistant(
+            submaptive::AzimuthalEquidistant::new()
+                .center(
+                    (long(parameters[0]), lat(parameters[1]))
+                        .try_into()
+                        .unwrap(),
+                )
+                .central_long(long(parameters[2]))
+                .build(),
+        ),
+        ProjectionData::Mercator(_) => ProjectionData::Mercator(projections::Mercator {
+            central_long: long(parameters[0]),
+            max_lat: parameters[1].clamp(60., 89.5),
+        }),
+        ProjectionData::TransverseMercator(_) => {
+            ProjectionData::TransverseMercator(projections::TransverseMercator {
+                central_long: long(parameters[0]),
+                origin_lat: lat(parameters[1]),
+                scale_factor: parameters[2].clamp(0.5, 1.5),
+            })
+        }
+        ProjectionData::WebMercator(_) => projection.clone(),
+        ProjectionData::Mollweide(_) => ProjectionData::Mollweide(projections::Mollweide {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::Robinson(_) => ProjectionData::Robinson(projections::Robinson {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::WinkelTripel(_) => {
+            ProjectionData::WinkelTripel(projections::WinkelTripel {
+                central_long: long(parameters[0]),
+                standard_parallel: parameters[1].clamp(0., 90.),
+            })
+        }
+        ProjectionData::Sinusoidal(_) => ProjectionData::Sinusoidal(projections::Sinusoidal {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::CylindricalEqualArea(_) => {
+            ProjectionData::CylindricalEqualArea(projections::CylindricalEqualArea {
+                central_long: long(parameters[0]),
+                standard_parallel: parameters[1]
+                    .clamp(0., projections::CylindricalEqualArea::MAX_STANDARD_PARALLEL),
+            })
+        }
+        ProjectionData::LambertConformalConic(_) => {
+            let max = projections::LambertConformalConic::MAX_STANDARD_PARALLEL;
+            ProjectionData::LambertConformalConic(projections::LambertConformalConic {
+                central_long: long(parameters[0]),
+                origin_lat: lat(parameters[1]),
+                standard_parallels: [parameters[2], parameters[3]].map(|lat| lat.clamp(-max, max)),
+            })
+        }
+        ProjectionData::AlbersEqualArea(_) => {
+            let max = projections::AlbersEqualArea::MAX_STANDARD_PARALLEL;
+            ProjectionData::AlbersEqualArea(projections::AlbersEqualArea {
+                central_long: long(parameters[0]),
+                standard_parallels: [parameters[1], parameters[2]].map(|lat| lat.clamp(-max, max)),
+            })
+        }
+        ProjectionData::EquidistantConic(_) => {
+            let max = projections::EquidistantConic::MAX_STANDARD_PARALLEL;
+            ProjectionData::EquidistantConic(projections::EquidistantConic {
+                central_long: long(parameters[0]),
+                standard_parallels: [parameters[1], parameters[2]].map(|lat| lat.clamp(-max, max)),
+            })
+        }
+        ProjectionData::Orthographic(_) => {
+            ProjectionData::Orthographic(projections::Orthographic {
+                central_long: long(parameters[0]),
+                center_lat: lat(parameters[1]),
+            })
+        }
+        ProjectionData::Stereographic(_) => {
+            ProjectionData::Stereographic(projections::Stereographic {
+                central_long: long(parameters[0]),
+                center_lat: lat(parameters[1]),
+            })
+        }
+        ProjectionData::Gnomonic(_) => ProjectionData::Gnomonic(projections::Gnomonic {
+            central_long: long(parameters[0]),
+            center_lat: lat(parameters[1]),
+            clip: parameters[2].clamp(
+                projections::Gnomonic::MIN_CLIP,
+                projections::Gnomonic::MAX_CLIP,
+            ),
+        }),
+        ProjectionData::LambertAzimuthalEqualArea(_) => {
+            ProjectionData::LambertAzimuthalEqualArea(projections::LambertAzimuthalEqualArea {
+                central_long: long(parameters[0]),
+                center_lat: lat(parameters[1]),
+            })
+        }
+        ProjectionData::Hammer(_) => ProjectionData::Hammer(projections::Hammer {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::Aitoff(_) => ProjectionData::Aitoff(projections::Aitoff {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::EckertIV(_) => ProjectionData::EckertIV(projections::EckertIV {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::EckertVI(_) => ProjectionData::EckertVI(projections::EckertVI {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::GoodeHomolosine(_) => {
+            ProjectionData::GoodeHomolosine(projections::GoodeHomolosine {
+                central_long: long(parameters[0]),
+                interruptions: projections::Interruptions::from_parameter(parameters[1]),
+            })
+        }
+        ProjectionData::Bonne(_) => ProjectionData::Bonne(projections::Bonne {
+            central_long: long(parameters[0]),
+            standard_parallel: lat(parameters[1]),
+        }),
+        ProjectionData::Werner(_) => ProjectionData::Werner(projections::Werner {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::VanDerGrinten(_) => {
+            ProjectionData::VanDerGrinten(projections::VanDerGrinten {
+                central_long: long(parameters[0]),
+            })
+        }
+        ProjectionData::Miller(_) => ProjectionData::Miller(projections::Miller {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::Cassini(_) => ProjectionData::Cassini(projections::Cassini {
+            central_long: long(parameters[0]),
+        }),
+        ProjectionData::Polyconic(_) => ProjectionData::Polyconic(projections::Polyconic {
+            central_long: long(parameters[0]),
+            origin_lat: lat(parameters[1]),
+        }),
+        ProjectionData::NaturalEarth(_) => {
+            ProjectionData::NaturalEarth(projections::NaturalEarth {
+                central_long: long(parameters[0]),
+            })
+        }
+        ProjectionData::KavrayskiyVII(_) => {
+            ProjectionData::KavrayskiyVII(projections::KavrayskiyVII {
+                central_long: long(parameters[0]),
+            })
+        }
+        ProjectionData::RubberSheet(_) => projection.clone(),
+    }
+}
+
+impl FeatureMatching {
+    pub fn load_reference(&mut self, path: std::path::PathBuf) {
+        match image::io::Reader::open(path).map(|data| data.decode()) {
+            Ok(Ok(image)) => {
+                self.reference = Some(Arc::new(DistanceField::from_equirectangular(&image)));
+                self.error = None;
+            }
+            Ok(Err(e)) => self.error = Some(e.to_string()),
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// Starts refining `projection` against the reference in the background.
+    fn start(
+        &mut self,
+        ctx: &egui::Context,
+        projection: &ProjectionData,
+        source: image::DynamicImage,
+    ) {
+        let Some(reference) = self.reference.clone() else {
+            return;
+        };
+        let projection = projection.clone();
+        self.job = Some(Job::spawn(
+            ctx,
+            "Refining the source projection",
+            move |send, progress| {
+                if let Some(refined) = refine(&reference, &projection, &source, progress) {
+                    send(refined);
+                }
+            },
+        ));
+    }
+
+    fn cancel(&mut self) {
+        if let Some(job) = self.job.take() {
+            job.cancel();
+        }
+    }
+
+    /// Puts the refined parameters into `projection` once the search is done.
+    pub fn poll(&mut self, projection: &mut ProjectionData) {
+        let Some(job) = &mut self.job else {
+            return;
+        };
+        for refined in job.poll() {
+            match refined {
+                Ok((refined, score)) => {
+                    *projection = refined;
+                    self.score = Some(score);
+                    self.error = None;
+                }
+                Err(e) => self.error = Some(e),
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+}
+
+/// Improves the parameters of `projection` by a shrinking pattern search
+/// starting from its current values. Returns the refined projection and its
+/// score, or nothing if cancelled.
+fn refine(
+    reference: &DistanceField,
+    projection: &ProjectionData,
+    source: &image::DynamicImage,
+    progress: &Progress,
+) -> Option<Result<(ProjectionData, f64), String>> {
+    let Some(mut best_parameters) = parameters(projection) else {
+        return Some(Err("Only analytic projections can be refined.".to_string()));
+    };
+    let edges = edge_pixels(source);
+    let dimensions = (source.width(), source.height());
+    let mut best = score(projection, &edges, dimensions, reference);
+    let halvings = (FIRST_STEP / LAST_STEP).log2().ceil();
+    let mut step = FIRST_STEP;
+    while step >= LAST_STEP {
+        let mut improved = false;
+        for index in 0..best_parameters.len() {
+            for direction in [-1., 1.] {
+                if progress.is_cancelled() {
+                    return None;
+                }
+                let mut candidate = best_parameters.clone();
+                candidate[index] += direction * step;
+                // Keep the candidate as the projection clamps it, so that steps
+                // past a limit don't pile up in the parameters.
+                let candidate_projection = with_parameters(projection, &candidate);
+                let candidate_score = score(&candidate_projection, &edges, dimensions, reference);
+                if candidate_score < best {
+                    best = candidate_score;
+                    best_parameters = parameters(&candidate_projection).unwrap_or(candidate);
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            step /= 2.;
+            progress.set((FIRST_STEP / step).log2() as f32 / halvings as f32);
+        }
+    }
+    Some(Ok((with_parameters(projection, &best_parameters), best)))
+}
+
+pub fn feature_matching_ui(
+    ui: &mut egui::Ui,
+    matching: &mut FeatureMatching,
+    projection: &ProjectionData,
+    unit: AngleUnit,
+    corrected_source: impl FnOnce() -> Option<image::DynamicImage>,
+) {
+    ui.collapsing("Match against reference", |ui| {
+        ui.label(
+            "Refines the source projection's parameters by aligning the scan's \
+             coastlines with an equirectangular reference map. Set rough \
+             parameters by hand first.",
+        );
+        if ui.button("Choose reference map...").clicked() {
+            if let Some(path) = rfd::FileDialog::new().pick_file() {
+                matching.load_reference(path);
+            }
+        }
+        match &matching.job {
+            Some(job) => {
+                ui.add(egui::ProgressBar::new(job.progress()).show_percentage());
+                if ui.button("Cancel").clicked() {
+                    matching.cancel();
+                }
+            }
+            None => {
+                if matching.reference.is_some() && ui.button("Refine source projection").clicked() {
+                    if let Some(source) = corrected_source() {
+                        matching.start(ui.ctx(), projection, source);
+                    }
+                }
+            }
+        }
+        if let Some(score) = matching.score {
+            let degrees = score * 360. / REFERENCE_WIDTH as f64;
+            ui.label(format!("Mean edge distance: {}", unit.format(degrees)));
+        }
+        if let Some(error) = &matching.error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+    });
+}
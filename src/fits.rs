@@ -0,0 +1,393 @@
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::longitudes::{Domain, Longitudes};
+use crate::{projections, ProjectionData};
+
+/// FITS files are made of blocks of this many bytes.
+const BLOCK: u64 = 2880;
+const CARD: usize = 80;
+/// Most blocks a header may take, against files that never end theirs.
+const MAX_HEADER_BLOCKS: usize = 10_000;
+/// Fraction of the values at either end that the stretch clips, so that one
+/// bright star doesn't leave the rest of the sky black.
+const CLIP: f64 = 0.0025;
+/// Values sampled for the stretch.
+const STRETCH_SAMPLES: usize = 1 << 20;
+
+pub fn is_fits(path: &Path) -> bool {
+    let extension = path.extension().unwrap_or_default();
+    matches!(
+        extension.to_string_lossy().to_lowercase().as_str(),
+        "fits" | "fit" | "fts"
+    )
+}
+
+/// The keywords of one header unit and their values, as written.
+pub struct Header {
+    cards: Vec<(String, String)>,
+    blocks: u64,
+}
+
+impl Header {
+    /// Reads a header up to its `END`, or returns `None` at the end of the file.
+    fn read(reader: &mut impl Read) -> Result<Option<Self>, String> {
+        let mut cards = Vec::new();
+        for block in 0..MAX_HEADER_BLOCKS {
+            let mut data = vec![0; BLOCK as usize];
+            match reader.read_exact(&mut data) {
+                Err(_) if block == 0 => return Ok(None),
+                Err(_) => return Err("it ends in the middle of a header".to_string()),
+                Ok(()) => {}
+            }
+            for card in data.chunks(CARD) {
+                let card = String::from_utf8_lossy(card);
+                let keyword = card.get(..8).unwrap_or(&card).trim_end().to_string();
+                if keyword == "END" {
+                    return Ok(Some(Header {
+                        cards,
+                        blocks: block as u64 + 1,
+                    }));
+                }
+                if let (Some("= "), Some(value)) = (card.get(8..10), card.get(10..)) {
+                    cards.push((keyword, value.to_string()));
+                }
+            }
+        }
+        Err("its header doesn't end".to_string())
+    }
+
+    fn value(&self, keyword: &str) -> Option<&str> {
+        self.cards
+            .iter()
+            .find(|(key, _)| key == keyword)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn number(&self, keyword: &str) -> Option<f64> {
+        let value = self.value(keyword)?;
+        let value = value.split('/').next()?.trim();
+        value.replace(['D', 'd'], "E").parse().ok()
+    }
+
+    /// A quoted string, in which `''` stands for a quote.
+    pub fn text(&self, keyword: &str) -> Option<String> {
+        let value = self.value(keyword)?.trim_start().strip_prefix('\'')?;
+        let mut text = String::new();
+        let mut characters = value.chars().peekable();
+        while let Some(character) = characters.next() {
+            match (character, characters.peek()) {
+                ('\'', Some('\'')) => {
+                    text.push('\'');
+                    characters.next();
+                }
+                ('\'', _) => break,
+                (character, _) => text.push(character),
+            }
+        }
+        Some(text.trim_end().to_string())
+    }
+
+    pub fn axis(&self, number: usize) -> u64 {
+        self.number(&format!("NAXIS{}", number)).unwrap_or(0.) as u64
+    }
+
+    fn bitpix(&self) -> i64 {
+        self.number("BITPIX").unwrap_or(8.) as i64
+    }
+
+    /// The size of the data after the header, without its padding.
+    fn data_size(&self) -> u64 {
+        let axes = self.axis_count();
+        if axes == 0 {
+            return 0;
+        }
+        // Random groups leave out the first axis.
+        let first = match self.axis(1) {
+            0 if self.value("GROUPS").is_some() => 1,
+            length => length,
+        };
+        let values = (2..=axes).fold(first, |count, axis| count.saturating_mul(self.axis(axis)));
+        let groups = self.number("GCOUNT").unwrap_or(1.) as u64;
+        let parameters = self.number("PCOUNT").unwrap_or(0.) as u64;
+        (self.bitpix().unsigned_abs() / 8)
+            .saturating_mul(groups)
+            .saturating_mul(values.saturating_add(parameters))
+    }
+
+    fn axis_count(&self) -> usize {
+        self.number("NAXIS").unwrap_or(0.) as usize
+    }
+}
+
+/// Finds the first header unit with an image, in the primary one or in an
+/// `IMAGE` extension, and where its data starts.
+fn image_header(path: &Path) -> Result<(Header, u64, BufReader<std::fs::File>), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    find_unit(path, |header, primary| {
+        let is_image = primary || header.text("XTENSION").as_deref() == Some("IMAGE");
+        is_image && header.axis_count() >= 2 && header.axis(1) > 0 && header.axis(2) > 0
+    })?
+    .ok_or_else(|| format!("{} holds no image.", name))
+}
+
+/// Finds the first header unit that is `wanted`, and a reader at the start
+/// of its data, or `None` if the file has none. The flag is set for the
+/// primary unit.
+pub fn find_unit(
+    path: &Path,
+    wanted: impl Fn(&Header, bool) -> bool,
+) -> Result<Option<(Header, u64, BufReader<std::fs::File>)>, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let file = std::fs::File::open(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+    let mut reader = BufReader::new(file);
+    let mut position = 0;
+    let mut primary = true;
+    loop {
+        let Some(header) =
+            Header::read(&mut reader).map_err(|e| format!("{} can't be read: {}", name, e))?
+        else {
+            return Ok(None);
+        };
+        let simple = header
+            .value("SIMPLE")
+            .and_then(|value| value.split('/').next());
+        if primary && simple.map(str::trim) != Some("T") {
+            return Err(format!("{} is not a FITS file.", name));
+        }
+        position += header.blocks * BLOCK;
+        if wanted(&header, primary) {
+            return Ok(Some((header, position, reader)));
+        }
+        position += header.data_size().div_ceil(BLOCK) * BLOCK;
+        reader
+            .seek(SeekFrom::Start(position))
+            .map_err(|e| format!("Could not read {}: {}", name, e))?;
+        primary = false;
+    }
+}
+
+pub fn dimensions(path: &Path) -> Result<(u32, u32), String> {
+    let (header, _, _) = image_header(path)?;
+    Ok((header.axis(1) as u32, header.axis(2) as u32))
+}
+
+/// Reads the first plane of the first image, stretched linearly between its
+/// faintest and brightest values but for the few at either end. Blank pixels
+/// are transparent.
+pub fn decode(path: &Path) -> Result<image::DynamicImage, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (header, position, mut reader) = image_header(path)?;
+    let (width, height) = (header.axis(1) as usize, header.axis(2) as usize);
+    let bitpix = header.bitpix();
+    let size = match bitpix {
+        8 | 16 | 32 | 64 | -32 | -64 => bitpix.unsigned_abs() as usize / 8,
+        _ => {
+            return Err(format!(
+                "{} has values of {} bits, which isn't valid.",
+                name, bitpix
+            ))
+        }
+    };
+    let length = reader
+        .get_ref()
+        .metadata()
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let bytes = (width as u64)
+        .saturating_mul(height as u64)
+        .saturating_mul(size as u64);
+    if position.saturating_add(bytes) > length {
+        return Err(format!("{} ends before all of its image.", name));
+    }
+    let mut data = vec![0; bytes as usize];
+    reader
+        .seek(SeekFrom::Start(position))
+        .and_then(|_| reader.read_exact(&mut data))
+        .map_err(|e| format!("Could not read {}: {}", name, e))?;
+    let blank = header.number("BLANK").filter(|_| bitpix > 0);
+    let scale = header.number("BSCALE").unwrap_or(1.);
+    let zero = header.number("BZERO").unwrap_or(0.);
+    let values: Vec<f32> = data
+        .chunks_exact(size)
+        .map(|bytes| {
+            let raw = match bitpix {
+                8 => bytes[0] as f64,
+                16 => i16::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                32 => i32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                64 => i64::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                -32 => f32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                _ => f64::from_be_bytes(bytes.try_into().unwrap()),
+            };
+            match Some(raw) == blank {
+                true => f32::NAN,
+                false => (zero + scale * raw) as f32,
+            }
+        })
+        .collect();
+    let mut sample: Vec<f32> = values
+        .iter()
+        .step_by((values.len() / STRETCH_SAMPLES).max(1))
+        .copied()
+        .filter(|value| value.is_finite())
+        .collect();
+    sample.sort_by(f32::total_cmp);
+    let quantile = |q: f64| {
+        let index = ((sample.len() as f64 * q) as usize).min(sample.len().saturating_sub(1));
+        sample.get(index).copied().unwrap_or(0.) as f64
+    };
+    let (low, high) = (quantile(CLIP), quantile(1. - CLIP));
+    let range = match high > low {
+        true => high - low,
+        false => 1.,
+    };
+    // The first row is the bottom one.
+    let image = image::ImageBuffer::from_fn(width as u32, height as u32, |x, y| {
+        let value = values[(height - 1 - y as usize) * width + x as usize];
+        match value.is_finite() {
+            true => {
+                let level = ((value as f64 - low) / range).clamp(0., 1.) * u16::MAX as f64;
+                image::LumaA([level.round() as u16, u16::MAX])
+            }
+            false => image::LumaA([0, 0]),
+        }
+    });
+    Ok(image::DynamicImage::ImageLumaA16(image))
+}
+
+/// A header from keywords and their values, written as FITS wants them.
+pub fn header(cards: &[(&str, Value)]) -> Vec<u8> {
+    let mut header: Vec<u8> = cards
+        .iter()
+        .map(|(keyword, value)| {
+            let value = match value {
+                Value::Logical(value) => format!("{:>20}", if *value { "T" } else { "F" }),
+                Value::Integer(value) => format!("{:>20}", value),
+                Value::Text(value) => format!("'{:8}'", value.replace('\'', "''")),
+            };
+            format!("{:8}= {:70}", keyword, value)
+        })
+        .chain(std::iter::once(format!("{:80}", "END")))
+        .flat_map(String::into_bytes)
+        .collect();
+    pad(&mut header, b' ');
+    header
+}
+
+/// Pads a header with spaces or data with zeros to a whole block.
+pub fn pad(data: &mut Vec<u8>, fill: u8) {
+    data.resize(data.len().div_ceil(BLOCK as usize) * BLOCK as usize, fill);
+}
+
+#[derive(Clone)]
+pub enum Value {
+    Logical(bool),
+    Integer(i64),
+    Text(String),
+}
+
+/// Where on the sky an image lies, by its world coordinate system.
+pub struct Sky {
+    /// The source projection that matches it, unless the app has none.
+    pub projection: Option<ProjectionData>,
+    /// Longitudes as the coordinate system counts them, from 0° to 360° and
+    /// increasing to the left if the image is seen from inside the sphere.
+    pub longitudes: Longitudes,
+    /// Why the projection won't line up exactly.
+    pub caveat: Option<String>,
+}
+
+/// Reads the world coordinate system of a celestial image from its header,
+/// or returns `None` if it has none or isn't a FITS file.
+pub fn sky(path: &Path) -> Option<Sky> {
+    if !is_fits(path) {
+        return None;
+    }
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (header, _, _) = image_header(path).ok()?;
+    let ctype = header.text("CTYPE1")?;
+    if !["RA--", "GLON", "ELON", "SLON", "HLON"]
+        .iter()
+        .any(|prefix| ctype.starts_with(prefix))
+    {
+        return None;
+    }
+    let code = ctype.get(5..8).unwrap_or_default().trim_matches('-');
+    let delta = |axis: usize| {
+        header
+            .number(&format!("CDELT{}", axis))
+            .or_else(|| header.number(&format!("CD{}_{}", axis, axis)))
+            .unwrap_or(1.)
+    };
+    let (width, height) = (header.axis(1) as f64, header.axis(2) as f64);
+    let reference = header.number("CRVAL1").unwrap_or(0.);
+    let west_positive = delta(1) < 0.;
+    let mut caveat = None;
+    // Only cylindrical projections are linear in longitude away from the reference point.
+    let center = match code {
+        "CAR" | "CEA" => {
+            reference + delta(1) * ((width + 1.) / 2. - header.number("CRPIX1").unwrap_or(0.))
+        }
+        _ => reference,
+    };
+    let central_long = projections::wrap_long(match west_positive {
+        true => -center,
+        false => center,
+    });
+    let projection = match code {
+        "CAR" => Some(ProjectionData::Equirectangular(
+            submaptive::Equirectangular::new()
+                .central_long(central_long)
+                .build(),
+        )),
+        "CEA" => {
+            // The parameter is the square of the cosine of the standard parallel.
+            let lambda = header.number("PV2_1").unwrap_or(1.).clamp(0., 1.);
+            Some(ProjectionData::CylindricalEqualArea(
+                projections::CylindricalEqualArea {
+                    central_long,
+                    standard_parallel: lambda
+                        .sqrt()
+                        .acos()
+                        .to_degrees()
+                        .min(projections::CylindricalEqualArea::MAX_STANDARD_PARALLEL),
+                },
+            ))
+        }
+        "MOL" => Some(ProjectionData::Mollweide(projections::Mollweide {
+            central_long,
+        })),
+        "SFL" | "GLS" => Some(ProjectionData::Sinusoidal(projections::Sinusoidal {
+            central_long,
+        })),
+        _ => {
+            caveat = Some(format!(
+                "{} is in the {} projection, which isn't available; choose the source projection yourself.",
+                name, code
+            ));
+            None
+        }
+    };
+    let whole_sky =
+        (width * delta(1).abs() - 360.).abs() < 3.6 && (height * delta(2).abs() - 180.).abs() < 1.8;
+    if projection.is_some() && header.number("CRVAL2").unwrap_or(0.) != 0. {
+        caveat = Some(format!(
+            "{} is centered off the celestial equator, which isn't supported.",
+            name
+        ));
+    } else if code == "CAR" && !whole_sky {
+        caveat = Some(format!(
+            "{} covers only part of the sky, but will be spread over all of it.",
+            name
+        ));
+    }
+    Some(Sky {
+        projection,
+        longitudes: Longitudes {
+            prime_meridian: 0.,
+            domain: Domain::Positive,
+            west_positive,
+        },
+        caveat,
+    })
+}
@@ -0,0 +1,331 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::graticule_detection::{self, GraticuleDetection};
+use crate::labels::{Labels, Priority};
+use crate::longitudes::Longitudes;
+use crate::tools::Tool;
+use crate::tps::ThinPlateSpline;
+
+/// Why a rubber sheet could not be fitted.
+pub const NOT_FITTED: &str =
+    "The rubber-sheet warp needs at least three control points that do not all lie on one line.";
+
+/// A pixel of the source image together with the geographic position it shows.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ControlPoint {
+    pub pixel: (f64, f64),
+    pub long: f64,
+    pub lat: f64,
+    /// Disabled points are kept (and their residual shown) but left out of the fit.
+    pub enabled: bool,
+}
+
+/// How well the control points agree with each other.
+#[derive(Default)]
+struct Residuals {
+    /// The control points these residuals were computed for.
+    points: Vec<ControlPoint>,
+    /// Per point: the distance in pixels between where it is and where the fit
+    /// through all other enabled points puts it.
+    errors: Vec<Option<f64>>,
+    rms: Option<f64>,
+}
+
+/// The rubber sheet fitted last, kept because fitting solves a dense system
+/// in the number of control points.
+#[derive(Default)]
+struct FittedSheet {
+    /// The control points and image size the sheet was fitted for.
+    points: Vec<ControlPoint>,
+    dimensions: (u32, u32),
+    sheet: RubberSheet,
+}
+
+#[derive(Default)]
+pub struct Georeference {
+    pub control_points: Vec<ControlPoint>,
+    pub detection: GraticuleDetection,
+    residuals: Residuals,
+    fitted_sheet: FittedSheet,
+}
+
+impl Georeference {
+    pub fn add_control_point(&mut self, pixel: (f64, f64)) {
+        self.control_points.push(ControlPoint {
+            pixel,
+            long: 0.0,
+            lat: 0.0,
+            enabled: true,
+        });
+    }
+
+    fn update_residuals(&mut self) {
+        if self.residuals.points == self.control_points {
+            return;
+        }
+        let errors: Vec<_> = self
+            .control_points
+            .iter()
+            .enumerate()
+            .map(|(index, point)| {
+                // Leave the point itself out, otherwise an interpolating spline
+                // would always reproduce it exactly. Only by its index: another
+                // point may be exactly the same.
+                let others: Vec<_> = self
+                    .control_points
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, other)| *i != index && other.enabled)
+                    .map(|(_, other)| other)
+                    .collect();
+                let spline = ThinPlateSpline::fit(
+                    &others.iter().map(|p| (p.long, p.lat)).collect::<Vec<_>>(),
+                    &others.iter().map(|p| p.pixel).collect::<Vec<_>>(),
+                )?;
+                let predicted = spline.apply((point.long, point.lat));
+                Some(
+                    ((predicted.0 - point.pixel.0).powi(2) + (predicted.1 - point.pixel.1).powi(2))
+                        .sqrt(),
+                )
+            })
+            .collect();
+        let enabled_errors: Vec<_> = errors
+            .iter()
+            .zip(&self.control_points)
+            .filter(|(_, point)| point.enabled)
+            .map(|(error, _)| *error)
+            .collect::<Option<_>>()
+            .unwrap_or_default();
+        self.residuals = Residuals {
+            points: self.control_points.clone(),
+            rms: (!enabled_errors.is_empty()).then(|| {
+                (enabled_errors.iter().map(|e| e * e).sum::<f64>() / enabled_errors.len() as f64)
+                    .sqrt()
+            }),
+            errors,
+        };
+    }
+
+    /// Green for points in line with the others, orange and red for increasingly
+    /// suspicious ones, grey for disabled points.
+    fn residual_color(&self, index: usize) -> egui::Color32 {
+        if !self.control_points[index].enabled {
+            return egui::Color32::GRAY;
+        }
+        match (
+            self.residuals.errors.get(index).copied().flatten(),
+            self.residuals.rms,
+        ) {
+            (Some(error), Some(rms)) if error > 3. * rms.max(1.) => egui::Color32::RED,
+            (Some(error), Some(rms)) if error > 1.5 * rms.max(1.) => {
+                egui::Color32::from_rgb(255, 165, 0)
+            }
+            (Some(_), Some(_)) => egui::Color32::GREEN,
+            _ => egui::Color32::YELLOW,
+        }
+    }
+
+    pub fn rubber_sheet(&mut self, image_dimensions: (u32, u32)) -> RubberSheet {
+        let fitted = &self.fitted_sheet;
+        if fitted.points != self.control_points || fitted.dimensions != image_dimensions {
+            let enabled: Vec<_> = self
+                .control_points
+                .iter()
+                .copied()
+                .filter(|point| point.enabled)
+                .collect();
+            self.fitted_sheet = FittedSheet {
+                points: self.control_points.clone(),
+                dimensions: image_dimensions,
+                sheet: RubberSheet::fit(&enabled, image_dimensions),
+            };
+        }
+        self.fitted_sheet.sheet.clone()
+    }
+}
+
+/// A "projection" that warps the source image onto the globe with a pair of
+/// thin-plate splines fitted to the control points instead of an analytic formula.
+///
+/// Projected coordinates are the pixel coordinates of the source image with the
+/// origin moved to its center and the y axis pointing up.
+#[derive(Clone, Default)]
+pub struct RubberSheet {
+    width: f64,
+    height: f64,
+    splines: Option<Box<(ThinPlateSpline, ThinPlateSpline)>>,
+}
+
+impl RubberSheet {
+    pub fn fit(control_points: &[ControlPoint], image_dimensions: (u32, u32)) -> Self {
+        let (width, height) = (image_dimensions.0 as f64, image_dimensions.1 as f64);
+        let projected: Vec<_> = control_points
+            .iter()
+            .map(|point| (point.pixel.0 - width / 2., height / 2. - point.pixel.1))
+            .collect();
+        let geographic: Vec<_> = control_points
+            .iter()
+            .map(|point| (point.long, point.lat))
+            .collect();
+        let splines = ThinPlateSpline::fit(&geographic, &projected)
+            .zip(ThinPlateSpline::fit(&projected, &geographic))
+            .map(Box::new);
+        RubberSheet {
+            width,
+            height,
+            splines,
+        }
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        self.splines.is_some()
+    }
+}
+
+impl submaptive::Projection for RubberSheet {
+    fn dimensions(&self) -> submaptive::Dimensions {
+        submaptive::Dimensions::new(self.width, self.height)
+    }
+
+    fn project(&self, point: &submaptive::Point) -> (f64, f64) {
+        match self.splines.as_deref() {
+            Some((forward, _)) => forward.apply((point.long(), point.lat())),
+            None => (0., 0.),
+        }
+    }
+
+    fn invert(&self, projected_point: (f64, f64)) -> submaptive::Point {
+        let (long, lat) = match self.splines.as_deref() {
+            Some((_, inverse)) => inverse.apply(projected_point),
+            None => (0., 0.),
+        };
+        (long.clamp(-180., 180.), lat.clamp(-90., 90.))
+            .try_into()
+            .unwrap()
+    }
+
+    fn projected_point_within_bounds(&self, point: (f64, f64)) -> bool {
+        self.splines.is_some()
+            && point.0.abs() <= self.width / 2.
+            && point.1.abs() <= self.height / 2.
+    }
+}
+
+pub fn georeference_ui(
+    ui: &mut egui::Ui,
+    georeference: &mut Georeference,
+    tool: &mut Tool,
+    longitudes: &Longitudes,
+    unit: AngleUnit,
+    corrected_source: impl FnOnce() -> Option<image::DynamicImage>,
+) {
+    georeference.update_residuals();
+    ui.collapsing("Georeferencing", |ui| {
+        ui.selectable_value(
+            tool,
+            Tool::Georeference,
+            "Add points by clicking the source",
+        );
+        let residuals: Vec<_> = (0..georeference.control_points.len())
+            .map(|index| {
+                let text = match georeference.residuals.errors[index] {
+                    Some(error) => format!("{:.1} px", error),
+                    None => "–".to_string(),
+                };
+                (georeference.residual_color(index), text)
+            })
+            .collect();
+        let mut removed = None;
+        egui::Grid::new("Control points")
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("Use");
+                ui.label("Point");
+                ui.label("Longitude");
+                ui.label("Latitude");
+                ui.label("Residual");
+                ui.end_row();
+                for (index, point) in georeference.control_points.iter_mut().enumerate() {
+                    ui.checkbox(&mut point.enabled, "");
+                    ui.label(format!(
+                        "{}: {:.0}, {:.0}",
+                        index + 1,
+                        point.pixel.0,
+                        point.pixel.1
+                    ));
+                    ui.add(
+                        longitudes
+                            .drag_value(&mut point.long)
+                            .speed(0.1)
+                            .angle(unit),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut point.lat)
+                            .clamp_range(-90.0..=90.)
+                            .speed(0.1)
+                            .angle(unit),
+                    );
+                    let (color, text) = &residuals[index];
+                    ui.colored_label(*color, text);
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(index) = removed {
+            georeference.control_points.remove(index);
+        }
+        if let Some(rms) = georeference.residuals.rms {
+            ui.label(format!("RMS error: {:.2} px", rms));
+        }
+        let enabled = georeference
+            .control_points
+            .iter()
+            .filter(|p| p.enabled)
+            .count();
+        if enabled < 3 {
+            ui.label("A rubber-sheet warp needs at least three enabled control points.");
+        } else if enabled == 3 {
+            ui.label("Residuals need at least four enabled control points.");
+        }
+        let proposed = graticule_detection::graticule_detection_ui(
+            ui,
+            &mut georeference.detection,
+            unit,
+            corrected_source,
+        );
+        georeference.control_points.extend(proposed);
+    });
+}
+
+/// Marks the control points on the source image drawn into `rect`.
+pub fn paint_control_points(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    georeference: &Georeference,
+    labels: &mut Labels,
+) {
+    for (index, point) in georeference.control_points.iter().enumerate() {
+        let position = rect.min
+            + egui::vec2(
+                point.pixel.0 as f32 / image_dimensions.0 as f32 * rect.width(),
+                point.pixel.1 as f32 / image_dimensions.1 as f32 * rect.height(),
+            );
+        let color = georeference.residual_color(index);
+        painter.circle_stroke(position, 5., (2., color));
+        labels.avoid(egui::Rect::from_center_size(position, egui::vec2(12., 12.)));
+        labels.add(
+            position,
+            (egui::Align2::LEFT_BOTTOM, egui::vec2(6., -6.)),
+            index + 1,
+            egui::FontId::default(),
+            color,
+            Priority::ControlPoint,
+        );
+    }
+}
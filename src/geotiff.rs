@@ -0,0 +1,615 @@
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use submaptive::Projection;
+use tiff::encoder::colortype::{self, ColorType};
+use tiff::encoder::{TiffEncoder, TiffValue};
+use tiff::tags::Tag;
+
+use crate::angles::AngleUnit;
+use crate::body::Body;
+use crate::projections;
+use crate::ProjectionData;
+
+/// GeoTIFF's geokeys, from its specification.
+const MODEL_TYPE: u16 = 1024;
+const RASTER_TYPE: u16 = 1025;
+const CITATION: u16 = 1026;
+const GEOGRAPHIC_TYPE: u16 = 2048;
+const GEOGRAPHIC_CITATION: u16 = 2049;
+const GEODETIC_DATUM: u16 = 2050;
+const PRIME_MERIDIAN: u16 = 2051;
+const ANGULAR_UNITS: u16 = 2054;
+const ELLIPSOID: u16 = 2056;
+const SEMI_MAJOR_AXIS: u16 = 2057;
+const SEMI_MINOR_AXIS: u16 = 2058;
+const PROJECTED_TYPE: u16 = 3072;
+const PROJECTION: u16 = 3074;
+const COORDINATE_TRANSFORMATION: u16 = 3075;
+const LINEAR_UNITS: u16 = 3076;
+const STANDARD_PARALLEL_1: u16 = 3078;
+const STANDARD_PARALLEL_2: u16 = 3079;
+const NATURAL_ORIGIN_LONG: u16 = 3080;
+const NATURAL_ORIGIN_LAT: u16 = 3081;
+const FALSE_EASTING: u16 = 3082;
+const FALSE_NORTHING: u16 = 3083;
+const FALSE_ORIGIN_LONG: u16 = 3084;
+const FALSE_ORIGIN_LAT: u16 = 3085;
+const CENTER_LONG: u16 = 3088;
+const CENTER_LAT: u16 = 3089;
+const SCALE_AT_NATURAL_ORIGIN: u16 = 3092;
+
+/// Values of the geokeys above.
+const PROJECTED: u16 = 1;
+const PIXEL_IS_AREA: u16 = 1;
+const USER_DEFINED: u16 = 32767;
+const GREENWICH: u16 = 8901;
+const DEGREE: u16 = 9102;
+const METER: u16 = 9001;
+
+/// Whether `path` is saved as a TIFF, which then carries its georeferencing.
+pub fn is_tiff(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("tif") || extension.eq_ignore_ascii_case("tiff")
+        })
+}
+
+/// One of GeoTIFF's coordinate transformations with its parameters, in
+/// degrees or as factors. Its false easting and northing are where the
+/// projection draws `origin`, as the map is centered on its own middle.
+#[derive(Clone, Debug, PartialEq)]
+struct Transformation {
+    code: u16,
+    parameters: Vec<(u16, f64)>,
+    /// Longitude and latitude in degrees.
+    origin: (f64, f64),
+}
+
+impl Transformation {
+    fn new(code: u16, parameters: &[(u16, f64)], origin: (f64, f64)) -> Self {
+        Transformation {
+            code,
+            parameters: parameters.to_vec(),
+            origin,
+        }
+    }
+}
+
+/// The transformation GeoTIFF knows `projection` as. Many of the
+/// compromise and interrupted projections have none.
+fn transformation(projection: &ProjectionData) -> Option<Transformation> {
+    // Projections with parameters for their center and nothing else.
+    let centered = |code, long: f64, lat: f64| {
+        Transformation::new(code, &[(CENTER_LONG, long), (CENTER_LAT, lat)], (long, lat))
+    };
+    let conic = |code, long: f64, lat: f64, [first, second]: [f64; 2]| {
+        Transformation::new(
+            code,
+            &[
+                (STANDARD_PARALLEL_1, first),
+                (STANDARD_PARALLEL_2, second),
+                (NATURAL_ORIGIN_LONG, long),
+                (NATURAL_ORIGIN_LAT, lat),
+            ],
+            (long, lat),
+        )
+    };
+    let meridian = |code, long: f64| Transformation::new(code, &[(CENTER_LONG, long)], (long, 0.));
+    Some(match projection {
+        ProjectionData::Equirectangular(data) => Transformation::new(
+            17,
+            &[
+                (CENTER_LONG, data.central_long()),
+                (CENTER_LAT, 0.),
+                (STANDARD_PARALLEL_1, data.true_scale_lat()),
+            ],
+            (data.central_long(), 0.),
+        ),
+        // Turned about its center, it is no longer the plain projection.
+        ProjectionData::AzimuthalEquidistant(data)
+            if projections::wrap_long(data.central_long() - data.center().long()).abs() < 1e-9 =>
+        {
+            centered(12, data.center().long(), data.center().lat())
+        }
+        ProjectionData::Mercator(data) => Transformation::new(
+            7,
+            &[
+                (NATURAL_ORIGIN_LONG, data.central_long),
+                (NATURAL_ORIGIN_LAT, 0.),
+                (SCALE_AT_NATURAL_ORIGIN, 1.),
+            ],
+            (data.central_long, 0.),
+        ),
+        ProjectionData::WebMercator(_) => Transformation::new(
+            7,
+            &[
+                (NATURAL_ORIGIN_LONG, 0.),
+                (NATURAL_ORIGIN_LAT, 0.),
+                (SCALE_AT_NATURAL_ORIGIN, 1.),
+            ],
+            (0., 0.),
+        ),
+        ProjectionData::TransverseMercator(data) => Transformation::new(
+            1,
+            &[
+                (NATURAL_ORIGIN_LONG, data.central_long),
+                (NATURAL_ORIGIN_LAT, data.origin_lat),
+                (SCALE_AT_NATURAL_ORIGIN, data.scale_factor),
+            ],
+            (data.central_long, data.origin_lat),
+        ),
+        ProjectionData::CylindricalEqualArea(data) => Transformation::new(
+            28,
+            &[
+                (NATURAL_ORIGIN_LONG, data.central_long),
+                (STANDARD_PARALLEL_1, data.standard_parallel),
+            ],
+            (data.central_long, 0.),
+        ),
+        ProjectionData::LambertConformalConic(data) => Transformation::new(
+            8,
+            &[
+                (STANDARD_PARALLEL_1, data.standard_parallels[0]),
+                (STANDARD_PARALLEL_2, data.standard_parallels[1]),
+                (FALSE_ORIGIN_LONG, data.central_long),
+                (FALSE_ORIGIN_LAT, data.origin_lat),
+            ],
+            (data.central_long, data.origin_lat),
+        ),
+        ProjectionData::AlbersEqualArea(data) => {
+            conic(11, data.central_long, 0., data.standard_parallels)
+        }
+        ProjectionData::EquidistantConic(data) => {
+            conic(13, data.central_long, 0., data.standard_parallels)
+        }
+        ProjectionData::Orthographic(data) => centered(21, data.central_long, data.center_lat),
+        ProjectionData::Stereographic(data) => Transformation::new(
+            14,
+            &[
+                (CENTER_LONG, data.central_long),
+                (CENTER_LAT, data.center_lat),
+                (SCALE_AT_NATURAL_ORIGIN, 1.),
+            ],
+            (data.central_long, data.center_lat),
+        ),
+        ProjectionData::Gnomonic(data) => centered(19, data.central_long, data.center_lat),
+        ProjectionData::LambertAzimuthalEqualArea(data) => {
+            centered(10, data.central_long, data.center_lat)
+        }
+        ProjectionData::Miller(data) => centered(20, data.central_long, 0.),
+        ProjectionData::Cassini(data) => Transformation::new(
+            18,
+            &[
+                (NATURAL_ORIGIN_LONG, data.central_long),
+                (NATURAL_ORIGIN_LAT, 0.),
+            ],
+            (data.central_long, 0.),
+        ),
+        ProjectionData::Polyconic(data) => Transformation::new(
+            22,
+            &[
+                (NATURAL_ORIGIN_LONG, data.central_long),
+                (NATURAL_ORIGIN_LAT, data.origin_lat),
+                (SCALE_AT_NATURAL_ORIGIN, 1.),
+            ],
+            (data.central_long, data.origin_lat),
+        ),
+        ProjectionData::Robinson(data) => meridian(23, data.central_long),
+        ProjectionData::Sinusoidal(data) => meridian(24, data.central_long),
+        ProjectionData::VanDerGrinten(data) => meridian(25, data.central_long),
+        _ => return None,
+    })
+}
+
+/// Where the pixels of an exported map lie, in meters in the plane of its
+/// projection on a sphere with the body's equatorial radius.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Georeferencing {
+    pub dimensions: (u32, u32),
+    /// Of the top left corner of the top left pixel.
+    pub origin: (f64, f64),
+    /// Width and height of a pixel.
+    pub pixel_size: (f64, f64),
+    /// In meters.
+    pub radius: f64,
+    pub projection_name: String,
+    pub body_name: &'static str,
+    /// Longitude and latitude in degrees shown in the corners of the map, from
+    /// the top left going clockwise, where it shows anything.
+    pub corner_points: [Option<(f64, f64)>; 4],
+    transformation: Option<Transformation>,
+}
+
+impl Georeferencing {
+    /// That of a map of `dimensions` pixels in `projection`, or `None` for
+    /// rubber sheets, whose plane is the source image's.
+    pub fn of(projection: &ProjectionData, body: &Body, dimensions: (u32, u32)) -> Option<Self> {
+        if let ProjectionData::RubberSheet(_) = projection {
+            return None;
+        }
+        let radius = body.equatorial_radius * 1000.;
+        let meters = meters_per_unit(projection, radius);
+        let plane = projection.dimensions();
+        let (width, height) = (plane.width() * meters, plane.height() * meters);
+        let transformation = transformation(projection).map(|mut transformation| {
+            let (long, lat) = transformation.origin;
+            let (x, y) = projection.project(&projections::point(long, lat));
+            transformation
+                .parameters
+                .extend([(FALSE_EASTING, x * meters), (FALSE_NORTHING, y * meters)]);
+            transformation
+        });
+        let (columns, rows) = (dimensions.0 as f64, dimensions.1 as f64);
+        let corner_points = [(0., 0.), (columns, 0.), (columns, rows), (0., rows)].map(|pixel| {
+            projection
+                .point_at_pixel(pixel, dimensions)
+                .map(|point| (point.long(), point.lat()))
+        });
+        Some(Georeferencing {
+            dimensions,
+            origin: (-width / 2., height / 2.),
+            pixel_size: (width / columns, height / rows),
+            radius,
+            projection_name: projection.kind().to_string(),
+            body_name: body.name(),
+            corner_points,
+            transformation,
+        })
+    }
+
+    /// As GDAL gives it: the easting of the origin, the pixel width and the
+    /// row rotation, then the northing of the origin, the column rotation and
+    /// the pixel height, which is negative as rows go south.
+    pub fn geotransform(&self) -> [f64; 6] {
+        [
+            self.origin.0,
+            self.pixel_size.0,
+            0.,
+            self.origin.1,
+            0.,
+            -self.pixel_size.1,
+        ]
+    }
+
+    /// Easting and northing of the corners, from the top left going clockwise.
+    pub fn corners(&self) -> [(f64, f64); 4] {
+        let (left, top) = self.origin;
+        let right = left + self.pixel_size.0 * self.dimensions.0 as f64;
+        let bottom = top - self.pixel_size.1 * self.dimensions.1 as f64;
+        [(left, top), (right, top), (right, bottom), (left, bottom)]
+    }
+
+    /// Whether readers learn the projection, and not only the grid.
+    pub fn has_projection(&self) -> bool {
+        self.transformation.is_some()
+    }
+
+    fn citation(&self) -> String {
+        format!(
+            "{} on a sphere of {} m radius ({})",
+            self.projection_name, self.radius, self.body_name
+        )
+    }
+
+    /// The directory of geokeys, with the values it points to in the double
+    /// and ASCII parameter tags.
+    fn geokeys(&self) -> (Vec<u16>, Vec<f64>, String) {
+        let mut ascii = String::new();
+        let mut doubles = Vec::new();
+        let mut keys: Vec<[u16; 4]> = Vec::new();
+        let short = |keys: &mut Vec<[u16; 4]>, key, value| keys.push([key, 0, 1, value]);
+        short(&mut keys, MODEL_TYPE, PROJECTED);
+        short(&mut keys, RASTER_TYPE, PIXEL_IS_AREA);
+        for (key, text) in [
+            (CITATION, self.citation()),
+            (GEOGRAPHIC_CITATION, format!("{} sphere", self.body_name)),
+        ] {
+            // Non-ASCII text would make the TIFF unreadable.
+            let text: String = text.chars().filter(char::is_ascii).collect();
+            keys.push([
+                key,
+                Tag::GeoAsciiParamsTag.to_u16(),
+                text.len() as u16 + 1,
+                ascii.len() as u16,
+            ]);
+            ascii.push_str(&text);
+            ascii.push('|');
+        }
+        let mut double = |keys: &mut Vec<[u16; 4]>, key, value| {
+            keys.push([
+                key,
+                Tag::GeoDoubleParamsTag.to_u16(),
+                1,
+                doubles.len() as u16,
+            ]);
+            doubles.push(value);
+        };
+        short(&mut keys, GEOGRAPHIC_TYPE, USER_DEFINED);
+        short(&mut keys, GEODETIC_DATUM, USER_DEFINED);
+        short(&mut keys, PRIME_MERIDIAN, GREENWICH);
+        short(&mut keys, ANGULAR_UNITS, DEGREE);
+        short(&mut keys, ELLIPSOID, USER_DEFINED);
+        double(&mut keys, SEMI_MAJOR_AXIS, self.radius);
+        double(&mut keys, SEMI_MINOR_AXIS, self.radius);
+        short(&mut keys, PROJECTED_TYPE, USER_DEFINED);
+        short(&mut keys, PROJECTION, USER_DEFINED);
+        if let Some(transformation) = &self.transformation {
+            short(&mut keys, COORDINATE_TRANSFORMATION, transformation.code);
+        }
+        short(&mut keys, LINEAR_UNITS, METER);
+        for (key, value) in self
+            .transformation
+            .iter()
+            .flat_map(|transformation| &transformation.parameters)
+        {
+            double(&mut keys, *key, *value);
+        }
+        // Readers look keys up in order.
+        keys.sort_by_key(|entry| entry[0]);
+        let mut directory = vec![1, 1, 0, keys.len() as u16];
+        directory.extend(keys.into_iter().flatten());
+        (directory, doubles, ascii)
+    }
+
+    fn write_tags<W: Write + Seek>(
+        &self,
+        encoder: &mut tiff::encoder::DirectoryEncoder<W, tiff::encoder::TiffKindStandard>,
+    ) -> tiff::TiffResult<()> {
+        let (directory, doubles, ascii) = self.geokeys();
+        encoder.write_tag(
+            Tag::ModelPixelScaleTag,
+            &[self.pixel_size.0, self.pixel_size.1, 0.][..],
+        )?;
+        encoder.write_tag(
+            Tag::ModelTiepointTag,
+            &[0., 0., 0., self.origin.0, self.origin.1, 0.][..],
+        )?;
+        encoder.write_tag(Tag::GeoKeyDirectoryTag, &directory[..])?;
+        encoder.write_tag(Tag::GeoDoubleParamsTag, &doubles[..])?;
+        encoder.write_tag(Tag::GeoAsciiParamsTag, ascii.as_str())
+    }
+}
+
+/// Meters per unit of the plane `projection` draws in. The unit is the
+/// radius for the projections of this crate, which draw the unit sphere;
+/// for those of submaptive a degree along the meridian through the middle
+/// of the map is measured.
+fn meters_per_unit(projection: &ProjectionData, radius: f64) -> f64 {
+    let middle = match projection {
+        ProjectionData::Equirectangular(data) => (data.central_long(), 0.),
+        ProjectionData::AzimuthalEquidistant(data) => (data.center().long(), data.center().lat()),
+        _ => return radius,
+    };
+    let next = match middle.1 > 89. {
+        true => middle.1 - 1.,
+        false => middle.1 + 1.,
+    };
+    let a = projection.project(&projections::point(middle.0, middle.1));
+    let b = projection.project(&projections::point(middle.0, next));
+    radius * 1f64.to_radians() / (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+/// Saves `image` as a TIFF with `georeferencing` in its GeoTIFF tags, in
+/// its own sample depth where TIFF has one.
+pub fn write(
+    path: &Path,
+    image: &image::DynamicImage,
+    georeferencing: &Georeferencing,
+) -> Result<(), String> {
+    let error = |e: tiff::TiffError| format!("Could not write {}: {}", path.display(), e);
+    let file = std::fs::File::create(path).map_err(|e| error(e.into()))?;
+    let mut encoder = TiffEncoder::new(std::io::BufWriter::new(file)).map_err(error)?;
+    let dimensions = (image.width(), image.height());
+    let encoder = &mut encoder;
+    let georeferencing = Some(georeferencing);
+    match image {
+        image::DynamicImage::ImageLuma8(data) => {
+            write_image::<_, colortype::Gray8>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgb8(data) => {
+            write_image::<_, colortype::RGB8>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgba8(data) => {
+            write_image::<_, colortype::RGBA8>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageLuma16(data) => {
+            write_image::<_, colortype::Gray16>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgb16(data) => {
+            write_image::<_, colortype::RGB16>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgba16(data) => {
+            write_image::<_, colortype::RGBA16>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgb32F(data) => {
+            write_image::<_, colortype::RGB32Float>(encoder, dimensions, data, georeferencing)
+        }
+        image::DynamicImage::ImageRgba32F(data) => {
+            write_image::<_, colortype::RGBA32Float>(encoder, dimensions, data, georeferencing)
+        }
+        other => write_image::<_, colortype::RGBA8>(
+            encoder,
+            dimensions,
+            &other.to_rgba8(),
+            georeferencing,
+        ),
+    }
+    .map_err(error)
+}
+
+fn write_image<W: Write + Seek, C: ColorType>(
+    encoder: &mut TiffEncoder<W>,
+    (width, height): (u32, u32),
+    data: &[C::Inner],
+    georeferencing: Option<&Georeferencing>,
+) -> tiff::TiffResult<()>
+where
+    [C::Inner]: TiffValue,
+{
+    let mut image = encoder.new_image::<C>(width, height)?;
+    if let Some(georeferencing) = georeferencing {
+        georeferencing.write_tags(image.encoder())?;
+    }
+    image.write_data(data)
+}
+
+/// A GeoTIFF export waiting for the user to check its georeferencing.
+pub struct Confirmation {
+    pub path: PathBuf,
+    /// The map at full resolution, before layers are put over it.
+    pub projected: image::DynamicImage,
+    pub georeferencing: Georeferencing,
+}
+
+/// Shows how the pending export is georeferenced. Returns it once the user
+/// confirms it, and drops it if they cancel.
+pub fn confirmation_window(
+    ctx: &egui::Context,
+    pending: &mut Option<Confirmation>,
+    unit: AngleUnit,
+) -> Option<Confirmation> {
+    let confirmation = pending.as_ref()?;
+    let (mut open, mut confirmed, mut cancelled) = (true, false, false);
+    egui::Window::new("Export GeoTIFF")
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(confirmation.path.display().to_string());
+            georeferencing_ui(ui, &confirmation.georeferencing, unit);
+            ui.horizontal(|ui| {
+                confirmed = ui.button("Export").clicked();
+                cancelled = ui.button("Cancel").clicked();
+            });
+        });
+    match (confirmed, open && !cancelled) {
+        (true, _) => pending.take(),
+        (false, true) => None,
+        (false, false) => {
+            *pending = None;
+            None
+        }
+    }
+}
+
+fn georeferencing_ui(ui: &mut egui::Ui, georeferencing: &Georeferencing, unit: AngleUnit) {
+    let meters = |(x, y): (f64, f64)| format!("E {:.3} m, N {:.3} m", x, y);
+    egui::Grid::new("Georeferencing").show(ui, |ui| {
+        ui.label("Projection");
+        ui.label(georeferencing.citation());
+        ui.end_row();
+        ui.label("Size");
+        ui.label(format!(
+            "{} × {} px",
+            georeferencing.dimensions.0, georeferencing.dimensions.1
+        ));
+        ui.end_row();
+        ui.label("Pixel size");
+        ui.label(format!(
+            "{:.3} × {:.3} m",
+            georeferencing.pixel_size.0, georeferencing.pixel_size.1
+        ));
+        ui.end_row();
+        ui.label("Geotransform");
+        ui.label(
+            georeferencing
+                .geotransform()
+                .map(|value| format!("{:.3}", value))
+                .join(", "),
+        );
+        ui.end_row();
+        let names = ["Top left", "Top right", "Bottom right", "Bottom left"];
+        for ((name, corner), point) in names
+            .into_iter()
+            .zip(georeferencing.corners())
+            .zip(georeferencing.corner_points)
+        {
+            ui.label(name);
+            ui.label(meters(corner));
+            ui.label(match point {
+                Some((long, lat)) => format!("{}, {}", unit.format(lat), unit.format(long)),
+                None => "Off the map".to_string(),
+            });
+            ui.end_row();
+        }
+    });
+    if !georeferencing.has_projection() {
+        ui.weak(
+            "GeoTIFF has no code for this projection, so other programs get the \
+             pixel grid in meters but not the projection.",
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProjectionKind;
+
+    #[test]
+    fn web_mercator_spans_the_world_as_epsg_3857_does() {
+        let projection = ProjectionKind::WebMercator.default_projection_data();
+        let georeferencing =
+            Georeferencing::of(&projection, &Body::default(), (1024, 1024)).unwrap();
+        let half = std::f64::consts::PI * 6378137.;
+        let [(left, top), _, (right, bottom), _] = georeferencing.corners();
+        for (value, expected) in [(left, -half), (top, half), (right, half), (bottom, -half)] {
+            assert!(
+                (value - expected).abs() < 1e-3,
+                "{} isn't {}",
+                value,
+                expected
+            );
+        }
+        assert!(georeferencing.has_projection());
+    }
+
+    #[test]
+    fn pixels_are_georeferenced_in_the_tiff() {
+        let projection = ProjectionKind::Mollweide.default_projection_data();
+        let georeferencing = Georeferencing::of(&projection, &Body::default(), (8, 4)).unwrap();
+        let path = std::env::temp_dir().join("submaptive-geotiff-test.tif");
+        let image = image::DynamicImage::ImageRgba8(image::RgbaImage::new(8, 4));
+        write(&path, &image, &georeferencing).unwrap();
+        let file = std::io::BufReader::new(std::fs::File::open(&path).unwrap());
+        let mut decoder = tiff::decoder::Decoder::new(file).unwrap();
+        let scale = decoder.get_tag_f64_vec(Tag::ModelPixelScaleTag).unwrap();
+        let tiepoint = decoder.get_tag_f64_vec(Tag::ModelTiepointTag).unwrap();
+        let keys = decoder.get_tag_u16_vec(Tag::GeoKeyDirectoryTag).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let [x, _, _, y, _, height] = georeferencing.geotransform();
+        assert_eq!(scale[..2], [georeferencing.pixel_size.0, -height]);
+        assert_eq!(tiepoint, [0., 0., 0., x, y, 0.]);
+        // Header, then the keys in order.
+        assert_eq!(keys[3] as usize, (keys.len() - 4) / 4);
+        let ids: Vec<_> = keys[4..].chunks(4).map(|key| key[0]).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        // Mollweide has no code, so only the grid is given.
+        assert!(!ids.contains(&COORDINATE_TRANSFORMATION));
+    }
+
+    #[test]
+    fn false_easting_and_northing_place_the_origin() {
+        let projection =
+            ProjectionData::LambertConformalConic(projections::LambertConformalConic {
+                central_long: 10.,
+                origin_lat: 52.,
+                standard_parallels: [35., 65.],
+            });
+        let georeferencing = Georeferencing::of(&projection, &Body::default(), (64, 64)).unwrap();
+        let parameters = &georeferencing.transformation.as_ref().unwrap().parameters;
+        let value = |key| {
+            parameters
+                .iter()
+                .find(|(other, _)| *other == key)
+                .unwrap()
+                .1
+        };
+        // The map is centered on its latitude of origin.
+        assert!(value(FALSE_EASTING).abs() < 1e-6);
+        assert!(value(FALSE_NORTHING).abs() < 1e-6);
+        assert_eq!(value(FALSE_ORIGIN_LAT), 52.);
+    }
+}
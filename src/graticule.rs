@@ -0,0 +1,446 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::labels::{Labels, Priority};
+use crate::longitudes::Longitudes;
+use crate::ProjectionData;
+
+/// Degrees of latitude or longitude between the points a line starts out with,
+/// before it is subdivided where it curves.
+const COARSE_STEP: f64 = 10.;
+/// How far (in points) a drawn line may stray from the true one.
+const TOLERANCE: f32 = 0.3;
+/// Subdividing 10° this often gets down to about a second of arc.
+const MAX_DEPTH: u32 = 15;
+/// A segment still longer than this (in points) at the finest subdivision
+/// crosses a gap in the map.
+const JUMP: f32 = 16.;
+/// How close (in points) the end of a line must come to the frame to get a tick.
+const FRAME_DISTANCE: f32 = 3.;
+const TICK_LENGTH: f32 = 8.;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Dash {
+    const ALL: [Dash; 3] = [Dash::Solid, Dash::Dashed, Dash::Dotted];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Dash::Solid => "Solid",
+            Dash::Dashed => "Dashed",
+            Dash::Dotted => "Dotted",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LineStyle {
+    pub color: [u8; 3],
+    pub width: f32,
+    pub dash: Dash,
+}
+
+impl Default for LineStyle {
+    fn default() -> Self {
+        LineStyle {
+            color: [255, 255, 255],
+            width: 1.,
+            dash: Dash::Solid,
+        }
+    }
+}
+
+impl LineStyle {
+    fn stroke(&self) -> egui::Stroke {
+        let [r, g, b] = self.color;
+        egui::Stroke::new(self.width, egui::Color32::from_rgb(r, g, b))
+    }
+
+    pub fn paint(&self, painter: &egui::Painter, points: &[egui::Pos2]) {
+        let stroke = self.stroke();
+        match self.dash {
+            Dash::Solid => {
+                painter.add(egui::Shape::line(points.to_vec(), stroke));
+            }
+            Dash::Dashed => {
+                let dash = 4. * self.width + 4.;
+                painter.extend(egui::Shape::dashed_line(points, stroke, dash, dash / 2.));
+            }
+            Dash::Dotted => {
+                painter.extend(egui::Shape::dotted_line(
+                    points,
+                    stroke.color,
+                    3. * self.width + 2.,
+                    self.width,
+                ));
+            }
+        }
+    }
+}
+
+/// Lines of latitude and longitude drawn over the source and projected maps.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Graticule {
+    /// Degrees between lines.
+    pub spacing: f64,
+    pub meridians: LineStyle,
+    pub parallels: LineStyle,
+    /// For the equator, the tropics and the polar circles, where they are shown.
+    pub special: LineStyle,
+    pub equator: bool,
+    pub tropics: bool,
+    pub polar_circles: bool,
+    /// Draw only a frame around the map, with ticks where the lines would meet it.
+    pub frame_only: bool,
+    pub labels: bool,
+}
+
+impl Default for Graticule {
+    fn default() -> Self {
+        let line = LineStyle {
+            color: [255, 255, 255],
+            width: 1.,
+            dash: Dash::Solid,
+        };
+        Graticule {
+            spacing: 30.,
+            meridians: line,
+            parallels: line,
+            special: LineStyle {
+                color: [255, 200, 80],
+                width: 1.5,
+                dash: Dash::Dashed,
+            },
+            equator: true,
+            tropics: false,
+            polar_circles: false,
+            frame_only: false,
+            labels: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineKind {
+    Meridian(f64),
+    Parallel(f64),
+}
+
+/// A graticule line as it appears on screen, split where it leaves the
+/// projection or jumps across the map.
+struct ScreenLine {
+    kind: LineKind,
+    pieces: Vec<Vec<egui::Pos2>>,
+}
+
+/// Multiples of `spacing` from `min` to `max`.
+fn multiples(spacing: f64, min: f64, max: f64) -> impl Iterator<Item = f64> {
+    let first = (min / spacing).ceil() as i64;
+    let last = (max / spacing).floor() as i64;
+    (first..=last).map(move |i| i as f64 * spacing)
+}
+
+impl LineKind {
+    /// The range the line runs over: latitudes for meridians, longitudes for parallels.
+    fn range(&self) -> (f64, f64) {
+        match self {
+            LineKind::Meridian(_) => (-90., 90.),
+            LineKind::Parallel(_) => (-180., 180.),
+        }
+    }
+
+    fn point(&self, along: f64) -> (f64, f64) {
+        match *self {
+            LineKind::Meridian(long) => (long, along),
+            LineKind::Parallel(lat) => (along, lat),
+        }
+    }
+}
+
+/// Adds the screen positions of a line from after `a` up to `b`, subdivided
+/// until the line is straight to within `TOLERANCE`. A `None` marks a break,
+/// where the line leaves the projection or jumps across the map.
+/// `to_screen` places the point a parameter (`a.0` to `b.0`) is at along the line.
+pub fn densify(
+    to_screen: &dyn Fn(f64) -> Option<egui::Pos2>,
+    clip: egui::Rect,
+    a: (f64, Option<egui::Pos2>),
+    b: (f64, Option<egui::Pos2>),
+    depth: u32,
+    positions: &mut Vec<Option<egui::Pos2>>,
+) {
+    let middle = (a.0 + b.0) / 2.;
+    let middle = (middle, to_screen(middle));
+    let subdivide = depth < MAX_DEPTH
+        && match (a.1, middle.1, b.1) {
+            (Some(start), Some(middle), Some(end)) => {
+                // Curvature only matters for what can be seen.
+                let bounds = egui::Rect::from_two_pos(start, end)
+                    .union(egui::Rect::from_two_pos(middle, middle));
+                bounds.expand(TOLERANCE).intersects(clip)
+                    && (start + (end - start) / 2.).distance(middle) > TOLERANCE
+            }
+            (None, None, None) => false,
+            // Find where exactly the line leaves the projection.
+            _ => true,
+        };
+    if subdivide {
+        densify(to_screen, clip, a, middle, depth + 1, positions);
+        densify(to_screen, clip, middle, b, depth + 1, positions);
+        return;
+    }
+    if let (Some(start), Some(end)) = (a.1, b.1) {
+        if depth == MAX_DEPTH && start.distance(end) > JUMP {
+            positions.push(None);
+        }
+    }
+    positions.push(b.1);
+}
+
+/// Puts `kind` on screen, for a map in `projection` drawn into `rect`, with
+/// as many points as it takes to look smooth within `clip`.
+fn screen_line(
+    kind: LineKind,
+    projection: &ProjectionData,
+    image_dimensions: (u32, u32),
+    rect: egui::Rect,
+    clip: egui::Rect,
+) -> ScreenLine {
+    let to_screen = |along: f64| {
+        let point = kind.point(along).try_into().ok()?;
+        let (x, y) = projection.pixel_at_point(&point, image_dimensions)?;
+        Some(
+            rect.min
+                + egui::vec2(
+                    x as f32 / image_dimensions.0 as f32 * rect.width(),
+                    y as f32 / image_dimensions.1 as f32 * rect.height(),
+                ),
+        )
+    };
+    let (min, max) = kind.range();
+    let samples: Vec<_> = multiples(COARSE_STEP, min, max)
+        .map(|along| (along, to_screen(along)))
+        .collect();
+    let mut positions = vec![samples[0].1];
+    for pair in samples.windows(2) {
+        densify(&to_screen, clip, pair[0], pair[1], 0, &mut positions);
+    }
+    let pieces = positions
+        .split(Option::is_none)
+        .filter(|piece| piece.len() > 1)
+        .map(|piece| piece.iter().flatten().copied().collect())
+        .collect();
+    ScreenLine { kind, pieces }
+}
+
+impl Graticule {
+    /// The axial tilt of the body decides where the tropics and polar circles are.
+    fn special_latitudes(&self, axial_tilt: f64) -> Vec<f64> {
+        let mut latitudes = Vec::new();
+        if self.equator {
+            latitudes.push(0.);
+        }
+        if self.tropics {
+            latitudes.extend([axial_tilt, -axial_tilt]);
+        }
+        if self.polar_circles {
+            latitudes.extend([90. - axial_tilt, axial_tilt - 90.]);
+        }
+        latitudes
+    }
+
+    /// The regular lines, which the special ones are drawn over.
+    fn regular_lines(&self) -> Vec<LineKind> {
+        let spacing = self.spacing.max(0.1);
+        let meridians = multiples(spacing, -180., 180.)
+            // −180° and 180° are the same meridian.
+            .filter(|long| *long > -180.)
+            .map(LineKind::Meridian);
+        let parallels = multiples(spacing, -90., 90.)
+            .filter(|lat| lat.abs() < 90.)
+            .map(LineKind::Parallel);
+        meridians.chain(parallels).collect()
+    }
+
+    fn style(&self, kind: LineKind) -> &LineStyle {
+        match kind {
+            LineKind::Meridian(_) => &self.meridians,
+            LineKind::Parallel(_) => &self.parallels,
+        }
+    }
+}
+
+fn label(kind: LineKind, longitudes: &Longitudes, unit: AngleUnit) -> String {
+    match kind {
+        LineKind::Meridian(long) => unit.format(longitudes.map_long(long)),
+        LineKind::Parallel(lat) => unit.format(lat),
+    }
+}
+
+/// Whether `position` is on the edge of `rect`.
+fn on_frame(rect: egui::Rect, position: egui::Pos2) -> bool {
+    let inner = rect.shrink(FRAME_DISTANCE);
+    rect.expand(FRAME_DISTANCE).contains(position) && !inner.contains(position)
+}
+
+/// Where each piece of `line` ends on the frame of `rect`: the position and
+/// the direction into the map.
+fn frame_ends(line: &ScreenLine, rect: egui::Rect) -> Vec<(egui::Pos2, egui::Vec2)> {
+    let mut ends = Vec::new();
+    for piece in &line.pieces {
+        let n = piece.len();
+        for (end, next) in [(piece[0], piece[1]), (piece[n - 1], piece[n - 2])] {
+            if on_frame(rect, end) {
+                ends.push((end, (next - end).normalized()));
+            }
+        }
+    }
+    ends
+}
+
+/// Draws the graticule over a map in `projection` drawn into `rect`.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_graticule(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    projection: &ProjectionData,
+    graticule: &Graticule,
+    axial_tilt: f64,
+    longitudes: &Longitudes,
+    unit: AngleUnit,
+    labels: &mut Labels,
+) {
+    let regular: Vec<_> = graticule
+        .regular_lines()
+        .into_iter()
+        .map(|kind| {
+            screen_line(
+                kind,
+                projection,
+                image_dimensions,
+                rect,
+                painter.clip_rect(),
+            )
+        })
+        .collect();
+    let font = egui::FontId::proportional(11.);
+    if graticule.frame_only {
+        painter.rect_stroke(rect, 0., graticule.meridians.stroke());
+        for line in &regular {
+            let stroke = graticule.style(line.kind).stroke();
+            for (end, inwards) in frame_ends(line, rect) {
+                painter.line_segment([end, end + inwards * TICK_LENGTH], stroke);
+                if graticule.labels {
+                    labels.add(
+                        end,
+                        (outward_align(inwards), -inwards * 2.),
+                        label(line.kind, longitudes, unit),
+                        font.clone(),
+                        stroke.color,
+                        Priority::Graticule,
+                    );
+                }
+            }
+        }
+        return;
+    }
+    for line in &regular {
+        let style = graticule.style(line.kind);
+        for piece in &line.pieces {
+            style.paint(painter, piece);
+        }
+    }
+    for lat in graticule.special_latitudes(axial_tilt) {
+        let line = screen_line(
+            LineKind::Parallel(lat),
+            projection,
+            image_dimensions,
+            rect,
+            painter.clip_rect(),
+        );
+        for piece in &line.pieces {
+            graticule.special.paint(painter, piece);
+        }
+    }
+    if graticule.labels {
+        for line in &regular {
+            let Some(start) = line.pieces.first().and_then(|piece| piece.first()) else {
+                continue;
+            };
+            labels.add(
+                *start,
+                (egui::Align2::LEFT_BOTTOM, egui::vec2(2., -2.)),
+                label(line.kind, longitudes, unit),
+                font.clone(),
+                graticule.style(line.kind).stroke().color,
+                Priority::Graticule,
+            );
+        }
+    }
+}
+
+/// Labels sit outside the frame, on the side away from the map.
+fn outward_align(inwards: egui::Vec2) -> egui::Align2 {
+    if inwards.x.abs() > inwards.y.abs() {
+        if inwards.x > 0. {
+            egui::Align2::RIGHT_CENTER
+        } else {
+            egui::Align2::LEFT_CENTER
+        }
+    } else if inwards.y > 0. {
+        egui::Align2::CENTER_BOTTOM
+    } else {
+        egui::Align2::CENTER_TOP
+    }
+}
+
+pub fn line_style_ui(ui: &mut egui::Ui, style: &mut LineStyle, label: &str) {
+    ui.horizontal(|ui| {
+        ui.color_edit_button_srgb(&mut style.color);
+        ui.add(
+            egui::DragValue::new(&mut style.width)
+                .clamp_range(0.5..=8.)
+                .speed(0.1)
+                .suffix(" pt"),
+        );
+        egui::ComboBox::new(label, label)
+            .selected_text(style.dash.name())
+            .show_ui(ui, |ui| {
+                for dash in Dash::ALL {
+                    ui.selectable_value(&mut style.dash, dash, dash.name());
+                }
+            });
+    });
+}
+
+pub fn graticule_ui(ui: &mut egui::Ui, graticule: &mut Graticule, unit: AngleUnit) {
+    ui.collapsing("Graticule", |ui| {
+        ui.add(
+            egui::DragValue::new(&mut graticule.spacing)
+                .clamp_range(0.1..=90.)
+                .speed(0.1)
+                .angle(unit)
+                .prefix("Every "),
+        );
+        line_style_ui(ui, &mut graticule.meridians, "Meridians");
+        line_style_ui(ui, &mut graticule.parallels, "Parallels");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut graticule.equator, "Equator");
+            ui.checkbox(&mut graticule.tropics, "Tropics");
+            ui.checkbox(&mut graticule.polar_circles, "Polar circles");
+        });
+        line_style_ui(ui, &mut graticule.special, "Special lines");
+        ui.checkbox(&mut graticule.frame_only, "Only a frame with ticks");
+        ui.checkbox(&mut graticule.labels, "Labels");
+    });
+}
@@ -0,0 +1,205 @@
+use eframe::egui;
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::georeference::ControlPoint;
+
+/// The largest side length the analysis works at; detected positions are
+/// scaled back to the full image afterwards.
+const ANALYSIS_SIZE: u32 = 2048;
+/// How far (in analysis pixels) a line is compared against its surroundings.
+const LINE_NEIGHBORHOOD: usize = 3;
+
+/// Experimental detection of graticule lines running parallel to the image edges.
+pub struct GraticuleDetection {
+    pub lines: Option<DetectedLines>,
+    /// Longitude of the leftmost and latitude of the topmost detected line.
+    pub first_long: f64,
+    pub first_lat: f64,
+    /// Degrees between neighbouring detected lines.
+    pub spacing: f64,
+}
+
+impl Default for GraticuleDetection {
+    fn default() -> Self {
+        GraticuleDetection {
+            lines: None,
+            first_long: -180.,
+            first_lat: 90.,
+            spacing: 30.,
+        }
+    }
+}
+
+/// Pixel positions of detected graticule lines in the full-resolution image.
+pub struct DetectedLines {
+    /// y coordinates of the parallels, top to bottom.
+    pub rows: Vec<f64>,
+    /// x coordinates of the meridians, left to right.
+    pub columns: Vec<f64>,
+}
+
+impl GraticuleDetection {
+    pub fn detect(&mut self, image: &image::DynamicImage) {
+        let analysed = image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+        let scale_x = image.width() as f64 / analysed.width() as f64;
+        let scale_y = image.height() as f64 / analysed.height() as f64;
+        let (width, height) = (analysed.width() as usize, analysed.height() as usize);
+        let luma = |x: usize, y: usize| analysed.get_pixel(x as u32, y as u32).0[0] as f64;
+        let row_profile = line_profile(height, width, |along, across| luma(across, along));
+        let column_profile = line_profile(width, height, luma);
+        self.lines = Some(DetectedLines {
+            rows: find_lines(&row_profile)
+                .into_iter()
+                .map(|y| (y + 0.5) * scale_y)
+                .collect(),
+            columns: find_lines(&column_profile)
+                .into_iter()
+                .map(|x| (x + 0.5) * scale_x)
+                .collect(),
+        });
+    }
+
+    /// Control points at every intersection of the detected lines.
+    pub fn proposed_control_points(&self) -> Vec<ControlPoint> {
+        let Some(lines) = &self.lines else {
+            return Vec::new();
+        };
+        let mut points = Vec::new();
+        for (row_index, y) in lines.rows.iter().enumerate() {
+            for (column_index, x) in lines.columns.iter().enumerate() {
+                let long = self.first_long + column_index as f64 * self.spacing;
+                let lat = self.first_lat - row_index as f64 * self.spacing;
+                if (-180.0..=180.).contains(&long) && (-90.0..=90.).contains(&lat) {
+                    points.push(ControlPoint {
+                        pixel: (*x, *y),
+                        long,
+                        lat,
+                        enabled: true,
+                    });
+                }
+            }
+        }
+        points
+    }
+}
+
+/// How much darker each line of the image is than the lines a few pixels to
+/// either side of it, summed along the line.
+fn line_profile(count: usize, length: usize, luma: impl Fn(usize, usize) -> f64) -> Vec<f64> {
+    (0..count)
+        .map(|line| {
+            if line < LINE_NEIGHBORHOOD || line + LINE_NEIGHBORHOOD >= count {
+                return 0.0;
+            }
+            (0..length)
+                .map(|position| {
+                    let surroundings = (luma(line - LINE_NEIGHBORHOOD, position)
+                        + luma(line + LINE_NEIGHBORHOOD, position))
+                        / 2.;
+                    (surroundings - luma(line, position)).max(0.)
+                })
+                .sum::<f64>()
+                / length as f64
+        })
+        .collect()
+}
+
+/// Picks the strong local maxima of a profile and drops those that don't fit
+/// the dominant spacing between lines.
+fn find_lines(profile: &[f64]) -> Vec<f64> {
+    let mean = profile.iter().sum::<f64>() / profile.len() as f64;
+    let deviation =
+        (profile.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / profile.len() as f64).sqrt();
+    let threshold = mean + 3. * deviation;
+    let mut peaks: Vec<usize> = (1..profile.len().saturating_sub(1))
+        .filter(|&i| {
+            profile[i] > threshold && profile[i] >= profile[i - 1] && profile[i] > profile[i + 1]
+        })
+        .collect();
+    // Merge peaks belonging to the same thick line.
+    peaks.dedup_by(|next, previous| *next - *previous <= 2 * LINE_NEIGHBORHOOD);
+    if peaks.len() < 3 {
+        return peaks.into_iter().map(|i| i as f64).collect();
+    }
+    let mut gaps: Vec<usize> = peaks.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    gaps.sort_unstable();
+    let spacing = gaps[gaps.len() / 2] as f64;
+    let origin = peaks[0] as f64;
+    peaks
+        .into_iter()
+        .map(|i| i as f64)
+        .filter(|&position| {
+            let steps = (position - origin) / spacing;
+            (steps - steps.round()).abs() < 0.2
+        })
+        .collect()
+}
+
+pub fn graticule_detection_ui(
+    ui: &mut egui::Ui,
+    detection: &mut GraticuleDetection,
+    unit: AngleUnit,
+    corrected_source: impl FnOnce() -> Option<image::DynamicImage>,
+) -> Vec<ControlPoint> {
+    let mut accepted = Vec::new();
+    ui.collapsing("Detect graticule (experimental)", |ui| {
+        ui.label("Finds graticule lines that run parallel to the image edges.");
+        if ui.button("Detect").clicked() {
+            if let Some(image) = corrected_source() {
+                detection.detect(&image);
+            }
+        }
+        let Some(lines) = &detection.lines else {
+            return;
+        };
+        ui.label(format!(
+            "Found {} parallels and {} meridians.",
+            lines.rows.len(),
+            lines.columns.len()
+        ));
+        for (value, range, label) in [
+            (
+                &mut detection.first_long,
+                -180.0..=180.,
+                "Leftmost meridian",
+            ),
+            (&mut detection.first_lat, -90.0..=90., "Topmost parallel"),
+            (&mut detection.spacing, 0.1..=90., "Between lines"),
+        ] {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(value).clamp_range(range).angle(unit));
+                ui.label(label);
+            });
+        }
+        let proposed = detection.proposed_control_points();
+        if ui
+            .button(format!("Add {} control points", proposed.len()))
+            .clicked()
+        {
+            accepted = proposed;
+            detection.lines = None;
+        }
+    });
+    accepted
+}
+
+/// Draws the detected lines over the source image drawn into `rect`.
+pub fn paint_detected_lines(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    detection: &GraticuleDetection,
+) {
+    let Some(lines) = &detection.lines else {
+        return;
+    };
+    let stroke = (1., egui::Color32::from_rgba_unmultiplied(0, 255, 255, 160));
+    for y in &lines.rows {
+        let y = rect.top() + *y as f32 / image_dimensions.1 as f32 * rect.height();
+        painter.hline(rect.x_range(), y, stroke);
+    }
+    for x in &lines.columns {
+        let x = rect.left() + *x as f32 / image_dimensions.0 as f32 * rect.width();
+        painter.vline(x, rect.y_range(), stroke);
+    }
+}
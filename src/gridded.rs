@@ -0,0 +1,727 @@
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::healpix;
+use crate::palettes::{self, Ramp};
+
+/// Scientific formats for values on a latitude-longitude grid, such as
+/// temperature or pressure from weather and climate models.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// NetCDF in its classic, 64-bit offset and 64-bit data forms. NetCDF-4
+    /// keeps its data in HDF5 and isn't read.
+    NetCdf,
+    /// GRIB edition 2.
+    Grib,
+    /// HEALPix maps in FITS tables, which are told from other FITS files by their header.
+    Healpix,
+}
+
+impl Format {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "nc" | "cdf" | "netcdf" => Some(Format::NetCdf),
+            "grib" | "grib2" | "grb" | "grb2" => Some(Format::Grib),
+            "fits" | "fit" | "fts" if healpix::is_map(path) => Some(Format::Healpix),
+            _ => None,
+        }
+    }
+}
+
+/// How values are turned into colors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Coloring {
+    pub ramp: Ramp,
+    /// The values at the ends of the ramp, or `None` for the field's own range.
+    pub range: Option<(f64, f64)>,
+}
+
+/// The first field of a file, on a regular grid with rows from north to
+/// south and columns from west to east. Missing values are NaN.
+pub struct Field {
+    pub name: String,
+    pub units: String,
+    width: usize,
+    height: usize,
+    values: Vec<f32>,
+    /// Centers of the first column and row, in degrees.
+    west: f64,
+    north: f64,
+    long_step: f64,
+    lat_step: f64,
+}
+
+impl Field {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let format = Format::for_path(path)
+            .ok_or_else(|| format!("{} is not a gridded data file.", name))?;
+        if format == Format::Healpix {
+            return healpix::read(path);
+        }
+        let file =
+            std::fs::File::open(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+        let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let reader = BufReader::new(file);
+        match format {
+            Format::NetCdf => read_netcdf(reader, length),
+            Format::Grib => read_grib(reader, length),
+            Format::Healpix => unreachable!(),
+        }
+        .map_err(|e| format!("{} can't be read: {}", name, e))
+    }
+
+    /// A field covering the whole world, its first row and column at the
+    /// north-west corner.
+    pub fn global(
+        name: String,
+        units: String,
+        width: usize,
+        height: usize,
+        values: Vec<f32>,
+    ) -> Self {
+        let (long_step, lat_step) = (360. / width as f64, 180. / height as f64);
+        Field {
+            name,
+            units,
+            width,
+            height,
+            values,
+            west: -180. + long_step / 2.,
+            north: 90. - lat_step / 2.,
+            long_step,
+            lat_step,
+        }
+    }
+
+    /// The smallest and largest value, or `None` if all of them are missing.
+    pub fn range(&self) -> Option<(f64, f64)> {
+        let mut values = self.values.iter().filter(|value| !value.is_nan());
+        let first = *values.next()? as f64;
+        Some(values.fold((first, first), |(low, high), &value| {
+            (low.min(value as f64), high.max(value as f64))
+        }))
+    }
+
+    /// The size of the world map the field is drawn on, a pixel per grid cell.
+    pub fn canvas_dimensions(&self) -> (u32, u32) {
+        (
+            (360. / self.long_step).round().max(1.) as u32,
+            (180. / self.lat_step).round().max(1.) as u32,
+        )
+    }
+
+    /// Draws the field on an equirectangular map of the whole world centered
+    /// on Greenwich, transparent wherever it has no value.
+    pub fn render(&self, coloring: &Coloring) -> image::DynamicImage {
+        let (width, height) = self.canvas_dimensions();
+        let (low, high) = coloring.range.or(self.range()).unwrap_or((0., 1.));
+        let west_edge = self.west - self.long_step / 2.;
+        let north_edge = self.north + self.lat_step / 2.;
+        let global = self.width as f64 * self.long_step >= 360. - self.long_step / 2.;
+        let image = image::RgbaImage::from_fn(width, height, |x, y| {
+            let long = -180. + (x as f64 + 0.5) * 360. / width as f64;
+            let lat = 90. - (y as f64 + 0.5) * 180. / height as f64;
+            let column = ((long - west_edge).rem_euclid(360.) / self.long_step) as usize;
+            let column = match global {
+                true => column.min(self.width - 1),
+                false => column,
+            };
+            let row = (north_edge - lat) / self.lat_step;
+            if row < 0. || row as usize >= self.height || column >= self.width {
+                return image::Rgba([0; 4]);
+            }
+            let value = self.values[row as usize * self.width + column];
+            if value.is_nan() {
+                return image::Rgba([0; 4]);
+            }
+            let t = match high > low {
+                true => (value as f64 - low) / (high - low),
+                false => 0.5,
+            };
+            let color = coloring.ramp.color_at(t);
+            image::Rgba([color.r(), color.g(), color.b(), 255])
+        });
+        image::DynamicImage::ImageRgba8(image)
+    }
+}
+
+/// The spacing of evenly spaced coordinates, which grids need to be drawn.
+fn regular(coordinates: &[f64], axis: &str) -> Result<f64, String> {
+    let (Some(first), Some(last)) = (coordinates.first(), coordinates.last()) else {
+        return Err(format!("it has no {} coordinates", axis));
+    };
+    let step = match coordinates.len() {
+        1 => return Err(format!("it has a single {}", axis)),
+        count => (last - first) / (count - 1) as f64,
+    };
+    let even = coordinates
+        .iter()
+        .enumerate()
+        .all(|(index, value)| (value - (first + index as f64 * step)).abs() <= step.abs() * 0.01);
+    match even && step != 0. {
+        true => Ok(step),
+        false => Err(format!("its {} coordinates aren't evenly spaced", axis)),
+    }
+}
+
+/// Turns values stored in any row and column order north up and west first.
+fn oriented(values: Vec<f32>, width: usize, flip_rows: bool, flip_columns: bool) -> Vec<f32> {
+    let mut rows: Vec<&[f32]> = values.chunks(width).collect();
+    if flip_rows {
+        rows.reverse();
+    }
+    rows.into_iter()
+        .flat_map(|row| {
+            let mut row = row.to_vec();
+            if flip_columns {
+                row.reverse();
+            }
+            row
+        })
+        .collect()
+}
+
+const ABSENT: u32 = 0;
+const DIMENSIONS: u32 = 0x0a;
+const VARIABLES: u32 = 0x0b;
+const ATTRIBUTES: u32 = 0x0c;
+/// Most entries a list in a header may have, against sizes from damaged files.
+const MAX_ENTRIES: u64 = 1 << 20;
+const CHAR: u32 = 2;
+
+/// Reads a NetCDF header, whose counts are 64 bits wide in version 5.
+struct Header<R> {
+    reader: R,
+    version: u8,
+}
+
+impl<R: Read> Header<R> {
+    fn bytes(&mut self, count: u64) -> Result<Vec<u8>, String> {
+        let mut data = Vec::new();
+        (&mut self.reader)
+            .take(count)
+            .read_to_end(&mut data)
+            .map_err(|e| e.to_string())?;
+        match data.len() as u64 == count {
+            true => Ok(data),
+            false => Err("it ends in the middle of its header".to_string()),
+        }
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let data = self.bytes(4)?;
+        Ok(u32::from_be_bytes(data[..].try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let data = self.bytes(8)?;
+        Ok(u64::from_be_bytes(data[..].try_into().unwrap()))
+    }
+
+    fn count(&mut self) -> Result<u64, String> {
+        match self.version {
+            5 => self.u64(),
+            _ => self.u32().map(u64::from),
+        }
+    }
+
+    fn offset(&mut self) -> Result<u64, String> {
+        match self.version {
+            1 => self.u32().map(u64::from),
+            _ => self.u64(),
+        }
+    }
+
+    /// Bytes padded to a multiple of four.
+    fn padded(&mut self, count: u64) -> Result<Vec<u8>, String> {
+        let data = self.bytes(count)?;
+        self.bytes((4 - count % 4) % 4)?;
+        Ok(data)
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let length = self.count()?;
+        Ok(String::from_utf8_lossy(&self.padded(length)?).into_owned())
+    }
+
+    fn list<T>(
+        &mut self,
+        tag: u32,
+        mut entry: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let found = self.u32()?;
+        let count = self.count()?;
+        if (found != tag && (found, count) != (ABSENT, 0)) || count > MAX_ENTRIES {
+            return Err("its header is damaged".to_string());
+        }
+        (0..count).map(|_| entry(self)).collect()
+    }
+
+    fn attributes(&mut self) -> Result<Vec<Attribute>, String> {
+        self.list(ATTRIBUTES, |header| {
+            let name = header.name()?;
+            let nc_type = header.u32()?;
+            let count = header.count()?;
+            let size = type_size(nc_type).ok_or("it has an attribute of an unknown type")?;
+            Ok(Attribute {
+                name,
+                nc_type,
+                data: header.padded(count.saturating_mul(size))?,
+            })
+        })
+    }
+}
+
+/// The size of a NetCDF type in bytes.
+fn type_size(nc_type: u32) -> Option<u64> {
+    match nc_type {
+        1 | 2 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 5 | 9 => Some(4),
+        6 | 10 | 11 => Some(8),
+        _ => None,
+    }
+}
+
+/// Big-endian values of a NetCDF number type.
+fn numbers(nc_type: u32, data: &[u8]) -> Vec<f64> {
+    let size = type_size(nc_type).unwrap_or(1) as usize;
+    data.chunks_exact(size)
+        .map(|bytes| {
+            let array = |bytes: &[u8]| -> [u8; 8] {
+                let mut array = [0; 8];
+                array[..bytes.len()].copy_from_slice(bytes);
+                array
+            };
+            match nc_type {
+                1 => bytes[0] as i8 as f64,
+                7 => bytes[0] as f64,
+                3 => i16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+                8 => u16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+                4 => i32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                9 => u32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                5 => f32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                6 => f64::from_be_bytes(array(bytes)),
+                10 => i64::from_be_bytes(array(bytes)) as f64,
+                _ => u64::from_be_bytes(array(bytes)) as f64,
+            }
+        })
+        .collect()
+}
+
+/// What NetCDF files hold where no value was written, for each type.
+fn default_fill(nc_type: u32) -> f64 {
+    match nc_type {
+        1 => -127.,
+        3 => -32767.,
+        4 => -2_147_483_647.,
+        5 | 6 => 9.969_209_968_386_869e36,
+        7 => 255.,
+        8 => 65535.,
+        9 => 4_294_967_295.,
+        _ => f64::NAN,
+    }
+}
+
+struct Attribute {
+    name: String,
+    nc_type: u32,
+    data: Vec<u8>,
+}
+
+struct Variable {
+    name: String,
+    dimensions: Vec<usize>,
+    attributes: Vec<Attribute>,
+    nc_type: u32,
+    begin: u64,
+}
+
+impl Variable {
+    fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+
+    fn number(&self, name: &str) -> Option<f64> {
+        let attribute = self.attribute(name)?;
+        numbers(attribute.nc_type, &attribute.data).first().copied()
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        let attribute = self
+            .attribute(name)
+            .filter(|attribute| attribute.nc_type == CHAR)?;
+        let text = String::from_utf8_lossy(&attribute.data);
+        Some(text.trim_end_matches('\0').trim().to_string())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Axis {
+    Latitude,
+    Longitude,
+}
+
+fn read_netcdf<R: Read + Seek>(reader: R, length: u64) -> Result<Field, String> {
+    let mut header = Header { reader, version: 0 };
+    let magic = header.bytes(4)?;
+    header.version = match magic[..] {
+        [b'C', b'D', b'F', version @ (1 | 2 | 5)] => version,
+        [0x89, b'H', b'D', b'F'] => {
+            return Err(
+                "it is NetCDF-4, which keeps its data in HDF5. Convert it with `nccopy -k classic` first."
+                    .to_string(),
+            )
+        }
+        _ => return Err("it is not a NetCDF file".to_string()),
+    };
+    let records = header.count()?;
+    let dimensions = header.list(DIMENSIONS, |header| {
+        let name = header.name()?;
+        // The record dimension has as many entries as there are records.
+        let length = match header.count()? {
+            0 => records,
+            length => length,
+        };
+        Ok((name, length))
+    })?;
+    header.attributes()?;
+    let variables = header.list(VARIABLES, |header| {
+        let name = header.name()?;
+        let count = header.count()?;
+        if count > MAX_ENTRIES {
+            return Err("its header is damaged".to_string());
+        }
+        let ids = (0..count)
+            .map(|_| header.count().map(|id| id as usize))
+            .collect::<Result<Vec<_>, _>>()?;
+        if ids.iter().any(|&id| id >= dimensions.len()) {
+            return Err("its header is damaged".to_string());
+        }
+        let attributes = header.attributes()?;
+        let nc_type = header.u32()?;
+        header.count()?;
+        Ok(Variable {
+            name,
+            dimensions: ids,
+            attributes,
+            nc_type,
+            begin: header.offset()?,
+        })
+    })?;
+    let mut reader = header.reader;
+    let mut read = |variable: &Variable, count: u64| -> Result<Vec<f64>, String> {
+        let size = type_size(variable.nc_type).ok_or("it has a variable of an unknown type")?;
+        if variable.begin.saturating_add(count.saturating_mul(size)) > length {
+            return Err("it ends before all of its data".to_string());
+        }
+        let mut data = vec![0; (count * size) as usize];
+        reader
+            .seek(SeekFrom::Start(variable.begin))
+            .and_then(|_| reader.read_exact(&mut data))
+            .map_err(|e| e.to_string())?;
+        Ok(numbers(variable.nc_type, &data))
+    };
+    // A dimension's values are in the variable of the same name.
+    let coordinates = |dimension: usize| {
+        variables.iter().find(|variable| {
+            variable.dimensions == [dimension] && variable.name == dimensions[dimension].0
+        })
+    };
+    let axis = |dimension: usize| {
+        let units = coordinates(dimension)
+            .and_then(|variable| variable.text("units"))
+            .unwrap_or_default()
+            .to_lowercase();
+        let name = dimensions[dimension].0.to_lowercase();
+        match (units.as_str(), name.as_str()) {
+            ("degrees_north" | "degree_north" | "degrees_n" | "degree_n", _)
+            | (_, "lat" | "latitude") => Some(Axis::Latitude),
+            ("degrees_east" | "degree_east" | "degrees_e" | "degree_e", _)
+            | (_, "lon" | "long" | "longitude") => Some(Axis::Longitude),
+            _ => None,
+        }
+    };
+    let field = variables
+        .iter()
+        .find(|variable| {
+            let count = variable.dimensions.len();
+            count >= 2
+                && variable.nc_type != CHAR
+                && axis(variable.dimensions[count - 2]) == Some(Axis::Latitude)
+                && axis(variable.dimensions[count - 1]) == Some(Axis::Longitude)
+        })
+        .ok_or("it has no variable on a latitude-longitude grid")?;
+    let count = field.dimensions.len();
+    let (lat_dimension, long_dimension) =
+        (field.dimensions[count - 2], field.dimensions[count - 1]);
+    if field.dimensions[..count - 2]
+        .iter()
+        .any(|&dimension| dimensions[dimension].1 == 0)
+    {
+        return Err(format!("{} has no values", field.name));
+    }
+    let mut axis_values = |dimension: usize, name: &str| match coordinates(dimension) {
+        Some(variable) => read(variable, dimensions[dimension].1),
+        None => Err(format!("it has no {} coordinates", name)),
+    };
+    let lats = axis_values(lat_dimension, "latitude")?;
+    let longs = axis_values(long_dimension, "longitude")?;
+    let lat_step = regular(&lats, "latitude")?;
+    let long_step = regular(&longs, "longitude")?;
+    let (width, height) = (longs.len(), lats.len());
+    // Only the first time step or level is shown.
+    let raw = read(field, (width * height) as u64)?;
+    let fills = [
+        field
+            .number("_FillValue")
+            .unwrap_or(default_fill(field.nc_type)),
+        field.number("missing_value").unwrap_or(f64::NAN),
+    ];
+    let scale = field.number("scale_factor").unwrap_or(1.);
+    let offset = field.number("add_offset").unwrap_or(0.);
+    let values = raw
+        .into_iter()
+        .map(|value| match fills.contains(&value) || value.is_nan() {
+            true => f32::NAN,
+            false => (value * scale + offset) as f32,
+        })
+        .collect();
+    Ok(Field {
+        name: field
+            .text("long_name")
+            .unwrap_or_else(|| field.name.clone()),
+        units: field.text("units").unwrap_or_default(),
+        values: oriented(values, width, lat_step > 0., long_step < 0.),
+        width,
+        height,
+        west: longs[0].min(longs[width - 1]),
+        north: lats[0].max(lats[height - 1]),
+        long_step: long_step.abs(),
+        lat_step: lat_step.abs(),
+    })
+}
+
+fn be_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+/// GRIB stores signed numbers as a sign bit and a magnitude.
+fn signed(magnitude: u64, bits: u32) -> f64 {
+    let sign = 1 << (bits - 1);
+    match magnitude & sign {
+        0 => magnitude as f64,
+        _ => -((magnitude & !sign) as f64),
+    }
+}
+
+/// Names of the common parameters by discipline, category and number.
+fn parameter(discipline: u8, category: u8, number: u8) -> (&'static str, &'static str) {
+    match (discipline, category, number) {
+        (0, 0, 0) => ("Temperature", "K"),
+        (0, 0, 6) => ("Dew point temperature", "K"),
+        (0, 1, 1) => ("Relative humidity", "%"),
+        (0, 1, 8) => ("Total precipitation", "kg m-2"),
+        (0, 2, 2) => ("Eastward wind", "m s-1"),
+        (0, 2, 3) => ("Northward wind", "m s-1"),
+        (0, 3, 0) => ("Pressure", "Pa"),
+        (0, 3, 1) => ("Pressure reduced to mean sea level", "Pa"),
+        (0, 3, 5) => ("Geopotential height", "gpm"),
+        (0, 6, 1) => ("Total cloud cover", "%"),
+        (10, 0, 3) => ("Significant wave height", "m"),
+        _ => ("", ""),
+    }
+}
+
+/// Most values a GRIB grid may have, against sizes from damaged files.
+const MAX_POINTS: usize = 1 << 30;
+
+/// Reads the first message of a GRIB 2 file, on a regular latitude-longitude
+/// grid and with simple packing.
+fn read_grib<R: Read>(mut reader: R, length: u64) -> Result<Field, String> {
+    let mut start = [0; 16];
+    reader
+        .read_exact(&mut start)
+        .map_err(|_| "it is not a GRIB file".to_string())?;
+    if &start[..4] != b"GRIB" {
+        return Err("it is not a GRIB file".to_string());
+    }
+    match start[7] {
+        2 => {}
+        1 => {
+            return Err(
+                "it is GRIB edition 1. Convert it with `grib_set -s edition=2` first.".to_string(),
+            )
+        }
+        edition => return Err(format!("it is GRIB edition {}, which isn't known", edition)),
+    }
+    let total = u64::from_be_bytes(start[8..].try_into().unwrap());
+    if total > length || total < 16 {
+        return Err("it ends before its first message".to_string());
+    }
+    let mut message = vec![0; total as usize - 16];
+    reader.read_exact(&mut message).map_err(|e| e.to_string())?;
+    let mut sections: [Option<&[u8]>; 8] = Default::default();
+    let mut position = 0;
+    while position + 5 <= message.len() && &message[position..position + 4] != b"7777" {
+        let size = be_u32(&message, position) as usize;
+        let number = message[position + 4] as usize;
+        if size < 5 || position + size > message.len() || number >= sections.len() {
+            return Err("its first message is damaged".to_string());
+        }
+        sections[number] = Some(&message[position..position + size]);
+        position += size;
+        if number == 7 {
+            break;
+        }
+    }
+    let section = |number: usize, size: usize| {
+        sections[number]
+            .filter(|section| section.len() >= size)
+            .ok_or_else(|| format!("its first message lacks section {}", number))
+    };
+    let grid = section(3, 72)?;
+    match be_u16(grid, 12) {
+        0 => {}
+        template => {
+            return Err(format!(
+            "it uses grid template 3.{}; only regular latitude-longitude grids (3.0) can be read",
+            template
+        ))
+        }
+    }
+    let (width, height) = (be_u32(grid, 30) as usize, be_u32(grid, 34) as usize);
+    let (basic, subdivisions) = (be_u32(grid, 38), be_u32(grid, 42));
+    let unit = match (basic, subdivisions) {
+        (0 | u32::MAX, _) | (_, 0 | u32::MAX) => 1e-6,
+        _ => basic as f64 / subdivisions as f64,
+    };
+    let angle = |at: usize| signed(be_u32(grid, at) as u64, 32) * unit;
+    let (lat1, long1, lat2, long2) = (angle(46), angle(50), angle(55), angle(59));
+    let scanning = grid[71];
+    if scanning & 0x30 != 0 || width < 2 || height < 2 || width == u32::MAX as usize {
+        return Err("its grid is stored in an order that isn't supported".to_string());
+    }
+    let west_to_east = scanning & 0x80 == 0;
+    let (west, east) = match west_to_east {
+        true => (long1, long2),
+        false => (long2, long1),
+    };
+    let long_step = (east - west).rem_euclid(360.) / (width - 1) as f64;
+    let lat_step = (lat1 - lat2).abs() / (height - 1) as f64;
+    if long_step == 0. || lat_step == 0. {
+        return Err("its grid has no extent".to_string());
+    }
+    let representation = section(5, 21)?;
+    match be_u16(representation, 9) {
+        0 => {}
+        template => {
+            return Err(format!(
+                "its values are packed with template 5.{}; only simple packing (5.0) can be read",
+                template
+            ))
+        }
+    }
+    let count = be_u32(representation, 5) as usize;
+    let reference = f32::from_be_bytes(representation[11..15].try_into().unwrap()) as f64;
+    let binary_scale = 2f64.powf(signed(be_u16(representation, 15) as u64, 16));
+    let decimal_scale = 10f64.powf(signed(be_u16(representation, 17) as u64, 16));
+    let bits = representation[19] as usize;
+    let points = match width.checked_mul(height) {
+        Some(points) if points <= MAX_POINTS => points,
+        _ => return Err("its grid is too large".to_string()),
+    };
+    let bitmap = match section(6, 6)?[5] {
+        255 => None,
+        0 => Some(&section(6, 6 + points.div_ceil(8))?[6..]),
+        _ => return Err("it refers to a bitmap that isn't in its first message".to_string()),
+    };
+    let data = &section(7, 5)?[5..];
+    if bitmap.is_none() && count != points {
+        return Err("its first message is damaged".to_string());
+    }
+    if data.len() * 8 < count * bits || bits > 32 {
+        return Err("it ends before all of its data".to_string());
+    }
+    let packed = |index: usize| -> u64 {
+        (0..bits).fold(0, |value, bit| {
+            let bit = index * bits + bit;
+            (value << 1) | ((data[bit / 8] >> (7 - bit % 8)) & 1) as u64
+        })
+    };
+    let mut next = 0;
+    let values = (0..points)
+        .map(|point| {
+            let present = match bitmap {
+                Some(bitmap) => bitmap[point / 8] & (0x80 >> (point % 8)) != 0,
+                None => true,
+            };
+            if !present || next >= count {
+                return f32::NAN;
+            }
+            next += 1;
+            ((reference + packed(next - 1) as f64 * binary_scale) / decimal_scale) as f32
+        })
+        .collect();
+    let product = section(4, 11)?;
+    let (name, units) = parameter(start[6], product[9], product[10]);
+    Ok(Field {
+        name: match name {
+            "" => format!("Parameter {}.{}.{}", start[6], product[9], product[10]),
+            name => name.to_string(),
+        },
+        units: units.to_string(),
+        values: oriented(values, width, scanning & 0x40 != 0, !west_to_east),
+        width,
+        height,
+        west,
+        north: lat1.max(lat2),
+        long_step,
+        lat_step,
+    })
+}
+
+/// Shows what the field is and how it is colored. Returns whether the coloring changed.
+pub fn gridded_ui(ui: &mut egui::Ui, field: &Field, coloring: &mut Coloring) -> bool {
+    let before = *coloring;
+    ui.collapsing("Gridded data", |ui| {
+        ui.label(match field.units.as_str() {
+            "" => field.name.clone(),
+            units => format!("{} ({})", field.name, units),
+        });
+        palettes::ramp_ui(ui, &mut coloring.ramp, "Data colors");
+        let range = field.range().unwrap_or((0., 1.));
+        let mut fixed = coloring.range.is_some();
+        if ui.checkbox(&mut fixed, "Fixed range").changed() {
+            coloring.range = fixed.then_some(range);
+        }
+        if let Some((low, high)) = &mut coloring.range {
+            ui.horizontal(|ui| {
+                ui.add(egui::DragValue::new(low).speed((range.1 - range.0) / 200.));
+                ui.label("to");
+                ui.add(egui::DragValue::new(high).speed((range.1 - range.0) / 200.));
+            });
+        }
+        let (low, high) = coloring.range.unwrap_or(range);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(160., 10.), egui::Sense::hover());
+        palettes::paint_ramp(ui.painter(), rect, coloring.ramp);
+        ui.horizontal(|ui| {
+            ui.label(format!("{:.4}", low));
+            ui.label("–");
+            ui.label(format!("{:.4}", high));
+        });
+    });
+    *coloring != before
+}
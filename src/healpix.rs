@@ -0,0 +1,294 @@
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use std::io::Read;
+use std::path::Path;
+
+use crate::fits::{self, Value};
+use crate::gridded::Field;
+use crate::ProjectionData;
+
+/// What HEALPix maps hold where they have no value.
+const UNSEEN: f64 = -1.6375e30;
+/// Grid cells per `nside` around the equator when a map is read, which
+/// samples the pixels a little more finely than they are.
+const CELLS_PER_SIDE: usize = 6;
+/// Pixels per row of the table written.
+const ROW_PIXELS: usize = 1024;
+
+/// Pixel orderings: by rings of equal latitude, or hierarchically within the
+/// twelve base pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ordering {
+    Ring,
+    Nested,
+}
+
+fn pixels(nside: usize) -> usize {
+    12 * nside * nside
+}
+
+/// The pixel at a latitude and longitude, in radians.
+pub fn pixel(nside: usize, ordering: Ordering, lat: f64, long: f64) -> usize {
+    let n = nside as i64;
+    let z = lat.sin();
+    let za = z.abs();
+    // Longitude in quarters of the circle, from 0 to 4.
+    let tt = long.rem_euclid(TAU) / FRAC_PI_2;
+    let (face, ix, iy) = match za <= 2. / 3. {
+        // The equatorial belt.
+        true => {
+            let temp1 = n as f64 * (0.5 + tt);
+            let temp2 = n as f64 * z * 0.75;
+            let jp = (temp1 - temp2) as i64;
+            let jm = (temp1 + temp2) as i64;
+            if ordering == Ordering::Ring {
+                let ring = n + 1 + jp - jm;
+                let shift = 1 - (ring & 1);
+                let ip = ((jp + jm - n + shift + 1) / 2).rem_euclid(4 * n);
+                return (2 * n * (n - 1) + (ring - 1) * 4 * n + ip) as usize;
+            }
+            let (ifp, ifm) = (jp / n, jm / n);
+            let face = match ifp.cmp(&ifm) {
+                std::cmp::Ordering::Equal => ifp | 4,
+                std::cmp::Ordering::Less => ifp,
+                std::cmp::Ordering::Greater => ifm + 8,
+            };
+            (face, jm & (n - 1), n - (jp & (n - 1)) - 1)
+        }
+        // The polar caps.
+        false => {
+            let ntt = (tt as i64).min(3);
+            let tp = tt - ntt as f64;
+            let tmp = n as f64 * (3. * (1. - za)).sqrt();
+            let jp = ((tp * tmp) as i64).min(n - 1);
+            let jm = (((1. - tp) * tmp) as i64).min(n - 1);
+            if ordering == Ordering::Ring {
+                let ring = jp + jm + 1;
+                let ip = ((tt * ring as f64) as i64).rem_euclid(4 * ring);
+                return match z > 0. {
+                    true => 2 * ring * (ring - 1) + ip,
+                    false => 12 * n * n - 2 * ring * (ring + 1) + ip,
+                } as usize;
+            }
+            match z >= 0. {
+                true => (ntt, n - jm - 1, n - jp - 1),
+                false => (ntt + 8, jp, jm),
+            }
+        }
+    };
+    (face * n * n + spread(ix) + 2 * spread(iy)) as usize
+}
+
+/// Moves the bits of `value` apart, into every other position.
+fn spread(value: i64) -> i64 {
+    (0..32).fold(0, |spread, bit| {
+        spread | (((value >> bit) & 1) << (2 * bit))
+    })
+}
+
+/// The latitude and longitude of the center of a pixel in ring order, in radians.
+pub fn center(nside: usize, pixel: usize) -> (f64, f64) {
+    let n = nside as f64;
+    let cap = 2 * nside * (nside - 1);
+    let (z, long) = if pixel < cap {
+        let ring = (((1 + 2 * pixel) as f64).sqrt() as usize).div_ceil(2);
+        let index = pixel + 1 - 2 * ring * (ring - 1);
+        let ring = ring as f64;
+        (
+            1. - ring * ring / (3. * n * n),
+            (index as f64 - 0.5) * FRAC_PI_2 / ring,
+        )
+    } else if pixel < pixels(nside) - cap {
+        let offset = pixel - cap;
+        let ring = offset / (4 * nside) + nside;
+        let index = offset % (4 * nside) + 1;
+        let shift = match (ring + nside) % 2 {
+            1 => 1.,
+            _ => 0.5,
+        };
+        (
+            (2. * n - ring as f64) * 2. / (3. * n),
+            (index as f64 - shift) * FRAC_PI_2 / n,
+        )
+    } else {
+        let offset = pixels(nside) - pixel;
+        let ring = (((2 * offset - 1) as f64).sqrt() as usize).div_ceil(2);
+        let index = 4 * ring + 1 - (offset - 2 * ring * (ring - 1));
+        let ring = ring as f64;
+        (
+            -1. + ring * ring / (3. * n * n),
+            (index as f64 - 0.5) * FRAC_PI_2 / ring,
+        )
+    };
+    (z.clamp(-1., 1.).asin(), long)
+}
+
+/// Whether a file is a HEALPix map in a FITS table.
+pub fn is_map(path: &Path) -> bool {
+    fits::is_fits(path) && matches!(map_table(path), Ok(Some(_)))
+}
+
+fn map_table(
+    path: &Path,
+) -> Result<Option<(fits::Header, u64, std::io::BufReader<std::fs::File>)>, String> {
+    fits::find_unit(path, |header, _| {
+        header.text("XTENSION").as_deref() == Some("BINTABLE")
+            && header.text("PIXTYPE").as_deref() == Some("HEALPIX")
+    })
+}
+
+/// Reads the first column of a whole-sky HEALPix map onto a latitude-longitude grid.
+pub fn read(path: &Path) -> Result<Field, String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let fail = |e: &str| format!("{} can't be read: {}", name, e);
+    let (header, position, mut reader) =
+        map_table(path)?.ok_or_else(|| fail("it is not a HEALPix map"))?;
+    if header.text("INDXSCHM").as_deref() == Some("EXPLICIT") {
+        return Err(fail(
+            "it covers part of the sky by explicit pixel indices, which isn't supported",
+        ));
+    }
+    let nside = header.number("NSIDE").unwrap_or(0.) as usize;
+    if nside == 0 || nside > 1 << 13 {
+        return Err(fail("its resolution isn't valid"));
+    }
+    let ordering = match header.text("ORDERING").unwrap_or_default().as_str() {
+        "RING" => Ordering::Ring,
+        "NESTED" | "NEST" if nside.is_power_of_two() => Ordering::Nested,
+        _ => return Err(fail("its pixel ordering isn't known")),
+    };
+    let form = header.text("TFORM1").unwrap_or_default();
+    let digits = form.trim_end_matches(|c: char| !c.is_ascii_digit()).len();
+    let repeat: usize = form[..digits].parse().unwrap_or(1);
+    let (size, code) = match &form[digits..] {
+        "B" => (1, 'B'),
+        "I" => (2, 'I'),
+        "J" => (4, 'J'),
+        "K" => (8, 'K'),
+        "E" => (4, 'E'),
+        "D" => (8, 'D'),
+        _ => return Err(fail("its values are of a type that isn't supported")),
+    };
+    let (row_bytes, rows) = (header.axis(1) as usize, header.axis(2) as usize);
+    if rows.saturating_mul(repeat) != pixels(nside) || row_bytes < repeat * size {
+        return Err(fail("its table doesn't hold one value per pixel"));
+    }
+    let length = reader
+        .get_ref()
+        .metadata()
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    if position + rows.saturating_mul(row_bytes) as u64 > length {
+        return Err(fail("it ends before all of its pixels"));
+    }
+    let mut data = vec![0; rows * row_bytes];
+    reader
+        .read_exact(&mut data)
+        .map_err(|e| fail(&e.to_string()))?;
+    let null = header.number("TNULL1");
+    let scale = header.number("TSCAL1").unwrap_or(1.);
+    let zero = header.number("TZERO1").unwrap_or(0.);
+    let values: Vec<f32> = data
+        .chunks(row_bytes)
+        .flat_map(|row| row[..repeat * size].chunks_exact(size))
+        .map(|bytes| {
+            let raw = match code {
+                'B' => bytes[0] as f64,
+                'I' => i16::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                'J' => i32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                'K' => i64::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                'E' => f32::from_be_bytes(bytes.try_into().unwrap()) as f64,
+                _ => f64::from_be_bytes(bytes.try_into().unwrap()),
+            };
+            match Some(raw) == null || (raw - UNSEEN).abs() < 1e25 || raw.is_nan() {
+                true => f32::NAN,
+                false => (zero + scale * raw) as f32,
+            }
+        })
+        .collect();
+    let (width, height) = (CELLS_PER_SIDE * nside, CELLS_PER_SIDE * nside / 2);
+    let grid = (0..width * height)
+        .map(|index| {
+            let long = ((index % width) as f64 + 0.5) / width as f64 * TAU - PI;
+            let lat = FRAC_PI_2 - ((index / width) as f64 + 0.5) / height as f64 * PI;
+            let index = pixel(nside, ordering, lat, long);
+            values.get(index).copied().unwrap_or(f32::NAN)
+        })
+        .collect();
+    Ok(Field::global(
+        header
+            .text("TTYPE1")
+            .unwrap_or_else(|| "HEALPix map".to_string()),
+        header.text("TUNIT1").unwrap_or_default(),
+        width,
+        height,
+        grid,
+    ))
+}
+
+/// Writes a map as a HEALPix table in ring order with a column for each of
+/// red, green and blue, at the resolution closest to the image's. Pixels the
+/// map doesn't show are left unseen.
+pub fn write(
+    path: &Path,
+    image: &image::RgbaImage,
+    projection: &ProjectionData,
+) -> Result<(), String> {
+    let (width, height) = image.dimensions();
+    let order = ((width as f64 * height as f64 / 12.).sqrt().log2().round()).clamp(0., 13.);
+    let nside = 1usize << order as u32;
+    let count = pixels(nside);
+    let row_pixels = ROW_PIXELS.min(count);
+    let mut channels: Vec<Vec<f32>> = (0..3).map(|_| Vec::with_capacity(count)).collect();
+    for index in 0..count {
+        let (lat, long) = center(nside, index);
+        let point = crate::projections::point(long.to_degrees(), lat.to_degrees());
+        let color = projection
+            .pixel_at_point(&point, (width, height))
+            .and_then(|(x, y)| image.get_pixel_checked(x as u32, y as u32))
+            .filter(|color| color[3] > 0);
+        for (channel, values) in channels.iter_mut().enumerate() {
+            let value = color.map_or(UNSEEN as f32, |color| color[channel] as f32);
+            values.push(value);
+        }
+    }
+    let form = Value::Text(format!("{}E", row_pixels));
+    let mut file = fits::header(&[
+        ("SIMPLE", Value::Logical(true)),
+        ("BITPIX", Value::Integer(8)),
+        ("NAXIS", Value::Integer(0)),
+        ("EXTEND", Value::Logical(true)),
+    ]);
+    file.extend(fits::header(&[
+        ("XTENSION", Value::Text("BINTABLE".to_string())),
+        ("BITPIX", Value::Integer(8)),
+        ("NAXIS", Value::Integer(2)),
+        ("NAXIS1", Value::Integer(3 * 4 * row_pixels as i64)),
+        ("NAXIS2", Value::Integer((count / row_pixels) as i64)),
+        ("PCOUNT", Value::Integer(0)),
+        ("GCOUNT", Value::Integer(1)),
+        ("TFIELDS", Value::Integer(3)),
+        ("TTYPE1", Value::Text("RED".to_string())),
+        ("TFORM1", form.clone()),
+        ("TTYPE2", Value::Text("GREEN".to_string())),
+        ("TFORM2", form.clone()),
+        ("TTYPE3", Value::Text("BLUE".to_string())),
+        ("TFORM3", form),
+        ("PIXTYPE", Value::Text("HEALPIX".to_string())),
+        ("ORDERING", Value::Text("RING".to_string())),
+        ("INDXSCHM", Value::Text("IMPLICIT".to_string())),
+        ("NSIDE", Value::Integer(nside as i64)),
+        ("FIRSTPIX", Value::Integer(0)),
+        ("LASTPIX", Value::Integer(count as i64 - 1)),
+        ("OBJECT", Value::Text("FULLSKY".to_string())),
+    ]));
+    let mut data = Vec::with_capacity(count * 12);
+    for row in 0..count / row_pixels {
+        for values in &channels {
+            let values = &values[row * row_pixels..(row + 1) * row_pixels];
+            data.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+        }
+    }
+    fits::pad(&mut data, 0);
+    file.extend(data);
+    std::fs::write(path, file).map_err(|e| format!("Could not write {}: {}", path.display(), e))
+}
@@ -0,0 +1,239 @@
+use eframe::egui;
+
+use crate::{ImageData, ProjectionData};
+
+/// How many results the strip keeps. Pinned ones are dropped last.
+const CAPACITY: usize = 6;
+/// In points.
+const THUMBNAIL_HEIGHT: f32 = 48.;
+/// The size of the compared results, in points.
+const COMPARE_HEIGHT: f32 = 400.;
+
+pub struct Entry {
+    /// Stays the same while older entries are dropped.
+    id: u64,
+    pub image: ImageData,
+    /// The projection the result was made into.
+    pub target: ProjectionData,
+    pub draft: bool,
+    pinned: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    /// One result on the left of a dividing line and the other on the right.
+    Swipe,
+    /// How far apart the colors are, brighter the more they differ.
+    Difference,
+}
+
+struct Compare {
+    /// The ids of the two pinned entries, in the order they were pinned.
+    entries: [u64; 2],
+    mode: CompareMode,
+    /// Where the line lies across the results, from 0 to 1.
+    split: f32,
+    /// Made when first shown.
+    difference: Option<egui::TextureHandle>,
+}
+
+/// The results projected recently, to go back to or to compare two of.
+#[derive(Default)]
+pub struct History {
+    entries: Vec<Entry>,
+    next_id: u64,
+    /// Pinned entries by id, oldest first.
+    pins: Vec<u64>,
+    compare: Option<Compare>,
+}
+
+impl History {
+    /// Keeps a new result, dropping the oldest unpinned one if there are
+    /// too many.
+    pub fn record(&mut self, image: &ImageData, target: &ProjectionData, draft: bool) {
+        if self.entries.len() >= CAPACITY {
+            if let Some(index) = self.entries.iter().position(|entry| !entry.pinned) {
+                self.entries.remove(index);
+            }
+        }
+        self.entries.push(Entry {
+            id: self.next_id,
+            image: image.clone(),
+            target: target.clone(),
+            draft,
+            pinned: false,
+        });
+        self.next_id += 1;
+    }
+
+    fn get(&self, id: u64) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    /// Pins an entry or unpins a pinned one. Only two stay pinned, so pinning
+    /// a third unpins the first.
+    fn toggle_pin(&mut self, id: u64) {
+        match self.pins.iter().position(|pinned| *pinned == id) {
+            Some(index) => {
+                self.pins.remove(index);
+            }
+            None => {
+                self.pins.push(id);
+                if self.pins.len() > 2 {
+                    self.pins.remove(0);
+                }
+            }
+        }
+        for entry in &mut self.entries {
+            entry.pinned = self.pins.contains(&entry.id);
+        }
+        self.compare = None;
+    }
+}
+
+/// The results as a row of thumbnails under the projected map, each of
+/// which can be restored or pinned. Returns the entry to restore.
+pub fn history_strip<'a>(ui: &mut egui::Ui, history: &'a mut History) -> Option<&'a Entry> {
+    if history.entries.is_empty() {
+        return None;
+    }
+    let mut restored = None;
+    let mut pinned = None;
+    ui.horizontal(|ui| {
+        egui::ScrollArea::horizontal()
+            .id_source("History")
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for entry in history.entries.iter().rev() {
+                        let size = entry.image.handle.size_vec2();
+                        let width = THUMBNAIL_HEIGHT * size.x / size.y.max(1.);
+                        ui.vertical(|ui| {
+                            let hover = match entry.draft {
+                                true => "Restore this draft",
+                                false => "Restore this result",
+                            };
+                            let thumbnail = egui::ImageButton::new(
+                                entry.image.handle.id(),
+                                (width, THUMBNAIL_HEIGHT),
+                            )
+                            .selected(entry.pinned);
+                            if ui.add(thumbnail).on_hover_text(hover).clicked() {
+                                restored = Some(entry.id);
+                            }
+                            ui.horizontal(|ui| {
+                                ui.small(entry.target.kind().to_string());
+                                let pin = match entry.pinned {
+                                    true => "Unpin",
+                                    false => "Pin",
+                                };
+                                if ui.small_button(pin).clicked() {
+                                    pinned = Some(entry.id);
+                                }
+                            });
+                        });
+                    }
+                });
+            });
+    });
+    if let Some(id) = pinned {
+        history.toggle_pin(id);
+    }
+    if let [a, b] = history.pins[..] {
+        if ui.button("Compare pinned results...").clicked() {
+            history.compare = Some(Compare {
+                entries: [a, b],
+                mode: CompareMode::Swipe,
+                split: 0.5,
+                difference: None,
+            });
+        }
+    }
+    restored.and_then(|id| history.get(id))
+}
+
+/// The color distance between two results, the second stretched to the size
+/// of the first. Transparent where neither shows anything.
+fn difference(a: &image::DynamicImage, b: &image::DynamicImage) -> egui::ColorImage {
+    let a = a.to_rgba8();
+    let b = image::imageops::resize(
+        &b.to_rgba8(),
+        a.width(),
+        a.height(),
+        image::imageops::FilterType::Triangle,
+    );
+    let pixels = a
+        .pixels()
+        .zip(b.pixels())
+        .map(|(a, b)| {
+            let [red, green, blue] = [0, 1, 2].map(|channel| a[channel].abs_diff(b[channel]));
+            egui::Color32::from_rgba_unmultiplied(red, green, blue, a[3].max(b[3]))
+        })
+        .collect();
+    egui::ColorImage {
+        size: [a.width() as usize, a.height() as usize],
+        pixels,
+    }
+}
+
+/// Shows the two pinned results side by side with a line to drag between
+/// them, or their difference.
+pub fn compare_window(ctx: &egui::Context, history: &mut History) {
+    let Some(compare) = &mut history.compare else {
+        return;
+    };
+    let (Some(a), Some(b)) = (
+        history
+            .entries
+            .iter()
+            .find(|entry| entry.id == compare.entries[0]),
+        history
+            .entries
+            .iter()
+            .find(|entry| entry.id == compare.entries[1]),
+    ) else {
+        history.compare = None;
+        return;
+    };
+    let mut open = true;
+    egui::Window::new("Compare results")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut compare.mode, CompareMode::Swipe, "Swipe");
+                ui.selectable_value(&mut compare.mode, CompareMode::Difference, "Difference");
+            });
+            ui.label(format!("{} and {}", a.target.kind(), b.target.kind()));
+            let size = a.image.handle.size_vec2();
+            let size = egui::vec2(COMPARE_HEIGHT * size.x / size.y.max(1.), COMPARE_HEIGHT);
+            match compare.mode {
+                CompareMode::Swipe => {
+                    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        compare.split = ((pointer.x - rect.left()) / rect.width()).clamp(0., 1.);
+                    }
+                    let painter = ui.painter_at(rect);
+                    let full = egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.));
+                    painter.image(a.image.handle.id(), rect, full, egui::Color32::WHITE);
+                    let x = rect.left() + compare.split * rect.width();
+                    let right = egui::Rect::from_min_max(egui::pos2(x, rect.top()), rect.max);
+                    let uv = egui::Rect::from_min_max(egui::pos2(compare.split, 0.), full.max);
+                    painter.image(b.image.handle.id(), right, uv, egui::Color32::WHITE);
+                    painter.vline(x, rect.y_range(), ui.visuals().widgets.active.fg_stroke);
+                    ui.add(egui::Slider::new(&mut compare.split, 0.0..=1.0).show_value(false));
+                }
+                CompareMode::Difference => {
+                    let handle = compare.difference.get_or_insert_with(|| {
+                        ctx.load_texture(
+                            "Result difference",
+                            difference(&a.image.image, &b.image.image),
+                            Default::default(),
+                        )
+                    });
+                    ui.image(handle.id(), size);
+                }
+            }
+        });
+    if !open {
+        history.compare = None;
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::batch::ProjectionSpec;
+use crate::private_files;
+use crate::settings::config_directory;
+
+/// Written by the running instance so that later ones can find it.
+#[derive(Serialize, Deserialize)]
+struct InstanceFile {
+    port: u16,
+    /// Random, and proves a connection comes from someone who can read this
+    /// file, which only the user can, not just anyone on this machine.
+    token: String,
+}
+
+fn instance_file_path() -> Option<PathBuf> {
+    config_directory().map(|directory| directory.join("instance.json"))
+}
+
+/// What other programs can ask the running instance to do. Clients connect
+/// to the port in `instance.json`, send its token on the first line and then
+/// one command per line; each is answered with a line starting with `ok` or
+/// `error`. Paths must be absolute.
+///
+/// - `open <path>` opens a source map, answered once it has loaded
+/// - `focus` brings the window to the front
+/// - `source-projection <kind> [name=value...]` and `target-projection ...`
+///   set a projection and its parameters in degrees, e.g.
+///   `target-projection azimuthal-equidistant center_long=10 center_lat=50`
+/// - `project` projects the map at full quality
+/// - `export <path>` saves the projected map, its format given by the
+///   extension. An existing file is treated as the settings say, as when
+///   exporting from the window
+pub enum Command {
+    Open(PathBuf),
+    Focus,
+    SourceProjection(ProjectionSpec),
+    TargetProjection(ProjectionSpec),
+    Project,
+    Export(PathBuf),
+}
+
+impl Command {
+    fn encode(&self) -> String {
+        match self {
+            Command::Open(path) => format!("open {}", path.display()),
+            Command::Focus => "focus".to_string(),
+            Command::SourceProjection(spec) | Command::TargetProjection(spec) => {
+                let name = match self {
+                    Command::SourceProjection(..) => "source-projection",
+                    _ => "target-projection",
+                };
+                let parameters: Vec<String> = spec
+                    .parameters
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+                format!("{} {} {}", name, spec.kind, parameters.join(" "))
+            }
+            Command::Project => "project".to_string(),
+            Command::Export(path) => format!("export {}", path.display()),
+        }
+    }
+
+    fn decode(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        // Relative paths would be taken from wherever this instance started.
+        let path = || match Path::new(argument).is_absolute() {
+            true => Ok(PathBuf::from(argument)),
+            false => Err(format!("{} needs an absolute path", name)),
+        };
+        let projection = || {
+            let mut words = argument.split_whitespace();
+            let kind = words.next().ok_or(format!("{} needs a kind", name))?;
+            let parameters = words
+                .map(|word| {
+                    let (name, value) = word
+                        .split_once('=')
+                        .ok_or(format!("Not name=value: {}", word))?;
+                    let value = value
+                        .parse()
+                        .map_err(|_| format!("Not a number: {}", value))?;
+                    Ok((name.to_string(), value))
+                })
+                .collect::<Result<_, String>>()?;
+            Ok::<_, String>(ProjectionSpec {
+                kind: kind.to_string(),
+                parameters,
+            })
+        };
+        match name {
+            "open" => path().map(Command::Open),
+            "focus" => Ok(Command::Focus),
+            "source-projection" => projection().map(Command::SourceProjection),
+            "target-projection" => projection().map(Command::TargetProjection),
+            "project" => Ok(Command::Project),
+            "export" => path().map(Command::Export),
+            _ => Err(format!("Unknown command: {}", name)),
+        }
+    }
+}
+
+/// A command together with the way back to whoever sent it.
+pub struct Request {
+    pub command: Command,
+    reply: mpsc::Sender<Result<String, String>>,
+}
+
+impl Request {
+    pub fn respond(self, result: Result<String, String>) {
+        // The client may not wait for an answer, like a second instance.
+        let _ = self.reply.send(result);
+    }
+}
+
+/// Passes `file` (or just a request to come to the front) to an instance
+/// that is already running. Returns whether there was one to take it.
+pub fn forward(file: Option<&Path>) -> bool {
+    let Some(contents) = instance_file_path().and_then(|path| std::fs::read_to_string(path).ok())
+    else {
+        return false;
+    };
+    let Ok(instance) = serde_json::from_str::<InstanceFile>(&contents) else {
+        return false;
+    };
+    let address = (Ipv4Addr::LOCALHOST, instance.port).into();
+    // A stale file from an instance that crashed leads nowhere.
+    let Ok(mut stream) = TcpStream::connect_timeout(&address, Duration::from_millis(500)) else {
+        return false;
+    };
+    let command = match file {
+        // Relative to where this instance was started, not the other one.
+        Some(file) => Command::Open(std::fs::canonicalize(file).unwrap_or(file.to_path_buf())),
+        None => Command::Focus,
+    };
+    writeln!(stream, "{}\n{}", instance.token, command.encode()).is_ok()
+}
+
+/// Listens for files handed over by later instances, and for commands from
+/// other programs.
+pub struct Listener {
+    token: String,
+    receiver: mpsc::Receiver<Request>,
+}
+
+/// Answers the commands of one client until it disconnects.
+fn serve(
+    stream: TcpStream,
+    token: &str,
+    sender: &mpsc::Sender<Request>,
+    ctx: &egui::Context,
+) -> std::io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut lines = BufReader::new(stream).lines();
+    if lines.next().transpose()?.as_deref() != Some(token) {
+        return Ok(());
+    }
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = match Command::decode(&line) {
+            Ok(command) => {
+                let (reply, answer) = mpsc::channel();
+                if sender.send(Request { command, reply }).is_err() {
+                    return Ok(());
+                }
+                ctx.request_repaint();
+                answer
+                    .recv()
+                    .unwrap_or(Err("The command was dropped.".to_string()))
+            }
+            Err(e) => Err(e),
+        };
+        let answer = match result {
+            Ok(message) => format!("ok {}", message),
+            Err(e) => format!("error {}", e),
+        };
+        writeln!(writer, "{}", answer.trim_end().replace('\n', " "))?;
+    }
+    Ok(())
+}
+
+impl Listener {
+    pub fn start(ctx: &egui::Context) -> Result<Self, String> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).map_err(|e| e.to_string())?;
+        let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+        let mut bytes = [0; 32];
+        getrandom::getrandom(&mut bytes).map_err(|e| e.to_string())?;
+        let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let path = instance_file_path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string(&InstanceFile {
+            port,
+            token: token.clone(),
+        })
+        .map_err(|e| e.to_string())?;
+        private_files::write(&path, contents.as_bytes())
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        let (sender, receiver) = mpsc::channel();
+        let (ctx, expected) = (ctx.clone(), token.clone());
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (sender, ctx, expected) = (sender.clone(), ctx.clone(), expected.clone());
+                std::thread::spawn(move || {
+                    // A client that went away is no concern of the others.
+                    let _ = serve(stream, &expected, &sender, &ctx);
+                });
+            }
+        });
+        Ok(Listener { token, receiver })
+    }
+
+    pub fn poll(&self) -> Vec<Request> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Removes the instance file, unless another instance has taken over since.
+    pub fn release(&self) {
+        let Some(path) = instance_file_path() else {
+            return;
+        };
+        let ours = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<InstanceFile>(&contents).ok())
+            .is_some_and(|instance| instance.token == self.token);
+        if ours {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
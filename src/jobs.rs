@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+
+use eframe::egui;
+
+/// Fraction of a background job that is done, and whether it should stop,
+/// shared with its worker thread.
+#[derive(Default)]
+pub struct Progress {
+    fraction: AtomicU32,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn set(&self, fraction: f32) {
+        self.fraction
+            .store(fraction.clamp(0., 1.).to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.fraction.load(Ordering::Relaxed))
+    }
+
+    /// Whether the job was cancelled. Work that can stop early checks this.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// How many jobs are running on worker threads right now.
+static BUSY_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn busy_workers() -> usize {
+    BUSY_WORKERS.load(Ordering::Relaxed)
+}
+
+/// Counts a worker as busy for as long as it is alive, even if the job panics.
+struct Busy;
+
+impl Busy {
+    fn start() -> Self {
+        BUSY_WORKERS.fetch_add(1, Ordering::Relaxed);
+        Busy
+    }
+}
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        BUSY_WORKERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Work running on its own thread that reports back with messages of type `T`.
+pub struct Job<T> {
+    pub label: String,
+    progress: Arc<Progress>,
+    receiver: mpsc::Receiver<T>,
+    finished: bool,
+}
+
+impl<T: Send + 'static> Job<T> {
+    /// Starts `work` on a worker thread. It gets a sender for its messages and
+    /// the progress to update; the UI is repainted whenever a message arrives.
+    pub fn spawn(
+        ctx: &egui::Context,
+        label: impl Into<String>,
+        work: impl FnOnce(&dyn Fn(T), &Progress) + Send + 'static,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let progress = Arc::new(Progress::default());
+        let worker_progress = progress.clone();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _busy = Busy::start();
+            let send = |message| {
+                // The job was dropped if nobody listens anymore, so that's fine.
+                let _ = sender.send(message);
+                ctx.request_repaint();
+            };
+            work(&send, &worker_progress);
+            ctx.request_repaint();
+        });
+        Job {
+            label: label.into(),
+            progress,
+            receiver,
+            finished: false,
+        }
+    }
+}
+
+impl<T> Job<T> {
+    /// The messages that arrived since the last call.
+    pub fn poll(&mut self) -> Vec<T> {
+        let mut messages = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(message) => messages.push(message),
+                Err(mpsc::TryRecvError::Empty) => break,
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    self.finished = true;
+                    break;
+                }
+            }
+        }
+        messages
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn progress(&self) -> f32 {
+        self.progress.get()
+    }
+
+    /// Asks the worker to stop. Nothing it sends afterwards is of interest.
+    pub fn cancel(&self) {
+        self.progress.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Wraps a reader and reports how much of a file of known size was read.
+pub struct ProgressReader<'a, R> {
+    inner: R,
+    position: u64,
+    length: u64,
+    progress: &'a Progress,
+}
+
+impl<'a, R> ProgressReader<'a, R> {
+    pub fn new(inner: R, length: u64, progress: &'a Progress) -> Self {
+        ProgressReader {
+            inner,
+            position: 0,
+            length: length.max(1),
+            progress,
+        }
+    }
+}
+
+impl<'a, R: std::io::Read> std::io::Read for ProgressReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        self.progress.set(self.position as f32 / self.length as f32);
+        Ok(read)
+    }
+}
+
+impl<'a, R: std::io::Seek> std::io::Seek for ProgressReader<'a, R> {
+    fn seek(&mut self, position: std::io::SeekFrom) -> std::io::Result<u64> {
+        self.position = self.inner.seek(position)?;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,97 @@
+use eframe::egui;
+
+/// Which labels win when there isn't room for all of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Graticule,
+    ControlPoint,
+    Annotation,
+    Measurement,
+}
+
+struct Label {
+    text: String,
+    font: egui::FontId,
+    color: egui::Color32,
+    priority: Priority,
+    anchor: egui::Pos2,
+    /// Where the overlay would put the label if nothing was in the way.
+    preferred: (egui::Align2, egui::Vec2),
+}
+
+/// The labels of all overlays of a view, placed together so that they don't
+/// cover each other or the markers they belong to.
+#[derive(Default)]
+pub struct Labels {
+    labels: Vec<Label>,
+    obstacles: Vec<egui::Rect>,
+}
+
+/// The gap between a label and its anchor when it has to move.
+const GAP: f32 = 6.;
+
+impl Labels {
+    pub fn add(
+        &mut self,
+        anchor: egui::Pos2,
+        preferred: (egui::Align2, egui::Vec2),
+        text: impl ToString,
+        font: egui::FontId,
+        color: egui::Color32,
+        priority: Priority,
+    ) {
+        self.labels.push(Label {
+            text: text.to_string(),
+            font,
+            color,
+            priority,
+            anchor,
+            preferred,
+        });
+    }
+
+    /// Keeps labels off a marker.
+    pub fn avoid(&mut self, rect: egui::Rect) {
+        self.obstacles.push(rect);
+    }
+
+    /// Draws the labels, most important first, each at the first place around
+    /// its anchor that is still free. Labels that fit nowhere are left out.
+    pub fn paint(mut self, painter: &egui::Painter) {
+        use egui::Align2;
+        // Stable, so labels of the same priority keep the order they were added in.
+        self.labels
+            .sort_by_key(|label| std::cmp::Reverse(label.priority));
+        let clip = painter.clip_rect();
+        let alternatives = [
+            (Align2::LEFT_CENTER, egui::vec2(GAP, 0.)),
+            (Align2::RIGHT_CENTER, egui::vec2(-GAP, 0.)),
+            (Align2::CENTER_BOTTOM, egui::vec2(0., -GAP)),
+            (Align2::CENTER_TOP, egui::vec2(0., GAP)),
+            (Align2::LEFT_BOTTOM, egui::vec2(GAP, -GAP)),
+            (Align2::RIGHT_BOTTOM, egui::vec2(-GAP, -GAP)),
+            (Align2::LEFT_TOP, egui::vec2(GAP, GAP)),
+            (Align2::RIGHT_TOP, egui::vec2(-GAP, GAP)),
+        ];
+        let mut taken = self.obstacles;
+        for label in self.labels {
+            let galley = painter.layout_no_wrap(label.text, label.font, label.color);
+            let free = std::iter::once(label.preferred)
+                .chain(alternatives)
+                .map(|(align, offset)| {
+                    align.anchor_rect(egui::Rect::from_min_size(
+                        label.anchor + offset,
+                        galley.size(),
+                    ))
+                })
+                .find(|rect| {
+                    clip.contains_rect(*rect)
+                        && !taken.iter().any(|other| other.intersects(rect.shrink(1.)))
+                });
+            if let Some(rect) = free {
+                painter.galley(rect.min, galley);
+                taken.push(rect);
+            }
+        }
+    }
+}
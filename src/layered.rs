@@ -0,0 +1,609 @@
+use std::io::Read;
+use std::path::Path;
+
+use crate::blend::BlendMode;
+use crate::color_types;
+
+/// Formats that keep an image as a stack of layers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// Photoshop documents, including the large document variant.
+    Psd,
+    /// OpenRaster, as written by Krita, GIMP and MyPaint.
+    Ora,
+}
+
+impl Format {
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "psd" | "psb" => Some(Format::Psd),
+            "ora" => Some(Format::Ora),
+            _ => None,
+        }
+    }
+}
+
+/// The size of a layered image, reading as little of it as the format allows.
+pub fn dimensions(path: &Path) -> Result<(u32, u32), String> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    if Format::for_path(path) != Some(Format::Psd) {
+        return LayeredImage::read(path).map(|image| (image.width, image.height));
+    }
+    let mut header = [0; 26];
+    std::fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_err(|e| format!("Could not read {}: {}", name, e))?;
+    let mut cursor = Cursor {
+        data: &header,
+        position: 14,
+    };
+    match &header[..4] {
+        b"8BPS" => {
+            let height = cursor.u32()?;
+            Ok((cursor.u32()?, height))
+        }
+        _ => Err(format!("{} is not a Photoshop document.", name)),
+    }
+}
+
+/// One layer, placed on a transparent canvas of the size of the document.
+pub struct ImageLayer {
+    pub name: String,
+    pub image: image::RgbaImage,
+    pub visible: bool,
+    pub opacity: f32,
+    pub blend: BlendMode,
+}
+
+pub struct LayeredImage {
+    pub width: u32,
+    pub height: u32,
+    /// Bottom layer first.
+    pub layers: Vec<ImageLayer>,
+    /// The flattened image as the application that saved the file made it.
+    merged: Option<image::DynamicImage>,
+}
+
+impl LayeredImage {
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let format =
+            Format::for_path(path).ok_or_else(|| format!("{} is not a layered image.", name))?;
+        let data = std::fs::read(path).map_err(|e| format!("Could not read {}: {}", name, e))?;
+        match format {
+            Format::Psd => read_psd(&data),
+            Format::Ora => read_ora(&data),
+        }
+        .map_err(|e| format!("{} can't be read: {}", name, e))
+    }
+
+    /// The image as saved, or where the file has no flattened copy, its
+    /// visible layers blended bottom to top.
+    pub fn flatten(&self) -> image::DynamicImage {
+        if let Some(merged) = &self.merged {
+            return merged.clone();
+        }
+        let mut flattened = image::RgbaImage::new(self.width, self.height);
+        for layer in self.layers.iter().filter(|layer| layer.visible) {
+            for (below, above) in flattened.pixels_mut().zip(layer.image.pixels()) {
+                let blended = layer.blend.composite(
+                    below.0.map(|channel| channel as f32 / 255.),
+                    above.0.map(|channel| channel as f32 / 255.),
+                    layer.opacity,
+                );
+                below.0 = blended.map(|channel| (channel * 255.).round() as u8);
+            }
+        }
+        image::DynamicImage::ImageRgba8(flattened)
+    }
+}
+
+/// Reads big-endian values from a file in memory.
+struct Cursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.data.len())
+            .ok_or("the file ends early")?;
+        let taken = &self.data[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> Result<i32, String> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    /// A section or channel length, which is 64-bit in large documents.
+    fn length(&mut self, large: bool) -> Result<usize, String> {
+        match large {
+            true => self
+                .array()
+                .map(u64::from_be_bytes)
+                .map(|length| length as usize),
+            false => self.u32().map(|length| length as usize),
+        }
+    }
+
+    /// The cursor over the next `length` bytes, skipping past them.
+    fn section(&mut self, length: usize) -> Result<Cursor<'a>, String> {
+        Ok(Cursor {
+            data: self.take(length)?,
+            position: 0,
+        })
+    }
+}
+
+const PSD_GRAYSCALE: u16 = 1;
+const PSD_RGB: u16 = 3;
+const PSD_CMYK: u16 = 4;
+
+/// What a document's channels are, from its header.
+struct PsdHeader {
+    large: bool,
+    width: u32,
+    height: u32,
+    depth: u16,
+    mode: u16,
+}
+
+impl PsdHeader {
+    /// Channels that make up the color, before any alpha.
+    fn color_channels(&self) -> usize {
+        match self.mode {
+            PSD_GRAYSCALE => 1,
+            PSD_RGB => 3,
+            _ => 4,
+        }
+    }
+
+    /// Reads one channel of `rows` rows of `width` pixels, converted to 8 bits.
+    fn read_channel(
+        &self,
+        cursor: &mut Cursor,
+        compression: u16,
+        width: usize,
+        rows: usize,
+        row_lengths: &[usize],
+    ) -> Result<Vec<u8>, String> {
+        let bytes = self.depth as usize / 8;
+        let raw = match compression {
+            0 => cursor.take(width * rows * bytes)?.to_vec(),
+            1 => {
+                let mut raw = Vec::with_capacity(width * rows * bytes);
+                for length in row_lengths {
+                    color_types::unpack_bits(cursor.take(*length)?, &mut raw);
+                }
+                raw
+            }
+            other => return Err(format!("compression method {} is not supported", other)),
+        };
+        if raw.len() < width * rows * bytes {
+            return Err("a channel has too little data".into());
+        }
+        // Of 16 bits, the high byte comes first.
+        Ok(raw.into_iter().step_by(bytes).take(width * rows).collect())
+    }
+
+    /// The lengths of the compressed rows of `channels` channels.
+    fn row_lengths(
+        &self,
+        cursor: &mut Cursor,
+        channels: usize,
+        rows: usize,
+    ) -> Result<Vec<usize>, String> {
+        (0..channels * rows)
+            .map(|_| match self.large {
+                true => cursor.u32().map(|length| length as usize),
+                false => cursor.u16().map(|length| length as usize),
+            })
+            .collect()
+    }
+
+    /// Interleaves color and alpha planes of the document's color mode into RGBA.
+    fn rgba(
+        &self,
+        width: u32,
+        height: u32,
+        planes: &[Vec<u8>],
+        alpha: Option<&[u8]>,
+    ) -> image::RgbaImage {
+        let pixels = width as usize * height as usize;
+        let mut rgba = Vec::with_capacity(pixels * 4);
+        for index in 0..pixels {
+            let color = match self.mode {
+                PSD_GRAYSCALE => [planes[0][index]; 3],
+                PSD_RGB => [planes[0][index], planes[1][index], planes[2][index]],
+                // Stored as 255 for no ink.
+                _ => {
+                    let ink = [0, 1, 2, 3].map(|channel| 255 - planes[channel][index]);
+                    let rgb = color_types::cmyk_to_rgb(&ink);
+                    [rgb[0], rgb[1], rgb[2]]
+                }
+            };
+            let alpha = alpha.map_or(255, |alpha| alpha[index]);
+            rgba.extend([color[0], color[1], color[2], alpha]);
+        }
+        image::RgbaImage::from_raw(width, height, rgba).unwrap()
+    }
+}
+
+fn read_psd(data: &[u8]) -> Result<LayeredImage, String> {
+    let mut cursor = Cursor { data, position: 0 };
+    if cursor.take(4)? != b"8BPS" {
+        return Err("it is not a Photoshop document".into());
+    }
+    let large = match cursor.u16()? {
+        1 => false,
+        2 => true,
+        version => return Err(format!("version {} is not supported", version)),
+    };
+    cursor.take(6)?;
+    let channels = cursor.u16()? as usize;
+    let height = cursor.u32()?;
+    let width = cursor.u32()?;
+    let depth = cursor.u16()?;
+    let mode = cursor.u16()?;
+    let header = PsdHeader {
+        large,
+        width,
+        height,
+        depth,
+        mode,
+    };
+    if !matches!(mode, PSD_GRAYSCALE | PSD_RGB | PSD_CMYK) {
+        return Err("only grayscale, RGB and CMYK documents are supported".into());
+    }
+    if !matches!(depth, 8 | 16) {
+        return Err(format!("{} bits per channel are not supported", depth));
+    }
+    if channels < header.color_channels() {
+        return Err("it has too few channels for its color mode".into());
+    }
+    // The color mode data and the image resources.
+    for _ in 0..2 {
+        let length = cursor.u32()? as usize;
+        cursor.take(length)?;
+    }
+    let length = cursor.length(large)?;
+    let mut layer_and_mask = cursor.section(length)?;
+    let (layers, merged_alpha) = match length {
+        0 => (Vec::new(), false),
+        _ => read_psd_layers(&header, &mut layer_and_mask)?,
+    };
+
+    let compression = cursor.u16()?;
+    let rows = height as usize;
+    let row_lengths = match compression {
+        1 => header.row_lengths(&mut cursor, channels, rows)?,
+        _ => Vec::new(),
+    };
+    let mut planes = Vec::new();
+    let used = header.color_channels() + merged_alpha as usize;
+    for channel in 0..used {
+        let lengths = row_lengths
+            .get(channel * rows..(channel + 1) * rows)
+            .unwrap_or(&[]);
+        planes.push(header.read_channel(
+            &mut cursor,
+            compression,
+            width as usize,
+            rows,
+            lengths,
+        )?);
+    }
+    let alpha = match merged_alpha {
+        true => planes.pop(),
+        false => None,
+    };
+    let merged = header.rgba(width, height, &planes, alpha.as_deref());
+    Ok(LayeredImage {
+        width,
+        height,
+        layers,
+        merged: Some(image::DynamicImage::ImageRgba8(merged)),
+    })
+}
+
+/// A layer's record, before its channels are read.
+struct PsdLayer {
+    name: String,
+    top: i32,
+    left: i32,
+    width: usize,
+    height: usize,
+    /// Channel IDs and the length of their data.
+    channels: Vec<(i16, usize)>,
+    opacity: f32,
+    visible: bool,
+    blend: BlendMode,
+}
+
+/// Reads the layers, bottom first, and whether the flattened image has an
+/// alpha channel.
+fn read_psd_layers(
+    header: &PsdHeader,
+    cursor: &mut Cursor,
+) -> Result<(Vec<ImageLayer>, bool), String> {
+    let length = cursor.length(header.large)?;
+    if length == 0 {
+        return Ok((Vec::new(), false));
+    }
+    let mut info = cursor.section(length)?;
+    let count = info.u16()? as i16;
+    // A negative count means the first alpha channel is the flattened image's.
+    let merged_alpha = count < 0;
+    let mut records = Vec::new();
+    for _ in 0..count.unsigned_abs() {
+        let top = info.i32()?;
+        let left = info.i32()?;
+        let bottom = info.i32()?;
+        let right = info.i32()?;
+        let channel_count = info.u16()?;
+        let channels = (0..channel_count)
+            .map(|_| {
+                let id = info.u16()? as i16;
+                Ok((id, info.length(header.large)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        info.take(4)?;
+        let blend = match &info.array::<4>()? {
+            b"mul " => BlendMode::Multiply,
+            b"scrn" => BlendMode::Screen,
+            b"over" => BlendMode::Overlay,
+            b"dark" => BlendMode::Darken,
+            b"lite" => BlendMode::Lighten,
+            b"diff" => BlendMode::Difference,
+            _ => BlendMode::Normal,
+        };
+        let opacity = info.u8()? as f32 / 255.;
+        info.u8()?;
+        let flags = info.u8()?;
+        info.u8()?;
+        let extra_length = info.u32()? as usize;
+        let mut extra = info.section(extra_length)?;
+        // The mask and the blending ranges, then the name, padded to 4 bytes.
+        for _ in 0..2 {
+            let length = extra.u32()? as usize;
+            extra.take(length)?;
+        }
+        let name_length = extra.u8()? as usize;
+        let name = String::from_utf8_lossy(extra.take(name_length)?).into_owned();
+        records.push(PsdLayer {
+            name,
+            top,
+            left,
+            width: (right - left).max(0) as usize,
+            height: (bottom - top).max(0) as usize,
+            channels,
+            opacity,
+            visible: flags & 2 == 0,
+            blend,
+        });
+    }
+
+    let mut layers = Vec::new();
+    for record in records {
+        let mut planes: [Option<Vec<u8>>; 5] = Default::default();
+        for (id, length) in &record.channels {
+            let mut channel = info.section(*length)?;
+            // Masks have sizes of their own, and group markers have no pixels.
+            let slot = match *id {
+                -1 => header.color_channels(),
+                id @ 0..=3 if (id as usize) < header.color_channels() => id as usize,
+                _ => continue,
+            };
+            if record.width == 0 || record.height == 0 {
+                continue;
+            }
+            let compression = channel.u16()?;
+            let row_lengths = match compression {
+                1 => header.row_lengths(&mut channel, 1, record.height)?,
+                _ => Vec::new(),
+            };
+            planes[slot] = Some(header.read_channel(
+                &mut channel,
+                compression,
+                record.width,
+                record.height,
+                &row_lengths,
+            )?);
+        }
+        let colors = header.color_channels();
+        let Some(color) = planes[..colors].iter().cloned().collect::<Option<Vec<_>>>() else {
+            continue;
+        };
+        let pixels = header.rgba(
+            record.width as u32,
+            record.height as u32,
+            &color,
+            planes[colors].as_deref(),
+        );
+        let mut image = image::RgbaImage::new(header.width, header.height);
+        image::imageops::replace(&mut image, &pixels, record.left as i64, record.top as i64);
+        layers.push(ImageLayer {
+            name: record.name,
+            image,
+            visible: record.visible,
+            opacity: record.opacity,
+            blend: record.blend,
+        });
+    }
+    Ok((layers, merged_alpha))
+}
+
+/// The files in a zip archive, by name.
+pub fn unzip(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    const END: [u8; 4] = [b'P', b'K', 5, 6];
+    const ENTRY: [u8; 4] = [b'P', b'K', 1, 2];
+    let little = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | *byte as usize)
+    };
+    let end = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|position| data[*position..].starts_with(&END))
+        .ok_or("it is not a zip archive")?;
+    let count = little(&data[end + 10..end + 12]);
+    let mut position = little(&data[end + 16..end + 20]);
+    let mut files = Vec::new();
+    for _ in 0..count {
+        let entry = data
+            .get(position..position + 46)
+            .ok_or("the archive ends early")?;
+        if !entry.starts_with(&ENTRY) {
+            return Err("the archive's directory is damaged".into());
+        }
+        let method = little(&entry[10..12]);
+        let compressed = little(&entry[20..24]);
+        let name_length = little(&entry[28..30]);
+        let skipped = little(&entry[30..32]) + little(&entry[32..34]);
+        let local = little(&entry[42..46]);
+        let name = data
+            .get(position + 46..position + 46 + name_length)
+            .ok_or("the archive ends early")?;
+        position += 46 + name_length + skipped;
+
+        let header = data
+            .get(local..local + 30)
+            .ok_or("the archive ends early")?;
+        let start = local + 30 + little(&header[26..28]) + little(&header[28..30]);
+        let stored = data
+            .get(start..start + compressed)
+            .ok_or("the archive ends early")?;
+        let contents = match method {
+            0 => stored.to_vec(),
+            8 => {
+                let mut contents = Vec::new();
+                flate2::read::DeflateDecoder::new(stored)
+                    .read_to_end(&mut contents)
+                    .map_err(|e| e.to_string())?;
+                contents
+            }
+            other => return Err(format!("zip compression method {} is not supported", other)),
+        };
+        files.push((String::from_utf8_lossy(name).into_owned(), contents));
+    }
+    Ok(files)
+}
+
+/// The attributes of every `<tag ...>` in an XML document, in order.
+fn xml_elements(xml: &str, tag: &str) -> Vec<Vec<(String, String)>> {
+    let opening = format!("<{}", tag);
+    xml.match_indices(&opening)
+        .map(|(start, _)| &xml[start + opening.len()..])
+        .filter(|rest| rest.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>'))
+        .map(|rest| {
+            let mut element = &rest[..rest.find('>').unwrap_or(rest.len())];
+            let mut attributes = Vec::new();
+            while let Some(equals) = element.find('=') {
+                let key = element[..equals].trim().to_string();
+                let value = element[equals + 1..].trim_start();
+                let Some(quote) = value.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+                    break;
+                };
+                let Some(length) = value[1..].find(quote) else {
+                    break;
+                };
+                let unescaped = value[1..1 + length]
+                    .replace("&lt;", "<")
+                    .replace("&gt;", ">")
+                    .replace("&quot;", "\"")
+                    .replace("&apos;", "'")
+                    .replace("&amp;", "&");
+                attributes.push((key, unescaped));
+                element = &value[length + 2..];
+            }
+            attributes
+        })
+        .collect()
+}
+
+fn read_ora(data: &[u8]) -> Result<LayeredImage, String> {
+    let files = unzip(data)?;
+    let file = |name: &str| {
+        files
+            .iter()
+            .find(|(file_name, _)| file_name == name)
+            .map(|(_, contents)| contents.as_slice())
+    };
+    let stack = file("stack.xml").ok_or("it has no stack.xml")?;
+    let stack = String::from_utf8_lossy(stack);
+    let attribute = |attributes: &[(String, String)], key: &str| {
+        attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.clone())
+    };
+    let number = |attributes: &[(String, String)], key: &str| {
+        attribute(attributes, key).and_then(|value| value.trim().parse::<f64>().ok())
+    };
+    let image = xml_elements(&stack, "image");
+    let image = image.first().ok_or("its stack.xml has no image")?;
+    let width = number(image, "w").ok_or("its stack.xml has no width")? as u32;
+    let height = number(image, "h").ok_or("its stack.xml has no height")? as u32;
+
+    let mut layers = Vec::new();
+    // Listed top first.
+    for attributes in xml_elements(&stack, "layer").iter().rev() {
+        let Some(source) = attribute(attributes, "src") else {
+            continue;
+        };
+        let png = file(&source).ok_or_else(|| format!("it has no {}", source))?;
+        let pixels = image::load_from_memory(png)
+            .map_err(|e| format!("{} is damaged: {}", source, e))?
+            .to_rgba8();
+        let mut image = image::RgbaImage::new(width, height);
+        let x = number(attributes, "x").unwrap_or(0.) as i64;
+        let y = number(attributes, "y").unwrap_or(0.) as i64;
+        image::imageops::replace(&mut image, &pixels, x, y);
+        let blend = match attribute(attributes, "composite-op").as_deref() {
+            Some("svg:multiply") => BlendMode::Multiply,
+            Some("svg:screen") => BlendMode::Screen,
+            Some("svg:overlay") => BlendMode::Overlay,
+            Some("svg:darken") => BlendMode::Darken,
+            Some("svg:lighten") => BlendMode::Lighten,
+            Some("svg:difference") => BlendMode::Difference,
+            _ => BlendMode::Normal,
+        };
+        layers.push(ImageLayer {
+            name: attribute(attributes, "name").unwrap_or(source),
+            image,
+            visible: attribute(attributes, "visibility").as_deref() != Some("hidden"),
+            opacity: number(attributes, "opacity").unwrap_or(1.).clamp(0., 1.) as f32,
+            blend,
+        });
+    }
+    let merged = file("mergedimage.png").and_then(|png| image::load_from_memory(png).ok());
+    Ok(LayeredImage {
+        width,
+        height,
+        layers,
+        merged,
+    })
+}
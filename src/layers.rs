@@ -0,0 +1,326 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use eframe::egui;
+
+use crate::angles::AngleUnit;
+use crate::blend::{self, BlendMode, Compositor};
+use crate::layered::LayeredImage;
+use crate::longitudes::Longitudes;
+use crate::{engine, feature_matching, projection_ui, ImageData, ProjectionData};
+
+/// Another map shown over the projected source, reprojected from its own
+/// projection into the target projection.
+pub struct Layer {
+    /// The file name, or for a layer of a layered file, the layer's name.
+    name: String,
+    image: image::DynamicImage,
+    pub projection: ProjectionData,
+    pub visible: bool,
+    pub opacity: f32,
+    pub blend: BlendMode,
+    /// The layer in the target projection, and the key of what it was made for.
+    reprojected: Option<(String, ImageData)>,
+}
+
+impl Layer {
+    pub fn load(path: PathBuf) -> Result<Self, String> {
+        let image = image::open(&path).map_err(|e| e.to_string())?;
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        Ok(Self::new(name, image))
+    }
+
+    fn new(name: String, image: image::DynamicImage) -> Self {
+        Layer {
+            name,
+            image,
+            projection: ProjectionData::Equirectangular(submaptive::Equirectangular::new().build()),
+            visible: true,
+            opacity: 1.,
+            blend: BlendMode::Normal,
+            reprojected: None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Identifies the layer's projection and the view it is shown in. Only
+    /// analytic projections can be keyed, since a rubber sheet belongs to the
+    /// source's control points.
+    fn key(&self, target_key: &str, dimensions: (u32, u32)) -> Option<String> {
+        let parameters = feature_matching::parameters(&self.projection)?;
+        Some(format!(
+            "{} {:?} -> {} {:?}",
+            self.projection.kind(),
+            parameters,
+            target_key,
+            dimensions
+        ))
+    }
+
+    /// The layer projected into `target` as the source map is, then scaled
+    /// to `dimensions`.
+    fn reproject(
+        &self,
+        target: &ProjectionData,
+        dimensions: (u32, u32),
+    ) -> Option<image::DynamicImage> {
+        let projected = engine::reproject(
+            self.image.clone(),
+            &self.projection,
+            target,
+            &Default::default(),
+        )
+        .ok()?;
+        Some(
+            match (projected.width(), projected.height()) == dimensions {
+                true => projected,
+                false => projected.resize_exact(
+                    dimensions.0,
+                    dimensions.1,
+                    image::imageops::FilterType::Triangle,
+                ),
+            },
+        )
+    }
+
+    /// The layer reprojected into `target`, at `dimensions`. It is only made
+    /// again when the projections or the size have changed, not when the
+    /// visibility or opacity of this or another layer has.
+    pub fn reprojected(
+        &mut self,
+        ctx: &egui::Context,
+        target: &ProjectionData,
+        target_key: &str,
+        dimensions: (u32, u32),
+    ) -> Option<&ImageData> {
+        let key = self.key(target_key, dimensions)?;
+        let stale = !matches!(&self.reprojected, Some((cached, _)) if *cached == key);
+        if stale {
+            let image = self.reproject(target, dimensions)?;
+            self.reprojected = Some((key, ImageData::new(ctx, &self.name(), image)));
+        }
+        self.reprojected.as_ref().map(|(_, data)| data)
+    }
+}
+
+/// Draws the visible layers blended over `base`, the projected map shown in
+/// `rect`, bottom layer first. Without a compositor (or past its limit),
+/// layers are drawn in the normal blend mode.
+#[allow(clippy::too_many_arguments)]
+pub fn paint_layers(
+    ui: &egui::Ui,
+    rect: egui::Rect,
+    base: egui::TextureId,
+    compositor: Option<&Arc<Mutex<Compositor>>>,
+    layers: &mut [Layer],
+    target: &ProjectionData,
+    target_key: &str,
+    dimensions: (u32, u32),
+) {
+    let painter = ui.painter_at(rect);
+    let mut textures = Vec::new();
+    for layer in layers.iter_mut().filter(|layer| layer.visible) {
+        let (opacity, mode) = (layer.opacity, layer.blend);
+        if let Some(reprojected) = layer.reprojected(ui.ctx(), target, target_key, dimensions) {
+            textures.push((reprojected.handle.id(), opacity, mode));
+        }
+    }
+    if textures.is_empty() {
+        return;
+    }
+    if let Some(compositor) = compositor {
+        textures = blend::paint_composite(&painter, rect, compositor, base, textures);
+    }
+    let uv = egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.));
+    for (texture, opacity, _) in textures {
+        painter.image(
+            texture,
+            rect,
+            uv,
+            egui::Color32::from_white_alpha((opacity * 255.) as u8),
+        );
+    }
+}
+
+/// The visible layers blended over `base`, for export. Layers are
+/// reprojected at the size of `base` where their cached copy doesn't match.
+pub fn composite(
+    base: image::DynamicImage,
+    layers: &[Layer],
+    target: &ProjectionData,
+) -> image::DynamicImage {
+    let mut composite = base.to_rgba8();
+    let dimensions = composite.dimensions();
+    for layer in layers.iter().filter(|layer| layer.visible) {
+        if feature_matching::parameters(&layer.projection).is_none() {
+            continue;
+        }
+        let cached = layer
+            .reprojected
+            .as_ref()
+            .map(|(_, data)| &data.image)
+            .filter(|image| (image.width(), image.height()) == dimensions);
+        let reprojected = match cached {
+            Some(image) => image.to_rgba8(),
+            None => match layer.reproject(target, dimensions) {
+                Some(image) => image.to_rgba8(),
+                None => continue,
+            },
+        };
+        for (below, above) in composite.pixels_mut().zip(reprojected.pixels()) {
+            let blended = layer.blend.composite(
+                below.0.map(|channel| channel as f32 / 255.),
+                above.0.map(|channel| channel as f32 / 255.),
+                layer.opacity,
+            );
+            below.0 = blended.map(|channel| (channel * 255.).round() as u8);
+        }
+    }
+    image::DynamicImage::ImageRgba8(composite)
+}
+
+/// A layered file being added, of which the user either adds the flattened
+/// image or picks layers to add one by one.
+pub struct LayerImport {
+    path: PathBuf,
+    file: LayeredImage,
+    selected: Vec<bool>,
+}
+
+impl LayerImport {
+    pub fn new(path: PathBuf, file: LayeredImage) -> Self {
+        let selected = file.layers.iter().map(|layer| layer.visible).collect();
+        LayerImport {
+            path,
+            file,
+            selected,
+        }
+    }
+
+    fn flattened(&self) -> Layer {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        Layer::new(name.into_owned(), self.file.flatten())
+    }
+
+    /// The selected layers, bottom first, each with the opacity and blend
+    /// mode it had in the file.
+    fn selected_layers(&self) -> Vec<Layer> {
+        self.file
+            .layers
+            .iter()
+            .zip(&self.selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(layer, _)| Layer {
+                opacity: layer.opacity,
+                blend: layer.blend,
+                ..Layer::new(
+                    layer.name.clone(),
+                    image::DynamicImage::ImageRgba8(layer.image.clone()),
+                )
+            })
+            .collect()
+    }
+}
+
+pub enum LayerAction {
+    Add(PathBuf),
+}
+
+/// Lists the layers of a file being added, topmost first as in image editors.
+fn import_ui(ui: &mut egui::Ui, import: &mut Option<LayerImport>, layers: &mut Vec<Layer>) {
+    let Some(pending) = import else {
+        return;
+    };
+    // What to add once the import is done, which is nothing when cancelled.
+    let mut added = None;
+    ui.group(|ui| {
+        ui.label(format!(
+            "{} has {} layers.",
+            pending
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            pending.file.layers.len()
+        ));
+        for (layer, selected) in pending.file.layers.iter().zip(&mut pending.selected).rev() {
+            ui.checkbox(selected, &layer.name);
+        }
+        ui.horizontal(|ui| {
+            let any = pending.selected.contains(&true);
+            if ui
+                .add_enabled(any, egui::Button::new("Add selected layers"))
+                .clicked()
+            {
+                added = Some(pending.selected_layers());
+            } else if ui.button("Add flattened").clicked() {
+                added = Some(vec![pending.flattened()]);
+            } else if ui.button("Cancel").clicked() {
+                added = Some(Vec::new());
+            }
+        });
+    });
+    if let Some(added) = added {
+        layers.extend(added);
+        *import = None;
+    }
+}
+
+pub fn layers_ui(
+    ui: &mut egui::Ui,
+    layers: &mut Vec<Layer>,
+    import: &mut Option<LayerImport>,
+    longitudes: &Longitudes,
+    unit: AngleUnit,
+) -> Option<LayerAction> {
+    let mut action = None;
+    ui.collapsing("Layers", |ui| {
+        if ui.button("Add layer...").clicked() {
+            if let Some(path) = rfd::FileDialog::new().pick_file() {
+                action = Some(LayerAction::Add(path));
+            }
+        }
+        import_ui(ui, import, layers);
+        let mut removed = None;
+        for (index, layer) in layers.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    let name = layer.name();
+                    ui.checkbox(&mut layer.visible, name);
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                });
+                ui.add(egui::Slider::new(&mut layer.opacity, 0.0..=1.).text("Opacity"));
+                egui::ComboBox::new("blend", "Blend mode")
+                    .selected_text(layer.blend.name())
+                    .show_ui(ui, |ui| {
+                        for mode in BlendMode::ALL {
+                            ui.selectable_value(&mut layer.blend, mode, mode.name());
+                        }
+                    });
+                projection_ui(
+                    ui,
+                    &mut layer.projection,
+                    longitudes,
+                    unit,
+                    "Layer projection",
+                );
+                if feature_matching::parameters(&layer.projection).is_none() {
+                    ui.colored_label(egui::Color32::RED, "Layers need an analytic projection.");
+                }
+            });
+        }
+        if let Some(index) = removed {
+            layers.remove(index);
+        }
+    });
+    action
+}
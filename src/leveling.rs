@@ -0,0 +1,200 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::preprocess::sample_bilinear;
+
+/// A rotation of the sphere that re-levels an equirectangular panorama shot
+/// with a tilted camera, in degrees.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Orientation {
+    /// Around the vertical axis, turning the view left or right.
+    pub yaw: f64,
+    /// Around the left-right axis, tilting the view up or down.
+    pub pitch: f64,
+    /// Around the viewing axis.
+    pub roll: f64,
+}
+
+pub type Vector = [f64; 3];
+type Matrix = [[f64; 3]; 3];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+fn transform(m: &Matrix, v: Vector) -> Vector {
+    std::array::from_fn(|i| m[i][0] * v[0] + m[i][1] * v[1] + m[i][2] * v[2])
+}
+
+fn cross(a: Vector, b: Vector) -> Vector {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn dot(a: Vector, b: Vector) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// The direction shown at a pixel of an equirectangular image, with z up and
+/// x towards its center.
+pub fn direction(pixel: (f64, f64), dimensions: (u32, u32)) -> Vector {
+    let long = (pixel.0 / dimensions.0 as f64 - 0.5) * std::f64::consts::TAU;
+    let lat = (0.5 - pixel.1 / dimensions.1 as f64) * std::f64::consts::PI;
+    [lat.cos() * long.cos(), lat.cos() * long.sin(), lat.sin()]
+}
+
+/// Where an equirectangular image shows `direction`.
+pub fn pixel(direction: Vector, dimensions: (u32, u32)) -> (f64, f64) {
+    let long = direction[1].atan2(direction[0]);
+    let lat = direction[2].clamp(-1., 1.).asin();
+    (
+        (long / std::f64::consts::TAU + 0.5) * dimensions.0 as f64,
+        (0.5 - lat / std::f64::consts::PI) * dimensions.1 as f64,
+    )
+}
+
+impl Orientation {
+    pub fn is_identity(&self) -> bool {
+        *self == Default::default()
+    }
+
+    /// Takes a direction of the source panorama to the leveled one.
+    fn matrix(&self) -> Matrix {
+        let (yaw, pitch, roll) = (
+            self.yaw.to_radians(),
+            self.pitch.to_radians(),
+            self.roll.to_radians(),
+        );
+        let z = [
+            [yaw.cos(), -yaw.sin(), 0.],
+            [yaw.sin(), yaw.cos(), 0.],
+            [0., 0., 1.],
+        ];
+        let y = [
+            [pitch.cos(), 0., pitch.sin()],
+            [0., 1., 0.],
+            [-pitch.sin(), 0., pitch.cos()],
+        ];
+        let x = [
+            [1., 0., 0.],
+            [0., roll.cos(), -roll.sin()],
+            [0., roll.sin(), roll.cos()],
+        ];
+        multiply(&z, &multiply(&y, &x))
+    }
+
+    /// The pitch and roll that put pixels on the source's horizon onto the
+    /// equator, keeping the yaw. Needs at least two points that aren't opposite.
+    pub fn from_horizon(points: &[(f64, f64)], dimensions: (u32, u32), yaw: f64) -> Option<Self> {
+        let directions: Vec<_> = points
+            .iter()
+            .map(|point| direction(*point, dimensions))
+            .collect();
+        let first = *directions.first()?;
+        // The horizon's normal, from the planes through the first point and
+        // each other one, all oriented upwards.
+        let mut normal = [0.; 3];
+        for other in &directions[1..] {
+            let mut plane = cross(first, *other);
+            if plane[2] < 0. {
+                plane = plane.map(|c| -c);
+            }
+            normal = std::array::from_fn(|i| normal[i] + plane[i]);
+        }
+        let length = dot(normal, normal).sqrt();
+        if length < 1e-9 {
+            return None;
+        }
+        let [nx, ny, nz] = normal.map(|c| c / length);
+        let roll = ny.atan2(nz);
+        let pitch = (-nx).atan2(ny.hypot(nz));
+        Some(Orientation {
+            yaw,
+            pitch: pitch.to_degrees(),
+            roll: roll.to_degrees(),
+        })
+    }
+
+    /// Re-renders an equirectangular panorama with this orientation.
+    pub fn apply(&self, image: image::DynamicImage) -> image::DynamicImage {
+        if self.is_identity() {
+            return image;
+        }
+        let source = image.to_rgba8();
+        let dimensions = source.dimensions();
+        // Leveled directions are looked up in the source with the inverse, the transpose.
+        let m = self.matrix();
+        let inverse: Matrix = std::array::from_fn(|i| std::array::from_fn(|j| m[j][i]));
+        let leveled = image::RgbaImage::from_fn(dimensions.0, dimensions.1, |x, y| {
+            let center = (x as f64 + 0.5, y as f64 + 0.5);
+            sample_direction(&source, transform(&inverse, direction(center, dimensions)))
+        });
+        image::DynamicImage::ImageRgba8(leveled)
+    }
+}
+
+/// Samples an equirectangular panorama in the given direction.
+pub fn sample_direction(panorama: &image::RgbaImage, direction: Vector) -> image::Rgba<u8> {
+    let dimensions = panorama.dimensions();
+    let (x, y) = pixel(direction, dimensions);
+    // Wrap around the antimeridian rather than sampling transparency there.
+    let x = (x - 0.5).rem_euclid(dimensions.0 as f64);
+    sample_bilinear(panorama, x.min(dimensions.0 as f64 - 1.), y - 0.5)
+}
+
+pub fn orientation_ui(
+    ui: &mut egui::Ui,
+    orientation: &mut Orientation,
+    horizon: &mut Vec<(f64, f64)>,
+    unit: AngleUnit,
+) {
+    ui.collapsing("Leveling", |ui| {
+        ui.label("Rotates equirectangular panoramas shot with a tilted camera.");
+        for (value, label) in [
+            (&mut orientation.yaw, "Yaw"),
+            (&mut orientation.pitch, "Pitch"),
+            (&mut orientation.roll, "Roll"),
+        ] {
+            ui.add(
+                egui::Slider::new(value, -180.0..=180.)
+                    .angle(unit)
+                    .text(label),
+            );
+        }
+        ui.label(format!(
+            "{} horizon point(s) picked with the level tool.",
+            horizon.len()
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Clear horizon").clicked() {
+                horizon.clear();
+            }
+            if ui.button("Reset").clicked() {
+                *orientation = Default::default();
+                horizon.clear();
+            }
+        });
+    });
+}
+
+pub fn paint_horizon(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    horizon: &[(f64, f64)],
+) {
+    let color = egui::Color32::from_rgb(120, 200, 255);
+    for (x, y) in horizon {
+        let position = rect.min
+            + egui::vec2(
+                *x as f32 / image_dimensions.0 as f32 * rect.width(),
+                *y as f32 / image_dimensions.1 as f32 * rect.height(),
+            );
+        painter.circle_stroke(position, 4., (2., color));
+    }
+}
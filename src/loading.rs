@@ -0,0 +1,381 @@
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use eframe::egui;
+
+use crate::attribution;
+use crate::cache::Cache;
+use crate::codecs::Codec;
+use crate::color_types;
+use crate::fits::{self, Sky};
+use crate::gridded::{self, Field};
+use crate::jobs::{Job, Progress, ProgressReader};
+use crate::layered::{self, LayeredImage};
+use crate::overviews::Overviews;
+use crate::remote::{self, Request};
+
+/// Side length of the quick preview shown while a large image is decoding.
+const PREVIEW_SIZE: u32 = 512;
+/// Longest side of an image that is decoded at all. A malformed header can
+/// claim any size, so anything beyond what real maps have is refused.
+const MAX_SIDE: u32 = 131_072;
+/// Most memory a decoder may take unless the settings say otherwise, in
+/// bytes. Well below what a machine has, so that a runaway allocation is
+/// refused rather than swapping or running out of memory.
+pub const DEFAULT_DECODE_MEMORY: u64 = 4 << 30;
+
+/// Set from the settings whenever they change.
+static DECODE_MEMORY: AtomicU64 = AtomicU64::new(DEFAULT_DECODE_MEMORY);
+
+/// Limits the memory decoders may take to `bytes` from now on.
+pub fn set_memory_limit(bytes: u64) {
+    DECODE_MEMORY.store(bytes, Ordering::Relaxed);
+}
+
+fn memory_limit() -> u64 {
+    DECODE_MEMORY.load(Ordering::Relaxed)
+}
+
+pub enum LoadEvent {
+    /// A reduced-resolution version, available before the full image.
+    Preview(egui::ColorImage),
+    Loaded(Box<Loaded>),
+    Failed(String),
+}
+
+pub struct Loaded {
+    pub image: image::DynamicImage,
+    /// A copy small enough to upload as a texture.
+    pub display: egui::ColorImage,
+    pub overviews: Overviews,
+    /// Content hash of the file, for looking up cached results derived from it.
+    pub hash: Option<String>,
+    /// How the image was converted from a color type the app doesn't work in.
+    pub conversion: Option<String>,
+    /// The values the image was drawn from, if the file holds gridded data.
+    pub field: Option<Field>,
+    /// Where on the sky the image lies, if it is an astronomical one that says.
+    pub sky: Option<Sky>,
+    /// What was read of a remote file.
+    pub remote: Option<String>,
+    /// The copyright the file states.
+    pub attribution: Option<String>,
+}
+
+/// A source image being decoded in the background.
+pub struct Loading {
+    pub path: PathBuf,
+    pub job: Job<LoadEvent>,
+    pub preview: Option<egui::TextureHandle>,
+}
+
+impl Loading {
+    pub fn start(ctx: &egui::Context, path: PathBuf, cache: Cache) -> Self {
+        let request = remote::url(&path).map(|url| Request {
+            url: url.to_string(),
+            ..Default::default()
+        });
+        Self::spawn(ctx, path, request, cache)
+    }
+
+    /// Reads part of a remote image.
+    pub fn start_remote(ctx: &egui::Context, request: Request, cache: Cache) -> Self {
+        Self::spawn(ctx, request.path(), Some(request), cache)
+    }
+
+    fn spawn(ctx: &egui::Context, path: PathBuf, request: Option<Request>, cache: Cache) -> Self {
+        let max_texture_side = ctx.input(|input| input.max_texture_side);
+        let worker_path = path.clone();
+        let label = format!(
+            "Decoding {}",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let job = Job::spawn(ctx, label, move |send, progress| {
+            let preview = guarded(&worker_path, || Ok(quick_preview(&worker_path)));
+            let sent_preview = match preview.ok().flatten() {
+                Some(preview) => {
+                    send(LoadEvent::Preview(preview));
+                    true
+                }
+                None => false,
+            };
+            let decoded = match (&request, gridded::Format::for_path(&worker_path)) {
+                (Some(request), _) => guarded(&worker_path, || remote::read(request, progress))
+                    .map(|(image, description)| (image, None, Some(description))),
+                (None, Some(_)) => guarded(&worker_path, || read_field(&worker_path))
+                    .map(|field| (field.render(&Default::default()), Some(field), None)),
+                (None, None) => decode(&worker_path, progress).map(|image| (image, None, None)),
+            };
+            match decoded {
+                Ok((image, field, remote)) => {
+                    if !sent_preview {
+                        send(LoadEvent::Preview(color_image(
+                            &image.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE),
+                        )));
+                    }
+                    let hash = Cache::hash_file(&worker_path).ok();
+                    let overviews =
+                        Overviews::for_image(&worker_path, &image, &cache, hash.as_deref());
+                    let fits = image.width().max(image.height()) as usize <= max_texture_side;
+                    let display = match overviews.fitting(max_texture_side as u32) {
+                        Some(level) if !fits => color_image(level),
+                        _ => display_image(&image, max_texture_side),
+                    };
+                    send(LoadEvent::Loaded(Box::new(Loaded {
+                        image,
+                        display,
+                        overviews,
+                        hash,
+                        conversion: color_types::inspect(&worker_path),
+                        field,
+                        sky: fits::sky(&worker_path),
+                        remote,
+                        attribution: attribution::from_file(&worker_path),
+                    })));
+                }
+                Err(e) => send(LoadEvent::Failed(e)),
+            }
+        });
+        Loading {
+            path,
+            job,
+            preview: None,
+        }
+    }
+}
+
+fn limits() -> image::io::Limits {
+    let mut limits = image::io::Limits::default();
+    limits.max_image_width = Some(MAX_SIDE);
+    limits.max_image_height = Some(MAX_SIDE);
+    limits.max_alloc = Some(memory_limit());
+    limits
+}
+
+/// Says what is wrong with the file in terms of the file rather than the decoder.
+fn describe_error(path: &std::path::Path, error: image::ImageError) -> String {
+    use image::ImageError;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    match error {
+        ImageError::IoError(e) => match e.kind() {
+            std::io::ErrorKind::NotFound => format!("{} doesn't exist.", name),
+            std::io::ErrorKind::PermissionDenied => format!("{} may not be read.", name),
+            std::io::ErrorKind::UnexpectedEof => {
+                format!(
+                    "{} ends early; it may not have been copied completely.",
+                    name
+                )
+            }
+            _ => format!("{} could not be read: {}", name, e),
+        },
+        ImageError::Unsupported(e) => format!("{} is in a form that can't be read: {}", name, e),
+        ImageError::Limits(e) => format!("{} is too large to open: {}", name, e),
+        ImageError::Decoding(e) => format!("{} is damaged: {}", name, e),
+        e => format!("{} could not be read: {}", name, e),
+    }
+}
+
+/// Runs a decoder, turning a panic on a malformed file into an error.
+fn guarded<T>(
+    path: &std::path::Path,
+    decode: impl FnOnce() -> Result<T, String>,
+) -> Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(decode)).unwrap_or_else(|_| {
+        Err(format!(
+            "{} is damaged in a way the decoder couldn't handle.",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        ))
+    })
+}
+
+/// Refuses what wouldn't fit before allocating any of it. Each pixel is
+/// counted with four channels as deep as those of `color_type`, as it will
+/// be once converted for projecting and drawing.
+pub fn check_size(
+    path: &std::path::Path,
+    width: u32,
+    height: u32,
+    color_type: image::ColorType,
+) -> Result<(), String> {
+    let channel_bytes = color_type.bytes_per_pixel() / color_type.channel_count();
+    let bytes = width as u64 * height as u64 * 4 * channel_bytes as u64;
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let claims = format!(
+        "{} claims to be {} × {} px, which would take {:.1} GB to open.",
+        name,
+        width,
+        height,
+        bytes as f64 / 1e9
+    );
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Err(claims);
+    }
+    if bytes > memory_limit() {
+        return Err(format!(
+            "{} The settings allow {:.1} GB.",
+            claims,
+            memory_limit() as f64 / 1e9
+        ));
+    }
+    Ok(())
+}
+
+/// The color type of an image in `format` from its header, for the formats
+/// that hold more than 8 bits per channel. Others are decoded to 8 bits.
+fn color_type(path: &std::path::Path, format: image::ImageFormat) -> Option<image::ColorType> {
+    use image::ImageDecoder;
+    let file = BufReader::new(std::fs::File::open(path).ok()?);
+    match format {
+        image::ImageFormat::Png => image::codecs::png::PngDecoder::new(file)
+            .ok()
+            .map(|decoder| decoder.color_type()),
+        image::ImageFormat::Tiff => image::codecs::tiff::TiffDecoder::new(file)
+            .ok()
+            .map(|decoder| decoder.color_type()),
+        image::ImageFormat::Pnm => image::codecs::pnm::PnmDecoder::new(file)
+            .ok()
+            .map(|decoder| decoder.color_type()),
+        image::ImageFormat::OpenExr => image::codecs::openexr::OpenExrDecoder::new(file)
+            .ok()
+            .map(|decoder| decoder.color_type()),
+        image::ImageFormat::Hdr => Some(image::ColorType::Rgb32F),
+        _ => None,
+    }
+}
+
+/// Reads gridded data, refusing what would draw too large an image.
+pub fn read_field(path: &std::path::Path) -> Result<Field, String> {
+    let field = Field::read(path)?;
+    let (width, height) = field.canvas_dimensions();
+    check_size(path, width, height, image::ColorType::Rgba8)?;
+    Ok(field)
+}
+
+/// Decodes an image within the limits on its size, with errors that make
+/// sense to the user. TIFFs in color types `image` can't decode are converted
+/// by `color_types`, and formats it can't read at all by `codecs`. Layered
+/// images are flattened, gridded data is drawn in the default colors and
+/// FITS images are stretched. Remote images are read at the default
+/// resolution.
+pub fn decode(path: &std::path::Path, progress: &Progress) -> Result<image::DynamicImage, String> {
+    guarded(path, || {
+        if let Some(url) = remote::url(path) {
+            let request = Request {
+                url: url.to_string(),
+                ..Default::default()
+            };
+            return remote::read(&request, progress).map(|(image, _)| image);
+        }
+        if gridded::Format::for_path(path).is_some() {
+            return read_field(path).map(|field| field.render(&Default::default()));
+        }
+        if layered::Format::for_path(path).is_some() {
+            let (width, height) = layered::dimensions(path)?;
+            check_size(path, width, height, image::ColorType::Rgba8)?;
+            return LayeredImage::read(path).map(|file| file.flatten());
+        }
+        if fits::is_fits(path) {
+            let (width, height) = fits::dimensions(path)?;
+            check_size(path, width, height, image::ColorType::La16)?;
+            return fits::decode(path);
+        }
+        if let Some(codec) = Codec::for_path(path) {
+            let (width, height) = codec.dimensions(path)?;
+            check_size(path, width, height, codec.color_type())?;
+            return codec.decode(path);
+        }
+        let header = std::fs::File::open(path)
+            .and_then(|file| image::io::Reader::new(BufReader::new(file)).with_guessed_format())
+            .map_err(|e| describe_error(path, e.into()))?;
+        if header.format().is_none() {
+            return Err(format!(
+                "{} is not an image in a known format.",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+        }
+        // Refuses what wouldn't fit before allocating any of it.
+        let color_type = header
+            .format()
+            .and_then(|format| color_type(path, format))
+            .unwrap_or(image::ColorType::Rgba8);
+        let (width, height) = match header.into_dimensions() {
+            Err(image::ImageError::Unsupported(e)) => color_types::tiff_dimensions(path)
+                .ok_or_else(|| describe_error(path, image::ImageError::Unsupported(e)))?,
+            result => result.map_err(|e| describe_error(path, e))?,
+        };
+        check_size(path, width, height, color_type)?;
+        let file = std::fs::File::open(path).map_err(|e| describe_error(path, e.into()))?;
+        let length = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        let reader = BufReader::new(ProgressReader::new(file, length, progress));
+        let mut reader = image::io::Reader::new(reader)
+            .with_guessed_format()
+            .map_err(|e| describe_error(path, e.into()))?;
+        reader.limits(limits());
+        let format = reader.format();
+        match reader.decode() {
+            Err(image::ImageError::Unsupported(e)) if format == Some(image::ImageFormat::Tiff) => {
+                color_types::decode_tiff(path)?
+                    .ok_or_else(|| describe_error(path, image::ImageError::Unsupported(e)))
+            }
+            result => result.map_err(|e| describe_error(path, e)),
+        }
+    })
+}
+
+/// JPEGs can be decoded at a fraction of their size much faster than in full,
+/// and TIFFs with overviews carry smaller copies of themselves, which gives a
+/// preview right away. Other images have to wait for the full decode.
+fn quick_preview(path: &std::path::Path) -> Option<egui::ColorImage> {
+    let reader = image::io::Reader::open(path)
+        .ok()?
+        .with_guessed_format()
+        .ok()?;
+    match reader.format() {
+        Some(image::ImageFormat::Jpeg) => {}
+        Some(image::ImageFormat::Tiff) => {
+            let overview = remote::overview(path, PREVIEW_SIZE)?;
+            return Some(color_image(&overview.thumbnail(PREVIEW_SIZE, PREVIEW_SIZE)));
+        }
+        _ => return None,
+    }
+    let file = BufReader::new(std::fs::File::open(path).ok()?);
+    let mut decoder = image::codecs::jpeg::JpegDecoder::new(file).ok()?;
+    decoder
+        .scale(PREVIEW_SIZE as u16, PREVIEW_SIZE as u16)
+        .ok()?;
+    let preview = image::DynamicImage::from_decoder(decoder).ok()?;
+    Some(color_image(&preview))
+}
+
+fn color_image(image: &image::DynamicImage) -> egui::ColorImage {
+    egui::ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.to_rgba8().as_flat_samples().as_slice(),
+    )
+}
+
+/// The image as a texture, downscaled if it exceeds what the GPU can hold.
+pub fn display_image(image: &image::DynamicImage, max_texture_side: usize) -> egui::ColorImage {
+    let max_side = max_texture_side as u32;
+    if image.width() > max_side || image.height() > max_side {
+        color_image(&image.thumbnail(max_side, max_side))
+    } else {
+        color_image(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deeper_images_take_more_memory() {
+        let path = std::path::Path::new("deep.png");
+        // 3.6 GB at 8 bits per channel, within the default limit.
+        let (width, height) = (30_000, 30_000);
+        assert!(check_size(path, width, height, image::ColorType::L8).is_ok());
+        assert!(check_size(path, width, height, image::ColorType::Rgb16).is_err());
+        assert!(check_size(path, width, height, image::ColorType::Rgba32F).is_err());
+    }
+}
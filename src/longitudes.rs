@@ -0,0 +1,281 @@
+use std::ops::RangeInclusive;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Domain {
+    /// −180° to 180°.
+    #[default]
+    Signed,
+    /// 0° to 360°.
+    Positive,
+}
+
+/// How a map counts longitudes, which is only a matter of display and entry:
+/// internally they are always east of Greenwich, from −180° to 180°.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Longitudes {
+    /// Where the map's zero meridian is, in degrees east of Greenwich (e.g.
+    /// 2.337° for Paris or −17.667° for Ferro).
+    pub prime_meridian: f64,
+    pub domain: Domain,
+    /// Planetographic maps of Mars and many older ones count westwards.
+    pub west_positive: bool,
+}
+
+/// Meridians maps are often centered on, in degrees east of Greenwich.
+const CITY_MERIDIANS: [(&str, f64); 8] = [
+    ("Honolulu", -157.858),
+    ("Washington", -77.037),
+    ("New York", -74.006),
+    ("Paris", 2.337),
+    ("Moscow", 37.618),
+    ("Beijing", 116.391),
+    ("Tokyo", 139.692),
+    ("Sydney", 151.209),
+];
+
+/// Steps, in degrees, to move a meridian by.
+const NUDGES: [f64; 4] = [-1., -0.1, 0.1, 1.];
+
+/// Wraps `long` into the 360° starting at `min`, but keeps the end itself.
+fn wrap(long: f64, min: f64) -> f64 {
+    if (min..=min + 360.).contains(&long) {
+        long
+    } else {
+        (long - min).rem_euclid(360.) + min
+    }
+}
+
+impl Longitudes {
+    pub fn range(&self) -> RangeInclusive<f64> {
+        match self.domain {
+            Domain::Signed => -180.0..=180.,
+            Domain::Positive => 0.0..=360.,
+        }
+    }
+
+    /// The map's longitude for an internal one.
+    pub fn map_long(&self, long: f64) -> f64 {
+        let relative = long - self.prime_meridian;
+        let signed = if self.west_positive {
+            -relative
+        } else {
+            relative
+        };
+        wrap(signed, *self.range().start())
+    }
+
+    /// The internal longitude for one of the map's.
+    pub fn internal_long(&self, long: f64) -> f64 {
+        let east = if self.west_positive { -long } else { long };
+        wrap(east + self.prime_meridian, -180.)
+    }
+
+    /// A slider showing and setting the internal longitude `long` in the map's terms.
+    pub fn slider<'a>(&self, long: &'a mut f64) -> LongitudeSlider<'a> {
+        LongitudeSlider {
+            long,
+            longitudes: *self,
+            unit: AngleUnit::Degrees,
+            text: String::new(),
+        }
+    }
+
+    /// Like [`Longitudes::slider`], for places where a slider doesn't fit.
+    pub fn drag_value<'a>(&self, long: &'a mut f64) -> egui::DragValue<'a> {
+        let longitudes = *self;
+        egui::DragValue::from_get_set(move |value| {
+            if let Some(value) = value {
+                *long = longitudes.internal_long(value);
+            }
+            longitudes.map_long(*long)
+        })
+        .clamp_range(self.range())
+    }
+}
+
+/// How long the knob takes to glide to a value set from elsewhere, in seconds.
+const GLIDE_TIME: f32 = 0.25;
+
+/// A slider for a longitude that goes round instead of stopping at the ends:
+/// dragging on past 180° carries on from −180°, and so does dragging the value
+/// next to it. Values set from elsewhere, such as by centering the map on a
+/// point, are glided to the short way round.
+pub struct LongitudeSlider<'a> {
+    long: &'a mut f64,
+    longitudes: Longitudes,
+    unit: AngleUnit,
+    text: String,
+}
+
+impl<'a> LongitudeSlider<'a> {
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = text.into();
+        self
+    }
+}
+
+impl<'a> AngleWidget for LongitudeSlider<'a> {
+    fn angle(mut self, unit: AngleUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+}
+
+impl<'a> egui::Widget for LongitudeSlider<'a> {
+    fn ui(self, ui: &mut egui::Ui) -> egui::Response {
+        let LongitudeSlider {
+            long,
+            longitudes,
+            unit,
+            text,
+        } = self;
+        let start = *longitudes.range().start();
+        ui.horizontal(|ui| {
+            let size = egui::vec2(ui.spacing().slider_width, ui.spacing().interact_size.y);
+            let (rect, mut response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+            let radius = rect.height() / 2.5;
+            let rail = rect.shrink2(egui::vec2(radius, 0.));
+            let value_at = |x: f32| start + ((x - rail.left()) / rail.width()) as f64 * 360.;
+            let value = longitudes.map_long(*long);
+            // Pressing jumps to the pointer, after which the knob follows the
+            // pointer's movement, so that it can go on round past the ends.
+            let new_value = match response.interact_pointer_pos() {
+                Some(pointer) if !response.dragged() || response.drag_started() => {
+                    Some(value_at(pointer.x.clamp(rail.left(), rail.right())))
+                }
+                Some(_) => {
+                    Some(value + response.drag_delta().x as f64 / rail.width() as f64 * 360.)
+                }
+                None => None,
+            };
+            if let Some(new_value) = new_value.filter(|new_value| *new_value != value) {
+                *long = longitudes.internal_long(new_value);
+                response.mark_changed();
+            }
+            // The knob's position is kept unwrapped, so that it glides the
+            // short way round, or jumps while being dragged.
+            let target = longitudes.map_long(*long);
+            let id = response.id.with("Knob");
+            let previous = ui
+                .data_mut(|data| data.get_temp::<f64>(id))
+                .unwrap_or(target);
+            let unwrapped = previous + ((target - previous + 180.).rem_euclid(360.) - 180.);
+            ui.data_mut(|data| data.insert_temp(id, unwrapped));
+            let glide = match response.dragged() {
+                true => 0.,
+                false => GLIDE_TIME,
+            };
+            let shown =
+                ui.ctx()
+                    .animate_value_with_time(id, unwrapped as f32, glide) as f64;
+            // The end of the range is drawn as such, not as its start.
+            let fraction = match (shown - start) / 360. {
+                fraction if fraction > 0. && fraction.fract() == 0. => 1.,
+                fraction => fraction.rem_euclid(1.),
+            };
+            if ui.is_rect_visible(rect) {
+                let widgets = &ui.visuals().widgets;
+                let rail_radius = (rect.height() / 4.).max(2.);
+                let rail_rect = egui::Rect::from_x_y_ranges(
+                    rect.x_range(),
+                    rect.center().y - rail_radius..=rect.center().y + rail_radius,
+                );
+                ui.painter().rect_filled(
+                    rail_rect,
+                    widgets.inactive.rounding,
+                    widgets.inactive.bg_fill,
+                );
+                let visuals = ui.style().interact(&response);
+                ui.painter().circle(
+                    egui::pos2(
+                        rail.left() + fraction as f32 * rail.width(),
+                        rect.center().y,
+                    ),
+                    radius + visuals.expansion,
+                    visuals.bg_fill,
+                    visuals.fg_stroke,
+                );
+            }
+            let value = ui.add(
+                egui::DragValue::from_get_set(|value| {
+                    if let Some(value) = value {
+                        *long = longitudes.internal_long(value);
+                    }
+                    longitudes.map_long(*long)
+                })
+                .speed(1.)
+                .angle(unit),
+            );
+            if !text.is_empty() {
+                ui.label(text);
+            }
+            response.union(value)
+        })
+        .inner
+    }
+}
+
+/// Buttons next to a longitude slider to nudge `long` or snap it to a round
+/// or well-known meridian, as the slider can hardly hit exact values. Round
+/// values and steps are in the map's terms, cities east of Greenwich.
+pub fn snap_ui(ui: &mut egui::Ui, longitudes: &Longitudes, long: &mut f64) {
+    ui.horizontal(|ui| {
+        for nudge in NUDGES {
+            let text = match nudge > 0. {
+                true => format!("+{}°", nudge),
+                false => format!("−{}°", -nudge),
+            };
+            if ui.small_button(text).clicked() {
+                *long = longitudes.internal_long(longitudes.map_long(*long) + nudge);
+            }
+        }
+        ui.menu_button("Snap", |ui| {
+            let start = *longitudes.range().start();
+            let mut round = [0., 90., 180., 270.].map(|value| wrap(value, start));
+            round.sort_by(f64::total_cmp);
+            ui.horizontal(|ui| {
+                for value in round {
+                    if ui.button(format!("{}°", value)).clicked() {
+                        *long = longitudes.internal_long(value);
+                        ui.close_menu();
+                    }
+                }
+            });
+            ui.separator();
+            for (city, meridian) in CITY_MERIDIANS {
+                if ui.button(city).clicked() {
+                    *long = meridian;
+                    ui.close_menu();
+                }
+            }
+        });
+    });
+}
+
+pub fn longitudes_ui(ui: &mut egui::Ui, longitudes: &mut Longitudes, unit: AngleUnit) {
+    ui.collapsing("Longitudes", |ui| {
+        ui.label("How the map counts longitudes, for entering and showing them.");
+        ui.add(
+            egui::DragValue::new(&mut longitudes.prime_meridian)
+                .clamp_range(-180.0..=180.)
+                .speed(0.1)
+                .angle(unit)
+                .prefix("Prime meridian "),
+        );
+        ui.label("east of Greenwich");
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut longitudes.domain, Domain::Signed, "−180° to 180°");
+            ui.selectable_value(&mut longitudes.domain, Domain::Positive, "0° to 360°");
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut longitudes.west_positive, false, "East-positive");
+            ui.selectable_value(&mut longitudes.west_positive, true, "West-positive");
+        });
+    });
+}
@@ -1,11 +1,150 @@
+use angles::AngleWidget;
 use eframe::egui;
 use image::GenericImageView;
 
+mod alignment;
+mod angles;
+mod annotations;
+mod appearance;
+mod attribution;
+mod batch;
+mod blend;
+mod body;
+mod bundle;
+mod cache;
+mod codecs;
+mod collisions;
+mod color_keys;
+mod color_types;
+mod crs;
+mod diagnostics;
+mod distortion;
+mod dock;
+mod dual_fisheye;
+mod engine;
+mod expressions;
+mod feature_matching;
+mod fits;
+mod georeference;
+mod geotiff;
+mod graticule;
+mod graticule_detection;
+mod gridded;
+mod healpix;
+mod history;
+mod instance;
+mod jobs;
+mod labels;
+mod layered;
+mod layers;
+mod leveling;
+mod loading;
+mod longitudes;
+mod mask;
+mod network;
+mod notifications;
+mod overviews;
+mod palettes;
+mod performance;
+mod polar_fill;
+mod preprocess;
+mod private_files;
+mod projections;
+mod remote;
+mod safe_mode;
+mod sampling;
+mod self_test;
+mod settings;
+mod sidecar;
+mod templates;
+mod tools;
+mod tps;
+mod update;
+mod vectors;
+mod viewer;
+mod virtual_camera;
+mod window_layout;
+mod workspaces;
+
+/// Longer side of the source overview that draft projections are made from.
+const DRAFT_SOURCE_SIZE: u32 = 1024;
+
 fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let flag = |name: &str| arguments.iter().any(|argument| argument == name);
+    settings::init_portable(flag("--portable"));
+    // `--batch manifest.json` converts without opening a window, and with
+    // `--dry-run` only checks what it would do. `--json` reports in JSON lines.
+    if let Some(index) = arguments.iter().position(|argument| argument == "--batch") {
+        let settings = settings::Settings::load();
+        let cache = cache::Cache::new(settings::cache_directory(), settings.cache_limit_bytes());
+        network::configure(&settings, &network::Credentials::load(), &cache);
+        loading::set_memory_limit(settings.decode_memory_limit_bytes());
+        let code = match arguments.get(index + 1) {
+            Some(manifest) => batch::run_headless(
+                std::path::Path::new(manifest),
+                flag("--dry-run"),
+                flag("--json"),
+            ),
+            None => batch::fail_headless(
+                batch::Failure {
+                    kind: batch::FailureKind::Usage,
+                    message: "--batch needs a manifest".to_string(),
+                },
+                flag("--json"),
+            ),
+        };
+        std::process::exit(code);
+    }
+    // Compares reference conversions with the images in test_images/golden,
+    // or with `--bless` replaces those images. `--golden <directory>` uses
+    // the images there instead.
+    if flag("--self-test") {
+        let golden = arguments
+            .iter()
+            .position(|argument| argument == "--golden")
+            .and_then(|index| arguments.get(index + 1))
+            .map(std::path::PathBuf::from);
+        std::process::exit(self_test::run(flag("--bless"), golden));
+    }
+    let file = arguments
+        .iter()
+        .find(|argument| !argument.starts_with("--"))
+        .map(std::path::PathBuf::from);
+    // A second launch, e.g. double-clicking another map, goes to the open window.
+    if !flag("--new-instance") && instance::forward(file.as_deref()) {
+        return;
+    }
+    // `--safe-mode` starts afresh, ignoring everything that was saved, in case
+    // that is what makes the app crash. It is offered after a crash, unless
+    // another instance may still be running.
+    let safe_mode = flag("--safe-mode")
+        || (!flag("--new-instance") && safe_mode::crashed() && safe_mode::ask());
+    safe_mode::started();
+    let window_layouts = match safe_mode {
+        true => Default::default(),
+        false => window_layout::WindowLayouts::load(),
+    };
+    let mut options = window_layouts.native_options();
+    if safe_mode {
+        safe_mode::software_rendering(&mut options);
+    }
     eframe::run_native(
         "Submaptive",
-        Default::default(),
-        Box::new(|_| Box::new(App::new())),
+        options,
+        Box::new(move |creation| {
+            let mut app = App::new(window_layouts, creation.gl.as_deref(), safe_mode);
+            match instance::Listener::start(&creation.egui_ctx) {
+                Ok(listener) => app.instance = Some(listener),
+                Err(e) => app
+                    .log
+                    .error(format!("Other instances can't hand over files: {}", e)),
+            }
+            if let Some(file) = file {
+                app.load_source_image(file, &creation.egui_ctx);
+            }
+            Box::new(app)
+        }),
     )
     .unwrap();
 }
@@ -16,10 +155,57 @@ struct ImageData {
     handle: egui::TextureHandle,
 }
 
+impl ImageData {
+    fn new(ctx: &egui::Context, name: &str, image: image::DynamicImage) -> Self {
+        let display = loading::display_image(&image, ctx.input(|input| input.max_texture_side));
+        Self::with_display(ctx, name, image, display)
+    }
+
+    /// Uses an already prepared texture image, e.g. one made on a worker thread.
+    fn with_display(
+        ctx: &egui::Context,
+        name: &str,
+        image: image::DynamicImage,
+        display: egui::ColorImage,
+    ) -> Self {
+        let handle = ctx.load_texture(name, display, Default::default());
+        ImageData { image, handle }
+    }
+}
+
 #[derive(Clone)]
 enum ProjectionData {
     Equirectangular(submaptive::Equirectangular),
     AzimuthalEquidistant(submaptive::AzimuthalEquidistant),
+    Mercator(projections::Mercator),
+    TransverseMercator(projections::TransverseMercator),
+    WebMercator(projections::WebMercator),
+    Mollweide(projections::Mollweide),
+    Robinson(projections::Robinson),
+    WinkelTripel(projections::WinkelTripel),
+    Sinusoidal(projections::Sinusoidal),
+    CylindricalEqualArea(projections::CylindricalEqualArea),
+    LambertConformalConic(projections::LambertConformalConic),
+    AlbersEqualArea(projections::AlbersEqualArea),
+    EquidistantConic(projections::EquidistantConic),
+    Orthographic(projections::Orthographic),
+    Stereographic(projections::Stereographic),
+    Gnomonic(projections::Gnomonic),
+    LambertAzimuthalEqualArea(projections::LambertAzimuthalEqualArea),
+    Hammer(projections::Hammer),
+    Aitoff(projections::Aitoff),
+    EckertIV(projections::EckertIV),
+    EckertVI(projections::EckertVI),
+    GoodeHomolosine(projections::GoodeHomolosine),
+    Bonne(projections::Bonne),
+    Werner(projections::Werner),
+    VanDerGrinten(projections::VanDerGrinten),
+    Miller(projections::Miller),
+    Cassini(projections::Cassini),
+    Polyconic(projections::Polyconic),
+    NaturalEarth(projections::NaturalEarth),
+    KavrayskiyVII(projections::KavrayskiyVII),
+    RubberSheet(georeference::RubberSheet),
 }
 
 impl ProjectionData {
@@ -28,37 +214,271 @@ impl ProjectionData {
         match self {
             Equirectangular(_) => ProjectionKind::Equirectangular,
             AzimuthalEquidistant(_) => ProjectionKind::AzimuthalEquidistant,
+            Mercator(_) => ProjectionKind::Mercator,
+            TransverseMercator(_) => ProjectionKind::TransverseMercator,
+            WebMercator(_) => ProjectionKind::WebMercator,
+            Mollweide(_) => ProjectionKind::Mollweide,
+            Robinson(_) => ProjectionKind::Robinson,
+            WinkelTripel(_) => ProjectionKind::WinkelTripel,
+            Sinusoidal(_) => ProjectionKind::Sinusoidal,
+            CylindricalEqualArea(_) => ProjectionKind::CylindricalEqualArea,
+            LambertConformalConic(_) => ProjectionKind::LambertConformalConic,
+            AlbersEqualArea(_) => ProjectionKind::AlbersEqualArea,
+            EquidistantConic(_) => ProjectionKind::EquidistantConic,
+            Orthographic(_) => ProjectionKind::Orthographic,
+            Stereographic(_) => ProjectionKind::Stereographic,
+            Gnomonic(_) => ProjectionKind::Gnomonic,
+            LambertAzimuthalEqualArea(_) => ProjectionKind::LambertAzimuthalEqualArea,
+            Hammer(_) => ProjectionKind::Hammer,
+            Aitoff(_) => ProjectionKind::Aitoff,
+            EckertIV(_) => ProjectionKind::EckertIV,
+            EckertVI(_) => ProjectionKind::EckertVI,
+            GoodeHomolosine(_) => ProjectionKind::GoodeHomolosine,
+            Bonne(_) => ProjectionKind::Bonne,
+            Werner(_) => ProjectionKind::Werner,
+            VanDerGrinten(_) => ProjectionKind::VanDerGrinten,
+            Miller(_) => ProjectionKind::Miller,
+            Cassini(_) => ProjectionKind::Cassini,
+            Polyconic(_) => ProjectionKind::Polyconic,
+            NaturalEarth(_) => ProjectionKind::NaturalEarth,
+            KavrayskiyVII(_) => ProjectionKind::KavrayskiyVII,
+            RubberSheet(_) => ProjectionKind::RubberSheet,
         }
     }
-}
 
-impl submaptive::Projection for ProjectionData {
-    fn dimensions(&self) -> submaptive::Dimensions {
+    /// The projection itself, whose methods `submaptive::Projection` passes on to.
+    fn projection(&self) -> &dyn submaptive::Projection {
+        use ProjectionData::*;
         match self {
-            ProjectionData::Equirectangular(data) => data.dimensions(),
-            ProjectionData::AzimuthalEquidistant(data) => data.dimensions(),
+            Equirectangular(data) => data,
+            AzimuthalEquidistant(data) => data,
+            Mercator(data) => data,
+            TransverseMercator(data) => data,
+            WebMercator(data) => data,
+            Mollweide(data) => data,
+            Robinson(data) => data,
+            WinkelTripel(data) => data,
+            Sinusoidal(data) => data,
+            CylindricalEqualArea(data) => data,
+            LambertConformalConic(data) => data,
+            AlbersEqualArea(data) => data,
+            EquidistantConic(data) => data,
+            Orthographic(data) => data,
+            Stereographic(data) => data,
+            Gnomonic(data) => data,
+            LambertAzimuthalEqualArea(data) => data,
+            Hammer(data) => data,
+            Aitoff(data) => data,
+            EckertIV(data) => data,
+            EckertVI(data) => data,
+            GoodeHomolosine(data) => data,
+            Bonne(data) => data,
+            Werner(data) => data,
+            VanDerGrinten(data) => data,
+            Miller(data) => data,
+            Cassini(data) => data,
+            Polyconic(data) => data,
+            NaturalEarth(data) => data,
+            KavrayskiyVII(data) => data,
+            RubberSheet(data) => data,
         }
     }
+}
+
+impl ProjectionData {
+    /// The location shown at a pixel of an image in this projection, if any.
+    pub fn point_at_pixel(
+        &self,
+        pixel: (f64, f64),
+        image_dimensions: (u32, u32),
+    ) -> Option<submaptive::Point> {
+        use submaptive::Projection;
+        let dimensions = self.dimensions();
+        let projected = (
+            (pixel.0 / image_dimensions.0 as f64 - 0.5) * dimensions.width(),
+            (0.5 - pixel.1 / image_dimensions.1 as f64) * dimensions.height(),
+        );
+        self.projected_point_within_bounds(projected)
+            .then(|| self.invert(projected))
+    }
 
-    fn project(&self, point: &submaptive::Point) -> (f64, f64) {
+    /// Where an image in this projection shows `point`, if it does.
+    pub fn pixel_at_point(
+        &self,
+        point: &submaptive::Point,
+        image_dimensions: (u32, u32),
+    ) -> Option<(f64, f64)> {
+        use submaptive::Projection;
+        let dimensions = self.dimensions();
+        let projected = self.project(point);
+        self.projected_point_within_bounds(projected).then(|| {
+            (
+                (projected.0 / dimensions.width() + 0.5) * image_dimensions.0 as f64,
+                (0.5 - projected.1 / dimensions.height()) * image_dimensions.1 as f64,
+            )
+        })
+    }
+
+    /// The same projection centered on `point`, as far as it has a center.
+    pub fn with_center(&self, point: &submaptive::Point) -> Self {
         match self {
-            ProjectionData::Equirectangular(data) => data.project(point),
-            ProjectionData::AzimuthalEquidistant(data) => data.project(point),
+            ProjectionData::Equirectangular(data) => ProjectionData::Equirectangular(
+                submaptive::Equirectangular::new()
+                    .central_long(point.long())
+                    .true_scale_lat(data.true_scale_lat())
+                    .build(),
+            ),
+            ProjectionData::AzimuthalEquidistant(data) => ProjectionData::AzimuthalEquidistant(
+                submaptive::AzimuthalEquidistant::new()
+                    .center(*point)
+                    .central_long(data.central_long())
+                    .build(),
+            ),
+            ProjectionData::Mercator(data) => ProjectionData::Mercator(projections::Mercator {
+                central_long: point.long(),
+                ..*data
+            }),
+            ProjectionData::TransverseMercator(data) => {
+                ProjectionData::TransverseMercator(projections::TransverseMercator {
+                    central_long: point.long(),
+                    origin_lat: point.lat(),
+                    ..*data
+                })
+            }
+            // Tiles are always centered on Greenwich.
+            ProjectionData::WebMercator(_) => self.clone(),
+            ProjectionData::Mollweide(_) => ProjectionData::Mollweide(projections::Mollweide {
+                central_long: point.long(),
+            }),
+            ProjectionData::Robinson(_) => ProjectionData::Robinson(projections::Robinson {
+                central_long: point.long(),
+            }),
+            ProjectionData::WinkelTripel(data) => {
+                ProjectionData::WinkelTripel(projections::WinkelTripel {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::Sinusoidal(_) => ProjectionData::Sinusoidal(projections::Sinusoidal {
+                central_long: point.long(),
+            }),
+            ProjectionData::CylindricalEqualArea(data) => {
+                ProjectionData::CylindricalEqualArea(projections::CylindricalEqualArea {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::LambertConformalConic(data) => {
+                ProjectionData::LambertConformalConic(projections::LambertConformalConic {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::AlbersEqualArea(data) => {
+                ProjectionData::AlbersEqualArea(projections::AlbersEqualArea {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::EquidistantConic(data) => {
+                ProjectionData::EquidistantConic(projections::EquidistantConic {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::Orthographic(_) => {
+                ProjectionData::Orthographic(projections::Orthographic {
+                    central_long: point.long(),
+                    center_lat: point.lat(),
+                })
+            }
+            ProjectionData::Stereographic(_) => {
+                ProjectionData::Stereographic(projections::Stereographic {
+                    central_long: point.long(),
+                    center_lat: point.lat(),
+                })
+            }
+            ProjectionData::Gnomonic(data) => ProjectionData::Gnomonic(projections::Gnomonic {
+                central_long: point.long(),
+                center_lat: point.lat(),
+                ..*data
+            }),
+            ProjectionData::LambertAzimuthalEqualArea(_) => {
+                ProjectionData::LambertAzimuthalEqualArea(projections::LambertAzimuthalEqualArea {
+                    central_long: point.long(),
+                    center_lat: point.lat(),
+                })
+            }
+            ProjectionData::Hammer(_) => ProjectionData::Hammer(projections::Hammer {
+                central_long: point.long(),
+            }),
+            ProjectionData::Aitoff(_) => ProjectionData::Aitoff(projections::Aitoff {
+                central_long: point.long(),
+            }),
+            ProjectionData::EckertIV(_) => ProjectionData::EckertIV(projections::EckertIV {
+                central_long: point.long(),
+            }),
+            ProjectionData::EckertVI(_) => ProjectionData::EckertVI(projections::EckertVI {
+                central_long: point.long(),
+            }),
+            ProjectionData::GoodeHomolosine(data) => {
+                ProjectionData::GoodeHomolosine(projections::GoodeHomolosine {
+                    central_long: point.long(),
+                    ..*data
+                })
+            }
+            ProjectionData::Bonne(data) => ProjectionData::Bonne(projections::Bonne {
+                central_long: point.long(),
+                ..*data
+            }),
+            ProjectionData::Werner(_) => ProjectionData::Werner(projections::Werner {
+                central_long: point.long(),
+            }),
+            ProjectionData::VanDerGrinten(_) => {
+                ProjectionData::VanDerGrinten(projections::VanDerGrinten {
+                    central_long: point.long(),
+                })
+            }
+            ProjectionData::Miller(_) => ProjectionData::Miller(projections::Miller {
+                central_long: point.long(),
+            }),
+            ProjectionData::Cassini(_) => ProjectionData::Cassini(projections::Cassini {
+                central_long: point.long(),
+            }),
+            ProjectionData::Polyconic(_) => ProjectionData::Polyconic(projections::Polyconic {
+                central_long: point.long(),
+                origin_lat: point.lat(),
+            }),
+            ProjectionData::NaturalEarth(_) => {
+                ProjectionData::NaturalEarth(projections::NaturalEarth {
+                    central_long: point.long(),
+                })
+            }
+            ProjectionData::KavrayskiyVII(_) => {
+                ProjectionData::KavrayskiyVII(projections::KavrayskiyVII {
+                    central_long: point.long(),
+                })
+            }
+            ProjectionData::RubberSheet(_) => self.clone(),
         }
     }
+}
+
+impl submaptive::Projection for ProjectionData {
+    fn dimensions(&self) -> submaptive::Dimensions {
+        self.projection().dimensions()
+    }
+
+    fn project(&self, point: &submaptive::Point) -> (f64, f64) {
+        self.projection().project(point)
+    }
 
     fn invert(&self, projected_point: (f64, f64)) -> submaptive::Point {
-        match self {
-            ProjectionData::Equirectangular(data) => data.invert(projected_point),
-            ProjectionData::AzimuthalEquidistant(data) => data.invert(projected_point),
-        }
+        self.projection().invert(projected_point)
     }
 
     fn projected_point_within_bounds(&self, point: (f64, f64)) -> bool {
-        match self {
-            ProjectionData::Equirectangular(data) => data.projected_point_within_bounds(point),
-            ProjectionData::AzimuthalEquidistant(data) => data.projected_point_within_bounds(point),
-        }
+        self.projection().projected_point_within_bounds(point)
     }
 }
 
@@ -66,12 +486,112 @@ impl submaptive::Projection for ProjectionData {
 enum ProjectionKind {
     Equirectangular,
     AzimuthalEquidistant,
+    Mercator,
+    TransverseMercator,
+    WebMercator,
+    Mollweide,
+    Robinson,
+    WinkelTripel,
+    Sinusoidal,
+    CylindricalEqualArea,
+    LambertConformalConic,
+    AlbersEqualArea,
+    EquidistantConic,
+    Orthographic,
+    Stereographic,
+    Gnomonic,
+    LambertAzimuthalEqualArea,
+    Hammer,
+    Aitoff,
+    EckertIV,
+    EckertVI,
+    GoodeHomolosine,
+    Bonne,
+    Werner,
+    VanDerGrinten,
+    Miller,
+    Cassini,
+    Polyconic,
+    NaturalEarth,
+    KavrayskiyVII,
+    RubberSheet,
 }
 
 impl ProjectionKind {
     pub fn all() -> impl Iterator<Item = Self> {
         use ProjectionKind::*;
-        vec![Equirectangular, AzimuthalEquidistant].into_iter()
+        vec![
+            Equirectangular,
+            AzimuthalEquidistant,
+            Mercator,
+            TransverseMercator,
+            WebMercator,
+            Mollweide,
+            Robinson,
+            WinkelTripel,
+            Sinusoidal,
+            CylindricalEqualArea,
+            LambertConformalConic,
+            AlbersEqualArea,
+            EquidistantConic,
+            Orthographic,
+            Stereographic,
+            Gnomonic,
+            LambertAzimuthalEqualArea,
+            Hammer,
+            Aitoff,
+            EckertIV,
+            EckertVI,
+            GoodeHomolosine,
+            Bonne,
+            Werner,
+            VanDerGrinten,
+            Miller,
+            Cassini,
+            Polyconic,
+            NaturalEarth,
+            KavrayskiyVII,
+            RubberSheet,
+        ]
+        .into_iter()
+    }
+
+    /// The name used for the projection in commands.
+    pub fn id(&self) -> &'static str {
+        use ProjectionKind::*;
+        match self {
+            Equirectangular => "equirectangular",
+            AzimuthalEquidistant => "azimuthal-equidistant",
+            Mercator => "mercator",
+            TransverseMercator => "transverse-mercator",
+            WebMercator => "web-mercator",
+            Mollweide => "mollweide",
+            Robinson => "robinson",
+            WinkelTripel => "winkel-tripel",
+            Sinusoidal => "sinusoidal",
+            CylindricalEqualArea => "cylindrical-equal-area",
+            LambertConformalConic => "lambert-conformal-conic",
+            AlbersEqualArea => "albers-equal-area",
+            EquidistantConic => "equidistant-conic",
+            Orthographic => "orthographic",
+            Stereographic => "stereographic",
+            Gnomonic => "gnomonic",
+            LambertAzimuthalEqualArea => "lambert-azimuthal-equal-area",
+            Hammer => "hammer",
+            Aitoff => "aitoff",
+            EckertIV => "eckert-iv",
+            EckertVI => "eckert-vi",
+            GoodeHomolosine => "goode-homolosine",
+            Bonne => "bonne",
+            Werner => "werner",
+            VanDerGrinten => "van-der-grinten",
+            Miller => "miller",
+            Cassini => "cassini",
+            Polyconic => "polyconic",
+            NaturalEarth => "natural-earth",
+            KavrayskiyVII => "kavrayskiy-vii",
+            RubberSheet => "rubber-sheet",
+        }
     }
 
     pub fn default_projection_data(&self) -> ProjectionData {
@@ -83,6 +603,37 @@ impl ProjectionKind {
             AzimuthalEquidistant => ProjectionData::AzimuthalEquidistant(
                 submaptive::AzimuthalEquidistant::new().build(),
             ),
+            Mercator => ProjectionData::Mercator(Default::default()),
+            TransverseMercator => ProjectionData::TransverseMercator(Default::default()),
+            WebMercator => ProjectionData::WebMercator(Default::default()),
+            Mollweide => ProjectionData::Mollweide(Default::default()),
+            Robinson => ProjectionData::Robinson(Default::default()),
+            WinkelTripel => ProjectionData::WinkelTripel(Default::default()),
+            Sinusoidal => ProjectionData::Sinusoidal(Default::default()),
+            CylindricalEqualArea => ProjectionData::CylindricalEqualArea(Default::default()),
+            LambertConformalConic => ProjectionData::LambertConformalConic(Default::default()),
+            AlbersEqualArea => ProjectionData::AlbersEqualArea(Default::default()),
+            EquidistantConic => ProjectionData::EquidistantConic(Default::default()),
+            Orthographic => ProjectionData::Orthographic(Default::default()),
+            Stereographic => ProjectionData::Stereographic(Default::default()),
+            Gnomonic => ProjectionData::Gnomonic(Default::default()),
+            LambertAzimuthalEqualArea => {
+                ProjectionData::LambertAzimuthalEqualArea(Default::default())
+            }
+            Hammer => ProjectionData::Hammer(Default::default()),
+            Aitoff => ProjectionData::Aitoff(Default::default()),
+            EckertIV => ProjectionData::EckertIV(Default::default()),
+            EckertVI => ProjectionData::EckertVI(Default::default()),
+            GoodeHomolosine => ProjectionData::GoodeHomolosine(Default::default()),
+            Bonne => ProjectionData::Bonne(Default::default()),
+            Werner => ProjectionData::Werner(Default::default()),
+            VanDerGrinten => ProjectionData::VanDerGrinten(Default::default()),
+            Miller => ProjectionData::Miller(Default::default()),
+            Cassini => ProjectionData::Cassini(Default::default()),
+            Polyconic => ProjectionData::Polyconic(Default::default()),
+            NaturalEarth => ProjectionData::NaturalEarth(Default::default()),
+            KavrayskiyVII => ProjectionData::KavrayskiyVII(Default::default()),
+            RubberSheet => ProjectionData::RubberSheet(Default::default()),
         }
     }
 }
@@ -93,23 +644,165 @@ impl std::fmt::Display for ProjectionKind {
         fmt.write_str(match self {
             Equirectangular => "Equirectangular",
             AzimuthalEquidistant => "Azimuthal equidistant",
+            Mercator => "Mercator",
+            TransverseMercator => "Transverse Mercator",
+            WebMercator => "Web Mercator (EPSG:3857)",
+            Mollweide => "Mollweide",
+            Robinson => "Robinson",
+            WinkelTripel => "Winkel Tripel",
+            Sinusoidal => "Sinusoidal (Sanson–Flamsteed)",
+            CylindricalEqualArea => "Cylindrical equal-area",
+            LambertConformalConic => "Lambert conformal conic",
+            AlbersEqualArea => "Albers equal-area conic",
+            EquidistantConic => "Equidistant conic",
+            Orthographic => "Orthographic",
+            Stereographic => "Stereographic",
+            Gnomonic => "Gnomonic",
+            LambertAzimuthalEqualArea => "Lambert azimuthal equal-area",
+            Hammer => "Hammer",
+            Aitoff => "Aitoff",
+            EckertIV => "Eckert IV",
+            EckertVI => "Eckert VI",
+            GoodeHomolosine => "Goode homolosine (interrupted)",
+            Bonne => "Bonne",
+            Werner => "Werner (cordiform)",
+            VanDerGrinten => "Van der Grinten",
+            Miller => "Miller cylindrical",
+            Cassini => "Cassini",
+            Polyconic => "American polyconic",
+            NaturalEarth => "Natural Earth",
+            KavrayskiyVII => "Kavrayskiy VII",
+            RubberSheet => "Rubber sheet (control points)",
         })
     }
 }
 
 struct App {
-    error: Option<String>,
+    log: notifications::Log,
+    source_path: Option<std::path::PathBuf>,
     source_image: Option<ImageData>,
+    source_overviews: overviews::Overviews,
+    loading: Option<loading::Loading>,
+    remote_open: remote::RemoteOpen,
+    /// An export to TIFF waiting for its georeferencing to be confirmed.
+    geotiff_export: Option<geotiff::Confirmation>,
     source_projection: ProjectionData,
     target_projection: ProjectionData,
     projected_image: Option<ImageData>,
+    /// Project from a reduced-resolution overview for quick previews.
+    draft_projection: bool,
+    /// Whether `projected_image` was made from an overview and must be redone for export.
+    projected_is_draft: bool,
+    history: history::History,
+    georeference: georeference::Georeference,
+    feature_matching: feature_matching::FeatureMatching,
+    alignment: alignment::AlignmentCheck,
+    preprocessing: preprocess::Preprocessing,
+    /// A downscaled, corrected copy of the source shown while photo corrections are active.
+    corrected_preview: Option<ImageData>,
+    /// What was last written to the source image's sidecar file.
+    saved_sidecar: sidecar::Sidecar,
+    settings: settings::Settings,
+    /// What was last written to the settings file.
+    saved_settings: settings::Settings,
+    /// Kept apart from the settings, which may be shared.
+    credentials: network::Credentials,
+    saved_credentials: network::Credentials,
+    settings_open: bool,
+    templates: templates::TemplatesWindow,
+    /// Applied to maps opened without a sidecar of their own, by name.
+    template: Option<(String, templates::Template)>,
+    update_check: update::UpdateCheck,
+    /// The appearance the style was last set up for.
+    applied_appearance: appearance::Appearance,
+    cache: cache::Cache,
+    /// Content hash of the source file, for keying cached results.
+    source_hash: Option<String>,
+    viewer: viewer::ViewerState,
+    sessions: viewer::Sessions,
+    workspaces: workspaces::Workspaces,
+    /// The `Ui` each panel was last drawn in.
+    panel_ids: std::collections::HashMap<dock::PanelKind, egui::Id>,
+    window_layouts: window_layout::WindowLayouts,
+    performance: performance::Performance,
+    /// The source image pixel under the pointer.
+    hovered_pixel: Option<(f64, f64)>,
+    tools: tools::Tools,
+    annotations: Vec<annotations::Annotation>,
+    mask: mask::Mask,
+    color_keys: color_keys::ColorKeys,
+    polar_fill: polar_fill::PolarFill,
+    sampling: sampling::Sampling,
+    orientation: leveling::Orientation,
+    virtual_camera: virtual_camera::VirtualCamera,
+    dual_fisheye: Option<dual_fisheye::DualFisheye>,
+    /// The loaded image and the settings it was stitched with, while the
+    /// source is the panorama stitched from it.
+    fisheye_frame: Option<(image::DynamicImage, dual_fisheye::DualFisheye)>,
+    /// The values the source image was drawn from, if it is gridded data.
+    gridded: Option<gridded::Field>,
+    data_coloring: gridded::Coloring,
+    body: body::Body,
+    longitudes: longitudes::Longitudes,
+    graticule: graticule::Graticule,
+    attribution: attribution::Attribution,
+    distortion: distortion::Distortion,
+    vector_overlays: Vec<vectors::VectorOverlay>,
+    layers: Vec<layers::Layer>,
+    /// A layered file whose layers are being picked to add.
+    layer_import: Option<layers::LayerImport>,
+    batch: batch::Batch,
+    /// The graphics driver, for diagnostic reports.
+    gpu: Option<String>,
+    /// Blends layers on the GPU, where the OpenGL backend allows it.
+    compositor: Option<std::sync::Arc<std::sync::Mutex<blend::Compositor>>>,
+    /// Receives files from instances started later.
+    instance: Option<instance::Listener>,
+    /// An `open` command waiting for the map to load.
+    open_request: Option<instance::Request>,
+    /// Brings the window to the front over the next two frames.
+    raise_window: bool,
+    window_raised: bool,
+    /// Started with the default settings, and without the shader compositor or
+    /// the last session, so that none of it is saved over either.
+    safe_mode: bool,
 }
 
 impl App {
-    pub fn new() -> Self {
+    pub fn new(
+        window_layouts: window_layout::WindowLayouts,
+        gl: Option<&eframe::glow::Context>,
+        safe_mode: bool,
+    ) -> Self {
+        let settings = match safe_mode {
+            true => Default::default(),
+            false => settings::Settings::load(),
+        };
+        let credentials = network::Credentials::load();
+        let cache = cache::Cache::new(settings::cache_directory(), settings.cache_limit_bytes());
+        network::configure(&settings, &credentials, &cache);
+        loading::set_memory_limit(settings.decode_memory_limit_bytes());
+        let mut log = notifications::Log::default();
+        if safe_mode {
+            log.warning("Started in safe mode, with the default settings");
+        }
+        let compositor =
+            gl.filter(|_| !safe_mode)
+                .and_then(|gl| match blend::Compositor::new(gl) {
+                    Ok(compositor) => Some(std::sync::Arc::new(std::sync::Mutex::new(compositor))),
+                    Err(e) => {
+                        log.error(format!("Layers are blended without shaders: {}", e));
+                        None
+                    }
+                });
         App {
-            error: None,
+            log,
+            source_path: None,
             source_image: None,
+            source_overviews: Default::default(),
+            loading: None,
+            remote_open: Default::default(),
+            geotiff_export: None,
             source_projection: ProjectionData::Equirectangular(
                 submaptive::Equirectangular::new().build(),
             ),
@@ -117,90 +810,1683 @@ impl App {
                 submaptive::Equirectangular::new().build(),
             ),
             projected_image: None,
+            draft_projection: false,
+            projected_is_draft: false,
+            history: Default::default(),
+            georeference: Default::default(),
+            feature_matching: Default::default(),
+            alignment: Default::default(),
+            preprocessing: Default::default(),
+            corrected_preview: None,
+            saved_sidecar: Default::default(),
+            cache,
+            saved_settings: settings.clone(),
+            settings,
+            saved_credentials: credentials.clone(),
+            credentials,
+            settings_open: false,
+            templates: Default::default(),
+            template: None,
+            update_check: Default::default(),
+            applied_appearance: Default::default(),
+            source_hash: None,
+            viewer: Default::default(),
+            sessions: match safe_mode {
+                true => Default::default(),
+                false => viewer::Sessions::load(),
+            },
+            workspaces: workspaces::Workspaces::load(),
+            panel_ids: Default::default(),
+            window_layouts,
+            performance: Default::default(),
+            hovered_pixel: None,
+            tools: Default::default(),
+            annotations: Vec::new(),
+            mask: Default::default(),
+            color_keys: Default::default(),
+            polar_fill: Default::default(),
+            sampling: Default::default(),
+            orientation: Default::default(),
+            virtual_camera: Default::default(),
+            dual_fisheye: None,
+            fisheye_frame: None,
+            gridded: None,
+            data_coloring: Default::default(),
+            body: Default::default(),
+            longitudes: Default::default(),
+            graticule: Default::default(),
+            attribution: Default::default(),
+            distortion: Default::default(),
+            vector_overlays: Vec::new(),
+            layers: Vec::new(),
+            layer_import: None,
+            batch: Default::default(),
+            gpu: gl.map(diagnostics::gpu_info),
+            compositor,
+            instance: None,
+            open_request: None,
+            raise_window: false,
+            window_raised: false,
+            safe_mode,
+        }
+    }
+
+    fn sidecar(&self) -> sidecar::Sidecar {
+        sidecar::Sidecar {
+            control_points: self.georeference.control_points.clone(),
+            preprocessing: self.preprocessing,
+            annotations: self.annotations.clone(),
+            mask: self.mask.clone(),
+            color_keys: self.color_keys,
+            polar_fill: self.polar_fill,
+            orientation: self.orientation,
+            dual_fisheye: self.dual_fisheye,
+            data_coloring: self.data_coloring,
+            body: self.body,
+            longitudes: self.longitudes,
+            graticule: self.graticule,
+            vector_overlays: self
+                .vector_overlays
+                .iter()
+                .map(|overlay| overlay.settings.clone())
+                .collect(),
+            attribution: self.attribution.clone(),
+            sampling: self.sampling,
+        }
+    }
+
+    /// Writes the sidecar once it has changed, but not in the middle of a drag.
+    fn save_sidecar(&mut self, ctx: &egui::Context) {
+        let Some(path) = &self.source_path else {
+            return;
+        };
+        // There is nowhere to keep one next to a remote file.
+        if remote::url(path).is_some() {
+            return;
+        }
+        let sidecar = self.sidecar();
+        if sidecar == self.saved_sidecar || ctx.input(|input| input.pointer.any_down()) {
+            return;
+        }
+        if let Err(e) = sidecar.save(path) {
+            self.log.error(e);
+        }
+        self.saved_sidecar = sidecar;
+    }
+
+    /// Writes the settings once they have changed, but not in the middle of a
+    /// drag, and the credentials once the settings window is closed, rather
+    /// than writing to the credential store for every key typed.
+    fn save_settings(&mut self, ctx: &egui::Context) {
+        let credentials_changed = self.credentials != self.saved_credentials && !self.settings_open;
+        let unchanged = self.settings == self.saved_settings && !credentials_changed;
+        if unchanged || ctx.input(|input| input.pointer.any_down()) {
+            return;
+        }
+        self.cache.set_limit(self.settings.cache_limit_bytes());
+        network::configure(&self.settings, &self.credentials, &self.cache);
+        loading::set_memory_limit(self.settings.decode_memory_limit_bytes());
+        // Safe mode starts from the defaults, which mustn't replace the
+        // settings it is there to get back to.
+        if self.settings != self.saved_settings {
+            if !self.safe_mode {
+                if let Err(e) = self.settings.save() {
+                    self.log.error(e);
+                }
+            }
+            self.saved_settings = self.settings.clone();
+        }
+        if credentials_changed {
+            if let Err(e) = self.credentials.save() {
+                self.log.error(e);
+            }
+            self.saved_credentials = self.credentials.clone();
+        }
+    }
+
+    /// Identifies the source image across sessions, even if it was moved.
+    fn session_key(&self) -> Option<String> {
+        self.source_hash.clone().or_else(|| {
+            self.source_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+        })
+    }
+
+    /// Remembers the viewer state of the current source image and writes all of them.
+    fn save_session(&mut self) {
+        if self.safe_mode {
+            return;
+        }
+        if let Some(key) = self.session_key() {
+            self.sessions.set(key, self.viewer);
+            if let Err(e) = self.sessions.save() {
+                self.log.error(e);
+            }
+        }
+    }
+
+    fn status_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let errors = self.log.error_count();
+            let log_label = match errors {
+                0 => "Log".to_string(),
+                _ => format!("Log ({} errors)", errors),
+            };
+            let dock = &mut self.settings.dock;
+            if ui
+                .selectable_label(dock.is_open(dock::PanelKind::Log), log_label)
+                .clicked()
+            {
+                dock.toggle(dock::PanelKind::Log);
+            }
+            ui.separator();
+            ui.menu_button("Panels", |ui| {
+                for kind in dock::PanelKind::ALL {
+                    if ui
+                        .selectable_label(dock.is_open(kind), kind.title())
+                        .clicked()
+                    {
+                        dock.toggle(kind);
+                    }
+                }
+            });
+            match workspaces::workspace_menu(ui, &mut self.workspaces) {
+                Ok(Some(action)) => self.switch_workspace(ui.ctx(), action),
+                Ok(None) => {}
+                Err(e) => self.log.error(e),
+            }
+            ui.menu_button("Help", |ui| {
+                if ui.button("Generate diagnostic report...").clicked() {
+                    ui.close_menu();
+                    self.diagnostic_report(ui.ctx());
+                }
+            });
+            ui.separator();
+            if self.safe_mode {
+                ui.colored_label(ui.visuals().warn_fg_color, "Safe mode");
+                ui.separator();
+            }
+            ui.toggle_value(&mut self.settings.offline, "Offline")
+                .on_hover_text(network::OFFLINE_HINT);
+            ui.toggle_value(&mut self.performance.open, "Performance")
+                .on_hover_text("Frame time, texture memory and background work");
+            if let Some(release) = &self.update_check.available {
+                ui.separator();
+                if ui
+                    .button(format!("Version {} available", release.version))
+                    .clicked()
+                {
+                    self.update_check.window_open = true;
+                }
+            }
+            let jobs = self.loading.is_some() as usize
+                + self.batch.is_running() as usize
+                + self.feature_matching.is_running() as usize;
+            if jobs > 0 {
+                ui.separator();
+                ui.spinner();
+                ui.label(format!("{} background job(s)", jobs));
+            }
+            let Some(source_image) = &self.source_image else {
+                return;
+            };
+            ui.separator();
+            ui.label(format!("Tool: {}", self.tools.active.name()));
+            let image_dimensions = source_image.image.dimensions();
+            ui.separator();
+            ui.label(format!(
+                "{} × {} px",
+                image_dimensions.0, image_dimensions.1
+            ));
+            ui.separator();
+            ui.label(format!("Zoom {:.0}%", self.viewer.zoom * 100.));
+            if let Some(pixel) = self.hovered_pixel {
+                ui.separator();
+                ui.label(format!("x {:.0}, y {:.0}", pixel.0, pixel.1));
+                if let Some(point) = self
+                    .source_projection
+                    .point_at_pixel(pixel, image_dimensions)
+                {
+                    let unit = self.settings.angle_unit;
+                    ui.label(format!(
+                        "{}, {}",
+                        unit.format(self.longitudes.map_long(point.long())),
+                        unit.format(point.lat())
+                    ));
+                }
+            }
+        });
+    }
+
+    /// The layout as it is now.
+    fn workspace(&self, ctx: &egui::Context) -> workspaces::Workspace {
+        let mut workspace = workspaces::Workspace {
+            dock: self.settings.dock.clone(),
+            virtual_camera_open: self.virtual_camera.open,
+            overlays: workspaces::Overlays {
+                control_points: self.viewer.show_control_points,
+                detected_lines: self.viewer.show_detected_lines,
+                scale_bar: self.viewer.show_scale_bar,
+                graticule: self.viewer.show_graticule,
+                vectors: self
+                    .vector_overlays
+                    .iter()
+                    .map(|overlay| (overlay.settings.path.clone(), overlay.settings.visible))
+                    .collect(),
+            },
+            ..Default::default()
+        };
+        workspaces::capture(ctx, &self.panel_ids, &mut workspace);
+        workspace
+    }
+
+    fn switch_workspace(&mut self, ctx: &egui::Context, action: workspaces::WorkspaceAction) {
+        let workspace = match action {
+            workspaces::WorkspaceAction::Save(name) => {
+                let workspace = self.workspace(ctx);
+                self.workspaces.saved.insert(name.clone(), workspace);
+                match self.workspaces.save() {
+                    Ok(()) => self.log.info(format!("Saved the workspace \"{}\"", name)),
+                    Err(e) => self.log.error(e),
+                }
+                return;
+            }
+            workspaces::WorkspaceAction::Restore(name) => match self.workspaces.saved.get(&name) {
+                Some(workspace) => workspace.clone(),
+                None => return,
+            },
+        };
+        workspaces::restore(ctx, &self.panel_ids, &workspace);
+        self.settings.dock = workspace.dock;
+        self.virtual_camera.open = workspace.virtual_camera_open && self.source_image.is_some();
+        let overlays = &workspace.overlays;
+        self.viewer.show_control_points = overlays.control_points;
+        self.viewer.show_detected_lines = overlays.detected_lines;
+        self.viewer.show_scale_bar = overlays.scale_bar;
+        self.viewer.show_graticule = overlays.graticule;
+        for overlay in &mut self.vector_overlays {
+            if let Some(visible) = overlays.vectors.get(&overlay.settings.path) {
+                overlay.settings.visible = *visible;
+            }
+        }
+    }
+
+    /// Clicking the source image at `pixel` with the active tool.
+    fn use_tool(&mut self, pixel: (f64, f64), image_dimensions: (u32, u32), pixels_per_point: f64) {
+        match self.tools.active {
+            tools::Tool::Pan => {}
+            tools::Tool::Measure => self.tools.add_measure_point(pixel),
+            tools::Tool::PickCenter => {
+                match self
+                    .source_projection
+                    .point_at_pixel(pixel, image_dimensions)
+                {
+                    Some(point) => {
+                        self.target_projection = self.target_projection.with_center(&point);
+                        let unit = self.settings.angle_unit;
+                        self.log.info(format!(
+                            "Centered the target projection on {}, {}",
+                            unit.format(self.longitudes.map_long(point.long())),
+                            unit.format(point.lat())
+                        ));
+                    }
+                    None => self
+                        .log
+                        .error("The source projection doesn't cover that point."),
+                }
+            }
+            tools::Tool::Annotate => self.annotations.push(annotations::Annotation {
+                pixel,
+                text: String::new(),
+                color: self.tools.annotation_color,
+            }),
+            tools::Tool::Mask => {
+                self.mask
+                    .add_corner(&mut self.tools.mask_draft, pixel, 8. * pixels_per_point)
+            }
+            tools::Tool::Georeference => self.georeference.add_control_point(pixel),
+            tools::Tool::Level => {
+                self.tools.horizon.push(pixel);
+                let yaw = self.orientation.yaw;
+                let leveled =
+                    leveling::Orientation::from_horizon(&self.tools.horizon, image_dimensions, yaw);
+                if let Some(orientation) = leveled {
+                    self.orientation = orientation;
+                }
+            }
+            tools::Tool::Eyedropper => {
+                let shown = match (&self.corrected_preview, &self.source_image) {
+                    (Some(preview), _) => &preview.image,
+                    (None, Some(source_image)) => &source_image.image,
+                    (None, None) => return,
+                };
+                if let Some(color) = sample(shown, pixel, image_dimensions) {
+                    self.use_sample(color);
+                }
+            }
+        }
+    }
+
+    /// Applies a color picked with the eyedropper to what it was meant for.
+    fn use_sample(&mut self, color: [u8; 3]) {
+        self.tools.sampled = Some(color);
+        match self.tools.sample_target {
+            tools::SampleTarget::NoData => self.color_keys.no_data = Some(color),
+            tools::SampleTarget::Background => self.color_keys.background = Some(color),
+            tools::SampleTarget::Annotation => self.tools.annotation_color = color,
+        }
+    }
+
+    /// The length of the measured line, on the globe if the source projection says where it is.
+    fn measurement(&self, image_dimensions: (u32, u32)) -> Option<String> {
+        let [a, b] = self.tools.measure[..] else {
+            return None;
+        };
+        let pixels = (a.0 - b.0).hypot(a.1 - b.1);
+        let points = (
+            self.source_projection.point_at_pixel(a, image_dimensions),
+            self.source_projection.point_at_pixel(b, image_dimensions),
+        );
+        Some(match points {
+            (Some(a), Some(b)) => {
+                format!("{:.1} km ({:.0} px)", self.body.distance(&a, &b), pixels)
+            }
+            _ => format!("{:.0} px", pixels),
+        })
+    }
+
+    /// Identifies a projection with its parameters, for caching what is drawn in it.
+    fn projection_key(&self, projection: &ProjectionData) -> String {
+        match feature_matching::parameters(projection) {
+            Some(parameters) => format!("{} {:?}", projection.kind(), parameters),
+            // A rubber sheet is fitted to the control points.
+            None => format!(
+                "{} {:?}",
+                projection.kind(),
+                self.georeference.control_points
+            ),
+        }
+    }
+
+    fn update_corrected_preview(&mut self, ctx: &egui::Context) {
+        self.corrected_preview = match &self.source_image {
+            Some(source_image) if !self.preprocessing.is_identity() => {
+                let preview = self
+                    .source_overviews
+                    .at_least(1024)
+                    .unwrap_or(&source_image.image)
+                    .thumbnail(1024, 1024);
+                Some(ImageData::new(
+                    ctx,
+                    "Corrected source preview",
+                    self.preprocessing.apply(&preview),
+                ))
+            }
+            _ => None,
+        };
+    }
+
+    /// The source with all corrections applied, ready to be projected. Drafts
+    /// are made from an overview.
+    fn prepared_source(&self, draft: bool) -> Result<image::DynamicImage, String> {
+        let Some(source_image) = &self.source_image else {
+            return Err("No source map loaded.".to_string());
+        };
+        let corrections = engine::Corrections {
+            preprocessing: self.preprocessing,
+            mask: self.mask.clone(),
+            color_keys: self.color_keys,
+            orientation: self.orientation,
+            polar_fill: self.polar_fill,
+        };
+        Ok(match self.source_overviews.at_least(DRAFT_SOURCE_SIZE) {
+            Some(overview) if draft => corrections.apply(overview, source_image.image.dimensions()),
+            _ => corrections.apply_cached(
+                &source_image.image,
+                &self.cache,
+                self.content_key().as_deref(),
+            ),
+        })
+    }
+
+    fn reproject(&self, draft: bool) -> Result<image::DynamicImage, String> {
+        let source = self.prepared_source(draft)?;
+        // Drafts are only for looking at, so they stay as wide as the overview.
+        let sampling = match draft {
+            true => Default::default(),
+            false => self.sampling,
+        };
+        engine::reproject(
+            source,
+            &self.source_projection,
+            &self.target_projection,
+            &sampling,
+        )
+    }
+
+    fn project(&mut self, ctx: &egui::Context) {
+        let started = std::time::Instant::now();
+        match self.reproject(self.draft_projection) {
+            Ok(image) => {
+                self.performance.last_reprojection =
+                    Some((started.elapsed(), self.draft_projection));
+                let projected = ImageData::new(ctx, "Projected image", image);
+                self.history
+                    .record(&projected, &self.target_projection, self.draft_projection);
+                self.projected_image = Some(projected);
+                self.projected_is_draft = self.draft_projection;
+            }
+            Err(e) => self.log.error(e),
+        }
+    }
+
+    /// Asks where to save the projected map, then saves it. TIFFs are only
+    /// saved once their georeferencing has been confirmed.
+    fn export(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .add_filter("JPEG", &["jpg", "jpeg"])
+            .add_filter("TIFF", &["tif", "tiff"])
+            .add_filter("HEALPix map", &["fits"])
+            .save_file()
+        else {
+            return;
+        };
+        let Some(path) = self.output_path(&path) else {
+            return;
+        };
+        if !geotiff::is_tiff(&path) {
+            let result = self.export_to(&path);
+            return self.log_export(&path, result);
+        }
+        let projected = match self.full_resolution() {
+            Ok(projected) => projected,
+            Err(e) => return self.log.error(e),
+        };
+        let georeferencing = geotiff::Georeferencing::of(
+            &self.target_projection,
+            &self.body,
+            projected.dimensions(),
+        );
+        match georeferencing {
+            Some(georeferencing) => {
+                self.geotiff_export = Some(geotiff::Confirmation {
+                    path,
+                    projected,
+                    georeferencing,
+                })
+            }
+            None => {
+                let result = self.export_projected(&path, projected);
+                self.log_export(&path, result);
+            }
+        }
+    }
+
+    fn log_export(&mut self, path: &std::path::Path, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.log.info(format!("Exported {}", path.display())),
+            Err(e) => self.log.error(e),
+        }
+    }
+
+    /// Where to save what was meant for `path`, as the settings say to if it
+    /// exists, or `None` if it is to be left alone.
+    fn output_path(&mut self, path: &std::path::Path) -> Option<std::path::PathBuf> {
+        let resolved = self.settings.on_existing_file.resolve(path);
+        if resolved.is_none() {
+            self.log
+                .info(format!("Skipped saving {}, which exists", path.display()));
+        }
+        resolved
+    }
+
+    /// Saves the projected map, redoing it at full resolution if it was a draft.
+    fn export_to(&self, path: &std::path::Path) -> Result<(), String> {
+        self.export_projected(path, self.full_resolution()?)
+    }
+
+    /// Saves the full-resolution `projected` map with the layers over it, and
+    /// its validity mask if the settings say to. FITS files are written as
+    /// HEALPix maps, without a mask, and TIFFs as GeoTIFFs where the target
+    /// projection can be georeferenced.
+    fn export_projected(
+        &self,
+        path: &std::path::Path,
+        projected: image::DynamicImage,
+    ) -> Result<(), String> {
+        if fits::is_fits(path) {
+            let image = layers::composite(projected, &self.layers, &self.target_projection);
+            return healpix::write(path, &image.to_rgba8(), &self.target_projection);
+        }
+        if self.settings.export_validity_mask {
+            engine::save_validity_mask(&projected, path)?;
+        }
+        let image = layers::composite(projected, &self.layers, &self.target_projection);
+        let image = self.finished(image, path);
+        let georeferencing =
+            geotiff::Georeferencing::of(&self.target_projection, &self.body, image.dimensions());
+        match georeferencing {
+            Some(georeferencing) if geotiff::is_tiff(path) => {
+                geotiff::write(path, &image, &georeferencing)
+            }
+            _ => image.save(path).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// The projected map at full resolution, redone if it was a draft.
+    fn full_resolution(&self) -> Result<image::DynamicImage, String> {
+        match &self.projected_image {
+            Some(projected) if !self.projected_is_draft => Ok(projected.image.clone()),
+            _ => self.reproject(false),
+        }
+    }
+
+    /// The projected map at full resolution, with the layers over it.
+    fn composited(&self) -> Result<image::DynamicImage, String> {
+        self.full_resolution()
+            .map(|image| layers::composite(image, &self.layers, &self.target_projection))
+    }
+
+    /// The composited map as it is saved to `path`.
+    fn finished(&self, image: image::DynamicImage, path: &std::path::Path) -> image::DynamicImage {
+        let image = match self.attribution.stamped() {
+            Some(text) => attribution::stamp(image, text),
+            None => image,
+        };
+        color_keys::for_export(image, self.color_keys.background, path)
+    }
+
+    /// Saves the projected map in a zip archive with what it takes to make it
+    /// again: the source map, a batch manifest, the sidecar and the vector
+    /// overlays.
+    fn export_bundle(&mut self) {
+        let Some(input) = self.source_path.clone() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Zip archive", &["zip"])
+            .save_file()
+        else {
+            return;
+        };
+        let Some(path) = self.output_path(&path) else {
+            return;
+        };
+        let result = self.composited().and_then(|image| {
+            bundle::write(
+                &path,
+                &self.finished(image, std::path::Path::new("map.png")),
+                &input,
+                &self.source_projection,
+                &self.target_projection,
+                &self.sidecar(),
+            )
+        });
+        match result {
+            Ok(()) if self.layers.is_empty() => {
+                self.log.info(format!("Exported {}", path.display()))
+            }
+            // Layers aren't kept anywhere a manifest could point to.
+            Ok(()) => self.log.warning(format!(
+                "Exported {}, but its manifest makes the map without the layers",
+                path.display()
+            )),
+            Err(e) => self.log.error(e),
+        }
+    }
+
+    /// Asks where to save a diagnostic report for a bug report, then saves it.
+    fn diagnostic_report(&mut self, ctx: &egui::Context) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Zip archive", &["zip"])
+            .set_file_name("submaptive-diagnostics.zip")
+            .save_file()
+        else {
+            return;
+        };
+        let system = diagnostics::System {
+            gpu: self.gpu.as_deref(),
+            max_texture_side: ctx.input(|input| input.max_texture_side),
+        };
+        let session = diagnostics::Session {
+            source_path: self.source_path.clone(),
+            source_dimensions: self.source_image.as_ref().map(|i| i.image.dimensions()),
+            source_projection: batch::ProjectionSpec::of(&self.source_projection),
+            target_projection: batch::ProjectionSpec::of(&self.target_projection),
+            viewer: self.viewer,
+            sidecar: self.sidecar(),
+        };
+        let images: Vec<_> = [
+            ("source", &self.source_image),
+            ("projected", &self.projected_image),
+        ]
+        .into_iter()
+        .filter_map(|(name, image)| Some((name, &image.as_ref()?.image)))
+        .collect();
+        let result =
+            diagnostics::write(&path, &self.log, &self.settings, &system, &session, &images);
+        match result {
+            Ok(()) => self.log.info(format!("Saved {}", path.display())),
+            Err(e) => self.log.error(e),
+        }
+    }
+
+    /// Carries out a command from another program or instance.
+    fn run_command(&mut self, request: instance::Request, ctx: &egui::Context) {
+        let result = match &request.command {
+            instance::Command::Open(path) => {
+                self.load_source_image(path.clone(), ctx);
+                self.raise_window = true;
+                // Answered once the map has loaded.
+                self.open_request = Some(request);
+                return;
+            }
+            instance::Command::Focus => {
+                self.raise_window = true;
+                Ok(String::new())
+            }
+            instance::Command::SourceProjection(spec) => spec
+                .build()
+                .map(|projection| self.source_projection = projection)
+                .map(|()| String::new()),
+            instance::Command::TargetProjection(spec) => spec
+                .build()
+                .map(|projection| self.target_projection = projection)
+                .map(|()| String::new()),
+            instance::Command::Project => self.reproject(false).map(|image| {
+                let dimensions = image.dimensions();
+                let projected = ImageData::new(ctx, "Projected image", image);
+                self.history
+                    .record(&projected, &self.target_projection, false);
+                self.projected_image = Some(projected);
+                self.projected_is_draft = false;
+                format!("{} × {} px", dimensions.0, dimensions.1)
+            }),
+            instance::Command::Export(path) => match self.output_path(path) {
+                Some(path) => self
+                    .export_to(&path)
+                    .map(|()| format!("exported {}", path.display())),
+                None => Ok(format!("skipped {}, which exists", path.display())),
+            },
+        };
+        request.respond(result);
+    }
+
+    /// Renders the virtual camera's view from the full-resolution panorama and saves it.
+    fn export_camera_view(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .add_filter("JPEG", &["jpg", "jpeg"])
+            .save_file()
+        else {
+            return;
+        };
+        let Some(path) = self.output_path(&path) else {
+            return;
+        };
+        let camera = &self.virtual_camera;
+        let result = self.prepared_source(false).and_then(|panorama| {
+            camera
+                .render(&panorama.to_rgba8(), camera.width, camera.height)
+                .save(&path)
+                .map_err(|e| e.to_string())
+        });
+        match result {
+            Ok(()) => self.log.info(format!("Exported {}", path.display())),
+            Err(e) => self.log.error(e),
         }
     }
 
     fn load_source_image(&mut self, path: std::path::PathBuf, ctx: &egui::Context) {
-        let image = image::io::Reader::open(path).map(|data| data.decode());
-        match image {
-            Ok(image) => match image {
-                Ok(image) => {
-                    self.source_image = Some(ImageData {
-                        image: image.clone(),
-                        handle: ctx.load_texture(
-                            "Source image",
-                            egui::ColorImage::from_rgba_unmultiplied(
-                                [image.width() as usize, image.height() as usize],
-                                image.to_rgba8().as_flat_samples().as_slice(),
-                            ),
-                            Default::default(),
-                        ),
-                    })
+        self.loading = Some(loading::Loading::start(ctx, path, self.cache.clone()));
+    }
+
+    fn poll_loading(&mut self, ctx: &egui::Context) {
+        let Some(loading) = &mut self.loading else {
+            return;
+        };
+        for event in loading.job.poll() {
+            match event {
+                loading::LoadEvent::Preview(preview) => {
+                    loading.preview =
+                        Some(ctx.load_texture("Source preview", preview, Default::default()));
                 }
-                Err(e) => {
-                    self.error = Some(e.to_string());
+                loading::LoadEvent::Loaded(loaded) => {
+                    let path = loading.path.clone();
+                    self.finish_loading(path.clone(), *loaded, ctx);
+                    if let Some(request) = self.open_request.take() {
+                        request.respond(Ok(format!("opened {}", path.display())));
+                    }
+                    break;
                 }
+                loading::LoadEvent::Failed(e) => {
+                    if let Some(request) = self.open_request.take() {
+                        request.respond(Err(e.clone()));
+                    }
+                    self.log.error(e);
+                    self.loading = None;
+                    break;
+                }
+            }
+        }
+        if self
+            .loading
+            .as_ref()
+            .is_some_and(|loading| loading.job.is_finished())
+        {
+            self.loading = None;
+        }
+    }
+
+    fn finish_loading(
+        &mut self,
+        path: std::path::PathBuf,
+        loaded: loading::Loaded,
+        ctx: &egui::Context,
+    ) {
+        self.loading = None;
+        self.save_session();
+        let (sidecar, template) = match sidecar::Sidecar::load(&path) {
+            Ok(Some(sidecar)) => (sidecar, None),
+            Ok(None) => match &self.template {
+                Some((_, template)) => (template.onto(&Default::default()), Some(template.viewer)),
+                None => Default::default(),
             },
             Err(e) => {
-                self.error = Some(e.to_string());
+                self.log.error(e);
+                Default::default()
+            }
+        };
+        self.georeference = Default::default();
+        self.apply_sidecar(&sidecar);
+        self.fisheye_frame = None;
+        self.tools = tools::Tools {
+            active: self.tools.active,
+            ..Default::default()
+        };
+        // What came from a template is saved as the new map's sidecar.
+        self.saved_sidecar = match template {
+            Some(_) => Default::default(),
+            None => sidecar,
+        };
+        self.source_path = Some(path);
+        self.source_image = Some(ImageData::with_display(
+            ctx,
+            "Source image",
+            loaded.image,
+            loaded.display,
+        ));
+        self.source_overviews = loaded.overviews;
+        self.source_hash = loaded.hash;
+        if let Some(conversion) = loaded.conversion {
+            self.log.warning(conversion);
+        }
+        if let Some(remote) = loaded.remote {
+            self.log.info(remote);
+        }
+        // Offered until the user turns it off, which the sidecar then keeps.
+        if let Some(text) = loaded
+            .attribution
+            .filter(|_| self.attribution.text.is_empty())
+        {
+            self.log.info(format!(
+                "The map states \"{}\", which exports are stamped with.",
+                text
+            ));
+            self.attribution = attribution::Attribution { text, stamp: true };
+        }
+        if let Some(sky) = loaded.sky {
+            if let Some(projection) = sky.projection {
+                self.source_projection = projection;
+            }
+            if self.longitudes == Default::default() {
+                self.longitudes = sky.longitudes;
+            }
+            if let Some(caveat) = sky.caveat {
+                self.log.warning(caveat);
+            }
+        }
+        self.gridded = loaded.field;
+        if self.gridded.is_some() {
+            self.source_projection = ProjectionKind::Equirectangular.default_projection_data();
+            if self.data_coloring != Default::default() {
+                self.apply_data_coloring(ctx);
+            }
+        }
+        self.viewer = match template {
+            Some(viewer) => viewer,
+            None => self
+                .session_key()
+                .map(|key| self.sessions.get(&key))
+                .unwrap_or_default(),
+        };
+        if self.dual_fisheye.is_some() {
+            self.apply_dual_fisheye(ctx);
+        } else {
+            self.update_corrected_preview(ctx);
+        }
+    }
+
+    /// Takes on the manual work and settings kept in a sidecar.
+    fn apply_sidecar(&mut self, sidecar: &sidecar::Sidecar) {
+        self.georeference.control_points = sidecar.control_points.clone();
+        self.preprocessing = sidecar.preprocessing;
+        self.annotations = sidecar.annotations.clone();
+        self.mask = sidecar.mask.clone();
+        self.color_keys = sidecar.color_keys;
+        self.polar_fill = sidecar.polar_fill;
+        self.orientation = sidecar.orientation;
+        self.dual_fisheye = sidecar.dual_fisheye;
+        self.data_coloring = sidecar.data_coloring;
+        self.body = sidecar.body;
+        self.longitudes = sidecar.longitudes;
+        self.graticule = sidecar.graticule;
+        self.attribution = sidecar.attribution.clone();
+        self.sampling = sidecar.sampling;
+        self.vector_overlays.clear();
+        for settings in &sidecar.vector_overlays {
+            match vectors::VectorOverlay::load(settings.clone()) {
+                Ok(overlay) => self.vector_overlays.push(overlay),
+                Err(e) => {
+                    self.log
+                        .error(format!("Could not read {}: {}", settings.path.display(), e))
+                }
             }
         }
     }
-}
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::SidePanel::left("Controls")
-            .width_range(100.0..=1000.0)
-            .show(ctx, |ui| {
+    fn template_action(&mut self, action: templates::TemplateAction, ctx: &egui::Context) {
+        use templates::TemplateAction::*;
+        let result = match action {
+            Save(name) => templates::Template::new(
+                batch::ProjectionSpec::of(&self.source_projection),
+                batch::ProjectionSpec::of(&self.target_projection),
+                self.viewer,
+                &self.sidecar(),
+            )
+            .save(&name)
+            .map(|()| format!("Saved the template \"{}\"", name)),
+            Apply(name) => templates::Template::load(&name).and_then(|template| {
+                self.source_projection = template.source.build()?;
+                self.target_projection = template.target.build()?;
+                self.viewer = template.viewer;
+                let sidecar = template.onto(&self.sidecar());
+                self.apply_sidecar(&sidecar);
+                if self.gridded.is_some() {
+                    self.apply_data_coloring(ctx);
+                }
+                self.template = Some((name.clone(), template));
+                Ok(format!("Applied the template \"{}\"", name))
+            }),
+            Delete(name) => templates::delete(&name).map(|()| {
+                if self
+                    .template
+                    .as_ref()
+                    .is_some_and(|(active, _)| *active == name)
+                {
+                    self.template = None;
+                }
+                format!("Deleted the template \"{}\"", name)
+            }),
+            Clear => {
+                self.template = None;
+                return;
+            }
+        };
+        match result {
+            Ok(message) => self.log.info(message),
+            Err(e) => self.log.error(e),
+        }
+    }
+
+    fn panel_ui(&mut self, ui: &mut egui::Ui, kind: dock::PanelKind, ctx: &egui::Context) {
+        // The sections are kept under this id, which workspaces need to find them.
+        self.panel_ids.insert(kind, ui.id());
+        match kind {
+            dock::PanelKind::Controls => self.controls_ui(ui, ctx),
+            dock::PanelKind::Layers => self.layers_ui(ui),
+            dock::PanelKind::Tasks => self.tasks_ui(ui),
+            dock::PanelKind::Inspector => self.inspector_ui(ui),
+            dock::PanelKind::Log => notifications::log_ui(ui, &mut self.log),
+        }
+    }
+
+    /// Opening the source, and how it is corrected, georeferenced and projected.
+    fn controls_ui(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        if let Some(loading) = &self.loading {
+            ui.add(
+                egui::ProgressBar::new(loading.job.progress())
+                    .show_percentage()
+                    .animate(true),
+            );
+            ui.label(&loading.job.label);
+        } else {
+            ui.horizontal(|ui| {
                 if ui.button("Choose source map...").clicked() {
                     if let Some(path) = rfd::FileDialog::new().pick_file() {
                         self.load_source_image(path, ctx);
                     }
                 }
-                projection_ui(ui, &mut self.source_projection, "Source projection");
-                projection_ui(ui, &mut self.target_projection, "Target projection");
-                if self.source_image.is_some() && ui.button("Project!").clicked() {
-                    let image = submaptive::Map::new(
-                        self.source_image.clone().unwrap().image,
-                        self.source_projection.clone(),
-                    )
-                    .convert_to(self.target_projection.clone())
-                    .to_image();
-                    let handle = ctx.load_texture(
-                        "Source image",
-                        egui::ColorImage::from_rgba_unmultiplied(
-                            [image.width() as usize, image.height() as usize],
-                            image.to_rgba8().as_flat_samples().as_slice(),
-                        ),
-                        Default::default(),
-                    );
-                    self.projected_image = Some(ImageData { image, handle });
+                if ui.button("Open from URL...").clicked() {
+                    self.remote_open.open = true;
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Settings...").clicked() {
+                self.settings_open = true;
+            }
+            if ui.button("Templates...").clicked() {
+                self.templates.open = true;
+            }
+        });
+        let unit = self.settings.angle_unit;
+        if preprocess::preprocessing_ui(ui, &mut self.preprocessing) {
+            self.update_corrected_preview(ctx);
+        }
+        if dual_fisheye::dual_fisheye_ui(ui, &mut self.dual_fisheye, unit) {
+            self.apply_dual_fisheye(ctx);
+        }
+        if let Some(field) = &self.gridded {
+            if gridded::gridded_ui(ui, field, &mut self.data_coloring) {
+                self.apply_data_coloring(ctx);
+            }
+        }
+        let longitudes = self.longitudes;
+        projection_ui(
+            ui,
+            &mut self.source_projection,
+            &longitudes,
+            unit,
+            "Source projection",
+        );
+        projection_ui(
+            ui,
+            &mut self.target_projection,
+            &longitudes,
+            unit,
+            "Target projection",
+        );
+        body::body_ui(ui, &mut self.body, unit);
+        longitudes::longitudes_ui(ui, &mut self.longitudes, unit);
+        graticule::graticule_ui(ui, &mut self.graticule, unit);
+        distortion::distortion_ui(ui, &mut self.distortion);
+        let content_key = self.content_key();
+        let corrected_source = || {
+            self.source_image.as_ref().map(|source_image| {
+                self.preprocessing.apply_cached(
+                    &source_image.image,
+                    &self.cache,
+                    content_key.as_deref(),
+                )
+            })
+        };
+        georeference::georeference_ui(
+            ui,
+            &mut self.georeference,
+            &mut self.tools.active,
+            &self.longitudes,
+            unit,
+            corrected_source,
+        );
+        let corrected_source = || {
+            self.source_image.as_ref().map(|source_image| {
+                self.preprocessing.apply_cached(
+                    &source_image.image,
+                    &self.cache,
+                    content_key.as_deref(),
+                )
+            })
+        };
+        annotations::annotations_ui(ui, &mut self.annotations);
+        mask::mask_ui(ui, &mut self.mask, &mut self.tools.mask_draft);
+        color_keys::color_keys_ui(ui, &mut self.color_keys);
+        let keyed_source = || {
+            let source_image = self.source_image.as_ref()?;
+            let overview = self
+                .source_overviews
+                .at_least(DRAFT_SOURCE_SIZE)
+                .unwrap_or(&source_image.image);
+            Some(self.color_keys.key_out(self.preprocessing.apply(overview)))
+        };
+        leveling::orientation_ui(ui, &mut self.orientation, &mut self.tools.horizon, unit);
+        polar_fill::polar_fill_ui(ui, &mut self.polar_fill, unit, keyed_source);
+        feature_matching::feature_matching_ui(
+            ui,
+            &mut self.feature_matching,
+            &self.source_projection,
+            unit,
+            corrected_source,
+        );
+        alignment::alignment_ui(
+            ui,
+            &mut self.alignment,
+            &self.target_projection,
+            self.projected_image
+                .as_ref()
+                .map(|projected| &projected.image),
+        );
+        if let Some(source_image) = &self.source_image {
+            let dimensions = source_image.image.dimensions();
+            for projection in [&mut self.source_projection, &mut self.target_projection] {
+                if let ProjectionData::RubberSheet(_) = projection {
+                    *projection =
+                        ProjectionData::RubberSheet(self.georeference.rubber_sheet(dimensions));
+                }
+            }
+        }
+        if self.source_image.is_some() {
+            ui.checkbox(&mut self.draft_projection, "Draft quality (from overview)");
+            let source_dimensions = self.source_image.as_ref().map(|i| i.image.dimensions());
+            sampling::sampling_ui(ui, &mut self.sampling, source_dimensions);
+            if ui.button("Project!").clicked() {
+                self.project(ctx);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Export...").clicked() {
+                    self.export();
+                }
+                if ui.button("Export bundle...").clicked() {
+                    self.export_bundle();
                 }
             });
+            ui.checkbox(
+                &mut self.settings.export_validity_mask,
+                "Export validity mask",
+            )
+            .on_hover_text(
+                "Also saves map-mask.png next to map.png, white where the map shows \
+                 something of the source and black elsewhere.",
+            );
+            attribution::attribution_ui(ui, &mut self.attribution);
+            if ui.button("Virtual camera...").clicked() {
+                self.virtual_camera.open = true;
+                // Pick up the current corrections.
+                self.virtual_camera.preview_source = None;
+            }
+            if !self.source_overviews.from_file && ui.button("Save overviews (.ovr)").clicked() {
+                if let Some(path) = &self.source_path {
+                    match self.source_overviews.save_ovr(path) {
+                        Ok(()) => {
+                            self.source_overviews.from_file = true;
+                            self.log.info("Saved overviews");
+                        }
+                        Err(e) => self.log.error(e),
+                    }
+                }
+            }
+        }
+    }
+
+    fn layers_ui(&mut self, ui: &mut egui::Ui) {
+        let unit = self.settings.angle_unit;
+        if let Some(vectors::OverlayAction::Import(path)) =
+            vectors::vector_overlays_ui(ui, &mut self.vector_overlays)
+        {
+            let mut settings = vectors::OverlaySettings {
+                path: path.clone(),
+                ..Default::default()
+            };
+            settings.style.color = palettes::categorical(self.vector_overlays.len());
+            match vectors::VectorOverlay::load(settings) {
+                Ok(overlay) => self.vector_overlays.push(overlay),
+                Err(e) => self
+                    .log
+                    .error(format!("Could not read {}: {}", path.display(), e)),
+            }
+        }
+        if let Some(layers::LayerAction::Add(path)) = layers::layers_ui(
+            ui,
+            &mut self.layers,
+            &mut self.layer_import,
+            &self.longitudes,
+            unit,
+        ) {
+            match layered::Format::for_path(&path) {
+                Some(_) => match layered::LayeredImage::read(&path) {
+                    Ok(file) => self.layer_import = Some(layers::LayerImport::new(path, file)),
+                    Err(e) => self.log.error(e),
+                },
+                None => match layers::Layer::load(path.clone()) {
+                    Ok(layer) => self.layers.push(layer),
+                    Err(e) => self
+                        .log
+                        .error(format!("Could not read {}: {}", path.display(), e)),
+                },
+            }
+        }
+    }
+
+    /// The open map, and what lies under the cursor in both projections.
+    fn inspector_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(source_image) = &self.source_image else {
+            ui.weak("No source map is open.");
+            return;
+        };
+        let image_dimensions = source_image.image.dimensions();
+        let unit = self.settings.angle_unit;
+        egui::Grid::new("Inspector").num_columns(2).show(ui, |ui| {
+            if let Some(path) = &self.source_path {
+                ui.label("File");
+                ui.label(path.file_name().unwrap_or_default().to_string_lossy())
+                    .on_hover_text(path.display().to_string());
+                ui.end_row();
+            }
+            ui.label("Size");
+            ui.label(format!(
+                "{} × {} px",
+                image_dimensions.0, image_dimensions.1
+            ));
+            ui.end_row();
+            ui.label("Source");
+            ui.label(self.source_projection.kind().to_string());
+            ui.end_row();
+            ui.label("Target");
+            ui.label(self.target_projection.kind().to_string());
+            ui.end_row();
+            ui.label("Cursor");
+            let Some(pixel) = self.hovered_pixel else {
+                ui.weak("Not over the map");
+                ui.end_row();
+                return;
+            };
+            ui.label(format!("x {:.1}, y {:.1}", pixel.0, pixel.1));
+            ui.end_row();
+            let Some(point) = self
+                .source_projection
+                .point_at_pixel(pixel, image_dimensions)
+            else {
+                return;
+            };
+            ui.label("Longitude");
+            ui.label(unit.format(self.longitudes.map_long(point.long())));
+            ui.end_row();
+            ui.label("Latitude");
+            ui.label(unit.format(point.lat()));
+            ui.end_row();
+            if let Some(projected) = &self.projected_image {
+                let dimensions = projected.image.dimensions();
+                ui.label("Projected");
+                match self.target_projection.pixel_at_point(&point, dimensions) {
+                    Some((x, y)) => ui.label(format!("x {:.1}, y {:.1}", x, y)),
+                    None => ui.weak("Not on the projected map"),
+                };
+                ui.end_row();
+            }
+        });
+    }
+
+    /// What runs in the background.
+    fn tasks_ui(&mut self, ui: &mut egui::Ui) {
+        match &self.loading {
+            Some(loading) => {
+                ui.label(format!("Opening {}", loading.path.display()));
+                ui.add(
+                    egui::ProgressBar::new(loading.job.progress())
+                        .show_percentage()
+                        .animate(true),
+                );
+                ui.label(&loading.job.label);
+            }
+            None => {
+                ui.weak("Nothing is being opened.");
+            }
+        }
+        let can_add = self.source_path.is_some();
+        if let Some(batch::BatchAction::AddCurrent) =
+            batch::batch_ui(ui, &mut self.batch, &mut self.log, can_add)
+        {
+            if let Some(path) = &self.source_path {
+                self.batch.manifest.conversions.push(batch::Conversion {
+                    validity_mask: self.settings.export_validity_mask,
+                    ..batch::Conversion::for_input(
+                        path.clone(),
+                        &self.source_projection,
+                        &self.target_projection,
+                    )
+                });
+            }
+        }
+    }
+
+    /// Identifies the current source image's content for caching: the file,
+    /// and how it was stitched if it is a dual-fisheye frame or colored if it
+    /// is gridded data.
+    fn content_key(&self) -> Option<String> {
+        let hash = self.source_hash.as_ref()?;
+        if self.gridded.is_some() {
+            let coloring = serde_json::to_vec(&self.data_coloring).unwrap_or_default();
+            return Some(cache::Cache::key("gridded", &[hash.as_bytes(), &coloring]));
+        }
+        match &self.fisheye_frame {
+            Some((_, fisheye)) => {
+                let settings = serde_json::to_vec(fisheye).unwrap_or_default();
+                Some(cache::Cache::key(
+                    "dual-fisheye",
+                    &[hash.as_bytes(), &settings],
+                ))
+            }
+            None => Some(hash.clone()),
+        }
+    }
+
+    /// Makes the source the panorama stitched from the loaded dual-fisheye
+    /// frame, or the frame itself again if it isn't treated as one anymore.
+    fn apply_dual_fisheye(&mut self, ctx: &egui::Context) {
+        let Some(source_image) = &self.source_image else {
+            return;
+        };
+        let frame = match self.fisheye_frame.take() {
+            Some((frame, _)) => frame,
+            None => source_image.image.clone(),
+        };
+        let image = match self.dual_fisheye {
+            Some(fisheye) => {
+                let panorama = fisheye.stitch(&frame);
+                self.fisheye_frame = Some((frame, fisheye));
+                self.source_projection = ProjectionKind::Equirectangular.default_projection_data();
+                panorama
+            }
+            None => frame,
+        };
+        self.source_overviews = overviews::Overviews::build(&image);
+        self.source_image = Some(ImageData::new(ctx, "Source image", image));
+        self.update_corrected_preview(ctx);
+    }
+
+    /// Draws the source again from its gridded data in the current colors.
+    fn apply_data_coloring(&mut self, ctx: &egui::Context) {
+        let Some(field) = &self.gridded else {
+            return;
+        };
+        let image = field.render(&self.data_coloring);
+        self.source_overviews = overviews::Overviews::build(&image);
+        self.source_image = Some(ImageData::new(ctx, "Source image", image));
+        self.update_corrected_preview(ctx);
+    }
+}
+
+impl eframe::App for App {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.window_layouts.update(frame);
+        self.performance.record(frame.info().cpu_usage);
+        if self.settings.appearance != self.applied_appearance {
+            ctx.set_style(self.settings.appearance.style());
+            self.applied_appearance = self.settings.appearance;
+        }
+        let commands = self.instance.as_ref().map(|instance| instance.poll());
+        for request in commands.into_iter().flatten() {
+            self.run_command(request, ctx);
+        }
+        // There is no way to focus the window, so it is lifted above all
+        // others for a moment.
+        if self.window_raised {
+            frame.set_always_on_top(false);
+            self.window_raised = false;
+        }
+        if self.raise_window {
+            frame.set_minimized(false);
+            frame.set_always_on_top(true);
+            self.raise_window = false;
+            self.window_raised = true;
+            ctx.request_repaint();
+        }
+        self.poll_loading(ctx);
+        if self.settings.check_for_updates {
+            self.update_check.start(ctx);
+        }
+        self.update_check.poll(&mut self.log);
+        self.batch.poll(&mut self.log);
+        self.feature_matching.poll(&mut self.source_projection);
+        egui::TopBottomPanel::bottom("Status bar").show(ctx, |ui| self.status_bar(ui));
+        let mut layout = std::mem::take(&mut self.settings.dock);
+        dock::show(ctx, &mut layout, |ui, kind| self.panel_ui(ui, kind, ctx));
+        self.settings.dock = layout;
         egui::CentralPanel::default().show(ctx, |ui| {
-            if let Some(source_image) = &self.source_image {
-                let dimensions = source_image.image.dimensions();
-                let dimensions = (dimensions.0 as f32, dimensions.1 as f32);
-                let dimensions = (400. * (dimensions.0 / dimensions.1), 400.);
-                ui.image(source_image.handle.id(), dimensions);
+            if let Some(preview) = self.loading.as_ref().and_then(|l| l.preview.as_ref()) {
+                let size = preview.size_vec2();
+                ui.image(preview.id(), (400. * (size.x / size.y), 400.));
+            } else if let Some(source_image) = &self.source_image {
+                let handle = match &self.corrected_preview {
+                    Some(preview) => &preview.handle,
+                    None => &source_image.handle,
+                };
+                let image_dimensions = source_image.image.dimensions();
+                tools::toolbar(ui, &mut self.tools);
+                viewer::viewer_toolbar(ui, &mut self.viewer);
+                let tool = self.tools.active;
+                let bindings = self.settings.mouse;
+                let (response, image_rect) = viewer::image_view(
+                    ui,
+                    &mut self.viewer,
+                    &bindings,
+                    handle.id(),
+                    image_dimensions,
+                    tool == tools::Tool::Pan,
+                    self.settings.backdrop,
+                );
+                let pan_held = bindings.pan_held(ui);
+                if response.hovered() {
+                    let cursor = if pan_held {
+                        egui::CursorIcon::Grab
+                    } else {
+                        tool.cursor()
+                    };
+                    ui.output_mut(|output| output.cursor_icon = cursor);
+                }
+                self.hovered_pixel = response.hover_pos().map(|position| {
+                    let relative = (position - image_rect.min) / image_rect.size();
+                    (
+                        (relative.x * image_dimensions.0 as f32) as f64,
+                        (relative.y * image_dimensions.1 as f32) as f64,
+                    )
+                });
+                if response.clicked() && !pan_held {
+                    if let Some(position) = response.interact_pointer_pos() {
+                        let relative = (position - image_rect.min) / image_rect.size();
+                        let pixel = (
+                            (relative.x * image_dimensions.0 as f32) as f64,
+                            (relative.y * image_dimensions.1 as f32) as f64,
+                        );
+                        let pixels_per_point =
+                            image_dimensions.0 as f64 / image_rect.width() as f64;
+                        self.use_tool(pixel, image_dimensions, pixels_per_point);
+                    }
+                }
+                let painter = ui.painter_at(response.rect);
+                mask::paint_mask(
+                    &painter,
+                    image_rect,
+                    image_dimensions,
+                    &self.mask,
+                    &self.tools.mask_draft,
+                );
+                let mut labels = labels::Labels::default();
+                annotations::paint_annotations(
+                    &painter,
+                    image_rect,
+                    image_dimensions,
+                    &self.annotations,
+                    &mut labels,
+                );
+                leveling::paint_horizon(
+                    &painter,
+                    image_rect,
+                    image_dimensions,
+                    &self.tools.horizon,
+                );
+                tools::paint_measurement(
+                    &painter,
+                    image_rect,
+                    image_dimensions,
+                    &self.tools,
+                    self.measurement(image_dimensions),
+                    &mut labels,
+                );
+                if self.viewer.show_graticule {
+                    graticule::paint_graticule(
+                        &painter,
+                        image_rect,
+                        image_dimensions,
+                        &self.source_projection,
+                        &self.graticule,
+                        self.body.axial_tilt,
+                        &self.longitudes,
+                        self.settings.angle_unit,
+                        &mut labels,
+                    );
+                }
+                let key = self.projection_key(&self.source_projection);
+                for overlay in &mut self.vector_overlays {
+                    vectors::paint_vector_overlay(
+                        &painter,
+                        image_rect,
+                        image_dimensions,
+                        &self.source_projection,
+                        key.clone(),
+                        overlay,
+                    );
+                }
+                if self.viewer.show_scale_bar {
+                    let km_per_point = km_per_point(
+                        &self.source_projection,
+                        &self.body,
+                        image_dimensions,
+                        image_rect,
+                        response.rect.center(),
+                    );
+                    if let Some(km_per_point) = km_per_point {
+                        body::paint_scale_bar(&painter, response.rect, km_per_point);
+                    }
+                }
+                if self.viewer.show_detected_lines {
+                    graticule_detection::paint_detected_lines(
+                        &painter,
+                        image_rect,
+                        image_dimensions,
+                        &self.georeference.detection,
+                    );
+                }
+                if self.viewer.show_control_points {
+                    georeference::paint_control_points(
+                        &painter,
+                        image_rect,
+                        image_dimensions,
+                        &self.georeference,
+                        &mut labels,
+                    );
+                }
+                labels.paint(&painter);
             }
             if let Some(target_image) = &self.projected_image {
                 let dimensions = target_image.image.dimensions();
                 let dimensions = (dimensions.0 as f32, dimensions.1 as f32);
                 let dimensions = (400. * (dimensions.0 / dimensions.1), 400.);
-                ui.image(target_image.handle.id(), dimensions);
+                let backdrop = ui.painter().add(egui::Shape::Noop);
+                let response = ui.add(
+                    egui::Image::new(target_image.handle.id(), dimensions)
+                        .sense(egui::Sense::click()),
+                );
+                ui.painter().set(
+                    backdrop,
+                    viewer::backdrop_shape(response.rect, ui.clip_rect(), self.settings.backdrop),
+                );
+                let key = self.projection_key(&self.target_projection);
+                layers::paint_layers(
+                    ui,
+                    response.rect,
+                    target_image.handle.id(),
+                    self.compositor.as_ref(),
+                    &mut self.layers,
+                    &self.target_projection,
+                    &key,
+                    target_image.image.dimensions(),
+                );
+                distortion::paint_distortion(
+                    &ui.painter_at(response.rect),
+                    response.rect,
+                    target_image.image.dimensions(),
+                    &self.target_projection,
+                    key.clone(),
+                    &mut self.distortion,
+                );
+                if self.viewer.show_graticule {
+                    let painter = ui.painter_at(response.rect);
+                    let mut labels = labels::Labels::default();
+                    graticule::paint_graticule(
+                        &painter,
+                        response.rect,
+                        target_image.image.dimensions(),
+                        &self.target_projection,
+                        &self.graticule,
+                        self.body.axial_tilt,
+                        &self.longitudes,
+                        self.settings.angle_unit,
+                        &mut labels,
+                    );
+                    labels.paint(&painter);
+                }
+                let overlays = self
+                    .vector_overlays
+                    .iter_mut()
+                    .chain(self.alignment.coastline());
+                for overlay in overlays {
+                    vectors::paint_vector_overlay(
+                        &ui.painter_at(response.rect),
+                        response.rect,
+                        target_image.image.dimensions(),
+                        &self.target_projection,
+                        key.clone(),
+                        overlay,
+                    );
+                }
+                if self.viewer.show_scale_bar {
+                    let km_per_point = km_per_point(
+                        &self.target_projection,
+                        &self.body,
+                        target_image.image.dimensions(),
+                        response.rect,
+                        response.rect.center(),
+                    );
+                    if let Some(km_per_point) = km_per_point {
+                        body::paint_scale_bar(ui.painter(), response.rect, km_per_point);
+                    }
+                }
+                if self.tools.active == tools::Tool::Eyedropper {
+                    let response = response.on_hover_cursor(egui::CursorIcon::Crosshair);
+                    if let Some(position) = response
+                        .interact_pointer_pos()
+                        .filter(|_| response.clicked())
+                    {
+                        let relative = (position - response.rect.min) / response.rect.size();
+                        let image_dimensions = target_image.image.dimensions();
+                        let pixel = (
+                            (relative.x * image_dimensions.0 as f32) as f64,
+                            (relative.y * image_dimensions.1 as f32) as f64,
+                        );
+                        let sampled = sample(&target_image.image, pixel, image_dimensions);
+                        if let Some(color) = sampled {
+                            self.use_sample(color);
+                        }
+                    }
+                }
+            }
+            if let Some(entry) = history::history_strip(ui, &mut self.history) {
+                self.projected_image = Some(entry.image.clone());
+                self.target_projection = entry.target.clone();
+                self.projected_is_draft = entry.draft;
             }
         });
-        if let Some(error) = &self.error {
-            egui::TopBottomPanel::bottom("Dialogue").show(ctx, |ui| {
-                ui.colored_label(egui::Color32::RED, error);
-            });
+        history::compare_window(ctx, &mut self.history);
+        if let Some(e) = settings::settings_window(
+            ctx,
+            &mut self.settings_open,
+            &mut self.settings,
+            &mut self.credentials,
+            &self.cache,
+        ) {
+            self.log.error(e);
+        }
+        if self.virtual_camera.open && self.virtual_camera.preview_source.is_none() {
+            self.virtual_camera.preview_source = self
+                .prepared_source(true)
+                .ok()
+                .map(|source| source.to_rgba8());
+        }
+        if let Some(virtual_camera::CameraAction::Export) = virtual_camera::virtual_camera_window(
+            ctx,
+            &mut self.virtual_camera,
+            self.settings.angle_unit,
+        ) {
+            self.export_camera_view();
+        }
+        update::update_window(ctx, &mut self.update_check);
+        let active = self.template.as_ref().map(|(name, _)| name.as_str());
+        if let Some(action) = templates::templates_window(ctx, &mut self.templates, active) {
+            self.template_action(action, ctx);
+        }
+        let unit = self.settings.angle_unit;
+        if let Some(export) = geotiff::confirmation_window(ctx, &mut self.geotiff_export, unit) {
+            let result = self.export_projected(&export.path, export.projected);
+            self.log_export(&export.path, result);
         }
+        if let Some(request) = remote::remote_open_window(ctx, &mut self.remote_open) {
+            self.loading = Some(loading::Loading::start_remote(
+                ctx,
+                request,
+                self.cache.clone(),
+            ));
+        }
+        self.save_sidecar(ctx);
+        self.save_settings(ctx);
+        notifications::show_toasts(ctx, &self.log);
+        performance::overlay(ctx, &self.performance);
+    }
+
+    fn on_exit(&mut self, gl: Option<&eframe::glow::Context>) {
+        if let (Some(gl), Some(compositor)) = (gl, &self.compositor) {
+            compositor.lock().unwrap().destroy(gl);
+        }
+        if let Some(instance) = &self.instance {
+            instance.release();
+        }
+        // There is nowhere left to report errors to.
+        self.save_session();
+        if self.credentials != self.saved_credentials {
+            let _ = self.credentials.save();
+        }
+        if !self.safe_mode {
+            let _ = self.window_layouts.save();
+        }
+        safe_mode::finished();
+    }
+}
+
+/// The color at `pixel` of an image of `full_dimensions`, of which `image` may
+/// be a reduced copy.
+fn sample(
+    image: &image::DynamicImage,
+    pixel: (f64, f64),
+    full_dimensions: (u32, u32),
+) -> Option<[u8; 3]> {
+    let x = pixel.0 / full_dimensions.0 as f64 * image.width() as f64;
+    let y = pixel.1 / full_dimensions.1 as f64 * image.height() as f64;
+    if x < 0. || y < 0. || x >= image.width() as f64 || y >= image.height() as f64 {
+        return None;
     }
+    let [r, g, b, _] = image.get_pixel(x as u32, y as u32).0;
+    Some([r, g, b])
+}
+
+/// The scale around `position` of an image shown at `image_rect`, measured
+/// along the image's x axis.
+fn km_per_point(
+    projection: &ProjectionData,
+    body: &body::Body,
+    image_dimensions: (u32, u32),
+    image_rect: egui::Rect,
+    position: egui::Pos2,
+) -> Option<f64> {
+    let pixels_per_point = image_dimensions.0 as f64 / image_rect.width() as f64;
+    let relative = (position - image_rect.min) / image_rect.size();
+    let pixel = (
+        relative.x as f64 * image_dimensions.0 as f64,
+        relative.y as f64 * image_dimensions.1 as f64,
+    );
+    let a = projection.point_at_pixel(pixel, image_dimensions)?;
+    let b = projection.point_at_pixel((pixel.0 + 1., pixel.1), image_dimensions)?;
+    Some(body.distance(&a, &b) * pixels_per_point)
 }
 
-fn projection_ui(ui: &mut egui::Ui, projection: &mut ProjectionData, label: &str) {
+fn projection_ui(
+    ui: &mut egui::Ui,
+    projection: &mut ProjectionData,
+    longitudes: &longitudes::Longitudes,
+    unit: angles::AngleUnit,
+    label: &str,
+) {
     egui::ComboBox::new(label, label)
         .selected_text(projection.kind().to_string())
         .show_ui(ui, |ui| {
@@ -217,14 +2503,15 @@ fn projection_ui(ui: &mut egui::Ui, projection: &mut ProjectionData, label: &str
             let mut central_long = equirect_data.central_long();
             let mut true_scale_lat = equirect_data.true_scale_lat();
             ui.add(
-                egui::Slider::new(&mut central_long, -180.0..=180.)
-                    .suffix("°")
-                    .clamp_to_range(true)
+                longitudes
+                    .slider(&mut central_long)
+                    .angle(unit)
                     .text("Central longitude"),
             );
+            longitudes::snap_ui(ui, longitudes, &mut central_long);
             ui.add(
                 egui::Slider::new(&mut true_scale_lat, -90.0..=90.0)
-                    .suffix("°")
+                    .angle(unit)
                     .clamp_to_range(true)
                     .text("True scale latitude"),
             );
@@ -241,22 +2528,24 @@ fn projection_ui(ui: &mut egui::Ui, projection: &mut ProjectionData, label: &str
             let mut central_long = azimuth_data.central_long();
             ui.add(
                 egui::Slider::new(&mut center_lat, -90.0..=90.)
-                    .suffix("°")
+                    .angle(unit)
                     .clamp_to_range(true)
                     .text("Center latitude"),
             );
             ui.add(
-                egui::Slider::new(&mut center_long, -180.0..=180.)
-                    .suffix("°")
-                    .clamp_to_range(true)
+                longitudes
+                    .slider(&mut center_long)
+                    .angle(unit)
                     .text("Center longitude"),
             );
+            longitudes::snap_ui(ui, longitudes, &mut center_long);
             ui.add(
-                egui::Slider::new(&mut central_long, -180.0..=180.)
-                    .suffix("°")
-                    .clamp_to_range(true)
+                longitudes
+                    .slider(&mut central_long)
+                    .angle(unit)
                     .text("Central longitude"),
             );
+            longitudes::snap_ui(ui, longitudes, &mut central_long);
             *projection = ProjectionData::AzimuthalEquidistant(
                 submaptive::AzimuthalEquidistant::new()
                     .center((center_long, center_lat).try_into().unwrap())
@@ -264,5 +2553,441 @@ fn projection_ui(ui: &mut egui::Ui, projection: &mut ProjectionData, label: &str
                     .build(),
             );
         }
+        ProjectionData::Mercator(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            // The poles are infinitely far away.
+            ui.add(
+                egui::Slider::new(&mut data.max_lat, 60.0..=89.5)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Latitude cutoff"),
+            );
+        }
+        ProjectionData::TransverseMercator(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.origin_lat, -90.0..=90.)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Latitude of origin"),
+            );
+            ui.add(
+                egui::Slider::new(&mut data.scale_factor, 0.5..=1.5)
+                    .custom_parser(expressions::evaluate)
+                    .clamp_to_range(true)
+                    .text("Scale factor"),
+            );
+        }
+        ProjectionData::WebMercator(_) => {
+            ui.label(format!(
+                "Centered on Greenwich and cut off at ±{:.5}°, like web map tiles.",
+                projections::WebMercator::MAX_LAT
+            ));
+        }
+        ProjectionData::Mollweide(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::Robinson(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::WinkelTripel(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.standard_parallel, 0.0..=90.)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Standard parallel"),
+            );
+        }
+        ProjectionData::Sinusoidal(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::CylindricalEqualArea(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(
+                    &mut data.standard_parallel,
+                    0.0..=projections::CylindricalEqualArea::MAX_STANDARD_PARALLEL,
+                )
+                .angle(unit)
+                .clamp_to_range(true)
+                .text("Standard parallel"),
+            );
+            ui.horizontal(|ui| {
+                for (name, standard_parallel) in projections::CylindricalEqualArea::PRESETS {
+                    if ui
+                        .selectable_label(data.standard_parallel == standard_parallel, name)
+                        .clicked()
+                    {
+                        data.standard_parallel = standard_parallel;
+                    }
+                }
+            });
+        }
+        ProjectionData::LambertConformalConic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.origin_lat, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Latitude of origin"),
+            );
+            let max = projections::LambertConformalConic::MAX_STANDARD_PARALLEL;
+            for (standard_parallel, text) in data
+                .standard_parallels
+                .iter_mut()
+                .zip(["First standard parallel", "Second standard parallel"])
+            {
+                ui.add(
+                    egui::Slider::new(standard_parallel, -max..=max)
+                        .angle(unit)
+                        .clamp_to_range(true)
+                        .text(text),
+                );
+            }
+        }
+        ProjectionData::AlbersEqualArea(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            let max = projections::AlbersEqualArea::MAX_STANDARD_PARALLEL;
+            for (standard_parallel, text) in data
+                .standard_parallels
+                .iter_mut()
+                .zip(["First standard parallel", "Second standard parallel"])
+            {
+                ui.add(
+                    egui::Slider::new(standard_parallel, -max..=max)
+                        .angle(unit)
+                        .clamp_to_range(true)
+                        .text(text),
+                );
+            }
+        }
+        ProjectionData::EquidistantConic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            let max = projections::EquidistantConic::MAX_STANDARD_PARALLEL;
+            for (standard_parallel, text) in data
+                .standard_parallels
+                .iter_mut()
+                .zip(["First standard parallel", "Second standard parallel"])
+            {
+                ui.add(
+                    egui::Slider::new(standard_parallel, -max..=max)
+                        .angle(unit)
+                        .clamp_to_range(true)
+                        .text(text),
+                );
+            }
+        }
+        ProjectionData::Orthographic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Center longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.center_lat, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Center latitude"),
+            );
+        }
+        ProjectionData::Stereographic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Center longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.center_lat, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Center latitude"),
+            );
+            ui.horizontal(|ui| {
+                for (name, center_lat) in projections::Stereographic::PRESETS {
+                    if ui
+                        .selectable_label(data.center_lat == center_lat, name)
+                        .clicked()
+                    {
+                        data.center_lat = center_lat;
+                    }
+                }
+            });
+        }
+        ProjectionData::Gnomonic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Tangent point longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.center_lat, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Tangent point latitude"),
+            );
+            ui.add(
+                egui::Slider::new(
+                    &mut data.clip,
+                    projections::Gnomonic::MIN_CLIP..=projections::Gnomonic::MAX_CLIP,
+                )
+                .angle(unit)
+                .clamp_to_range(true)
+                .text("Clip angle"),
+            );
+        }
+        ProjectionData::LambertAzimuthalEqualArea(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Center longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.center_lat, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Center latitude"),
+            );
+        }
+        ProjectionData::Hammer(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::Aitoff(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::EckertIV(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::EckertVI(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::GoodeHomolosine(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.horizontal(|ui| {
+                for interruptions in projections::Interruptions::ALL {
+                    ui.selectable_value(
+                        &mut data.interruptions,
+                        interruptions,
+                        interruptions.name(),
+                    );
+                }
+            });
+        }
+        ProjectionData::Bonne(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.standard_parallel, -90.0..=90.0)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Standard parallel"),
+            );
+            ui.horizontal(|ui| {
+                for (name, standard_parallel) in projections::Bonne::PRESETS {
+                    if ui
+                        .selectable_label(data.standard_parallel == standard_parallel, name)
+                        .clicked()
+                    {
+                        data.standard_parallel = standard_parallel;
+                    }
+                }
+            });
+        }
+        ProjectionData::Werner(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::VanDerGrinten(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::Miller(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::Cassini(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::Polyconic(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+            ui.add(
+                egui::Slider::new(&mut data.origin_lat, -90.0..=90.)
+                    .angle(unit)
+                    .clamp_to_range(true)
+                    .text("Latitude of origin"),
+            );
+        }
+        ProjectionData::NaturalEarth(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::KavrayskiyVII(data) => {
+            ui.add(
+                longitudes
+                    .slider(&mut data.central_long)
+                    .angle(unit)
+                    .text("Central longitude"),
+            );
+            longitudes::snap_ui(ui, longitudes, &mut data.central_long);
+        }
+        ProjectionData::RubberSheet(_) => {
+            ui.label("Warped by the control points set under \"Georeferencing\".");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safe_mode_leaves_the_stored_settings_alone() {
+        // Next to the test executable, away from the user's own settings.
+        settings::init_portable(true);
+        let directory = settings::config_directory().unwrap();
+        let executable = std::env::current_exe().unwrap();
+        assert!(directory.starts_with(executable.parent().unwrap()));
+        let path = directory.join("settings.json");
+        let _ = std::fs::remove_file(&path);
+        let mut app = App::new(Default::default(), None, true);
+        app.settings.cache_limit_mb += 1;
+        app.save_settings(&egui::Context::default());
+        assert!(!path.exists());
+        assert!(app.saved_settings == app.settings);
     }
 }
@@ -0,0 +1,135 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Areas of the source image left out of the projection, such as legends,
+/// cartouches or the table the map was photographed on.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Mask {
+    /// Polygons in pixels of the full-resolution (corrected) source image.
+    pub polygons: Vec<Vec<(f64, f64)>>,
+}
+
+impl Mask {
+    /// Adds a corner to the polygon being drawn, or closes it if `pixel` is
+    /// within `tolerance` of its first corner.
+    pub fn add_corner(&mut self, draft: &mut Vec<(f64, f64)>, pixel: (f64, f64), tolerance: f64) {
+        let closes = draft
+            .first()
+            .is_some_and(|first| (first.0 - pixel.0).hypot(first.1 - pixel.1) <= tolerance);
+        if closes {
+            self.finish(draft);
+        } else {
+            draft.push(pixel);
+        }
+    }
+
+    pub fn finish(&mut self, draft: &mut Vec<(f64, f64)>) {
+        if draft.len() >= 3 {
+            self.polygons.push(std::mem::take(draft));
+        }
+        draft.clear();
+    }
+
+    /// Makes the masked areas transparent. The image may be a reduced copy of
+    /// the one of `full_dimensions` the polygons were drawn on.
+    pub fn apply(
+        &self,
+        image: image::DynamicImage,
+        full_dimensions: (u32, u32),
+    ) -> image::DynamicImage {
+        if self.polygons.is_empty() {
+            return image;
+        }
+        let mut image = image.to_rgba8();
+        let (width, height) = image.dimensions();
+        let scale = (
+            width as f64 / full_dimensions.0 as f64,
+            height as f64 / full_dimensions.1 as f64,
+        );
+        for polygon in &self.polygons {
+            let polygon: Vec<_> = polygon
+                .iter()
+                .map(|(x, y)| (x * scale.0, y * scale.1))
+                .collect();
+            // Fill between pairs of edge crossings of each row through pixel centers.
+            for y in 0..height {
+                let center = y as f64 + 0.5;
+                let mut crossings: Vec<f64> = polygon
+                    .iter()
+                    .zip(polygon.iter().cycle().skip(1))
+                    .filter(|(a, b)| (a.1 <= center) != (b.1 <= center))
+                    .map(|(a, b)| a.0 + (center - a.1) / (b.1 - a.1) * (b.0 - a.0))
+                    .collect();
+                crossings.sort_by(f64::total_cmp);
+                for span in crossings.chunks_exact(2) {
+                    let start = (span[0] - 0.5).ceil().max(0.) as u32;
+                    let end = ((span[1] - 0.5).floor() + 1.).clamp(0., width as f64) as u32;
+                    for x in start..end {
+                        image.get_pixel_mut(x, y).0[3] = 0;
+                    }
+                }
+            }
+        }
+        image::DynamicImage::ImageRgba8(image)
+    }
+}
+
+pub fn mask_ui(ui: &mut egui::Ui, mask: &mut Mask, draft: &mut Vec<(f64, f64)>) {
+    ui.collapsing("Masks", |ui| {
+        if mask.polygons.is_empty() && draft.is_empty() {
+            ui.label("Outline areas to leave out with the mask tool.");
+        }
+        let mut removed = None;
+        for (index, polygon) in mask.polygons.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!("Area {}: {} corners", index + 1, polygon.len()));
+                if ui.small_button("✖").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            mask.polygons.remove(index);
+        }
+        if !draft.is_empty() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(draft.len() >= 3, egui::Button::new("Close area"))
+                    .clicked()
+                {
+                    mask.finish(draft);
+                }
+                if ui.button("Discard").clicked() {
+                    draft.clear();
+                }
+            });
+        }
+    });
+}
+
+pub fn paint_mask(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    mask: &Mask,
+    draft: &[(f64, f64)],
+) {
+    let to_screen = |(x, y): &(f64, f64)| {
+        rect.min
+            + egui::vec2(
+                *x as f32 / image_dimensions.0 as f32 * rect.width(),
+                *y as f32 / image_dimensions.1 as f32 * rect.height(),
+            )
+    };
+    let color = egui::Color32::from_rgb(255, 60, 60);
+    for polygon in &mask.polygons {
+        let points = polygon.iter().map(to_screen).collect();
+        painter.add(egui::epaint::PathShape::closed_line(points, (2., color)));
+    }
+    let points: Vec<_> = draft.iter().map(to_screen).collect();
+    for point in &points {
+        painter.circle_filled(*point, 3., color);
+    }
+    painter.add(egui::epaint::PathShape::line(points, (1., color)));
+}
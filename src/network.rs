@@ -0,0 +1,511 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::Cache;
+use crate::private_files;
+use crate::settings::{self, Settings};
+
+/// How to reach the network, kept with the other settings.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Proxy {
+    /// Such as `http://proxy.example.com:3128` or `socks5://localhost:1080`,
+    /// or empty to connect directly.
+    pub url: String,
+    pub user: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Auth {
+    #[default]
+    None,
+    Basic {
+        user: String,
+        password: String,
+    },
+    Bearer {
+        token: String,
+    },
+}
+
+/// What is sent along with every request to one host.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Site {
+    /// Such as `tiles.example.com`, or with a port, `localhost:8080`.
+    pub host: String,
+    pub headers: Vec<(String, String)>,
+    pub auth: Auth,
+}
+
+/// Passwords, tokens and headers that may hold either, kept apart from the
+/// settings in the system's credential store: the Keychain on macOS, the
+/// Credential Manager on Windows and the Secret Service elsewhere. Where
+/// there is none, or it won't take them, as the Credential Manager won't
+/// past a few kilobytes, they are kept in `credentials.json` in the
+/// configuration directory instead, a file only the user may read.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Credentials {
+    pub proxy_password: String,
+    pub sites: Vec<Site>,
+}
+
+impl Credentials {
+    const SERVICE: &'static str = "submaptive";
+    const USER: &'static str = "credentials";
+
+    fn entry() -> Option<keyring::Entry> {
+        keyring::Entry::new(Self::SERVICE, Self::USER).ok()
+    }
+
+    /// The fallback file.
+    fn path() -> Option<PathBuf> {
+        settings::config_directory().map(|directory| directory.join("credentials.json"))
+    }
+
+    /// The stored credentials, or none if there are none (or they are
+    /// unreadable). Those in the fallback file are used if the credential
+    /// store has none, as when they were saved by an earlier version.
+    pub fn load() -> Self {
+        Self::entry()
+            .and_then(|entry| entry.get_password().ok())
+            .or_else(|| Self::path().and_then(|path| std::fs::read_to_string(path).ok()))
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Saves to the credential store and removes the fallback file, so that
+    /// no copy stays behind in it, or saves to the file if the store can't
+    /// be used.
+    pub fn save(&self) -> Result<(), String> {
+        let stored = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        if Self::entry().is_some_and(|entry| entry.set_password(&stored).is_ok()) {
+            if let Some(path) = Self::path().filter(|path| path.exists()) {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Could not remove {}: {}", path.display(), e))?;
+            }
+            return Ok(());
+        }
+        // Or the older credentials left there would be loaded instead.
+        if let Some(entry) = Self::entry() {
+            let _ = entry.delete_credential();
+        }
+        let path = Self::path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        private_files::write(&path, contents.as_bytes())
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+/// What requests are made with, set from the settings whenever they change.
+struct Network {
+    proxy: Proxy,
+    credentials: Credentials,
+    offline: bool,
+    /// Where downloads are kept, to be served again without asking the server.
+    cache: Cache,
+}
+
+static NETWORK: RwLock<Option<Network>> = RwLock::new(None);
+
+pub fn configure(settings: &Settings, credentials: &Credentials, cache: &Cache) {
+    *NETWORK.write().unwrap() = Some(Network {
+        proxy: settings.proxy.clone(),
+        credentials: credentials.clone(),
+        offline: settings.offline,
+        cache: cache.clone(),
+    });
+}
+
+const OFFLINE: &str = "Offline mode is on.";
+pub const OFFLINE_HINT: &str = "Only use what was downloaded before and is still in the cache.";
+
+fn offline() -> bool {
+    NETWORK
+        .read()
+        .unwrap()
+        .as_ref()
+        .is_some_and(|network| network.offline)
+}
+
+/// Escapes what would end the user or password part of a URL.
+fn escape(text: &str) -> String {
+    text.bytes()
+        .map(
+            |byte| match byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                true => (byte as char).to_string(),
+                false => format!("%{:02X}", byte),
+            },
+        )
+        .collect()
+}
+
+fn proxy_url(proxy: &Proxy, password: &str) -> String {
+    match (proxy.user.is_empty(), proxy.url.split_once("://")) {
+        (false, Some((scheme, rest))) => format!(
+            "{}://{}:{}@{}",
+            scheme,
+            escape(&proxy.user),
+            escape(password),
+            rest
+        ),
+        (false, None) => format!("{}:{}@{}", escape(&proxy.user), escape(password), proxy.url),
+        (true, _) => proxy.url.clone(),
+    }
+}
+
+/// The host and port of a URL, as in `example.com:8080`.
+fn authority(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    // Credentials in the URL itself aren't part of the host.
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    data.chunks(3)
+        .flat_map(|chunk| {
+            let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, &byte)| {
+                bits | ((byte as u32) << (16 - 8 * i))
+            });
+            (0..4).map(move |i| match i <= chunk.len() {
+                true => ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize] as char,
+                false => '=',
+            })
+        })
+        .collect()
+}
+
+/// An agent that goes through the configured proxy, unless offline mode is on.
+pub fn agent(timeout: Duration) -> Result<ureq::Agent, String> {
+    if offline() {
+        return Err(OFFLINE.to_string());
+    }
+    let mut builder = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .user_agent(&format!("submaptive-ui/{}", env!("CARGO_PKG_VERSION")));
+    if let Some(Network {
+        proxy, credentials, ..
+    }) = &*NETWORK.read().unwrap()
+    {
+        if !proxy.url.is_empty() {
+            let url = proxy_url(proxy, &credentials.proxy_password);
+            let proxy = ureq::Proxy::new(url)
+                .map_err(|e| format!("The proxy {} can't be used: {}", proxy.url, e))?;
+            builder = builder.proxy(proxy);
+        }
+    }
+    Ok(builder.build())
+}
+
+/// A request for `url`, with the headers and credentials configured for its
+/// host. Other hosts never see them.
+pub fn get(agent: &ureq::Agent, url: &str) -> ureq::Request {
+    for_site(agent.get(url), url)
+}
+
+fn for_site(mut request: ureq::Request, url: &str) -> ureq::Request {
+    let Some(Network { credentials, .. }) = &*NETWORK.read().unwrap() else {
+        return request;
+    };
+    let authority = authority(url);
+    let host = authority.split(':').next().unwrap_or_default();
+    for site in &credentials.sites {
+        let site_host = site.host.trim();
+        if !site_host.eq_ignore_ascii_case(authority) && !site_host.eq_ignore_ascii_case(host) {
+            continue;
+        }
+        for (name, value) in &site.headers {
+            if !name.trim().is_empty() {
+                request = request.set(name.trim(), value);
+            }
+        }
+        request = match &site.auth {
+            Auth::None => request,
+            Auth::Basic { user, password } => request.set(
+                "Authorization",
+                &format!(
+                    "Basic {}",
+                    base64(format!("{}:{}", user, password).as_bytes())
+                ),
+            ),
+            Auth::Bearer { token } => {
+                request.set("Authorization", &format!("Bearer {}", token.trim()))
+            }
+        };
+    }
+    request
+}
+
+/// Fetches parts of remote files, keeping each part in the cache under its
+/// URL, range and version. The version is the file's ETag or Last-Modified
+/// date, asked of the server once per file and session, so parts of a file
+/// that changed since they were cached are downloaded again. Offline, the
+/// version last seen is used. A file whose server gives no version is always
+/// downloaded while online.
+pub struct Downloader {
+    timeout: Duration,
+    /// Made on the first download that isn't in the cache.
+    agent: Option<ureq::Agent>,
+    /// The version of each file asked for so far, empty if it has none.
+    versions: HashMap<String, String>,
+}
+
+impl Downloader {
+    pub fn new(timeout: Duration) -> Self {
+        Downloader {
+            timeout,
+            agent: None,
+            versions: HashMap::new(),
+        }
+    }
+
+    fn agent(&mut self) -> Result<&ureq::Agent, String> {
+        match self.agent {
+            Some(ref agent) => Ok(agent),
+            None => Ok(self.agent.insert(agent(self.timeout)?)),
+        }
+    }
+
+    /// What the server currently says identifies the contents of `url`, or
+    /// offline what it said last.
+    fn version(&mut self, url: &str, cache: Option<&Cache>) -> Result<String, String> {
+        if let Some(version) = self.versions.get(url) {
+            return Ok(version.clone());
+        }
+        let key = Cache::key("download-version", &[url.as_bytes()]);
+        let version = match offline() {
+            true => cache
+                .and_then(|cache| cache.get(&key))
+                .and_then(|version| String::from_utf8(version).ok())
+                .ok_or_else(|| format!("{} {} hasn't been downloaded before.", OFFLINE, url))?,
+            false => {
+                // Servers refusing HEAD requests leave the file without a
+                // version; fetching its parts says whether it can be read.
+                let response = for_site(self.agent()?.head(url), url).call().ok();
+                let version = response.as_ref().map(version_of).unwrap_or_default();
+                if let Some(cache) = cache {
+                    let _ = cache.put(&key, version.as_bytes());
+                }
+                version
+            }
+        };
+        self.versions.insert(url.to_string(), version.clone());
+        Ok(version)
+    }
+
+    /// The bytes from `start` to `end` inclusive, and how long the whole file is.
+    pub fn range(&mut self, url: &str, start: u64, end: u64) -> Result<(Vec<u8>, u64), String> {
+        let cache = NETWORK
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|network| network.cache.clone());
+        let version = self.version(url, cache.as_ref())?;
+        let key = Cache::key(
+            "download",
+            &[
+                url.as_bytes(),
+                version.as_bytes(),
+                &start.to_le_bytes(),
+                &end.to_le_bytes(),
+            ],
+        );
+        // The length of the whole file comes first.
+        let cached = match offline() || !version.is_empty() {
+            true => cache.as_ref().and_then(|cache| cache.get(&key)),
+            false => None,
+        };
+        if let Some((length, data)) = cached
+            .as_ref()
+            .and_then(|entry| entry.split_first_chunk::<8>())
+        {
+            return Ok((data.to_vec(), u64::from_le_bytes(*length)));
+        }
+        if offline() {
+            return Err(format!(
+                "{} {} hasn't been downloaded before.",
+                OFFLINE, url
+            ));
+        }
+        let mut request = get(self.agent()?, url).set("Range", &format!("bytes={}-{}", start, end));
+        // The server sends all of the file instead of the part if it has
+        // changed since its version was asked for.
+        if !version.is_empty() {
+            request = request.set("If-Range", &version);
+        }
+        let response = request
+            .call()
+            .map_err(|e| format!("Could not fetch {}: {}", url, e))?;
+        if !version.is_empty() && response.status() != 206 && version != version_of(&response) {
+            self.versions.remove(url);
+            return Err(format!(
+                "{} has changed while it was being read. Open it again.",
+                url
+            ));
+        }
+        if response.status() != 206 {
+            return Err(format!(
+                "{} doesn't serve parts of files, so all of it would have to be downloaded.",
+                url
+            ));
+        }
+        // As in `bytes 0-262143/1234567`.
+        let length = response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit_once('/'))
+            .and_then(|(_, length)| length.trim().parse().ok())
+            .unwrap_or(u64::MAX);
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(
+            &mut std::io::Read::take(response.into_reader(), end + 1 - start),
+            &mut data,
+        )
+        .map_err(|e| format!("Could not fetch {}: {}", url, e))?;
+        if let Some(cache) = cache {
+            let mut entry = length.to_le_bytes().to_vec();
+            entry.extend(&data);
+            // Failing to cache only costs a download the next time.
+            let _ = cache.put(&key, &entry);
+        }
+        Ok((data, length))
+    }
+}
+
+/// The strong ETag of a response, or its Last-Modified date, which are what
+/// `If-Range` accepts.
+fn version_of(response: &ureq::Response) -> String {
+    response
+        .header("ETag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("Last-Modified"))
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn auth_ui(ui: &mut egui::Ui, auth: &mut Auth) {
+    let name = match auth {
+        Auth::None => "None",
+        Auth::Basic { .. } => "Basic",
+        Auth::Bearer { .. } => "Bearer token",
+    };
+    egui::ComboBox::from_label("Authentication")
+        .selected_text(name)
+        .show_ui(ui, |ui| {
+            let basic = Auth::Basic {
+                user: String::new(),
+                password: String::new(),
+            };
+            let bearer = Auth::Bearer {
+                token: String::new(),
+            };
+            for (choice, text) in [
+                (Auth::None, "None"),
+                (basic, "Basic"),
+                (bearer, "Bearer token"),
+            ] {
+                let selected = std::mem::discriminant(auth) == std::mem::discriminant(&choice);
+                if ui.selectable_label(selected, text).clicked() && !selected {
+                    *auth = choice;
+                }
+            }
+        });
+    match auth {
+        Auth::None => {}
+        Auth::Basic { user, password } => {
+            ui.horizontal(|ui| {
+                ui.label("User");
+                ui.text_edit_singleline(user);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Password");
+                ui.add(egui::TextEdit::singleline(password).password(true));
+            });
+        }
+        Auth::Bearer { token } => {
+            ui.horizontal(|ui| {
+                ui.label("Token");
+                ui.add(egui::TextEdit::singleline(token).password(true));
+            });
+        }
+    }
+}
+
+/// Offline mode, the proxy, and the headers and credentials sent to each host.
+pub fn network_ui(ui: &mut egui::Ui, settings: &mut Settings, credentials: &mut Credentials) {
+    ui.checkbox(&mut settings.offline, "Offline")
+        .on_hover_text(OFFLINE_HINT);
+    let proxy = &mut settings.proxy;
+    ui.horizontal(|ui| {
+        ui.label("Proxy");
+        ui.add(
+            egui::TextEdit::singleline(&mut proxy.url).hint_text("http://proxy.example.com:3128"),
+        );
+    });
+    if !proxy.url.is_empty() {
+        ui.horizontal(|ui| {
+            ui.label("Proxy user");
+            ui.text_edit_singleline(&mut proxy.user);
+        });
+        if !proxy.user.is_empty() {
+            ui.horizontal(|ui| {
+                ui.label("Proxy password");
+                ui.add(egui::TextEdit::singleline(&mut credentials.proxy_password).password(true));
+            });
+        }
+    }
+    let mut removed = None;
+    for (index, site) in credentials.sites.iter_mut().enumerate() {
+        ui.push_id(index, |ui| {
+            ui.group(|ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Host");
+                    ui.add(
+                        egui::TextEdit::singleline(&mut site.host).hint_text("tiles.example.com"),
+                    );
+                    if ui.button("Remove").clicked() {
+                        removed = Some(index);
+                    }
+                });
+                auth_ui(ui, &mut site.auth);
+                let mut removed_header = None;
+                for (index, (name, value)) in site.headers.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(name)
+                                .hint_text("Header")
+                                .desired_width(120.),
+                        );
+                        ui.add(egui::TextEdit::singleline(value).hint_text("Value"));
+                        if ui.small_button("✖").clicked() {
+                            removed_header = Some(index);
+                        }
+                    });
+                }
+                if let Some(index) = removed_header {
+                    site.headers.remove(index);
+                }
+                if ui.button("Add header").clicked() {
+                    site.headers.push(Default::default());
+                }
+            });
+        });
+    }
+    if let Some(index) = removed {
+        credentials.sites.remove(index);
+    }
+    if ui.button("Add host").clicked() {
+        credentials.sites.push(Default::default());
+    }
+    ui.weak("Credentials and headers are kept in the system's credential store, or else in a file of their own that only you may read, and are only sent to their host.");
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// How long a message stays on screen before it is only in the log.
+const TOAST_DURATION: Duration = Duration::from_secs(6);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn color(&self, ui: &egui::Ui) -> egui::Color32 {
+        match self {
+            Level::Info => ui.visuals().text_color(),
+            Level::Warning => ui.visuals().warn_fg_color,
+            Level::Error => ui.visuals().error_fg_color,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+struct Entry {
+    level: Level,
+    message: String,
+    time: Instant,
+}
+
+/// Everything that was reported to the user, shown briefly as a toast and kept
+/// in the log panel.
+pub struct Log {
+    entries: Vec<Entry>,
+    started: Instant,
+}
+
+impl Default for Log {
+    fn default() -> Self {
+        Log {
+            entries: Vec::new(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Log {
+    pub fn add(&mut self, level: Level, message: impl Into<String>) {
+        self.entries.push(Entry {
+            level,
+            message: message.into(),
+            time: Instant::now(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.add(Level::Info, message);
+    }
+
+    pub fn warning(&mut self, message: impl Into<String>) {
+        self.add(Level::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.add(Level::Error, message);
+    }
+
+    /// Minutes and seconds since the start, as the log shows them.
+    fn elapsed(&self, entry: &Entry) -> String {
+        let elapsed = entry.time.duration_since(self.started).as_secs();
+        format!("{:02}:{:02}", elapsed / 60, elapsed % 60)
+    }
+
+    /// The whole log as plain text, one message to a line.
+    pub fn text(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{} {}: {}\n",
+                    self.elapsed(entry),
+                    entry.level.name(),
+                    entry.message
+                )
+            })
+            .collect()
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.level == Level::Error)
+            .count()
+    }
+}
+
+/// Shows the recent messages in the bottom right corner, above the status bar.
+pub fn show_toasts(ctx: &egui::Context, log: &Log) {
+    let now = Instant::now();
+    let recent: Vec<_> = log
+        .entries
+        .iter()
+        .filter(|entry| now.duration_since(entry.time) < TOAST_DURATION)
+        .collect();
+    let Some(oldest) = recent.first() else {
+        return;
+    };
+    egui::Area::new("Toasts")
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10., -40.))
+        .interactable(false)
+        .show(ctx, |ui| {
+            for entry in &recent {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.colored_label(entry.level.color(ui), &entry.message);
+                });
+            }
+        });
+    ctx.request_repaint_after(TOAST_DURATION.saturating_sub(now.duration_since(oldest.time)));
+}
+
+pub fn log_ui(ui: &mut egui::Ui, log: &mut Log) {
+    if ui.button("Clear").clicked() {
+        log.entries.clear();
+    }
+    for entry in &log.entries {
+        ui.horizontal(|ui| {
+            ui.weak(log.elapsed(entry));
+            ui.colored_label(entry.level.color(ui), &entry.message);
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::cache::Cache;
+
+/// Overviews stop once the next level would be smaller than this.
+const SMALLEST_OVERVIEW: u32 = 256;
+
+/// Reduced-resolution copies of a source image, each half the size of the
+/// previous one, for previews and draft projections.
+#[derive(Clone, Default)]
+pub struct Overviews {
+    /// From the largest to the smallest level, not including the full image.
+    levels: Vec<image::DynamicImage>,
+    /// Whether the levels came from the file (or its `.ovr`) rather than being built.
+    pub from_file: bool,
+}
+
+impl Overviews {
+    /// Uses the overviews stored with the image if there are any, and builds
+    /// them otherwise. Built overviews are kept in the cache under the
+    /// source's content hash, so they only have to be built once.
+    pub fn for_image(
+        path: &Path,
+        image: &image::DynamicImage,
+        cache: &Cache,
+        source_hash: Option<&str>,
+    ) -> Self {
+        let key = source_hash.map(|hash| Cache::key("overviews", &[hash.as_bytes()]));
+        Self::read(path)
+            .filter(|overviews| {
+                // Other pages of a multi-page TIFF aren't necessarily overviews.
+                overviews
+                    .levels
+                    .iter()
+                    .all(|level| level.width() < image.width() && level.height() < image.height())
+            })
+            .map(|mut overviews| {
+                overviews
+                    .levels
+                    .sort_by_key(|level| std::cmp::Reverse(level.width()));
+                overviews
+            })
+            .or_else(|| {
+                let data = cache.get(key.as_ref()?)?;
+                Some(Overviews {
+                    levels: read_tiff_pages(std::io::Cursor::new(data), 0)?,
+                    from_file: false,
+                })
+            })
+            .unwrap_or_else(|| {
+                let overviews = Self::build(image);
+                if let Some(key) = &key {
+                    let mut data = Vec::new();
+                    if overviews
+                        .write_tiff(std::io::Cursor::new(&mut data))
+                        .is_ok()
+                    {
+                        // Failing to cache only costs time on the next load.
+                        let _ = cache.put(key, &data);
+                    }
+                }
+                overviews
+            })
+    }
+
+    pub fn build(image: &image::DynamicImage) -> Self {
+        let mut levels = Vec::new();
+        let (mut width, mut height) = (image.width(), image.height());
+        while width.max(height) / 2 >= SMALLEST_OVERVIEW {
+            (width, height) = ((width / 2).max(1), (height / 2).max(1));
+            let previous = levels.last().unwrap_or(image);
+            let level = previous.resize_exact(width, height, image::imageops::FilterType::Triangle);
+            levels.push(level);
+        }
+        Overviews {
+            levels,
+            from_file: false,
+        }
+    }
+
+    /// GDAL keeps external overviews of `map.tif` in `map.tif.ovr`.
+    pub fn ovr_path(image_path: &Path) -> PathBuf {
+        let mut file_name = image_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".ovr");
+        image_path.with_file_name(file_name)
+    }
+
+    /// Reads the reduced-resolution pages of a TIFF: those following the full
+    /// image in the file itself, or all pages of an external `.ovr` file.
+    fn read(image_path: &Path) -> Option<Self> {
+        let ovr_path = Self::ovr_path(image_path);
+        let levels = if ovr_path.exists() {
+            read_tiff_pages(open(&ovr_path)?, 0)?
+        } else {
+            read_tiff_pages(open(image_path)?, 1)?
+        };
+        if levels.is_empty() {
+            return None;
+        }
+        Some(Overviews {
+            levels,
+            from_file: true,
+        })
+    }
+
+    /// Writes the levels as the pages of an external `.ovr` TIFF.
+    pub fn save_ovr(&self, image_path: &Path) -> Result<(), String> {
+        let path = Self::ovr_path(image_path);
+        let file = std::fs::File::create(&path)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        self.write_tiff(std::io::BufWriter::new(file))
+    }
+
+    fn write_tiff(&self, writer: impl Write + Seek) -> Result<(), String> {
+        let mut encoder = tiff::encoder::TiffEncoder::new(writer).map_err(|e| e.to_string())?;
+        for level in &self.levels {
+            let rgba = level.to_rgba8();
+            encoder
+                .write_image::<tiff::encoder::colortype::RGBA8>(
+                    rgba.width(),
+                    rgba.height(),
+                    rgba.as_raw(),
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// The smallest level that is at least `side` pixels along its longer side.
+    pub fn at_least(&self, side: u32) -> Option<&image::DynamicImage> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|level| level.width().max(level.height()) >= side)
+    }
+
+    /// The largest level that fits within `side` pixels along its longer side.
+    pub fn fitting(&self, side: u32) -> Option<&image::DynamicImage> {
+        self.levels
+            .iter()
+            .find(|level| level.width().max(level.height()) <= side)
+    }
+}
+
+fn open(path: &Path) -> Option<std::io::BufReader<std::fs::File>> {
+    std::fs::File::open(path).ok().map(std::io::BufReader::new)
+}
+
+fn read_tiff_pages(reader: impl Read + Seek, skip: usize) -> Option<Vec<image::DynamicImage>> {
+    let mut decoder = tiff::decoder::Decoder::new(reader).ok()?;
+    let mut pages = Vec::new();
+    let mut index = 0;
+    loop {
+        if index >= skip {
+            pages.push(read_tiff_page(&mut decoder)?);
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().ok()?;
+        index += 1;
+    }
+    Some(pages)
+}
+
+fn read_tiff_page<R: std::io::Read + std::io::Seek>(
+    decoder: &mut tiff::decoder::Decoder<R>,
+) -> Option<image::DynamicImage> {
+    use tiff::decoder::DecodingResult;
+    use tiff::ColorType;
+    let (width, height) = decoder.dimensions().ok()?;
+    let color_type = decoder.colortype().ok()?;
+    match (color_type, decoder.read_image().ok()?) {
+        (ColorType::Gray(8), DecodingResult::U8(data)) => {
+            image::GrayImage::from_raw(width, height, data).map(image::DynamicImage::ImageLuma8)
+        }
+        (ColorType::RGB(8), DecodingResult::U8(data)) => {
+            image::RgbImage::from_raw(width, height, data).map(image::DynamicImage::ImageRgb8)
+        }
+        (ColorType::RGBA(8), DecodingResult::U8(data)) => {
+            image::RgbaImage::from_raw(width, height, data).map(image::DynamicImage::ImageRgba8)
+        }
+        (ColorType::Gray(16), DecodingResult::U16(data)) => {
+            image::ImageBuffer::from_raw(width, height, data).map(image::DynamicImage::ImageLuma16)
+        }
+        (ColorType::RGB(16), DecodingResult::U16(data)) => {
+            image::ImageBuffer::from_raw(width, height, data).map(image::DynamicImage::ImageRgb16)
+        }
+        (ColorType::RGBA(16), DecodingResult::U16(data)) => {
+            image::ImageBuffer::from_raw(width, height, data).map(image::DynamicImage::ImageRgba16)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,143 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+/// Okabe and Ito's palette, whose colors stay apart with any common color
+/// vision deficiency. Used for overlays that need telling apart.
+pub const CATEGORICAL: [[u8; 3]; 8] = [
+    [230, 159, 0],
+    [86, 180, 233],
+    [0, 158, 115],
+    [240, 228, 66],
+    [0, 114, 178],
+    [213, 94, 0],
+    [204, 121, 167],
+    [0, 0, 0],
+];
+
+/// The `index`th overlay color, repeating after the palette runs out.
+pub fn categorical(index: usize) -> [u8; 3] {
+    CATEGORICAL[index % CATEGORICAL.len()]
+}
+
+/// Color ramps for continuous values, such as distortion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ramp {
+    #[default]
+    Viridis,
+    Cividis,
+    Magma,
+    RedBlue,
+    RedYellowGreen,
+    Rainbow,
+}
+
+impl Ramp {
+    pub const ALL: [Ramp; 6] = [
+        Ramp::Viridis,
+        Ramp::Cividis,
+        Ramp::Magma,
+        Ramp::RedBlue,
+        Ramp::RedYellowGreen,
+        Ramp::Rainbow,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ramp::Viridis => "Viridis",
+            Ramp::Cividis => "Cividis",
+            Ramp::Magma => "Magma",
+            Ramp::RedBlue => "Red–blue",
+            Ramp::RedYellowGreen => "Red–yellow–green",
+            Ramp::Rainbow => "Rainbow",
+        }
+    }
+
+    /// Whether the ramp stays readable with red-green color blindness:
+    /// lightness changes steadily and no two ends differ only in red and green.
+    pub fn colorblind_safe(&self) -> bool {
+        !matches!(self, Ramp::RedYellowGreen | Ramp::Rainbow)
+    }
+
+    fn stops(&self) -> &'static [u32] {
+        match self {
+            Ramp::Viridis => &[
+                0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e, 0x1f9e89, 0x35b779, 0x6ece58,
+                0xb5de2b, 0xfde725,
+            ],
+            Ramp::Cividis => &[
+                0x00204d, 0x00336f, 0x39486b, 0x575d6d, 0x707173, 0x8a8779, 0xa69d75, 0xc4b56c,
+                0xe4cf5b, 0xffea46,
+            ],
+            Ramp::Magma => &[
+                0x000004, 0x180f3d, 0x440f76, 0x721f81, 0x9e2f7f, 0xcd4071, 0xf1605d, 0xfd9668,
+                0xfeca8d, 0xfcfdbf,
+            ],
+            Ramp::RedBlue => &[
+                0x67001f, 0xb2182b, 0xd6604d, 0xf4a582, 0xfddbc7, 0xf7f7f7, 0xd1e5f0, 0x92c5de,
+                0x4393c3, 0x2166ac, 0x053061,
+            ],
+            Ramp::RedYellowGreen => &[
+                0xa50026, 0xd73027, 0xf46d43, 0xfdae61, 0xfee08b, 0xffffbf, 0xd9ef8b, 0xa6d96a,
+                0x66bd63, 0x1a9850, 0x006837,
+            ],
+            Ramp::Rainbow => &[
+                0x00007f, 0x0000ff, 0x007fff, 0x00ffff, 0x7fff7f, 0xffff00, 0xff7f00, 0xff0000,
+                0x7f0000,
+            ],
+        }
+    }
+
+    /// The color at `t`, from 0 at the start of the ramp to 1 at its end.
+    pub fn color_at(&self, t: f64) -> egui::Color32 {
+        let stops = self.stops();
+        let position = t.clamp(0., 1.) * (stops.len() - 1) as f64;
+        let index = (position as usize).min(stops.len() - 2);
+        let fraction = position - index as f64;
+        let channel = |stop: u32, shift: u32| ((stop >> shift) & 0xff) as f64;
+        let mix = |shift| {
+            (channel(stops[index], shift) * (1. - fraction)
+                + channel(stops[index + 1], shift) * fraction)
+                .round() as u8
+        };
+        egui::Color32::from_rgb(mix(16), mix(8), mix(0))
+    }
+}
+
+/// A strip showing the ramp from start to end.
+pub fn paint_ramp(painter: &egui::Painter, rect: egui::Rect, ramp: Ramp) {
+    const STEPS: usize = 32;
+    let mut mesh = egui::Mesh::default();
+    for step in 0..STEPS {
+        let x = |step: usize| rect.left() + rect.width() * step as f32 / STEPS as f32;
+        let band = egui::Rect::from_x_y_ranges(x(step)..=x(step + 1), rect.y_range());
+        mesh.add_colored_rect(band, ramp.color_at((step as f64 + 0.5) / STEPS as f64));
+    }
+    painter.add(mesh);
+}
+
+fn ramp_entry(ui: &mut egui::Ui, ramp: Ramp) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(64., 12.), egui::Sense::hover());
+    paint_ramp(ui.painter(), rect, ramp);
+    if !ramp.colorblind_safe() {
+        ui.label("⚠")
+            .on_hover_text("Hard to read with red-green color blindness.");
+    }
+}
+
+/// A picker for ramps, each shown with a preview and marked if it's not
+/// colorblind-safe.
+pub fn ramp_ui(ui: &mut egui::Ui, ramp: &mut Ramp, label: &str) {
+    ui.horizontal(|ui| {
+        egui::ComboBox::new(label, label)
+            .selected_text(ramp.name())
+            .show_ui(ui, |ui| {
+                for candidate in Ramp::ALL {
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(ramp, candidate, candidate.name());
+                        ramp_entry(ui, candidate);
+                    });
+                }
+            });
+        ramp_entry(ui, *ramp);
+    });
+}
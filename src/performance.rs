@@ -0,0 +1,118 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::jobs;
+
+/// How many of the most recent frames the figures are taken over.
+const FRAMES: usize = 120;
+
+/// Figures for finding out why the app feels slow, shown over the map.
+#[derive(Default)]
+pub struct Performance {
+    pub open: bool,
+    /// The time spent on each of the most recent frames, in seconds.
+    frame_times: VecDeque<f32>,
+    /// The number of busy worker threads at each of the most recent frames.
+    busy_workers: VecDeque<usize>,
+    /// How long the last projection took, and whether it was a draft.
+    pub last_reprojection: Option<(Duration, bool)>,
+}
+
+impl Performance {
+    /// Notes a new frame, given the time the previous one took as eframe
+    /// measures it. Nothing is kept while the overlay is hidden.
+    pub fn record(&mut self, cpu_usage: Option<f32>) {
+        if !self.open {
+            self.frame_times.clear();
+            self.busy_workers.clear();
+            return;
+        }
+        if let Some(seconds) = cpu_usage {
+            self.frame_times.push_back(seconds);
+        }
+        self.busy_workers.push_back(jobs::busy_workers());
+        if self.frame_times.len() > FRAMES {
+            self.frame_times.pop_front();
+        }
+        if self.busy_workers.len() > FRAMES {
+            self.busy_workers.pop_front();
+        }
+    }
+}
+
+/// The memory taken by egui's textures, in bytes, and how many there are.
+/// Textures the layer compositor keeps on the GPU itself aren't counted.
+fn texture_memory(ctx: &egui::Context) -> (usize, usize) {
+    let manager = ctx.tex_manager();
+    let manager = manager.read();
+    let bytes = manager.allocated().map(|(_, meta)| meta.bytes_used()).sum();
+    (bytes, manager.num_allocated())
+}
+
+pub fn overlay(ctx: &egui::Context, performance: &Performance) {
+    if !performance.open {
+        return;
+    }
+    // Worker threads report back only now and then.
+    ctx.request_repaint_after(Duration::from_millis(500));
+    let milliseconds = |seconds: f32| format!("{:.1} ms", seconds * 1000.);
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+    egui::Area::new("Performance")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10., 40.))
+        .order(egui::Order::Foreground)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                egui::Grid::new("Performance").show(ui, |ui| {
+                    let times = &performance.frame_times;
+                    ui.label("Frame time");
+                    match times.is_empty() {
+                        true => ui.label("–"),
+                        false => {
+                            let mean = times.iter().sum::<f32>() / times.len() as f32;
+                            let worst = times.iter().copied().fold(0., f32::max);
+                            ui.label(format!(
+                                "{} on average, {} at worst",
+                                milliseconds(mean),
+                                milliseconds(worst)
+                            ))
+                        }
+                    };
+                    ui.end_row();
+                    let (bytes, textures) = texture_memory(ctx);
+                    ui.label("Texture memory");
+                    ui.label(format!(
+                        "{:.1} MB in {} textures",
+                        bytes as f64 / 1e6,
+                        textures
+                    ));
+                    ui.end_row();
+                    ui.label("Last projection");
+                    match performance.last_reprojection {
+                        Some((duration, draft)) => ui.label(format!(
+                            "{}{}",
+                            milliseconds(duration.as_secs_f32()),
+                            match draft {
+                                true => " (draft)",
+                                false => "",
+                            }
+                        )),
+                        None => ui.label("–"),
+                    };
+                    ui.end_row();
+                    let busy = &performance.busy_workers;
+                    let mean = busy.iter().sum::<usize>() as f32 / busy.len().max(1) as f32;
+                    ui.label("Worker threads");
+                    ui.label(format!(
+                        "{} busy, {:.0}% of {} cores",
+                        jobs::busy_workers(),
+                        (mean / cores as f32 * 100.).min(100.),
+                        cores
+                    ));
+                    ui.end_row();
+                });
+            });
+        });
+}
@@ -0,0 +1,170 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{AngleUnit, AngleWidget};
+
+/// How the missing caps of a panorama are filled in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FillMethod {
+    /// Blurs the edge of the cap more and more towards its average color at the pole.
+    #[default]
+    Smooth,
+    /// Mirrors the band next to the cap into it, fading to the average at the pole.
+    Clone,
+}
+
+/// Fills in the zenith and nadir of equirectangular panoramas, which are often
+/// missing (the tripod hole), so that little-planet projections don't show a hole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolarFill {
+    /// How far the caps reach from the poles, in degrees of latitude. Zero leaves a cap alone.
+    pub zenith: f64,
+    pub nadir: f64,
+    pub method: FillMethod,
+}
+
+/// A row counts as missing once this fraction of it is transparent.
+const MISSING_FRACTION: f64 = 0.5;
+
+impl PolarFill {
+    pub fn is_active(&self) -> bool {
+        self.zenith > 0. || self.nadir > 0.
+    }
+
+    /// Sets the caps to the rows at the top and bottom of the image that are mostly transparent.
+    pub fn detect(&mut self, image: &image::DynamicImage) {
+        let image = image.to_rgba8();
+        let (width, height) = image.dimensions();
+        let missing = |y: u32| {
+            let transparent = (0..width)
+                .filter(|&x| image.get_pixel(x, y).0[3] < 128)
+                .count();
+            transparent as f64 >= MISSING_FRACTION * width as f64
+        };
+        let top = (0..height).take_while(|&y| missing(y)).count();
+        let bottom = (0..height).rev().take_while(|&y| missing(y)).count();
+        let degrees = |rows: usize| (rows as f64 / height as f64 * 180.).min(90.);
+        self.zenith = degrees(top);
+        self.nadir = degrees(bottom);
+    }
+
+    pub fn apply(&self, image: image::DynamicImage) -> image::DynamicImage {
+        if !self.is_active() {
+            return image;
+        }
+        let mut image = image.to_rgba8();
+        let height = image.height() as f64;
+        let rows =
+            |degrees: f64| ((degrees / 180. * height).round() as u32).min(image.height() / 2);
+        let (zenith, nadir) = (rows(self.zenith), rows(self.nadir));
+        if zenith > 0 {
+            fill_cap(&mut image, zenith, self.method);
+        }
+        if nadir > 0 {
+            image::imageops::flip_vertical_in_place(&mut image);
+            fill_cap(&mut image, nadir, self.method);
+            image::imageops::flip_vertical_in_place(&mut image);
+        }
+        image::DynamicImage::ImageRgba8(image)
+    }
+}
+
+/// Fills the top `rows` rows from the ones below them.
+fn fill_cap(image: &mut image::RgbaImage, rows: u32, method: FillMethod) {
+    let (width, height) = image.dimensions();
+    if rows >= height {
+        return;
+    }
+    let edge: Vec<[f64; 4]> = (0..width)
+        .map(|x| image.get_pixel(x, rows).0.map(|channel| channel as f64))
+        .collect();
+    let mut pole = [0.; 4];
+    for pixel in &edge {
+        for (sum, channel) in pole.iter_mut().zip(pixel) {
+            *sum += channel / width as f64;
+        }
+    }
+    pole[3] = 255.;
+    for y in 0..rows {
+        // 0 at the edge of the cap, 1 at the pole.
+        let t = (rows - y) as f64 / rows as f64;
+        let band: Vec<[f64; 4]> = match method {
+            FillMethod::Smooth => edge.clone(),
+            FillMethod::Clone => {
+                let mirrored = (2 * rows - y).min(height - 1);
+                (0..width)
+                    .map(|x| image.get_pixel(x, mirrored).0.map(|channel| channel as f64))
+                    .collect()
+            }
+        };
+        // Rows near the pole span little of the sphere, so they are blurred by
+        // more columns; the whole row at the pole is one point.
+        let radius = (t * t * width as f64 / 2.) as usize;
+        let blurred = blur_wrapping(&band, radius);
+        for (x, pixel) in blurred.iter().enumerate() {
+            let value = std::array::from_fn(|i| {
+                let channel = pixel[i] * (1. - t) + pole[i] * t;
+                channel.round().clamp(0., 255.) as u8
+            });
+            image.put_pixel(x as u32, y, image::Rgba(value));
+        }
+    }
+}
+
+/// A box blur of a row that wraps around, as rows of a panorama do.
+fn blur_wrapping(row: &[[f64; 4]], radius: usize) -> Vec<[f64; 4]> {
+    let width = row.len();
+    let radius = radius.min(width / 2);
+    if radius == 0 {
+        return row.to_vec();
+    }
+    // Prefix sums over three copies of the row, so that every window lies within them.
+    let mut prefix = vec![[0.; 4]; 3 * width + 1];
+    for i in 0..3 * width {
+        prefix[i + 1] = std::array::from_fn(|c| prefix[i][c] + row[i % width][c]);
+    }
+    let count = (2 * radius + 1) as f64;
+    (0..width)
+        .map(|x| {
+            let start = x + width - radius;
+            let end = start + 2 * radius + 1;
+            std::array::from_fn(|c| (prefix[end][c] - prefix[start][c]) / count)
+        })
+        .collect()
+}
+
+/// `source` gives the (keyed) source image for detecting the caps.
+pub fn polar_fill_ui(
+    ui: &mut egui::Ui,
+    fill: &mut PolarFill,
+    unit: AngleUnit,
+    source: impl FnOnce() -> Option<image::DynamicImage>,
+) {
+    ui.collapsing("Polar caps", |ui| {
+        ui.label("Fills in the missing zenith and nadir of equirectangular panoramas.");
+        ui.add(
+            egui::Slider::new(&mut fill.zenith, 0.0..=45.)
+                .angle(unit)
+                .text("Zenith cap"),
+        );
+        ui.add(
+            egui::Slider::new(&mut fill.nadir, 0.0..=45.)
+                .angle(unit)
+                .text("Nadir cap"),
+        );
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut fill.method, FillMethod::Smooth, "Smooth");
+            ui.selectable_value(&mut fill.method, FillMethod::Clone, "Clone");
+        });
+        if ui
+            .button("Detect")
+            .on_hover_text("Finds transparent or no-data rows at the top and bottom.")
+            .clicked()
+        {
+            if let Some(source) = source() {
+                fill.detect(&source);
+            }
+        }
+    });
+}
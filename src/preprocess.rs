@@ -0,0 +1,182 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::cache::Cache;
+
+/// Corrections applied to the source image before it is reprojected, for maps
+/// that were photographed rather than scanned.
+#[derive(Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Preprocessing {
+    /// Radial distortion coefficients of the Brown model, relative to the half diagonal.
+    pub k1: f64,
+    pub k2: f64,
+    /// Positive values widen the top edge, negative values the bottom edge.
+    pub keystone_vertical: f64,
+    /// Positive values heighten the left edge, negative values the right edge.
+    pub keystone_horizontal: f64,
+}
+
+impl Preprocessing {
+    pub fn is_identity(&self) -> bool {
+        *self == Default::default()
+    }
+
+    /// Like `apply`, but reuses a corrected copy of the same source from the cache.
+    pub fn apply_cached(
+        &self,
+        image: &image::DynamicImage,
+        cache: &Cache,
+        source_hash: Option<&str>,
+    ) -> image::DynamicImage {
+        let Some(source_hash) = source_hash.filter(|_| !self.is_identity()) else {
+            return self.apply(image);
+        };
+        let settings = serde_json::to_vec(self).unwrap_or_default();
+        let size = format!("{}x{}", image.width(), image.height());
+        let key = Cache::key(
+            "corrected",
+            &[source_hash.as_bytes(), &settings, size.as_bytes()],
+        );
+        let cached = cache.get(&key).and_then(|data| {
+            image::RgbaImage::from_raw(image.width(), image.height(), data)
+                .map(image::DynamicImage::ImageRgba8)
+        });
+        cached.unwrap_or_else(|| {
+            let corrected = self.apply(image);
+            // Failing to cache only costs time on the next projection.
+            let _ = cache.put(&key, corrected.as_bytes());
+            corrected
+        })
+    }
+
+    pub fn apply(&self, image: &image::DynamicImage) -> image::DynamicImage {
+        if self.is_identity() {
+            return image.clone();
+        }
+        let source = image.to_rgba8();
+        let (width, height) = source.dimensions();
+        let (w, h) = (width as f64, height as f64);
+        let homography = self.keystone_homography(w, h);
+        let half_diagonal = (w * w + h * h).sqrt() / 2.;
+        let corrected = image::RgbaImage::from_fn(width, height, |x, y| {
+            let (u, v) = ((x as f64 + 0.5) / w, (y as f64 + 0.5) / h);
+            let (px, py) = homography.apply(u, v);
+            let (dx, dy) = ((px - w / 2.) / half_diagonal, (py - h / 2.) / half_diagonal);
+            let r2 = dx * dx + dy * dy;
+            let factor = 1. + self.k1 * r2 + self.k2 * r2 * r2;
+            sample_bilinear(
+                &source,
+                w / 2. + dx * factor * half_diagonal - 0.5,
+                h / 2. + dy * factor * half_diagonal - 0.5,
+            )
+        });
+        image::DynamicImage::ImageRgba8(corrected)
+    }
+
+    /// Maps the unit square of the corrected image onto the (trapezoidal) area
+    /// of the photograph that shows the flat map.
+    fn keystone_homography(&self, w: f64, h: f64) -> Homography {
+        let top = self.keystone_vertical.max(0.) * w / 2.;
+        let bottom = (-self.keystone_vertical).max(0.) * w / 2.;
+        let left = self.keystone_horizontal.max(0.) * h / 2.;
+        let right = (-self.keystone_horizontal).max(0.) * h / 2.;
+        Homography::from_unit_square([
+            (top, left),
+            (w - top, right),
+            (w - bottom, h - right),
+            (bottom, h - left),
+        ])
+    }
+}
+
+/// A projective transformation of the plane.
+struct Homography([f64; 9]);
+
+impl Homography {
+    /// The mapping sending (0, 0), (1, 0), (1, 1) and (0, 1) to the given corners
+    /// in that order (Heckbert's square-to-quad construction).
+    fn from_unit_square(corners: [(f64, f64); 4]) -> Self {
+        let [(x0, y0), (x1, y1), (x2, y2), (x3, y3)] = corners;
+        let (sx, sy) = (x0 - x1 + x2 - x3, y0 - y1 + y2 - y3);
+        if sx.abs() < 1e-12 && sy.abs() < 1e-12 {
+            return Homography([x1 - x0, x2 - x1, x0, y1 - y0, y2 - y1, y0, 0., 0., 1.]);
+        }
+        let (dx1, dx2, dy1, dy2) = (x1 - x2, x3 - x2, y1 - y2, y3 - y2);
+        let denominator = dx1 * dy2 - dx2 * dy1;
+        let g = (sx * dy2 - dx2 * sy) / denominator;
+        let h = (dx1 * sy - sx * dy1) / denominator;
+        Homography([
+            x1 - x0 + g * x1,
+            x3 - x0 + h * x3,
+            x0,
+            y1 - y0 + g * y1,
+            y3 - y0 + h * y3,
+            y0,
+            g,
+            h,
+            1.,
+        ])
+    }
+
+    fn apply(&self, u: f64, v: f64) -> (f64, f64) {
+        let m = &self.0;
+        let w = m[6] * u + m[7] * v + m[8];
+        (
+            (m[0] * u + m[1] * v + m[2]) / w,
+            (m[3] * u + m[4] * v + m[5]) / w,
+        )
+    }
+}
+
+/// Samples the image at a fractional pixel position, transparent outside of it.
+pub fn sample_bilinear(image: &image::RgbaImage, x: f64, y: f64) -> image::Rgba<u8> {
+    let (width, height) = image.dimensions();
+    if x < -0.5 || y < -0.5 || x > width as f64 - 0.5 || y > height as f64 - 0.5 {
+        return image::Rgba([0, 0, 0, 0]);
+    }
+    let (x, y) = (
+        x.clamp(0., width as f64 - 1.),
+        y.clamp(0., height as f64 - 1.),
+    );
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let [a, b, c, d] =
+        [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| image.get_pixel(x, y).0);
+    image::Rgba(std::array::from_fn(|i| {
+        let top = a[i] as f64 * (1. - fx) + b[i] as f64 * fx;
+        let bottom = c[i] as f64 * (1. - fx) + d[i] as f64 * fx;
+        (top * (1. - fy) + bottom * fy).round() as u8
+    }))
+}
+
+/// Returns whether any of the corrections changed.
+pub fn preprocessing_ui(ui: &mut egui::Ui, preprocessing: &mut Preprocessing) -> bool {
+    let before = *preprocessing;
+    ui.collapsing("Photo corrections", |ui| {
+        ui.add(
+            egui::Slider::new(&mut preprocessing.k1, -0.5..=0.5)
+                .clamp_to_range(true)
+                .text("Radial distortion k1"),
+        );
+        ui.add(
+            egui::Slider::new(&mut preprocessing.k2, -0.5..=0.5)
+                .clamp_to_range(true)
+                .text("Radial distortion k2"),
+        );
+        ui.add(
+            egui::Slider::new(&mut preprocessing.keystone_vertical, -0.9..=0.9)
+                .clamp_to_range(true)
+                .text("Vertical keystone"),
+        );
+        ui.add(
+            egui::Slider::new(&mut preprocessing.keystone_horizontal, -0.9..=0.9)
+                .clamp_to_range(true)
+                .text("Horizontal keystone"),
+        );
+        if ui.button("Reset").clicked() {
+            *preprocessing = Default::default();
+        }
+    });
+    *preprocessing != before
+}
@@ -0,0 +1,72 @@
+use std::io::Write;
+use std::path::Path;
+
+/// Writes `contents` to `path` so that only the user can read it, for files
+/// holding secrets. The file is restricted before anything is written to it,
+/// and again if it already existed with looser permissions.
+pub fn write(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+    #[cfg(windows)]
+    restrict(path)?;
+    file.write_all(contents)
+}
+
+/// Replaces the access list of `path`, which it would otherwise inherit from
+/// its directory, with one giving its owner alone full access.
+#[cfg(windows)]
+fn restrict(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SetNamedSecurityInfoW,
+        SDDL_REVISION_1, SE_FILE_OBJECT,
+    };
+    use windows_sys::Win32::Security::{
+        GetSecurityDescriptorDacl, ACL, DACL_SECURITY_INFORMATION,
+        PROTECTED_DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR,
+    };
+    use windows_sys::Win32::System::Memory::LocalFree;
+    // Protected, so nothing is inherited, and allowing everything to the owner.
+    let sddl: Vec<u16> = "D:P(A;;FA;;;OW)".encode_utf16().chain([0]).collect();
+    let path: Vec<u16> = path.as_os_str().encode_wide().chain([0]).collect();
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    // SAFETY: The strings are NUL-terminated and outlive the calls, and the
+    // descriptor is freed once its access list has been applied.
+    unsafe {
+        let converted = ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            std::ptr::null_mut(),
+        );
+        if converted == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let (mut present, mut defaulted) = (0, 0);
+        let mut dacl: *mut ACL = std::ptr::null_mut();
+        let result =
+            match GetSecurityDescriptorDacl(descriptor, &mut present, &mut dacl, &mut defaulted) {
+                0 => Err(std::io::Error::last_os_error()),
+                _ => match SetNamedSecurityInfoW(
+                    path.as_ptr(),
+                    SE_FILE_OBJECT,
+                    DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    dacl,
+                    std::ptr::null(),
+                ) {
+                    ERROR_SUCCESS => Ok(()),
+                    code => Err(std::io::Error::from_raw_os_error(code as i32)),
+                },
+            };
+        LocalFree(descriptor as isize);
+        result
+    }
+}
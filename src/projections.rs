@@ -0,0 +1,184 @@
+mod azimuthal;
+mod conic;
+mod cylindrical;
+mod modified_azimuthal;
+mod polyconic;
+mod pseudocylindrical;
+
+pub use azimuthal::{Gnomonic, LambertAzimuthalEqualArea, Orthographic, Stereographic};
+pub use conic::{AlbersEqualArea, Bonne, EquidistantConic, LambertConformalConic, Werner};
+pub use cylindrical::{
+    Cassini, CylindricalEqualArea, Mercator, Miller, TransverseMercator, WebMercator,
+};
+pub use modified_azimuthal::{Aitoff, Hammer, WinkelTripel};
+pub use polyconic::{Polyconic, VanDerGrinten};
+pub use pseudocylindrical::{
+    EckertIV, EckertVI, GoodeHomolosine, Interruptions, KavrayskiyVII, Mollweide, NaturalEarth,
+    Robinson, Sinusoidal,
+};
+
+/// A projection of the unit sphere given by its formulas, in radians and
+/// relative to its central meridian. `submaptive::Projection` is implemented
+/// for each one with `spherical!`.
+pub trait Spherical {
+    /// In degrees.
+    fn central_long(&self) -> f64;
+    /// Half the width and height of the whole map.
+    fn extent(&self) -> (f64, f64);
+    /// Where the point `long` east of the central meridian at `lat` is drawn.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64);
+    /// The longitude (from the central meridian) and latitude drawn at a
+    /// point, or `None` if the map shows nothing there.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)>;
+}
+
+/// Wraps a longitude in degrees into -180..180.
+pub fn wrap_long(long: f64) -> f64 {
+    (long + 180.).rem_euclid(360.) - 180.
+}
+
+/// A point from degrees that may have strayed out of range by rounding.
+pub fn point(long: f64, lat: f64) -> submaptive::Point {
+    (wrap_long(long), lat.clamp(-90., 90.)).try_into().unwrap()
+}
+
+macro_rules! spherical {
+    ($($projection:ty),* $(,)?) => {$(
+        impl submaptive::Projection for $projection {
+            fn dimensions(&self) -> submaptive::Dimensions {
+                let (width, height) = self.extent();
+                submaptive::Dimensions::new(2. * width, 2. * height)
+            }
+
+            fn project(&self, point: &submaptive::Point) -> (f64, f64) {
+                let long = $crate::projections::wrap_long(point.long() - self.central_long());
+                self.forward(long.to_radians(), point.lat().to_radians())
+            }
+
+            fn invert(&self, projected_point: (f64, f64)) -> submaptive::Point {
+                let (long, lat) = self
+                    .inverse(projected_point.0, projected_point.1)
+                    .unwrap_or((0., 0.));
+                $crate::projections::point(
+                    self.central_long() + long.to_degrees(),
+                    lat.to_degrees(),
+                )
+            }
+
+            fn projected_point_within_bounds(&self, point: (f64, f64)) -> bool {
+                let (width, height) = self.extent();
+                point.0.abs() <= width
+                    && point.1.abs() <= height
+                    && self.inverse(point.0, point.1).is_some()
+            }
+        }
+    )*};
+}
+
+pub(crate) use spherical;
+
+#[cfg(test)]
+mod tests {
+    use submaptive::Projection;
+
+    use super::point;
+    use crate::{feature_matching, ProjectionKind};
+
+    /// Parameter sets tried per projection, and points per parameter set.
+    const ROUND_TRIP_PROJECTIONS: usize = 50;
+    const ROUND_TRIP_POINTS: usize = 200;
+
+    /// How far in degrees a point may end up from where it started after
+    /// projecting and inverting it.
+    fn round_trip_epsilon(kind: ProjectionKind) -> f64 {
+        match kind {
+            // Next to the antipode of its center, a whole circle is squeezed into a point.
+            ProjectionKind::AzimuthalEquidistant => 1e-4,
+            _ => 1e-6,
+        }
+    }
+
+    /// A xorshift generator, so that every run tries the same points.
+    struct Random(u64);
+
+    impl Random {
+        /// Uniform in `range`.
+        fn next(&mut self, range: std::ops::RangeInclusive<f64>) -> f64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            let unit = (self.0 >> 11) as f64 / (1u64 << 53) as f64;
+            range.start() + unit * (range.end() - range.start())
+        }
+    }
+
+    /// The angle between two points, in degrees.
+    fn distance(a: &submaptive::Point, b: &submaptive::Point) -> f64 {
+        let (lat_a, lat_b) = (a.lat().to_radians(), b.lat().to_radians());
+        let half_long = (b.long() - a.long()).to_radians() / 2.;
+        let half_lat = (lat_b - lat_a) / 2.;
+        let h = half_lat.sin().powi(2) + lat_a.cos() * lat_b.cos() * half_long.sin().powi(2);
+        (2. * h.sqrt().min(1.).asin()).to_degrees()
+    }
+
+    /// Projects and inverts random points with random parameters for every
+    /// projection, and reports the worst round trip of those that don't.
+    fn round_trips(kind: ProjectionKind) -> Result<(), String> {
+        let mut random = Random(0x9e3779b97f4a7c15 ^ kind.id().len() as u64);
+        let epsilon = round_trip_epsilon(kind);
+        let default = kind.default_projection_data();
+        let mut worst: Option<(f64, String)> = None;
+        for _ in 0..ROUND_TRIP_PROJECTIONS {
+            // Out of range values are clamped to what each parameter allows.
+            let projection = match feature_matching::parameters(&default) {
+                Some(parameters) => {
+                    let parameters: Vec<f64> = parameters
+                        .iter()
+                        .map(|_| random.next(-180.0..=180.))
+                        .collect();
+                    feature_matching::with_parameters(&default, &parameters)
+                }
+                None => default.clone(),
+            };
+            for _ in 0..ROUND_TRIP_POINTS {
+                let long = random.next(-180.0..=180.);
+                let lat = random.next(-1.0..=1.).asin().to_degrees();
+                let point = point(long, lat);
+                let projected = projection.project(&point);
+                if !projected.0.is_finite()
+                    || !projected.1.is_finite()
+                    || !projection.projected_point_within_bounds(projected)
+                {
+                    continue;
+                }
+                let error = distance(&point, &projection.invert(projected));
+                let worse = match &worst {
+                    Some((worst, _)) => error > *worst,
+                    None => true,
+                };
+                if error > epsilon && worse {
+                    let parameters = feature_matching::parameters(&projection).unwrap_or_default();
+                    let case = format!("({:.3}, {:.3}) with {:?}", long, lat, parameters);
+                    worst = Some((error, case));
+                }
+            }
+        }
+        match worst {
+            Some((error, case)) => Err(format!("off by {:.2e}° at {}", error, case)),
+            None => Ok(()),
+        }
+    }
+
+    #[test]
+    fn every_projection_inverts_what_it_projects() {
+        let failures: Vec<String> = ProjectionKind::all()
+            .filter(|kind| *kind != ProjectionKind::RubberSheet)
+            .filter_map(|kind| {
+                round_trips(kind)
+                    .err()
+                    .map(|e| format!("{}: {}", kind.id(), e))
+            })
+            .collect();
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,231 @@
+use super::{spherical, Spherical};
+
+/// The direction of a point as seen from the center of an azimuthal
+/// projection: the cosine of its angular distance from the center, and the
+/// point on the plane through the center that it lies on before scaling.
+fn direction(center_lat: f64, long: f64, lat: f64) -> (f64, f64, f64) {
+    let (sin_center, cos_center) = center_lat.sin_cos();
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_long, cos_long) = long.sin_cos();
+    (
+        sin_center * sin_lat + cos_center * cos_lat * cos_long,
+        cos_lat * sin_long,
+        cos_center * sin_lat - sin_center * cos_lat * cos_long,
+    )
+}
+
+/// The longitude and latitude at angular distance `distance` from the center,
+/// in the direction of `(x, y)`.
+fn from_direction(center_lat: f64, x: f64, y: f64, distance: f64) -> (f64, f64) {
+    let rho = x.hypot(y);
+    if rho == 0. {
+        return (0., center_lat);
+    }
+    let (sin_center, cos_center) = center_lat.sin_cos();
+    let (sin_distance, cos_distance) = distance.sin_cos();
+    let lat = (cos_distance * sin_center + y * sin_distance * cos_center / rho)
+        .clamp(-1., 1.)
+        .asin();
+    let long =
+        (x * sin_distance).atan2(rho * cos_distance * cos_center - y * sin_center * sin_distance);
+    (long, lat)
+}
+
+/// The globe as seen from infinitely far away: the hemisphere around the
+/// center, in a disc.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orthographic {
+    /// In degrees.
+    pub central_long: f64,
+    pub center_lat: f64,
+}
+
+impl Spherical for Orthographic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (1., 1.)
+    }
+
+    /// Points on the far side of the globe aren't drawn anywhere, and come
+    /// out as NaN.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        match direction(self.center_lat.to_radians(), long, lat) {
+            (cos, x, y) if cos >= 0. => (x, y),
+            _ => (f64::NAN, f64::NAN),
+        }
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let rho = x.hypot(y);
+        match rho <= 1. {
+            true => Some(from_direction(
+                self.center_lat.to_radians(),
+                x,
+                y,
+                rho.asin(),
+            )),
+            false => None,
+        }
+    }
+}
+
+/// The conformal azimuthal projection, which draws every circle on the globe
+/// as a circle. Around the antipode of the center the scale grows without
+/// bound, so the map is cut off at `MAX_DISTANCE` from the center.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Stereographic {
+    /// In degrees.
+    pub central_long: f64,
+    pub center_lat: f64,
+}
+
+impl Stereographic {
+    /// The polar aspects, by center latitude.
+    pub const PRESETS: [(&'static str, f64); 2] = [("North polar", 90.), ("South polar", -90.)];
+    /// In degrees from the center.
+    pub const MAX_DISTANCE: f64 = 120.;
+
+    /// Distance from the center of a point at angular `distance` from it.
+    fn radius(distance: f64) -> f64 {
+        2. * (distance / 2.).tan()
+    }
+}
+
+impl Spherical for Stereographic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let radius = Self::radius(Self::MAX_DISTANCE.to_radians());
+        (radius, radius)
+    }
+
+    /// Points beyond `MAX_DISTANCE` aren't drawn, and come out as NaN.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        match direction(self.center_lat.to_radians(), long, lat) {
+            (cos, x, y) if cos >= Self::MAX_DISTANCE.to_radians().cos() => {
+                let scale = 2. / (1. + cos);
+                (scale * x, scale * y)
+            }
+            _ => (f64::NAN, f64::NAN),
+        }
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let distance = 2. * (x.hypot(y) / 2.).atan();
+        match distance <= Self::MAX_DISTANCE.to_radians() {
+            true => Some(from_direction(self.center_lat.to_radians(), x, y, distance)),
+            false => None,
+        }
+    }
+}
+
+/// The globe projected from its center, which draws every great circle as a
+/// straight line. The scale grows without bound towards 90° from the tangent
+/// point, so the map is cut off at `clip` from it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gnomonic {
+    /// In degrees.
+    pub central_long: f64,
+    pub center_lat: f64,
+    /// In degrees from the tangent point.
+    pub clip: f64,
+}
+
+impl Default for Gnomonic {
+    fn default() -> Self {
+        Gnomonic {
+            central_long: 0.,
+            center_lat: 0.,
+            clip: 60.,
+        }
+    }
+}
+
+impl Gnomonic {
+    /// In degrees.
+    pub const MIN_CLIP: f64 = 10.;
+    pub const MAX_CLIP: f64 = 85.;
+}
+
+impl Spherical for Gnomonic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let radius = self.clip.to_radians().tan();
+        (radius, radius)
+    }
+
+    /// Points beyond `clip` aren't drawn, and come out as NaN.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        match direction(self.center_lat.to_radians(), long, lat) {
+            (cos, x, y) if cos >= self.clip.to_radians().cos() => (x / cos, y / cos),
+            _ => (f64::NAN, f64::NAN),
+        }
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let distance = x.hypot(y).atan();
+        match distance <= self.clip.to_radians() {
+            true => Some(from_direction(self.center_lat.to_radians(), x, y, distance)),
+            false => None,
+        }
+    }
+}
+
+/// The equal-area azimuthal projection, which shows the whole globe in a disc.
+/// The antipode of the center is stretched around the rim.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LambertAzimuthalEqualArea {
+    /// In degrees.
+    pub central_long: f64,
+    pub center_lat: f64,
+}
+
+impl Spherical for LambertAzimuthalEqualArea {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (2., 2.)
+    }
+
+    /// The antipode itself goes to the bottom of the rim.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let (cos, x, y) = direction(self.center_lat.to_radians(), long, lat);
+        let scale = (2. / (1. + cos)).sqrt();
+        match scale.is_finite() {
+            true => (scale * x, scale * y),
+            false => (0., -2.),
+        }
+    }
+
+    /// Every point of the rim is the antipode, which lies at distance π in any
+    /// direction.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let rho = x.hypot(y);
+        match rho <= 2. + 1e-9 {
+            true => Some(from_direction(
+                self.center_lat.to_radians(),
+                x,
+                y,
+                2. * (rho / 2.).min(1.).asin(),
+            )),
+            false => None,
+        }
+    }
+}
+
+spherical!(
+    Gnomonic,
+    LambertAzimuthalEqualArea,
+    Orthographic,
+    Stereographic
+);
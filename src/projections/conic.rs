@@ -0,0 +1,477 @@
+use std::cell::Cell;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use super::{spherical, Spherical};
+
+/// The conformal conic projection, true to scale along two standard parallels
+/// and centered on the latitude of origin. The pole on the far side of the
+/// cone lies at infinity, so the map is cut off at `FAR_LAT`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LambertConformalConic {
+    /// In degrees.
+    pub central_long: f64,
+    pub origin_lat: f64,
+    pub standard_parallels: [f64; 2],
+}
+
+impl Default for LambertConformalConic {
+    fn default() -> Self {
+        LambertConformalConic {
+            central_long: 0.,
+            origin_lat: 39.,
+            standard_parallels: [33., 45.],
+        }
+    }
+}
+
+/// The equal-area conic projection, true to scale along two standard
+/// parallels. It shows the whole world as a ring sector, centered in the image.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlbersEqualArea {
+    /// In degrees.
+    pub central_long: f64,
+    pub standard_parallels: [f64; 2],
+}
+
+impl Default for AlbersEqualArea {
+    fn default() -> Self {
+        AlbersEqualArea {
+            central_long: 0.,
+            standard_parallels: [29.5, 45.5],
+        }
+    }
+}
+
+/// The simple conic projection, true to scale along the meridians and the two
+/// standard parallels, which are evenly spaced.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EquidistantConic {
+    /// In degrees.
+    pub central_long: f64,
+    pub standard_parallels: [f64; 2],
+}
+
+impl Default for EquidistantConic {
+    fn default() -> Self {
+        EquidistantConic {
+            central_long: 0.,
+            standard_parallels: [20., 60.],
+        }
+    }
+}
+
+/// The cone of a conic projection, worked out as if it opened towards the
+/// south; a southern cone is drawn upside down and turned back.
+struct Cone {
+    /// 1 for a cone around the north pole, -1 for one around the south pole.
+    sign: f64,
+    /// How much of a full circle the unrolled cone takes.
+    n: f64,
+    /// The constant in the distance of a parallel from the apex.
+    f: f64,
+    /// Distance of the center of the image from the apex.
+    rho0: f64,
+}
+
+/// Which way the cone of two standard parallels opens, and the parallels in
+/// radians as if that were towards the south.
+fn hemisphere(standard_parallels: [f64; 2]) -> (f64, [f64; 2]) {
+    let sign = match standard_parallels[0] + standard_parallels[1] < 0. {
+        true => -1.,
+        false => 1.,
+    };
+    (sign, standard_parallels.map(|lat| sign * lat.to_radians()))
+}
+
+/// Parallels on either side of the equator at the same distance make a
+/// cylinder, which a cone can only come close to.
+const MIN_CONE: f64 = 0.01;
+
+/// Half the width of the ring sector between `near` and `far` from the apex,
+/// and how far it reaches above the apex (below it, if negative).
+fn sector(n: f64, near: f64, far: f64) -> (f64, f64) {
+    let angle = n * PI;
+    let width = match angle < FRAC_PI_2 {
+        true => far * angle.sin(),
+        false => far,
+    };
+    let top = match angle.cos() < 0. {
+        true => -far * angle.cos(),
+        false => -near * angle.cos(),
+    };
+    (width, top)
+}
+
+impl LambertConformalConic {
+    /// In degrees, on the side of the equator away from the cone's pole.
+    pub const FAR_LAT: f64 = -60.;
+    /// In degrees. At the poles the cone becomes a plane.
+    pub const MAX_STANDARD_PARALLEL: f64 = 89.;
+
+    fn t(lat: f64) -> f64 {
+        (FRAC_PI_4 + lat / 2.).tan()
+    }
+
+    fn cone(&self) -> Cone {
+        let (sign, [lat1, lat2]) = hemisphere(self.standard_parallels);
+        let origin = (sign * self.origin_lat.to_radians()).max(Self::FAR_LAT.to_radians());
+        let n = match (lat1 - lat2).abs() < 1e-10 {
+            true => lat1.sin(),
+            false => (lat1.cos() / lat2.cos()).ln() / (Self::t(lat2) / Self::t(lat1)).ln(),
+        };
+        let n = n.max(MIN_CONE);
+        let f = lat1.cos() * Self::t(lat1).powf(n) / n;
+        Cone {
+            sign,
+            n,
+            f,
+            rho0: f / Self::t(origin).powf(n),
+        }
+    }
+}
+
+impl Spherical for LambertConformalConic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    /// Large enough for the whole fan around the latitude of origin.
+    fn extent(&self) -> (f64, f64) {
+        let cone = self.cone();
+        let far = cone.f / Self::t(Self::FAR_LAT.to_radians()).powf(cone.n);
+        let (width, top) = sector(cone.n, 0., far);
+        let top = cone.rho0 + top;
+        let bottom = cone.rho0 - far;
+        (width, top.abs().max(bottom.abs()))
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let cone = self.cone();
+        let rho = cone.f / Self::t(cone.sign * lat).powf(cone.n);
+        let angle = cone.n * long;
+        (
+            cone.sign * rho * angle.sin(),
+            cone.sign * (cone.rho0 - rho * angle.cos()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let cone = self.cone();
+        let (x, y) = (cone.sign * x, cone.rho0 - cone.sign * y);
+        let rho = x.hypot(y);
+        let lat = match rho {
+            0. => FRAC_PI_2,
+            rho => 2. * (cone.f / rho).powf(1. / cone.n).atan() - FRAC_PI_2,
+        };
+        let long = x.atan2(y) / cone.n;
+        match long.abs() <= PI && lat >= Self::FAR_LAT.to_radians() {
+            true => Some((long, cone.sign * lat)),
+            false => None,
+        }
+    }
+}
+
+impl AlbersEqualArea {
+    /// In degrees. At the poles the cone becomes a plane.
+    pub const MAX_STANDARD_PARALLEL: f64 = 89.;
+
+    fn cone(&self) -> Cone {
+        let (sign, [lat1, lat2]) = hemisphere(self.standard_parallels);
+        let n = ((lat1.sin() + lat2.sin()) / 2.).max(MIN_CONE);
+        // The same for either parallel unless the cone has been widened, and
+        // then the nearer parallel keeps every distance real.
+        let lat = lat1.max(lat2);
+        let f = lat.cos().powi(2) + 2. * n * lat.sin();
+        let far = Self::rho(n, f, -FRAC_PI_2);
+        let (_, top) = sector(n, Self::rho(n, f, FRAC_PI_2), far);
+        Cone {
+            sign,
+            n,
+            f,
+            rho0: (far - top) / 2.,
+        }
+    }
+
+    fn rho(n: f64, f: f64, lat: f64) -> f64 {
+        (f - 2. * n * lat.sin()).max(0.).sqrt() / n
+    }
+}
+
+impl Spherical for AlbersEqualArea {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let cone = self.cone();
+        let far = Self::rho(cone.n, cone.f, -FRAC_PI_2);
+        let (width, top) = sector(cone.n, Self::rho(cone.n, cone.f, FRAC_PI_2), far);
+        (width, (top + far) / 2.)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let cone = self.cone();
+        let rho = Self::rho(cone.n, cone.f, cone.sign * lat);
+        let angle = cone.n * long;
+        (
+            cone.sign * rho * angle.sin(),
+            cone.sign * (cone.rho0 - rho * angle.cos()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let cone = self.cone();
+        let (x, y) = (cone.sign * x, cone.rho0 - cone.sign * y);
+        let sin = (cone.f - (cone.n * x.hypot(y)).powi(2)) / (2. * cone.n);
+        let long = x.atan2(y) / cone.n;
+        match long.abs() <= PI && sin.abs() <= 1. {
+            true => Some((long, cone.sign * sin.asin())),
+            false => None,
+        }
+    }
+}
+
+impl EquidistantConic {
+    /// In degrees. At the poles the cone becomes a plane.
+    pub const MAX_STANDARD_PARALLEL: f64 = 89.;
+
+    fn cone(&self) -> Cone {
+        let (sign, [lat1, lat2]) = hemisphere(self.standard_parallels);
+        let n = match (lat1 - lat2).abs() < 1e-10 {
+            true => lat1.sin(),
+            false => (lat1.cos() - lat2.cos()) / (lat2 - lat1),
+        };
+        let n = n.max(MIN_CONE);
+        // As for `AlbersEqualArea`, the nearer parallel if the cone has been widened.
+        let lat = lat1.max(lat2);
+        let f = lat.cos() / n + lat;
+        let far = f + FRAC_PI_2;
+        let (_, top) = sector(n, (f - FRAC_PI_2).max(0.), far);
+        Cone {
+            sign,
+            n,
+            f,
+            rho0: (far - top) / 2.,
+        }
+    }
+}
+
+impl Spherical for EquidistantConic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let cone = self.cone();
+        let far = cone.f + FRAC_PI_2;
+        let (width, top) = sector(cone.n, (cone.f - FRAC_PI_2).max(0.), far);
+        (width, (top + far) / 2.)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let cone = self.cone();
+        let rho = cone.f - cone.sign * lat;
+        let angle = cone.n * long;
+        (
+            cone.sign * rho * angle.sin(),
+            cone.sign * (cone.rho0 - rho * angle.cos()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let cone = self.cone();
+        let (x, y) = (cone.sign * x, cone.rho0 - cone.sign * y);
+        let lat = cone.f - x.hypot(y);
+        let long = x.atan2(y) / cone.n;
+        match long.abs() <= PI && lat.abs() <= FRAC_PI_2 {
+            true => Some((long, cone.sign * lat)),
+            false => None,
+        }
+    }
+}
+
+/// Bonne's equal-area pseudoconic projection: the parallels are concentric
+/// arcs drawn true to length, spaced evenly from the standard parallel. With
+/// that at a pole it becomes Werner's heart, and at the equator it flattens
+/// into the sinusoidal projection. Nothing is drawn outside the outline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bonne {
+    /// In degrees.
+    pub central_long: f64,
+    /// In degrees.
+    pub standard_parallel: f64,
+}
+
+impl Default for Bonne {
+    fn default() -> Self {
+        Bonne {
+            central_long: 0.,
+            standard_parallel: 45.,
+        }
+    }
+}
+
+/// The box around Bonne's projection of the whole world, for a standard
+/// parallel north of the equator.
+#[derive(Clone, Copy)]
+struct Outline {
+    half_width: f64,
+    top: f64,
+    bottom: f64,
+}
+
+thread_local! {
+    /// The outline for the standard parallel last asked about, as it is
+    /// needed for every pixel and takes a while to work out.
+    static OUTLINE: Cell<Option<(f64, Outline)>> = const { Cell::new(None) };
+}
+
+impl Bonne {
+    /// The limiting cases of the standard parallel, by name.
+    pub const PRESETS: [(&'static str, f64); 2] = [("Sinusoidal", 0.), ("Werner", 90.)];
+    /// Closer to the equator than this, in radians, the projection is drawn as
+    /// the sinusoidal one it comes to.
+    const MIN_STANDARD_PARALLEL: f64 = 1e-9;
+
+    /// 1 for a standard parallel north of the equator and -1 for one south of
+    /// it, whose maps are worked out as the northern ones turned upside down,
+    /// and the parallel as if it were in the north.
+    fn hemisphere(&self) -> (f64, f64) {
+        let lat = self.standard_parallel.to_radians();
+        match lat < 0. {
+            true => (-1., -lat),
+            false => (1., lat),
+        }
+    }
+
+    /// The projection for a standard parallel `lat1` north of the equator,
+    /// before it is centered in the image.
+    fn northern(lat1: f64, long: f64, lat: f64) -> (f64, f64) {
+        if lat1 < Self::MIN_STANDARD_PARALLEL {
+            return (long * lat.cos(), lat);
+        }
+        let cot = 1. / lat1.tan();
+        let rho = cot + lat1 - lat;
+        if rho < 1e-12 {
+            return (0., cot);
+        }
+        let angle = long * lat.cos() / rho;
+        (rho * angle.sin(), cot - rho * angle.cos())
+    }
+
+    /// Found by following the outer meridians, which make the outline
+    /// between the poles.
+    fn outline(lat1: f64) -> Outline {
+        if let Some((cached, outline)) = OUTLINE.with(Cell::get) {
+            if cached == lat1 {
+                return outline;
+            }
+        }
+        let mut outline = Outline {
+            half_width: 0.,
+            top: f64::MIN,
+            bottom: f64::MAX,
+        };
+        const STEPS: usize = 720;
+        for step in 0..=STEPS {
+            let lat = -FRAC_PI_2 + PI * step as f64 / STEPS as f64;
+            for long in [0., PI] {
+                let (x, y) = Self::northern(lat1, long, lat);
+                outline.half_width = outline.half_width.max(x.abs());
+                outline.top = outline.top.max(y);
+                outline.bottom = outline.bottom.min(y);
+            }
+        }
+        OUTLINE.with(|cache| cache.set(Some((lat1, outline))));
+        outline
+    }
+}
+
+impl Spherical for Bonne {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let outline = Self::outline(self.hemisphere().1);
+        (outline.half_width, (outline.top - outline.bottom) / 2.)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let (sign, lat1) = self.hemisphere();
+        let outline = Self::outline(lat1);
+        let (x, y) = Self::northern(lat1, long, sign * lat);
+        (x, sign * (y - (outline.top + outline.bottom) / 2.))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (sign, lat1) = self.hemisphere();
+        let outline = Self::outline(lat1);
+        let y = sign * y + (outline.top + outline.bottom) / 2.;
+        let (long, lat) = match lat1 < Self::MIN_STANDARD_PARALLEL {
+            true => (x, y),
+            false => {
+                let cot = 1. / lat1.tan();
+                let rho = x.hypot(cot - y);
+                let lat = cot + lat1 - rho;
+                (rho * x.atan2(cot - y), lat)
+            }
+        };
+        if lat.abs() > FRAC_PI_2 + 1e-12 {
+            return None;
+        }
+        let long = match lat.cos() {
+            // At the poles, where the outer meridians meet.
+            cos if cos < 1e-12 => 0.,
+            cos => long / cos,
+        };
+        match long.abs() <= PI + 1e-9 {
+            true => Some((long.clamp(-PI, PI), sign * lat.clamp(-FRAC_PI_2, FRAC_PI_2))),
+            false => None,
+        }
+    }
+}
+
+/// The Stab–Werner cordiform projection, Bonne's with the standard parallel at
+/// the north pole, which draws the world as a heart with distances from the
+/// pole true to scale.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Werner {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Werner {
+    fn bonne(&self) -> Bonne {
+        Bonne {
+            central_long: self.central_long,
+            standard_parallel: 90.,
+        }
+    }
+}
+
+impl Spherical for Werner {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        self.bonne().extent()
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        self.bonne().forward(long, lat)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        self.bonne().inverse(x, y)
+    }
+}
+
+spherical!(
+    AlbersEqualArea,
+    Bonne,
+    EquidistantConic,
+    LambertConformalConic,
+    Werner
+);
@@ -0,0 +1,259 @@
+use std::f64::consts::{FRAC_PI_4, PI};
+
+use super::{spherical, Spherical};
+
+/// The conformal cylindrical projection. The poles lie at infinity, so the
+/// map is cut off at `max_lat` north and south.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mercator {
+    /// In degrees.
+    pub central_long: f64,
+    pub max_lat: f64,
+}
+
+impl Default for Mercator {
+    fn default() -> Self {
+        Mercator {
+            central_long: 0.,
+            max_lat: 85.,
+        }
+    }
+}
+
+impl Mercator {
+    fn y(lat: f64) -> f64 {
+        (FRAC_PI_4 + lat / 2.).tan().ln()
+    }
+}
+
+impl Spherical for Mercator {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, Self::y(self.max_lat.to_radians()))
+    }
+
+    /// Beyond the cutoff this lands outside the map rather than on its edge.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (long, Self::y(lat))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        Some((x, 2. * y.exp().atan() - PI / 2.))
+    }
+}
+
+/// Mercator turned on its side, conformal and true to scale along the
+/// central meridian, for regions that stretch north to south. Reaches
+/// `MAX_OFFSET` degrees to either side, as it grows without bound towards 90°.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TransverseMercator {
+    /// In degrees.
+    pub central_long: f64,
+    pub origin_lat: f64,
+    pub scale_factor: f64,
+}
+
+impl TransverseMercator {
+    const MAX_OFFSET: f64 = 80.;
+}
+
+impl Default for TransverseMercator {
+    fn default() -> Self {
+        TransverseMercator {
+            central_long: 0.,
+            origin_lat: 0.,
+            scale_factor: 1.,
+        }
+    }
+}
+
+impl Spherical for TransverseMercator {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    /// The central meridian runs once round the globe from the origin,
+    /// which is off the middle with an origin latitude other than 0.
+    fn extent(&self) -> (f64, f64) {
+        let k = self.scale_factor;
+        (
+            k * Self::MAX_OFFSET.to_radians().sin().atanh(),
+            k * (PI + self.origin_lat.to_radians().abs()),
+        )
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let k = self.scale_factor;
+        let b = (lat.cos() * long.sin()).clamp(-1. + 1e-12, 1. - 1e-12);
+        (
+            k * b.atanh(),
+            k * (lat.tan().atan2(long.cos()) - self.origin_lat.to_radians()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let k = self.scale_factor;
+        let d = y / k + self.origin_lat.to_radians();
+        // Beyond that the central meridian would come round again.
+        if d.abs() > PI {
+            return None;
+        }
+        let x = x / k;
+        Some((x.sinh().atan2(d.cos()), (d.sin() / x.cosh()).asin()))
+    }
+}
+
+/// The equirectangular projection turned on its side: true to scale along
+/// the central meridian and along the great circles at right angles to it.
+/// Used for many national surveys of the 19th century.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Cassini {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Spherical for Cassini {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI / 2., PI)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (
+            (lat.cos() * long.sin()).clamp(-1., 1.).asin(),
+            lat.tan().atan2(long.cos()),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        Some((x.tan().atan2(y.cos()), (y.sin() * x.cos()).asin()))
+    }
+}
+
+/// Mercator as web map tiles use it (EPSG:3857): on a sphere, centered on
+/// Greenwich and cut off where the map becomes square, so that projected maps
+/// line up with the tiles.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WebMercator;
+
+impl WebMercator {
+    /// In degrees, where Mercator's y reaches π.
+    pub const MAX_LAT: f64 = 85.051_128_779_806_6;
+
+    fn mercator() -> Mercator {
+        Mercator {
+            central_long: 0.,
+            max_lat: Self::MAX_LAT,
+        }
+    }
+}
+
+impl Spherical for WebMercator {
+    fn central_long(&self) -> f64 {
+        0.
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, PI)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        Self::mercator().forward(long, lat)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        Self::mercator().inverse(x, y)
+    }
+}
+
+/// The cylindrical projection that keeps areas, true to scale along the
+/// standard parallels north and south. Which parallels those are gives it its
+/// many names.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CylindricalEqualArea {
+    /// In degrees.
+    pub central_long: f64,
+    pub standard_parallel: f64,
+}
+
+impl CylindricalEqualArea {
+    /// The variants with names of their own, by standard parallel.
+    pub const PRESETS: [(&'static str, f64); 3] =
+        [("Lambert", 0.), ("Behrmann", 30.), ("Gall–Peters", 45.)];
+    /// In degrees. Towards 90° the map becomes a line.
+    pub const MAX_STANDARD_PARALLEL: f64 = 85.;
+
+    fn stretch(&self) -> f64 {
+        self.standard_parallel.to_radians().cos()
+    }
+}
+
+impl Spherical for CylindricalEqualArea {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI * self.stretch(), 1. / self.stretch())
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (long * self.stretch(), lat.sin() / self.stretch())
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let sin = y * self.stretch();
+        match sin.abs() <= 1. {
+            true => Some((x / self.stretch(), sin.asin())),
+            false => None,
+        }
+    }
+}
+
+/// Miller's modification of Mercator, which scales latitudes down by 4/5,
+/// projects them and scales the result back up, so that the poles come out at
+/// a finite distance and the whole world fits.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Miller {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Miller {
+    fn y(lat: f64) -> f64 {
+        1.25 * Mercator::y(0.8 * lat)
+    }
+}
+
+impl Spherical for Miller {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, Self::y(PI / 2.))
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (long, Self::y(lat))
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        Some((x, 2.5 * (0.8 * y).exp().atan() - 5. * PI / 8.))
+    }
+}
+
+spherical!(
+    Cassini,
+    CylindricalEqualArea,
+    Mercator,
+    Miller,
+    TransverseMercator,
+    WebMercator
+);
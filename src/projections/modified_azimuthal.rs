@@ -0,0 +1,187 @@
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+use super::{spherical, Spherical};
+
+/// The equal-area projection of the whole world into an ellipse twice as wide
+/// as it is high, stretched from the equatorial aspect of the Lambert
+/// azimuthal equal-area projection.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Hammer {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+/// The equatorial aspect of the azimuthal equidistant projection, stretched
+/// into an ellipse twice as wide as it is high.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aitoff {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+/// Winkel's compromise, the mean of the Aitoff projection and an
+/// equirectangular one true to scale at `standard_parallel`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WinkelTripel {
+    /// In degrees.
+    pub central_long: f64,
+    /// In degrees.
+    pub standard_parallel: f64,
+}
+
+impl Default for WinkelTripel {
+    fn default() -> Self {
+        WinkelTripel {
+            central_long: 0.,
+            standard_parallel: 50.467,
+        }
+    }
+}
+
+/// Newton's method gives up after this many steps.
+const MAX_STEPS: usize = 50;
+
+/// A point of the Aitoff projection and its derivatives by longitude and
+/// latitude, as given by Ipbüker and Bildirici.
+fn aitoff(long: f64, lat: f64) -> ((f64, f64), [[f64; 2]; 2]) {
+    let (sin_lat, cos_lat) = lat.sin_cos();
+    let (sin_half, cos_half) = (long / 2.).sin_cos();
+    let c = 1. - cos_lat * cos_lat * cos_half * cos_half;
+    // α / sin α for the angular distance α from the center, and 1 / sin² α.
+    let (e, f) = match c {
+        c if c > 0. => ((cos_lat * cos_half).acos() / c.sqrt(), 1. / c),
+        _ => (1., 0.),
+    };
+    let point = (2. * e * cos_lat * sin_half, e * sin_lat);
+    let dx_dlong =
+        f * (cos_lat * cos_lat * sin_half * sin_half + e * cos_lat * cos_half * sin_lat * sin_lat);
+    let dx_dlat = f * (long.sin() * (2. * lat).sin() / 2. - 2. * e * sin_lat * sin_half);
+    let dy_dlong =
+        f / 4. * ((2. * lat).sin() * sin_half - e * sin_lat * cos_lat * cos_lat * long.sin());
+    let dy_dlat = f * (sin_lat * sin_lat * cos_half + e * sin_half * sin_half * cos_lat);
+    (point, [[dx_dlong, dx_dlat], [dy_dlong, dy_dlat]])
+}
+
+/// Where `projection`, a point together with its derivatives, draws `(x, y)`.
+/// There is no closed form, so the point is found by Newton's method,
+/// starting from where an equirectangular map of the same `extent` has it.
+fn invert(
+    projection: impl Fn(f64, f64) -> ((f64, f64), [[f64; 2]; 2]),
+    extent: (f64, f64),
+    x: f64,
+    y: f64,
+) -> Option<(f64, f64)> {
+    let (mut long, mut lat) = (x / extent.0 * PI, y / extent.1 * FRAC_PI_2);
+    for _ in 0..MAX_STEPS {
+        let ((px, py), [[dx_dlong, dx_dlat], [dy_dlong, dy_dlat]]) = projection(long, lat);
+        let (fx, fy) = (px - x, py - y);
+        let determinant = dx_dlat * dy_dlong - dy_dlat * dx_dlong;
+        if determinant == 0. {
+            break;
+        }
+        let step_long = (fy * dx_dlat - fx * dy_dlat) / determinant;
+        let step_lat = (fx * dy_dlong - fy * dx_dlong) / determinant;
+        long -= step_long;
+        lat -= step_lat;
+        if step_long.abs() < 1e-13 && step_lat.abs() < 1e-13 {
+            break;
+        }
+    }
+    // Outside the outline, the steps lead somewhere that doesn't project
+    // back to the point, if they settle at all.
+    let ((back_x, back_y), _) = projection(long, lat);
+    let on_map = long.abs() <= PI + 1e-9
+        && lat.abs() <= FRAC_PI_2 + 1e-9
+        && (back_x - x).hypot(back_y - y) < 1e-9;
+    match on_map {
+        true => Some((long.clamp(-PI, PI), lat.clamp(-FRAC_PI_2, FRAC_PI_2))),
+        false => None,
+    }
+}
+
+impl WinkelTripel {
+    /// The mean of the two projections, and of their derivatives.
+    fn with_derivatives(&self, long: f64, lat: f64) -> ((f64, f64), [[f64; 2]; 2]) {
+        let scale = self.standard_parallel.to_radians().cos();
+        let ((x, y), [[dx_dlong, dx_dlat], [dy_dlong, dy_dlat]]) = aitoff(long, lat);
+        (
+            ((x + long * scale) / 2., (y + lat) / 2.),
+            [
+                [(dx_dlong + scale) / 2., dx_dlat / 2.],
+                [dy_dlong / 2., (dy_dlat + 1.) / 2.],
+            ],
+        )
+    }
+}
+
+impl Spherical for WinkelTripel {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        let scale = self.standard_parallel.to_radians().cos();
+        (PI * (scale + 1.) / 2., FRAC_PI_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        self.with_derivatives(long, lat).0
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        invert(
+            |long, lat| self.with_derivatives(long, lat),
+            self.extent(),
+            x,
+            y,
+        )
+    }
+}
+
+impl Spherical for Hammer {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (2. * SQRT_2, SQRT_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let scale = SQRT_2 / (1. + cos_lat * (long / 2.).cos()).sqrt();
+        (2. * scale * cos_lat * (long / 2.).sin(), scale * sin_lat)
+    }
+
+    /// Nothing is drawn outside the ellipse, which leaves the corners of the
+    /// map transparent.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (width, height) = self.extent();
+        if (x / width).powi(2) + (y / height).powi(2) > 1. + 1e-12 {
+            return None;
+        }
+        let z = (1. - (x / 4.).powi(2) - (y / 2.).powi(2)).max(0.).sqrt();
+        let long = 2. * (z * x).atan2(2. * (2. * z * z - 1.));
+        Some((long, (z * y).clamp(-1., 1.).asin()))
+    }
+}
+
+impl Spherical for Aitoff {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, FRAC_PI_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        aitoff(long, lat).0
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        invert(aitoff, self.extent(), x, y)
+    }
+}
+
+spherical!(Aitoff, Hammer, WinkelTripel);
@@ -0,0 +1,157 @@
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use super::{spherical, Spherical};
+
+/// Van der Grinten's first projection, a compromise showing the whole world
+/// in a circle, as in National Geographic's world maps from 1922 to 1988.
+/// The poles lie on the circle, so the polar regions are much enlarged.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct VanDerGrinten {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Spherical for VanDerGrinten {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, PI)
+    }
+
+    /// As given by Snyder in "Map Projections: A Working Manual", p. 241.
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let theta = (2. * lat.abs() / PI).min(1.).asin();
+        if lat == 0. {
+            return (long, 0.);
+        }
+        if long == 0. || lat.abs() >= FRAC_PI_2 {
+            return (0., PI * lat.signum() * (theta / 2.).tan());
+        }
+        let (sin_theta, cos_theta) = theta.sin_cos();
+        let a = (PI / long - long / PI).abs() / 2.;
+        let g = cos_theta / (sin_theta + cos_theta - 1.);
+        let p = g * (2. / sin_theta - 1.);
+        let q = a * a + g;
+        let (a2, p2) = (a * a, p * p);
+        let x = (a * (g - p2)
+            + (a2 * (g - p2) * (g - p2) - (p2 + a2) * (g * g - p2))
+                .max(0.)
+                .sqrt())
+            / (p2 + a2);
+        let y = (p * q - a * ((a2 + 1.) * (p2 + a2) - q * q).max(0.).sqrt()).abs() / (p2 + a2);
+        (PI * long.signum() * x, PI * lat.signum() * y)
+    }
+
+    /// Snyder's closed form, p. 242. Nothing outside the circle.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (x, y) = (x / PI, y / PI);
+        let (x2, y2) = (x * x, y * y);
+        let r2 = x2 + y2;
+        if r2 > 1. {
+            return None;
+        }
+        let lat = match y == 0. {
+            true => 0.,
+            false => {
+                let c1 = -y.abs() * (1. + r2);
+                let c2 = c1 - 2. * y2 + x2;
+                let c3 = -2. * c1 + 1. + 2. * y2 + r2 * r2;
+                let d = y2 / c3 + (2. * c2.powi(3) / c3.powi(3) - 9. * c1 * c2 / (c3 * c3)) / 27.;
+                let a1 = (c1 - c2 * c2 / (3. * c3)) / c3;
+                let m1 = 2. * (-a1 / 3.).sqrt();
+                let theta1 = (3. * d / (a1 * m1)).clamp(-1., 1.).acos() / 3.;
+                let lat = PI * (-m1 * (theta1 + PI / 3.).cos() - c2 / (3. * c3));
+                y.signum() * lat.clamp(0., FRAC_PI_2)
+            }
+        };
+        let long = match x == 0. {
+            true => 0.,
+            false => PI * (r2 - 1. + (1. + 2. * (x2 - y2) + r2 * r2).sqrt()) / (2. * x),
+        };
+        Some((long, lat))
+    }
+}
+
+/// The American polyconic projection, in which every parallel is drawn as on
+/// a cone touching the globe along it, so that parallels and the central
+/// meridian are true to scale. The projection of the USGS topographic
+/// quadrangles until the 1950s.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Polyconic {
+    /// In degrees.
+    pub central_long: f64,
+    /// In degrees, the latitude drawn at the middle of the map.
+    pub origin_lat: f64,
+}
+
+impl Polyconic {
+    /// How far north of the equator the world reaches, at 180° from the
+    /// central meridian around 40.9°N.
+    const MAX_Y: f64 = 2.407_633_359_663;
+    /// How many pieces the latitudes are split into to look for parallels
+    /// through a point, and how often each piece is halved once one is found.
+    const BRACKETS: usize = 64;
+    const BISECTIONS: usize = 48;
+}
+
+impl Spherical for Polyconic {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, Self::MAX_Y + self.origin_lat.to_radians().abs())
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let origin = self.origin_lat.to_radians();
+        if lat == 0. {
+            return (long, -origin);
+        }
+        let e = long * lat.sin();
+        let cot = 1. / lat.tan();
+        (cot * e.sin(), lat - origin + cot * (1. - e.cos()))
+    }
+
+    /// Each parallel is a circle through the central meridian, so this looks
+    /// for the parallels whose circle passes through the point, from the
+    /// equator out. Snyder's Newton iteration (p. 130) strays off towards the
+    /// poles from points far from the central meridian. Nothing beyond 180°
+    /// from the central meridian, where the outer meridians would fold back.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        // The map is symmetric about the equator, so look north only.
+        let a = y + self.origin_lat.to_radians();
+        let (north, a) = (a.signum(), a.abs());
+        if a == 0. {
+            return (x.abs() <= PI).then_some((x, 0.));
+        }
+        // Times tan φ, so that it doesn't blow up at the equator.
+        let off_circle = |lat: f64| (x * x + (a - lat) * (a - lat)) * lat.tan() - 2. * (a - lat);
+        let point = |lat: f64| {
+            // Of sin E = x tan φ and cos E = 1 - (A - φ) tan φ, as E may pass 90°.
+            let tan = lat.tan();
+            let long = (x * tan).atan2(1. - (a - lat) * tan) / lat.sin();
+            let (x_back, y_back) = self.forward(long, lat);
+            let y_back = y_back + self.origin_lat.to_radians();
+            (long.abs() <= PI && (x_back - x).hypot(y_back - a) < 1e-6).then_some((long, lat))
+        };
+        let step = FRAC_PI_2 / Self::BRACKETS as f64;
+        (0..Self::BRACKETS)
+            .map(|index| (index as f64 * step, (index + 1) as f64 * step))
+            .filter(|(low, high)| off_circle(*low) * off_circle(*high) <= 0.)
+            .find_map(|(mut low, mut high)| {
+                for _ in 0..Self::BISECTIONS {
+                    let middle = (low + high) / 2.;
+                    match (off_circle(middle) < 0.) == (off_circle(low) < 0.) {
+                        true => low = middle,
+                        false => high = middle,
+                    }
+                }
+                point((low + high) / 2.).map(|(long, lat)| (long, north * lat))
+            })
+    }
+}
+
+spherical!(Polyconic, VanDerGrinten);
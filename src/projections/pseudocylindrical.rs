@@ -0,0 +1,624 @@
+use std::f64::consts::{FRAC_PI_2, PI, SQRT_2};
+
+use super::{spherical, Spherical};
+
+/// The equal-area projection of the whole world into an ellipse twice as wide
+/// as it is high, with straight parallels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mollweide {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Mollweide {
+    /// Solves 2θ + sin 2θ = π sin φ for the auxiliary angle θ by Newton's
+    /// method, which converges slowly towards the poles, where θ = φ.
+    fn theta(lat: f64) -> f64 {
+        if FRAC_PI_2 - lat.abs() < 1e-12 {
+            return lat;
+        }
+        let target = PI * lat.sin();
+        // In terms of 2θ, which keeps the steps small.
+        let mut double = lat;
+        for _ in 0..100 {
+            let step = (double + double.sin() - target) / (1. + double.cos());
+            double -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        double / 2.
+    }
+}
+
+impl Spherical for Mollweide {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (2. * SQRT_2, SQRT_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let theta = Self::theta(lat);
+        (2. * SQRT_2 / PI * long * theta.cos(), SQRT_2 * theta.sin())
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if y.abs() > SQRT_2 {
+            return None;
+        }
+        let theta = (y / SQRT_2).asin();
+        let lat = ((2. * theta + (2. * theta).sin()) / PI)
+            .clamp(-1., 1.)
+            .asin();
+        let long = match theta.cos() {
+            // At the poles, where the outline comes to a point.
+            cos if cos < 1e-12 => 0.,
+            cos => PI * x / (2. * SQRT_2 * cos),
+        };
+        match long.abs() <= PI {
+            true => Some((long, lat)),
+            false => None,
+        }
+    }
+}
+
+/// A compromise that looks right rather than preserving any property, defined
+/// by a table of the length of each parallel and its distance from the equator
+/// for every 5° of latitude.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Robinson {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Robinson {
+    /// Relative length and distance from the equator of the parallels from
+    /// 0° to 90°, as published by Robinson.
+    const TABLE: [(f64, f64); 19] = [
+        (1.0000, 0.0000),
+        (0.9986, 0.0620),
+        (0.9954, 0.1240),
+        (0.9900, 0.1860),
+        (0.9822, 0.2480),
+        (0.9730, 0.3100),
+        (0.9600, 0.3720),
+        (0.9427, 0.4340),
+        (0.9216, 0.4958),
+        (0.8962, 0.5571),
+        (0.8679, 0.6176),
+        (0.8350, 0.6769),
+        (0.7986, 0.7346),
+        (0.7597, 0.7903),
+        (0.7186, 0.8435),
+        (0.6732, 0.8936),
+        (0.6213, 0.9394),
+        (0.5722, 0.9761),
+        (0.5322, 1.0000),
+    ];
+    const STEP: f64 = 5.;
+    const WIDTH: f64 = 0.8487;
+    const HEIGHT: f64 = 1.3523;
+
+    /// A row of the table, mirrored across the equator and continued in a
+    /// straight line past the pole, so that every interval has neighbors.
+    fn row(index: isize) -> (f64, f64) {
+        let last = Self::TABLE.len() as isize - 1;
+        match index {
+            ..=-1 => {
+                let (length, distance) = Self::TABLE[index.unsigned_abs()];
+                (length, -distance)
+            }
+            index if index > last => {
+                let (a, b) = (Self::TABLE[last as usize], Self::TABLE[last as usize - 1]);
+                (2. * a.0 - b.0, 2. * a.1 - b.1)
+            }
+            index => Self::TABLE[index as usize],
+        }
+    }
+
+    /// The length and distance of the parallel at `lat` degrees north or
+    /// south, interpolated between the rows with a Catmull–Rom spline.
+    fn parallel(lat: f64) -> (f64, f64) {
+        let position = lat.abs() / Self::STEP;
+        let index = (position.floor() as isize).min(Self::TABLE.len() as isize - 2);
+        let t = position - index as f64;
+        let spline = |p0: f64, p1: f64, p2: f64, p3: f64| {
+            0.5 * (2. * p1
+                + (p2 - p0) * t
+                + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t * t
+                + (3. * (p1 - p2) + p3 - p0) * t * t * t)
+        };
+        let [p0, p1, p2, p3] = [-1, 0, 1, 2].map(|offset| Self::row(index + offset));
+        (
+            spline(p0.0, p1.0, p2.0, p3.0),
+            spline(p0.1, p1.1, p2.1, p3.1),
+        )
+    }
+}
+
+impl Spherical for Robinson {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (Self::WIDTH * PI, Self::HEIGHT)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let (length, distance) = Self::parallel(lat.to_degrees());
+        (
+            Self::WIDTH * length * long,
+            Self::HEIGHT * distance.copysign(lat),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let distance = y.abs() / Self::HEIGHT;
+        if distance > 1. {
+            return None;
+        }
+        // The distance grows with latitude, so bisection finds the parallel.
+        let (mut low, mut high) = (0., 90.);
+        for _ in 0..60 {
+            let middle = (low + high) / 2.;
+            match Self::parallel(middle).1 < distance {
+                true => low = middle,
+                false => high = middle,
+            }
+        }
+        let lat = (low + high) / 2.;
+        let long = x / (Self::WIDTH * Self::parallel(lat).0);
+        match long.abs() <= PI {
+            true => Some((long, lat.to_radians().copysign(y))),
+            false => None,
+        }
+    }
+}
+
+/// Šavrič, Jenny, Patterson and Hurni's compromise, which looks like Robinson
+/// but is given by polynomials in the latitude instead of a table, and has
+/// rounded corners where the pole lines meet the outer meridians.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NaturalEarth {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl NaturalEarth {
+    /// The length of the parallel at `lat` radians, relative to the equator's
+    /// in radians of longitude.
+    fn length(lat: f64) -> f64 {
+        let lat2 = lat * lat;
+        let lat4 = lat2 * lat2;
+        0.870_700 - 0.131_979 * lat2
+            + lat4 * (-0.013_791 + lat4 * lat2 * (0.003_971 - 0.001_529 * lat2))
+    }
+
+    /// How far north of the equator the parallel at `lat` is, and how fast
+    /// that grows with the latitude.
+    fn distance(lat: f64) -> (f64, f64) {
+        let lat2 = lat * lat;
+        let lat6 = lat2 * lat2 * lat2;
+        (
+            lat * (1.007_226
+                + 0.015_085 * lat2
+                + lat6 * (-0.044_475 + 0.028_874 * lat2 - 0.005_916 * lat2 * lat2)),
+            1.007_226
+                + 3. * 0.015_085 * lat2
+                + lat6 * (-7. * 0.044_475 + 9. * 0.028_874 * lat2 - 11. * 0.005_916 * lat2 * lat2),
+        )
+    }
+}
+
+impl Spherical for NaturalEarth {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (Self::length(0.) * PI, Self::distance(FRAC_PI_2).0)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (Self::length(lat) * long, Self::distance(lat).0)
+    }
+
+    /// Solves for the latitude by Newton's method, from the equator's scale.
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if y.abs() > Self::distance(FRAC_PI_2).0 {
+            return None;
+        }
+        let mut lat = y;
+        for _ in 0..100 {
+            let (distance, slope) = Self::distance(lat);
+            let step = (distance - y) / slope;
+            lat = (lat - step).clamp(-FRAC_PI_2, FRAC_PI_2);
+            if step.abs() < 1e-12 {
+                break;
+            }
+        }
+        let long = x / Self::length(lat);
+        match long.abs() <= PI {
+            true => Some((long, lat)),
+            false => None,
+        }
+    }
+}
+
+/// Kavrayskiy's seventh projection, a compromise with parallels spaced evenly
+/// and meridians drawn as ellipses, well liked for its low distortion. The
+/// poles are lines half as long as the equator.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KavrayskiyVII {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl KavrayskiyVII {
+    /// The length of the parallel at `lat` radians, relative to the equator's
+    /// in radians of longitude. Always positive, even at the poles.
+    fn length(lat: f64) -> f64 {
+        3. / (2. * PI) * (PI * PI / 3. - lat * lat).sqrt()
+    }
+}
+
+impl Spherical for KavrayskiyVII {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (Self::length(0.) * PI, FRAC_PI_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (Self::length(lat) * long, lat)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if y.abs() > FRAC_PI_2 {
+            return None;
+        }
+        let long = x / Self::length(y);
+        match long.abs() <= PI {
+            true => Some((long, y)),
+            false => None,
+        }
+    }
+}
+
+/// The equal-area Sanson–Flamsteed projection, with parallels spaced evenly
+/// and drawn true to length, so that the meridians are sine curves meeting at
+/// the poles. Nothing is drawn outside the lens they enclose.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Sinusoidal {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl Spherical for Sinusoidal {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, FRAC_PI_2)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        (long * lat.cos(), lat)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        if y.abs() > FRAC_PI_2 {
+            return None;
+        }
+        let long = match y.cos() {
+            cos if cos < 1e-12 => 0.,
+            cos => x / cos,
+        };
+        match long.abs() <= PI {
+            true => Some((long, y)),
+            false => None,
+        }
+    }
+}
+
+/// Eckert's equal-area projection with the poles drawn as lines half as long
+/// as the equator, and semicircles for the outer meridians.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EckertIV {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl EckertIV {
+    /// Solves θ + sin θ cos θ + 2 sin θ = (2 + π/2) sin φ for the parametric
+    /// angle θ by Newton's method, which converges slowly towards the poles,
+    /// where θ = φ.
+    fn theta(lat: f64) -> f64 {
+        if FRAC_PI_2 - lat.abs() < 1e-12 {
+            return lat;
+        }
+        let target = (2. + FRAC_PI_2) * lat.sin();
+        let mut theta = lat / 2.;
+        for _ in 0..100 {
+            let (sin, cos) = theta.sin_cos();
+            let step = (theta + sin * cos + 2. * sin - target) / (2. * cos * (1. + cos));
+            theta -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        theta
+    }
+
+    fn x_scale() -> f64 {
+        2. / (PI * (4. + PI)).sqrt()
+    }
+
+    fn y_scale() -> f64 {
+        2. * (PI / (4. + PI)).sqrt()
+    }
+}
+
+impl Spherical for EckertIV {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (2. * PI * Self::x_scale(), Self::y_scale())
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let theta = Self::theta(lat);
+        (
+            Self::x_scale() * long * (1. + theta.cos()),
+            Self::y_scale() * theta.sin(),
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let height = Self::y_scale();
+        if y.abs() > height {
+            return None;
+        }
+        let theta = (y / height).clamp(-1., 1.).asin();
+        let (sin, cos) = theta.sin_cos();
+        let lat = ((theta + sin * cos + 2. * sin) / (2. + FRAC_PI_2))
+            .clamp(-1., 1.)
+            .asin();
+        // The poles are lines, so 1 + cos θ is never below 1.
+        let long = x / (Self::x_scale() * (1. + cos));
+        match long.abs() <= PI {
+            true => Some((long, lat)),
+            false => None,
+        }
+    }
+}
+
+/// Eckert's equal-area projection with the poles drawn as lines half as long
+/// as the equator, and sine curves for the meridians.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EckertVI {
+    /// In degrees.
+    pub central_long: f64,
+}
+
+impl EckertVI {
+    /// Solves θ + sin θ = (1 + π/2) sin φ for the parametric angle θ by
+    /// Newton's method.
+    fn theta(lat: f64) -> f64 {
+        let target = (1. + FRAC_PI_2) * lat.sin();
+        let mut theta = lat;
+        for _ in 0..100 {
+            let step = (theta + theta.sin() - target) / (1. + theta.cos());
+            theta -= step;
+            if step.abs() < 1e-14 {
+                break;
+            }
+        }
+        theta.clamp(-FRAC_PI_2, FRAC_PI_2)
+    }
+
+    fn scale() -> f64 {
+        1. / (2. + PI).sqrt()
+    }
+}
+
+impl Spherical for EckertVI {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (2. * PI * Self::scale(), PI * Self::scale())
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let theta = Self::theta(lat);
+        (
+            Self::scale() * long * (1. + theta.cos()),
+            Self::scale() * 2. * theta,
+        )
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let theta = y / (2. * Self::scale());
+        if theta.abs() > FRAC_PI_2 {
+            return None;
+        }
+        let lat = ((theta + theta.sin()) / (1. + FRAC_PI_2))
+            .clamp(-1., 1.)
+            .asin();
+        // The poles are lines, so 1 + cos θ is never below 1.
+        let long = x / (Self::scale() * (1. + theta.cos()));
+        match long.abs() <= PI {
+            true => Some((long, lat)),
+            false => None,
+        }
+    }
+}
+
+/// Where Goode's homolosine projection is interrupted, to keep either the
+/// continents or the oceans whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interruptions {
+    #[default]
+    Land,
+    Ocean,
+}
+
+impl Interruptions {
+    pub const ALL: [Interruptions; 2] = [Interruptions::Land, Interruptions::Ocean];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interruptions::Land => "Continents whole",
+            Interruptions::Ocean => "Oceans whole",
+        }
+    }
+
+    /// The lobes of the northern or southern hemisphere from west to east,
+    /// each as its western edge, central meridian and eastern edge in degrees
+    /// from the map's central meridian.
+    fn lobes(&self, north: bool) -> &'static [(f64, f64, f64)] {
+        match (self, north) {
+            (Interruptions::Land, true) => &[(-180., -100., -40.), (-40., 30., 180.)],
+            (Interruptions::Land, false) => &[
+                (-180., -160., -100.),
+                (-100., -60., -20.),
+                (-20., 20., 80.),
+                (80., 140., 180.),
+            ],
+            (Interruptions::Ocean, true) => {
+                &[(-180., -140., -90.), (-90., -10., 60.), (60., 130., 180.)]
+            }
+            (Interruptions::Ocean, false) => {
+                &[(-180., -110., -60.), (-60., 20., 90.), (90., 150., 180.)]
+            }
+        }
+    }
+
+    /// As feature matching and manifests take it, 0 or 1.
+    pub fn parameter(&self) -> f64 {
+        match self {
+            Interruptions::Land => 0.,
+            Interruptions::Ocean => 1.,
+        }
+    }
+
+    pub fn from_parameter(value: f64) -> Self {
+        match value < 0.5 {
+            true => Interruptions::Land,
+            false => Interruptions::Ocean,
+        }
+    }
+}
+
+/// Goode's interrupted homolosine projection: equal-area, sinusoidal near the
+/// equator and Mollweide towards the poles, and cut into lobes around central
+/// meridians of their own. Nothing is drawn in the gaps between the lobes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GoodeHomolosine {
+    /// In degrees.
+    pub central_long: f64,
+    pub interruptions: Interruptions,
+}
+
+impl GoodeHomolosine {
+    /// Where the two projections meet, 40°44′11.8″, in radians.
+    const JOIN_LAT: f64 = 0.710_987_989_993;
+    /// How far the Mollweide part is moved towards the equator to meet the
+    /// sinusoidal one there.
+    const MOLLWEIDE_SHIFT: f64 = 0.052_803_527_368;
+
+    /// The uninterrupted projection, as the height of the parallel at `lat`
+    /// and how much shorter than the equator it is drawn.
+    fn parallel(lat: f64) -> (f64, f64) {
+        if lat.abs() <= Self::JOIN_LAT {
+            return (lat, lat.cos());
+        }
+        let theta = Mollweide::theta(lat);
+        (
+            (SQRT_2 * theta.sin().abs() - Self::MOLLWEIDE_SHIFT).copysign(lat),
+            2. * SQRT_2 / PI * theta.cos(),
+        )
+    }
+
+    /// The latitude of the parallel drawn at `y` and how much shorter than the
+    /// equator it is, or `None` beyond the poles.
+    fn parallel_at(y: f64) -> Option<(f64, f64)> {
+        if y.abs() <= Self::JOIN_LAT {
+            return Some((y, y.cos()));
+        }
+        let sin_theta = (y.abs() + Self::MOLLWEIDE_SHIFT) / SQRT_2;
+        if sin_theta > 1. + 1e-12 {
+            return None;
+        }
+        let theta = sin_theta.min(1.).asin();
+        let lat = ((2. * theta + (2. * theta).sin()) / PI)
+            .clamp(-1., 1.)
+            .asin();
+        Some((lat.copysign(y), 2. * SQRT_2 / PI * theta.cos()))
+    }
+}
+
+impl Spherical for GoodeHomolosine {
+    fn central_long(&self) -> f64 {
+        self.central_long
+    }
+
+    fn extent(&self) -> (f64, f64) {
+        (PI, SQRT_2 - Self::MOLLWEIDE_SHIFT)
+    }
+
+    fn forward(&self, long: f64, lat: f64) -> (f64, f64) {
+        let degrees = long.to_degrees();
+        let lobes = self.interruptions.lobes(lat >= 0.);
+        let &(_, center, _) = lobes
+            .iter()
+            .find(|(west, _, east)| (*west..=*east).contains(&degrees))
+            .unwrap_or(&lobes[lobes.len() - 1]);
+        let center = center.to_radians();
+        let (y, scale) = Self::parallel(lat);
+        (center + (long - center) * scale, y)
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> Option<(f64, f64)> {
+        let (lat, scale) = Self::parallel_at(y)?;
+        // The lobes keep their order along every parallel, but leave gaps.
+        self.interruptions
+            .lobes(y >= 0.)
+            .iter()
+            .find_map(|&(west, center, east)| {
+                let center = center.to_radians();
+                let long = match scale {
+                    // At the poles, where each lobe comes to a point.
+                    scale if scale < 1e-12 => center,
+                    scale => center + (x - center) / scale,
+                };
+                let degrees = long.to_degrees();
+                match (west - 1e-9..=east + 1e-9).contains(&degrees) {
+                    true => Some((long.clamp(-PI, PI), lat)),
+                    false => None,
+                }
+            })
+    }
+}
+
+spherical!(
+    EckertIV,
+    EckertVI,
+    GoodeHomolosine,
+    KavrayskiyVII,
+    Mollweide,
+    NaturalEarth,
+    Robinson,
+    Sinusoidal
+);
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use eframe::egui;
+use tiff::decoder::{Decoder, DecodingResult};
+use tiff::tags::Tag;
+use tiff::ColorType;
+
+use crate::jobs::Progress;
+use crate::network::Downloader;
+
+/// Remote files are fetched in pieces of this size. The header of a
+/// cloud-optimized GeoTIFF comes first and fits in one.
+const BLOCK_SIZE: u64 = 256 << 10;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The address of a remote file, if `path` is one rather than a local file.
+pub fn url(path: &Path) -> Option<&str> {
+    let path = path.to_str()?;
+    match path.starts_with("http://") || path.starts_with("https://") {
+        true => Some(path),
+        false => None,
+    }
+}
+
+/// Reads a file over HTTP as if it were local, fetching only the parts that
+/// are read with range requests and keeping them for when they are read again.
+pub struct RangeReader {
+    downloader: Downloader,
+    url: String,
+    length: u64,
+    position: u64,
+    blocks: HashMap<u64, Vec<u8>>,
+}
+
+impl RangeReader {
+    pub fn open(url: &str) -> Result<Self, String> {
+        let mut reader = RangeReader {
+            downloader: Downloader::new(TIMEOUT),
+            url: url.to_string(),
+            length: u64::MAX,
+            position: 0,
+            blocks: HashMap::new(),
+        };
+        // Also finds out how long the file is.
+        reader.block(0).map_err(|e| e.to_string())?;
+        Ok(reader)
+    }
+
+    /// The size of the whole file, in bytes.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// How much of the file has been fetched, in bytes.
+    pub fn fetched(&self) -> u64 {
+        self.blocks.values().map(|block| block.len() as u64).sum()
+    }
+
+    fn fetch(&mut self, index: u64) -> Result<Vec<u8>, String> {
+        let start = index * BLOCK_SIZE;
+        let end = (start + BLOCK_SIZE).min(self.length) - 1;
+        let (data, length) = self.downloader.range(&self.url, start, end)?;
+        self.length = length;
+        Ok(data)
+    }
+
+    fn block(&mut self, index: u64) -> std::io::Result<&[u8]> {
+        if !self.blocks.contains_key(&index) {
+            let block = self.fetch(index).map_err(std::io::Error::other)?;
+            self.blocks.insert(index, block);
+        }
+        Ok(&self.blocks[&index])
+    }
+}
+
+impl Read for RangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.position >= self.length || buf.is_empty() {
+            return Ok(0);
+        }
+        let offset = (self.position % BLOCK_SIZE) as usize;
+        let block = self.block(self.position / BLOCK_SIZE)?;
+        let count = buf.len().min(block.len().saturating_sub(offset));
+        buf[..count].copy_from_slice(&block[offset..offset + count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Seek for RangeReader {
+    fn seek(&mut self, position: SeekFrom) -> std::io::Result<u64> {
+        let position = match position {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        let Some(position) = position else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek to before the start of the file",
+            ));
+        };
+        self.position = position;
+        Ok(position)
+    }
+}
+
+/// The part of an image to read, as fractions of its width and height.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub left: f64,
+    pub top: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Region {
+            left: 0.,
+            top: 0.,
+            right: 1.,
+            bottom: 1.,
+        }
+    }
+}
+
+impl Region {
+    /// The pixels it covers in an image of the given size, as the left, top,
+    /// right and bottom edges, with at least one pixel each way.
+    fn pixels(&self, width: u32, height: u32) -> [u32; 4] {
+        let edge = |fraction: f64, size: u32| (fraction.clamp(0., 1.) * size as f64) as u32;
+        let (left, top) = (
+            edge(self.left, width).min(width - 1),
+            edge(self.top, height).min(height - 1),
+        );
+        [
+            left,
+            top,
+            edge(self.right, width).max(left + 1),
+            edge(self.bottom, height).max(top + 1),
+        ]
+    }
+}
+
+/// What to read of a remote image: a region, at the coarsest of its
+/// resolutions that still gives the region `max_side` pixels along its longer side.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub url: String,
+    pub max_side: u32,
+    pub region: Region,
+}
+
+impl Default for Request {
+    fn default() -> Self {
+        Request {
+            url: String::new(),
+            max_side: 8192,
+            region: Default::default(),
+        }
+    }
+}
+
+impl Request {
+    pub fn path(&self) -> PathBuf {
+        PathBuf::from(&self.url)
+    }
+}
+
+/// One resolution of a tiled TIFF: the full image or one of its overviews.
+struct Level {
+    index: usize,
+    width: u32,
+    height: u32,
+}
+
+fn levels<R: Read + Seek>(decoder: &mut Decoder<R>) -> Result<Vec<Level>, String> {
+    let mut levels: Vec<Level> = Vec::new();
+    let mut index = 0;
+    loop {
+        let (width, height) = decoder.dimensions().map_err(|e| e.to_string())?;
+        let subfile_type = decoder
+            .find_tag_unsigned::<u32>(Tag::NewSubfileType)
+            .ok()
+            .flatten()
+            .unwrap_or(0);
+        // Transparency masks aren't images of their own, and other pages of a
+        // multi-page TIFF aren't necessarily overviews.
+        let smaller = match levels.first() {
+            Some(full) => width <= full.width && height <= full.height,
+            None => true,
+        };
+        if subfile_type & 4 == 0 && smaller && width > 0 && height > 0 {
+            levels.push(Level {
+                index,
+                width,
+                height,
+            });
+        }
+        if !decoder.more_images() {
+            break;
+        }
+        decoder.next_image().map_err(|e| e.to_string())?;
+        index += 1;
+    }
+    levels.sort_by_key(|level| std::cmp::Reverse(level.width));
+    Ok(levels)
+}
+
+/// The smallest level that still gives the region of `request` its
+/// `max_side`, or the full image.
+fn level<'a>(levels: &'a [Level], request: &Request) -> Result<&'a Level, String> {
+    let Some(full) = levels.first() else {
+        return Err(format!("{} holds no image.", request.url));
+    };
+    let longer_side = |level: &Level| {
+        let [left, top, right, bottom] = request.region.pixels(level.width, level.height);
+        (right - left).max(bottom - top)
+    };
+    Ok(levels
+        .iter()
+        .rev()
+        .find(|level| longer_side(level) >= request.max_side)
+        .unwrap_or(full))
+}
+
+/// The size of the image `read` makes of `request`, from the header of the
+/// remote TIFF alone.
+pub fn dimensions(request: &Request) -> Result<(u32, u32), String> {
+    let mut decoder = Decoder::new(RangeReader::open(&request.url)?)
+        .map_err(|e| format!("{} is not a TIFF: {}", request.url, e))?
+        .with_limits(tiff::decoder::Limits::unlimited());
+    let levels = levels(&mut decoder)?;
+    let level = level(&levels, request)?;
+    let [left, top, right, bottom] = request.region.pixels(level.width, level.height);
+    Ok((right - left, bottom - top))
+}
+
+/// Copies the part of a chunk at `origin` that lies in `window` into the
+/// window's pixels.
+fn place<T: Copy>(
+    chunk: &[T],
+    (chunk_width, chunk_height): (u32, u32),
+    origin: (u32, u32),
+    [left, top, right, bottom]: [u32; 4],
+    channels: usize,
+    pixels: &mut [T],
+) {
+    let window_width = (right - left) as usize;
+    let (from_x, to_x) = (origin.0.max(left), (origin.0 + chunk_width).min(right));
+    let (from_y, to_y) = (origin.1.max(top), (origin.1 + chunk_height).min(bottom));
+    if from_x >= to_x {
+        return;
+    }
+    let count = (to_x - from_x) as usize * channels;
+    for y in from_y..to_y {
+        let source = (((y - origin.1) * chunk_width + from_x - origin.0) as usize) * channels;
+        let target = (((y - top) as usize) * window_width + (from_x - left) as usize) * channels;
+        pixels[target..target + count].copy_from_slice(&chunk[source..source + count]);
+    }
+}
+
+/// Reads the region of a tiled or striped TIFF at the resolution asked for,
+/// decoding only the chunks that overlap it. Returns the image and which
+/// level it came from.
+pub fn read_from<R: Read + Seek>(
+    reader: R,
+    request: &Request,
+    progress: &Progress,
+) -> Result<(image::DynamicImage, String), String> {
+    let mut decoder = Decoder::new(reader)
+        .map_err(|e| format!("{} is not a TIFF: {}", request.url, e))?
+        .with_limits(tiff::decoder::Limits::unlimited());
+    let levels = levels(&mut decoder)?;
+    let level = level(&levels, request)?;
+    let region = request.region;
+    decoder
+        .seek_to_image(level.index)
+        .map_err(|e| e.to_string())?;
+    if let Ok(Some(2)) = decoder.find_tag_unsigned::<u16>(Tag::PlanarConfiguration) {
+        return Err(format!(
+            "{} keeps each channel separately, which can't be read remotely.",
+            request.url
+        ));
+    }
+    let (channels, sixteen_bits) = match decoder.colortype().map_err(|e| e.to_string())? {
+        ColorType::Gray(8) => (1, false),
+        ColorType::GrayA(8) => (2, false),
+        ColorType::RGB(8) => (3, false),
+        ColorType::RGBA(8) => (4, false),
+        ColorType::Gray(16) => (1, true),
+        ColorType::GrayA(16) => (2, true),
+        ColorType::RGB(16) => (3, true),
+        ColorType::RGBA(16) => (4, true),
+        color_type => {
+            return Err(format!(
+                "{} is in a color type that can't be read remotely: {:?}",
+                request.url, color_type
+            ))
+        }
+    };
+    let window = region.pixels(level.width, level.height);
+    let [left, top, right, bottom] = window;
+    let (width, height) = (right - left, bottom - top);
+    let color_type = match sixteen_bits {
+        true => image::ColorType::Rgba16,
+        false => image::ColorType::Rgba8,
+    };
+    crate::loading::check_size(&request.path(), width, height, color_type)?;
+    let (chunk_width, chunk_height) = decoder.chunk_dimensions();
+    let across = level.width.div_ceil(chunk_width);
+    let columns = left / chunk_width..right.div_ceil(chunk_width);
+    let rows = top / chunk_height..bottom.div_ceil(chunk_height);
+    let chunks = columns.len() * rows.len();
+    let length = width as usize * height as usize * channels;
+    let (mut eight, mut sixteen) = match sixteen_bits {
+        true => (Vec::new(), vec![0u16; length]),
+        false => (vec![0u8; length], Vec::new()),
+    };
+    for (done, (row, column)) in rows
+        .flat_map(|row| columns.clone().map(move |column| (row, column)))
+        .enumerate()
+    {
+        let index = row * across + column;
+        let size = decoder.chunk_data_dimensions(index);
+        let origin = (column * chunk_width, row * chunk_height);
+        match decoder.read_chunk(index).map_err(|e| e.to_string())? {
+            DecodingResult::U8(chunk) => place(&chunk, size, origin, window, channels, &mut eight),
+            DecodingResult::U16(chunk) => {
+                place(&chunk, size, origin, window, channels, &mut sixteen)
+            }
+            _ => return Err(format!("{} has samples of the wrong size.", request.url)),
+        }
+        progress.set((done + 1) as f32 / chunks as f32);
+    }
+    let image = match (channels, sixteen_bits) {
+        (1, false) => image::GrayImage::from_raw(width, height, eight).map(Into::into),
+        (2, false) => image::GrayAlphaImage::from_raw(width, height, eight).map(Into::into),
+        (3, false) => image::RgbImage::from_raw(width, height, eight).map(Into::into),
+        (4, false) => image::RgbaImage::from_raw(width, height, eight).map(Into::into),
+        (1, true) => image::ImageBuffer::from_raw(width, height, sixteen)
+            .map(image::DynamicImage::ImageLuma16),
+        (2, true) => image::ImageBuffer::from_raw(width, height, sixteen)
+            .map(image::DynamicImage::ImageLumaA16),
+        (3, true) => image::ImageBuffer::from_raw(width, height, sixteen)
+            .map(image::DynamicImage::ImageRgb16),
+        _ => image::ImageBuffer::from_raw(width, height, sixteen)
+            .map(image::DynamicImage::ImageRgba16),
+    }
+    .ok_or_else(|| format!("{} has less image data than its size needs.", request.url))?;
+    let description = format!(
+        "{} of {} × {} px, read in {} of {} chunks",
+        match level.index {
+            0 => "The full image".to_string(),
+            _ => format!("The overview at {} × {} px", level.width, level.height),
+        },
+        levels[0].width,
+        levels[0].height,
+        chunks,
+        across as usize * level.height.div_ceil(chunk_height) as usize
+    );
+    Ok((image, description))
+}
+
+/// Reads the smallest overview of a local TIFF that is at least `max_side`
+/// px across, or none if the file has no such overview.
+pub fn overview(path: &Path, max_side: u32) -> Option<image::DynamicImage> {
+    let request = Request {
+        url: path.display().to_string(),
+        max_side,
+        ..Default::default()
+    };
+    let open = || std::fs::File::open(path).ok().map(std::io::BufReader::new);
+    let mut decoder = Decoder::new(open()?).ok()?;
+    let levels = levels(&mut decoder).ok()?;
+    match level(&levels, &request).ok()?.index {
+        0 => None,
+        _ => read_from(open()?, &request, &Progress::default())
+            .ok()
+            .map(|(image, _)| image),
+    }
+}
+
+/// Reads a region of a cloud-optimized GeoTIFF over HTTP. Returns the image
+/// and what was read of the file.
+pub fn read(
+    request: &Request,
+    progress: &Progress,
+) -> Result<(image::DynamicImage, String), String> {
+    let mut reader = RangeReader::open(&request.url)?;
+    let (image, description) = read_from(&mut reader, request, progress)?;
+    Ok((
+        image,
+        format!(
+            "{}, {:.1} of {:.1} MB downloaded.",
+            description,
+            reader.fetched() as f64 / 1e6,
+            reader.length() as f64 / 1e6
+        ),
+    ))
+}
+
+/// Asks for the address of a remote image and what of it to read.
+#[derive(Default)]
+pub struct RemoteOpen {
+    pub open: bool,
+    pub request: Request,
+}
+
+/// Returns the request once the user asks to open it.
+pub fn remote_open_window(ctx: &egui::Context, remote: &mut RemoteOpen) -> Option<Request> {
+    let mut requested = None;
+    egui::Window::new("Open from URL")
+        .open(&mut remote.open)
+        .show(ctx, |ui| {
+            let request = &mut remote.request;
+            ui.label("A cloud-optimized GeoTIFF, of which only the tiles needed are downloaded.");
+            ui.add(
+                egui::TextEdit::singleline(&mut request.url)
+                    .hint_text("https://example.com/map.tif")
+                    .desired_width(f32::INFINITY),
+            );
+            ui.add(
+                egui::Slider::new(&mut request.max_side, 256..=65_536)
+                    .logarithmic(true)
+                    .suffix(" px")
+                    .text("Longer side at least"),
+            );
+            ui.label("Part of the image");
+            let region = &mut request.region;
+            egui::Grid::new("Remote region").show(ui, |ui| {
+                for (text, from, to) in [
+                    ("Across", &mut region.left, &mut region.right),
+                    ("Down", &mut region.top, &mut region.bottom),
+                ] {
+                    ui.label(text);
+                    ui.add(
+                        egui::DragValue::new(from)
+                            .clamp_range(0.0..=1.0)
+                            .speed(0.005)
+                            .custom_formatter(|value, _| format!("{:.1} %", value * 100.)),
+                    );
+                    ui.label("to");
+                    let min = *from;
+                    ui.add(
+                        egui::DragValue::new(to)
+                            .clamp_range(min..=1.0)
+                            .speed(0.005)
+                            .custom_formatter(|value, _| format!("{:.1} %", value * 100.)),
+                    );
+                    ui.end_row();
+                }
+            });
+            if ui
+                .add_enabled(url(&request.path()).is_some(), egui::Button::new("Open"))
+                .clicked()
+            {
+                requested = Some(request.clone());
+            }
+        });
+    if requested.is_some() {
+        remote.open = false;
+    }
+    requested
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tiff::encoder::{colortype, TiffEncoder};
+
+    #[test]
+    fn only_tiffs_with_overviews_have_one_to_preview() {
+        let path = std::env::temp_dir().join("submaptive-overview-test.tif");
+        let write = |sides: &[u32]| {
+            let mut encoder = TiffEncoder::new(std::fs::File::create(&path).unwrap()).unwrap();
+            for &side in sides {
+                let pixels = vec![128u8; (side * side) as usize];
+                encoder
+                    .write_image::<colortype::Gray8>(side, side, &pixels)
+                    .unwrap();
+            }
+        };
+        write(&[2048, 1024, 256]);
+        let preview = overview(&path, 512).unwrap();
+        assert_eq!((preview.width(), preview.height()), (1024, 1024));
+        write(&[2048]);
+        assert!(overview(&path, 512).is_none());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use crate::settings::config_directory;
+
+/// Left in the configuration directory while the app runs, so that a crash
+/// leaves it behind.
+const MARKER: &str = "running";
+
+fn marker() -> Option<PathBuf> {
+    config_directory().map(|directory| directory.join(MARKER))
+}
+
+/// Whether the last run ended without removing its marker.
+pub fn crashed() -> bool {
+    marker().is_some_and(|marker| marker.exists())
+}
+
+/// Marks the app as running until `finished` is called. Nothing can be done
+/// about failing to, other than not noticing a crash.
+pub fn started() {
+    if let Some(marker) = marker() {
+        let _ = std::fs::create_dir_all(marker.parent().unwrap());
+        let _ = std::fs::write(marker, std::process::id().to_string());
+    }
+}
+
+pub fn finished() {
+    if let Some(marker) = marker() {
+        let _ = std::fs::remove_file(marker);
+    }
+}
+
+/// Asks whether to start in safe mode, as the last run crashed.
+pub fn ask() -> bool {
+    rfd::MessageDialog::new()
+        .set_level(rfd::MessageLevel::Warning)
+        .set_title("Submaptive")
+        .set_description(
+            "Submaptive didn't close properly last time. Start in safe mode, with \
+             the default settings, software rendering and nothing restored from \
+             the last session?",
+        )
+        .set_buttons(rfd::MessageButtons::YesNo)
+        .show()
+}
+
+/// Renders without the graphics driver's acceleration, in case it is what
+/// crashed.
+pub fn software_rendering(options: &mut eframe::NativeOptions) {
+    options.hardware_acceleration = eframe::HardwareAcceleration::Off;
+    options.multisampling = 0;
+}
@@ -0,0 +1,325 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::ProjectionData;
+
+/// Each output pixel is split into at most this many samples across and down.
+const MAX_SAMPLES: u32 = 16;
+/// Adaptive sampling takes more samples in a pixel whose one half covers
+/// this many times as much of the source as the other.
+const MAX_SCALE_CHANGE: f64 = 1.5;
+/// The narrowest and widest projected maps that can be asked for.
+const MIN_WIDTH: u32 = 16;
+const MAX_WIDTH: u32 = 32768;
+
+/// How projected maps are sampled from the source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sampling {
+    /// The width of projected maps in pixels, or `None` for as wide as the
+    /// source. The height follows from the target projection.
+    pub output_width: Option<u32>,
+    /// Take `oversampling` samples across and down the pixels where the scale
+    /// changes quickly, such as near the poles and the edges of the map.
+    pub adaptive: bool,
+    pub oversampling: u32,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling {
+            output_width: None,
+            adaptive: false,
+            oversampling: 4,
+        }
+    }
+}
+
+impl Sampling {
+    /// The size of the map projected from a source of `source_dimensions`.
+    pub fn dimensions(&self, source_dimensions: (u32, u32), target: &ProjectionData) -> (u32, u32) {
+        use submaptive::Projection;
+        let width = self.output_width.unwrap_or(source_dimensions.0);
+        let aspect = target.dimensions().height() / target.dimensions().width();
+        (width, ((width as f64 * aspect).round() as u32).max(1))
+    }
+
+    /// Whether `submaptive` samples the map itself, one source pixel to each
+    /// projected one, rather than `resample`.
+    pub fn is_direct(&self, source_width: u32) -> bool {
+        !self.adaptive && self.output_width.is_none_or(|width| width == source_width)
+    }
+}
+
+/// Where in the source a pixel of the projected map shows, in source pixels.
+fn source_pixel(
+    source: &ProjectionData,
+    target: &ProjectionData,
+    source_dimensions: (u32, u32),
+    dimensions: (u32, u32),
+    pixel: (f64, f64),
+) -> Option<(f64, f64)> {
+    let point = target.point_at_pixel(pixel, dimensions)?;
+    source.pixel_at_point(&point, source_dimensions)
+}
+
+/// How a projected pixel lies over the source.
+struct Footprint {
+    /// How many source pixels it spans along each of its sides.
+    across: f64,
+    down: f64,
+    /// How many times as much of the source one half of it covers as the
+    /// other, along the side where they differ most. Infinite at the edge of
+    /// the map, where one half shows nothing.
+    scale_change: f64,
+}
+
+/// Where the middles of the edges of the projected pixel at `pixel` land, or
+/// `None` if none of them shows anything. A jump across the source's left and
+/// right edges counts the short way round.
+fn footprint(
+    source: &ProjectionData,
+    target: &ProjectionData,
+    source_dimensions: (u32, u32),
+    dimensions: (u32, u32),
+    pixel: (f64, f64),
+) -> Option<Footprint> {
+    let at = |offset: (f64, f64)| {
+        source_pixel(
+            source,
+            target,
+            source_dimensions,
+            dimensions,
+            (pixel.0 + offset.0, pixel.1 + offset.1),
+        )
+    };
+    let [right, left, below, above] = [(0.5, 0.), (-0.5, 0.), (0., 0.5), (0., -0.5)].map(at);
+    let Some(center) = at((0., 0.)) else {
+        return [right, left, below, above]
+            .iter()
+            .any(Option::is_some)
+            .then_some(Footprint {
+                across: 1.,
+                down: 1.,
+                scale_change: f64::INFINITY,
+            });
+    };
+    let distance = |edge: Option<(f64, f64)>| {
+        edge.map(|edge| {
+            let dx = (edge.0 - center.0).abs();
+            let dx = dx.min(source_dimensions.0 as f64 - dx);
+            dx.hypot(edge.1 - center.1)
+        })
+    };
+    // The span along one side and how differently its halves are scaled.
+    let side = |a: Option<(f64, f64)>, b: Option<(f64, f64)>| match (distance(a), distance(b)) {
+        (Some(a), Some(b)) => (a + b, a.max(b) / a.min(b).max(f64::MIN_POSITIVE)),
+        (Some(half), None) | (None, Some(half)) => (2. * half, f64::INFINITY),
+        (None, None) => (1., f64::INFINITY),
+    };
+    let (across, change_across) = side(right, left);
+    let (down, change_down) = side(below, above);
+    Some(Footprint {
+        across,
+        down,
+        scale_change: change_across.max(change_down),
+    })
+}
+
+/// Projects `prepared` from `source` into `target` at `dimensions`. Where a
+/// projected pixel covers more than one source pixel, as when the map is made
+/// much smaller than the source, it is the average of evenly spread samples
+/// over what it covers rather than a single one, so that fine detail neither
+/// drops out nor turns into moiré. Adaptive `sampling` also takes more
+/// samples where the scale changes within a pixel, which a single sample
+/// would get wrong. Transparent source pixels count as such. The samples lie
+/// on a fixed grid in each pixel rather than being jittered, so the same
+/// source and settings give the same pixels on every run without a seed.
+pub fn resample(
+    prepared: &image::DynamicImage,
+    source: &ProjectionData,
+    target: &ProjectionData,
+    dimensions: (u32, u32),
+    sampling: &Sampling,
+) -> image::DynamicImage {
+    let prepared = prepared.to_rgba8();
+    let source_dimensions = prepared.dimensions();
+    let (width, height) = dimensions;
+    let sample = |pixel: (f64, f64)| {
+        let (x, y) = source_pixel(source, target, source_dimensions, dimensions, pixel)?;
+        let x = (x.floor() as i64).clamp(0, source_dimensions.0 as i64 - 1) as u32;
+        let y = (y.floor() as i64).clamp(0, source_dimensions.1 as i64 - 1) as u32;
+        Some(prepared.get_pixel(x, y).0)
+    };
+    let pixel = |x: u32, y: u32| {
+        let center = (x as f64 + 0.5, y as f64 + 0.5);
+        let footprint = footprint(source, target, source_dimensions, dimensions, center);
+        let samples = |span: f64| (span.ceil() as u32).clamp(1, MAX_SAMPLES);
+        let (across, down) = match footprint {
+            Some(footprint) if sampling.adaptive && footprint.scale_change > MAX_SCALE_CHANGE => (
+                samples(footprint.across).max(sampling.oversampling),
+                samples(footprint.down).max(sampling.oversampling),
+            ),
+            Some(footprint) => (samples(footprint.across), samples(footprint.down)),
+            None => (1, 1),
+        };
+        // Premultiplied, so that transparent samples don't darken the average.
+        let mut sum = [0.; 4];
+        for j in 0..down {
+            for i in 0..across {
+                let offset = (
+                    (i as f64 + 0.5) / across as f64 - 0.5,
+                    (j as f64 + 0.5) / down as f64 - 0.5,
+                );
+                if let Some(color) = sample((center.0 + offset.0, center.1 + offset.1)) {
+                    let alpha = color[3] as f64;
+                    for channel in 0..3 {
+                        sum[channel] += color[channel] as f64 * alpha;
+                    }
+                    sum[3] += alpha;
+                }
+            }
+        }
+        let count = (across * down) as f64;
+        match sum[3] > 0. {
+            true => image::Rgba([
+                (sum[0] / sum[3]).round() as u8,
+                (sum[1] / sum[3]).round() as u8,
+                (sum[2] / sum[3]).round() as u8,
+                (sum[3] / count).round() as u8,
+            ]),
+            false => image::Rgba([0, 0, 0, 0]),
+        }
+    };
+    let threads = std::thread::available_parallelism().map_or(1, |cores| cores.get()) as u32;
+    let rows_per_thread = height.div_ceil(threads).max(1);
+    let mut projected = image::RgbaImage::new(width, height);
+    let bands: Vec<_> = std::thread::scope(|scope| {
+        let pixel = &pixel;
+        let bands: Vec<_> = (0..height)
+            .step_by(rows_per_thread as usize)
+            .map(|top| {
+                let bottom = (top + rows_per_thread).min(height);
+                scope.spawn(move || {
+                    let band: Vec<_> = (top..bottom)
+                        .flat_map(|y| (0..width).map(move |x| pixel(x, y)))
+                        .collect();
+                    (top, band)
+                })
+            })
+            .collect();
+        bands.into_iter().map(|band| band.join().unwrap()).collect()
+    });
+    for (top, band) in bands {
+        for (index, color) in band.into_iter().enumerate() {
+            let index = index as u32;
+            projected.put_pixel(index % width, top + index / width, color);
+        }
+    }
+    image::DynamicImage::ImageRgba8(projected)
+}
+
+pub fn sampling_ui(ui: &mut egui::Ui, sampling: &mut Sampling, source: Option<(u32, u32)>) {
+    let source_width = source.map_or(1024, |(width, _)| width);
+    ui.horizontal(|ui| {
+        let mut custom = sampling.output_width.is_some();
+        if ui.checkbox(&mut custom, "Output width").changed() {
+            sampling.output_width = custom.then_some(source_width);
+        }
+        match &mut sampling.output_width {
+            Some(width) => {
+                ui.add(
+                    egui::DragValue::new(width)
+                        .clamp_range(MIN_WIDTH..=MAX_WIDTH)
+                        .suffix(" px"),
+                );
+            }
+            None => {
+                ui.weak(format!("{} px, as the source", source_width));
+            }
+        }
+    });
+    if sampling
+        .output_width
+        .is_some_and(|width| width < source_width)
+    {
+        ui.weak("Each pixel is averaged over the source pixels it covers.");
+    }
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut sampling.adaptive, "Adaptive oversampling")
+            .on_hover_text(
+                "Takes more samples in pixels where the projection's scale changes \
+                 quickly, such as near the poles and along the edges of the map.",
+            );
+        ui.add_enabled(
+            sampling.adaptive,
+            egui::Slider::new(&mut sampling.oversampling, 2..=MAX_SAMPLES)
+                .logarithmic(true)
+                .suffix("×"),
+        );
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProjectionKind;
+
+    #[test]
+    fn thin_lines_show_as_an_even_grey_on_a_map_a_sixteenth_as_wide() {
+        let source = image::RgbaImage::from_fn(1024, 512, |x, _| match x % 8 {
+            0 => image::Rgba([0, 0, 0, 255]),
+            _ => image::Rgba([255, 255, 255, 255]),
+        });
+        let equirectangular = ProjectionKind::Equirectangular.default_projection_data();
+        let sampling = Sampling {
+            output_width: Some(64),
+            ..Default::default()
+        };
+        let dimensions = sampling.dimensions((1024, 512), &equirectangular);
+        assert_eq!(dimensions, (64, 32));
+        let projected = resample(
+            &image::DynamicImage::ImageRgba8(source),
+            &equirectangular,
+            &equirectangular,
+            dimensions,
+            &sampling,
+        )
+        .to_rgba8();
+        assert_eq!(projected.dimensions(), dimensions);
+        // Two of every sixteen source pixels are black.
+        let expected = 255. * 14. / 16.;
+        for pixel in projected.pixels() {
+            assert!(
+                (pixel[0] as f64 - expected).abs() <= 8. && pixel[3] == 255,
+                "a pixel came out as {:?}",
+                pixel.0
+            );
+        }
+    }
+
+    #[test]
+    fn adaptive_oversampling_smooths_the_edge_and_leaves_the_middle_alone() {
+        let white = image::RgbaImage::from_pixel(256, 128, image::Rgba([255, 255, 255, 255]));
+        let sampling = Sampling {
+            adaptive: true,
+            ..Default::default()
+        };
+        let mollweide = ProjectionKind::Mollweide.default_projection_data();
+        let projected = resample(
+            &image::DynamicImage::ImageRgba8(white),
+            &ProjectionKind::Equirectangular.default_projection_data(),
+            &mollweide,
+            sampling.dimensions((256, 128), &mollweide),
+            &sampling,
+        )
+        .to_rgba8();
+        let (width, height) = projected.dimensions();
+        let partial = projected
+            .pixels()
+            .filter(|pixel| pixel[3] > 0 && pixel[3] < 255)
+            .count();
+        assert!(partial > 0, "the edge isn't smoothed");
+        assert_eq!(projected[(width / 2, height / 2)].0, [255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,1008 @@
+use std::path::{Path, PathBuf};
+
+use crate::batch::ProjectionSpec;
+use crate::collisions::Collision;
+use crate::gridded::Field;
+use crate::jobs::Progress;
+use crate::layered::{self, LayeredImage};
+use crate::{
+    attribution, batch, bundle, engine, fits, healpix, loading, remote, sidecar, vectors,
+    ProjectionKind,
+};
+
+/// A channel may be off by this much, as interpolation differs slightly
+/// between platforms.
+const CHANNEL_TOLERANCE: u8 = 8;
+/// Fraction of the pixels that may be off by more than that.
+const PIXEL_TOLERANCE: f64 = 0.005;
+const INPUT_SIZE: (u32, u32) = (128, 64);
+
+/// A case's name, the projection's kind and the parameters that differ from
+/// its defaults.
+type Variant = (&'static str, &'static str, &'static [(&'static str, f64)]);
+
+/// Conversions beyond each projection with its default parameters.
+const VARIANTS: &[Variant] = &[
+    (
+        "equirectangular-shifted",
+        "equirectangular",
+        &[("central_long", 90.), ("true_scale_lat", 30.)],
+    ),
+    (
+        "azimuthal-equidistant-south",
+        "azimuthal-equidistant",
+        &[
+            ("center_long", 0.),
+            ("center_lat", -90.),
+            ("central_long", 0.),
+        ],
+    ),
+    (
+        "azimuthal-equidistant-oblique",
+        "azimuthal-equidistant",
+        &[
+            ("center_long", 10.),
+            ("center_lat", 50.),
+            ("central_long", 10.),
+        ],
+    ),
+    (
+        "mercator-pacific",
+        "mercator",
+        &[("central_long", 150.), ("max_lat", 80.)],
+    ),
+    (
+        "transverse-mercator-utm33",
+        "transverse-mercator",
+        &[
+            ("central_long", 15.),
+            ("origin_lat", 0.),
+            ("scale_factor", 0.9996),
+        ],
+    ),
+    (
+        "transverse-mercator-north",
+        "transverse-mercator",
+        &[
+            ("central_long", 15.),
+            ("origin_lat", 45.),
+            ("scale_factor", 1.),
+        ],
+    ),
+    ("mollweide-pacific", "mollweide", &[("central_long", 150.)]),
+    ("robinson-pacific", "robinson", &[("central_long", 150.)]),
+    (
+        "winkel-tripel-equidistant",
+        "winkel-tripel",
+        &[("central_long", 0.), ("standard_parallel", 0.)],
+    ),
+    (
+        "sinusoidal-pacific",
+        "sinusoidal",
+        &[("central_long", 150.)],
+    ),
+    (
+        "cylindrical-equal-area-gall-peters",
+        "cylindrical-equal-area",
+        &[("central_long", 0.), ("standard_parallel", 45.)],
+    ),
+    (
+        "lambert-conformal-conic-europe",
+        "lambert-conformal-conic",
+        &[
+            ("central_long", 10.),
+            ("origin_lat", 52.),
+            ("standard_parallel_1", 35.),
+            ("standard_parallel_2", 65.),
+        ],
+    ),
+    (
+        "albers-equal-area-south",
+        "albers-equal-area",
+        &[
+            ("central_long", 135.),
+            ("standard_parallel_1", -18.),
+            ("standard_parallel_2", -36.),
+        ],
+    ),
+    (
+        "equidistant-conic-russia",
+        "equidistant-conic",
+        &[
+            ("central_long", 100.),
+            ("standard_parallel_1", 52.),
+            ("standard_parallel_2", 68.),
+        ],
+    ),
+    (
+        "orthographic-oblique",
+        "orthographic",
+        &[("central_long", -30.), ("center_lat", 40.)],
+    ),
+    (
+        "stereographic-north-polar",
+        "stereographic",
+        &[("central_long", 0.), ("center_lat", 90.)],
+    ),
+    (
+        "gnomonic-wide",
+        "gnomonic",
+        &[("central_long", -100.), ("center_lat", 45.), ("clip", 80.)],
+    ),
+    (
+        "lambert-azimuthal-equal-area-europe",
+        "lambert-azimuthal-equal-area",
+        &[("central_long", 10.), ("center_lat", 52.)],
+    ),
+    ("hammer-pacific", "hammer", &[("central_long", 150.)]),
+    ("aitoff-atlantic", "aitoff", &[("central_long", -30.)]),
+    ("eckert-iv-pacific", "eckert-iv", &[("central_long", 160.)]),
+    ("eckert-vi-atlantic", "eckert-vi", &[("central_long", -20.)]),
+    (
+        "goode-homolosine-ocean",
+        "goode-homolosine",
+        &[("central_long", 0.), ("interruptions", 1.)],
+    ),
+    (
+        "bonne-werner",
+        "bonne",
+        &[("central_long", 0.), ("standard_parallel", 90.)],
+    ),
+    (
+        "bonne-south",
+        "bonne",
+        &[("central_long", 20.), ("standard_parallel", -40.)],
+    ),
+    ("werner-americas", "werner", &[("central_long", -90.)]),
+    (
+        "van-der-grinten-pacific",
+        "van-der-grinten",
+        &[("central_long", 150.)],
+    ),
+    ("miller-pacific", "miller", &[("central_long", 170.)]),
+    ("cassini-europe", "cassini", &[("central_long", 10.)]),
+    (
+        "polyconic-usa",
+        "polyconic",
+        &[("central_long", -96.), ("origin_lat", 39.)],
+    ),
+    (
+        "natural-earth-pacific",
+        "natural-earth",
+        &[("central_long", 150.)],
+    ),
+    (
+        "kavrayskiy-vii-pacific",
+        "kavrayskiy-vii",
+        &[("central_long", 150.)],
+    ),
+];
+
+/// `test_images/golden` in the nearest directory above the executable that
+/// has `test_images`, as a checkout does with the executable under `target`,
+/// or else in the working directory.
+fn default_golden_directory() -> PathBuf {
+    let test_images = Path::new("test_images");
+    std::env::current_exe()
+        .ok()
+        .and_then(|executable| {
+            executable
+                .ancestors()
+                .skip(1)
+                .map(|directory| directory.join(test_images))
+                .find(|directory| directory.is_dir())
+        })
+        .unwrap_or(test_images.to_path_buf())
+        .join("golden")
+}
+
+/// Where renders that don't match are left for inspection.
+fn failure_directory() -> PathBuf {
+    std::env::temp_dir().join("submaptive-self-test")
+}
+
+/// An equirectangular world of 30° cells, each a different color, so that
+/// any cell ending up in the wrong place changes the render.
+fn input() -> image::DynamicImage {
+    let (width, height) = INPUT_SIZE;
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(width, height, |x, y| {
+        let column = x * 12 / width;
+        let row = y * 6 / height;
+        let light = match (column + row) % 2 {
+            0 => 255,
+            _ => 160,
+        };
+        image::Rgba([(column * 21) as u8, (row * 50) as u8, light, 255])
+    }))
+}
+
+fn cases() -> Vec<(String, ProjectionSpec)> {
+    let defaults = ProjectionKind::all()
+        .filter(|kind| *kind != ProjectionKind::RubberSheet)
+        .map(|kind| {
+            let spec = ProjectionSpec::of(&kind.default_projection_data());
+            (kind.id().to_string(), spec)
+        });
+    let variants = VARIANTS.iter().map(|(name, kind, parameters)| {
+        let spec = ProjectionSpec {
+            kind: kind.to_string(),
+            parameters: parameters
+                .iter()
+                .map(|(name, value)| (name.to_string(), *value))
+                .collect(),
+        };
+        (name.to_string(), spec)
+    });
+    defaults.chain(variants).collect()
+}
+
+/// Whether `render` matches `golden` within the tolerance, and if not, why.
+fn compare(render: &image::RgbaImage, golden: &image::RgbaImage) -> Result<(), String> {
+    if render.dimensions() != golden.dimensions() {
+        return Err(format!(
+            "{} × {} px instead of {} × {} px",
+            render.width(),
+            render.height(),
+            golden.width(),
+            golden.height()
+        ));
+    }
+    let differing = render
+        .pixels()
+        .zip(golden.pixels())
+        .filter(|(a, b)| {
+            a.0.iter()
+                .zip(b.0)
+                .any(|(a, b)| a.abs_diff(b) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    let fraction = differing as f64 / (render.width() * render.height()).max(1) as f64;
+    match fraction <= PIXEL_TOLERANCE {
+        true => Ok(()),
+        false => Err(format!("{:.2}% of the pixels differ", fraction * 100.)),
+    }
+}
+
+fn run_case(
+    name: &str,
+    spec: &ProjectionSpec,
+    golden_directory: &Path,
+    bless: bool,
+) -> Result<&'static str, String> {
+    let source = ProjectionKind::Equirectangular.default_projection_data();
+    let render =
+        engine::reproject(input(), &source, &spec.build()?, &Default::default())?.to_rgba8();
+    let golden_path = golden_directory.join(format!("{}.png", name));
+    if bless {
+        std::fs::create_dir_all(golden_directory).map_err(|e| e.to_string())?;
+        render.save(&golden_path).map_err(|e| e.to_string())?;
+        return Ok("blessed");
+    }
+    let golden = image::open(&golden_path)
+        .map_err(|e| format!("No golden image at {}: {}", golden_path.display(), e))?
+        .to_rgba8();
+    compare(&render, &golden).map_err(|e| {
+        let _ = std::fs::create_dir_all(failure_directory());
+        let path = failure_directory().join(format!("{}.png", name));
+        match render.save(&path) {
+            Ok(()) => format!("{}; the render is at {}", e, path.display()),
+            Err(_) => e,
+        }
+    })?;
+    Ok("ok")
+}
+
+/// Where a pixel is and the color it should have.
+type Pixel = ((u32, u32), [u8; 4]);
+/// A check that needs no setup.
+type Check = fn() -> Result<(), String>;
+
+/// Writes a made up input where the decoders can read it from.
+fn write_input(name: &str, data: &[u8]) -> Result<PathBuf, String> {
+    let directory = std::env::temp_dir().join("submaptive-self-test-inputs");
+    let path = directory.join(name);
+    std::fs::create_dir_all(&directory)
+        .and_then(|_| std::fs::write(&path, data))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+fn check_pixels(image: &image::RgbaImage, pixels: &[Pixel]) -> Result<(), String> {
+    for &((x, y), expected) in pixels {
+        let pixel = image
+            .get_pixel_checked(x, y)
+            .ok_or_else(|| format!("no pixel at ({}, {})", x, y))?;
+        if pixel.0 != expected {
+            return Err(format!(
+                "({}, {}) is {:?} instead of {:?}",
+                x, y, pixel.0, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// What a layered file should hold.
+struct LayeredCase {
+    name: &'static str,
+    data: Vec<u8>,
+    /// The name, visibility and some pixels of each layer, bottom first.
+    layers: Vec<(&'static str, bool, Vec<Pixel>)>,
+    flattened: Vec<Pixel>,
+}
+
+/// A 2 × 1 px red layer under a half transparent blue pixel on the right,
+/// as a Photoshop document and as OpenRaster.
+fn layered_cases() -> Vec<LayeredCase> {
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+    let mut psd = b"8BPS\0\x01\0\0\0\0\0\0\0\x03".to_vec();
+    psd.extend([0, 0, 0, 1, 0, 0, 0, 2, 0, 8, 0, 3]);
+    psd.extend([0; 8]);
+    let mut info = 2u16.to_be_bytes().to_vec();
+    // Bounds, then the alpha, red, green and blue channels with their
+    // lengths, each uncompressed.
+    let records: [(i32, [u8; 3], &[u8]); 2] = [
+        (0, [255, 0, 0], b"\x03red"),
+        (1, [0, 0, 255], b"\x04blue\0\0\0"),
+    ];
+    for (left, _, name) in &records {
+        for bound in [0, *left, 1, 2] {
+            info.extend(bound.to_be_bytes());
+        }
+        info.extend(4u16.to_be_bytes());
+        for id in [-1i16, 0, 1, 2] {
+            info.extend(id.to_be_bytes());
+            info.extend((2 + 2 - *left as u32).to_be_bytes());
+        }
+        info.extend(b"8BIMnorm");
+        // Opacity, clipping, flags and filler, with the blue layer hidden.
+        match left {
+            0 => info.extend([255, 0, 0, 0]),
+            _ => info.extend([255, 0, 2, 0]),
+        }
+        info.extend((8 + name.len() as u32).to_be_bytes());
+        info.extend([0; 8]);
+        info.extend(*name);
+    }
+    for (left, color, _) in &records {
+        let pixels = 2 - *left as usize;
+        let alpha = match left {
+            0 => 255,
+            _ => 128,
+        };
+        for value in [alpha, color[0], color[1], color[2]] {
+            info.extend([0, 0]);
+            info.extend(vec![value; pixels]);
+        }
+    }
+    psd.extend((info.len() as u32 + 8).to_be_bytes());
+    psd.extend((info.len() as u32).to_be_bytes());
+    psd.extend(info);
+    psd.extend([0; 4]);
+    // The flattened image, with the blue layer hidden.
+    psd.extend([0, 0, 255, 255, 0, 0, 0, 0]);
+
+    let png = |image: image::RgbaImage| {
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    };
+    let red = png(image::RgbaImage::from_pixel(2, 1, image::Rgba(RED)));
+    let blue = png(image::RgbaImage::from_pixel(
+        1,
+        1,
+        image::Rgba([0, 0, 255, 255]),
+    ));
+    let stack = concat!(
+        "<?xml version='1.0' encoding='UTF-8'?>",
+        "<image w=\"2\" h=\"1\"><stack>",
+        "<layer name=\"blue\" src=\"data/blue.png\" x=\"1\" y=\"0\" opacity=\"0.5\" />",
+        "<layer name=\"red\" src=\"data/red.png\" visibility='visible'/>",
+        "</stack></image>"
+    );
+    let ora = bundle::zip(&[
+        ("mimetype", b"image/openraster"),
+        ("stack.xml", stack.as_bytes()),
+        ("data/red.png", &red),
+        ("data/blue.png", &blue),
+    ])
+    .unwrap();
+    vec![
+        LayeredCase {
+            name: "layers.psd",
+            data: psd,
+            layers: vec![
+                ("red", true, vec![((0, 0), RED), ((1, 0), RED)]),
+                (
+                    "blue",
+                    false,
+                    vec![((0, 0), CLEAR), ((1, 0), [0, 0, 255, 128])],
+                ),
+            ],
+            flattened: vec![((0, 0), RED), ((1, 0), RED)],
+        },
+        LayeredCase {
+            name: "layers.ora",
+            data: ora,
+            layers: vec![
+                ("red", true, vec![((0, 0), RED), ((1, 0), RED)]),
+                (
+                    "blue",
+                    true,
+                    vec![((0, 0), CLEAR), ((1, 0), [0, 0, 255, 255])],
+                ),
+            ],
+            flattened: vec![((0, 0), RED), ((1, 0), [128, 0, 128, 255])],
+        },
+    ]
+}
+
+fn run_layered_case(case: &LayeredCase) -> Result<(), String> {
+    let path = write_input(case.name, &case.data)?;
+    let file = LayeredImage::read(&path)?;
+    if file.layers.len() != case.layers.len() {
+        return Err(format!(
+            "{} layers instead of {}",
+            file.layers.len(),
+            case.layers.len()
+        ));
+    }
+    for (layer, (name, visible, pixels)) in file.layers.iter().zip(&case.layers) {
+        if layer.name != *name || layer.visible != *visible {
+            return Err(format!("layer {:?} is not layer {:?}", layer.name, name));
+        }
+        check_pixels(&layer.image, pixels).map_err(|e| format!("in layer {}, {}", name, e))?;
+    }
+    let flattened = loading::decode(&path, &Progress::default())?.to_rgba8();
+    check_pixels(&flattened, &case.flattened).map_err(|e| format!("flattened, {}", e))
+}
+
+/// The same field of a 4 × 2 grid at 90° spacing in a NetCDF and a GRIB file,
+/// its northern row 5, 6, missing, 8 east from Greenwich and its southern
+/// row 1, 2, 3, 4, with the name its file gives it.
+fn gridded_cases() -> Vec<(&'static str, Vec<u8>, &'static str)> {
+    let name = |name: &str| {
+        let mut data = (name.len() as u32).to_be_bytes().to_vec();
+        data.extend(name.as_bytes());
+        data.resize(data.len().div_ceil(4) * 4, 0);
+        data
+    };
+    let attribute = |attribute: &str, nc_type: u32, count: u32, values: &[u8]| {
+        let mut data = name(attribute);
+        data.extend(nc_type.to_be_bytes());
+        data.extend(count.to_be_bytes());
+        data.extend(values);
+        data.resize(data.len().div_ceil(4) * 4, 0);
+        data
+    };
+    let floats =
+        |values: &[f32]| -> Vec<u8> { values.iter().flat_map(|v| v.to_be_bytes()).collect() };
+    // Latitudes south first, and values scaled by half, with a record dimension for time.
+    let lats = floats(&[-45., 45.]);
+    let longs = floats(&[0., 90., 180., 270.]);
+    let values: Vec<u8> = [2i16, 4, 6, 8, 10, 12, -999, 16]
+        .iter()
+        .flat_map(|v| v.to_be_bytes())
+        .collect();
+    let variables = [
+        (
+            "lat",
+            vec![1u32],
+            vec![attribute("units", 2, 13, b"degrees_north")],
+            5,
+            &lats,
+        ),
+        (
+            "lon",
+            vec![2],
+            vec![attribute("units", 2, 12, b"degrees_east")],
+            5,
+            &longs,
+        ),
+        (
+            "t2m",
+            vec![0, 1, 2],
+            vec![
+                attribute("scale_factor", 5, 1, &0.5f32.to_be_bytes()),
+                attribute("_FillValue", 3, 1, &(-999i16).to_be_bytes()),
+            ],
+            3u32,
+            &values,
+        ),
+    ];
+    let header = |begins: &[u32]| {
+        let mut header = b"CDF\x01".to_vec();
+        header.extend(1u32.to_be_bytes());
+        header.extend([0x0au32.to_be_bytes(), 3u32.to_be_bytes()].concat());
+        for (dimension, length) in [("time", 0u32), ("lat", 2), ("lon", 4)] {
+            header.extend(name(dimension));
+            header.extend(length.to_be_bytes());
+        }
+        header.extend([0; 8]);
+        header.extend([0x0bu32.to_be_bytes(), 3u32.to_be_bytes()].concat());
+        for ((variable, dimensions, attributes, nc_type, data), begin) in
+            variables.iter().zip(begins)
+        {
+            header.extend(name(variable));
+            header.extend((dimensions.len() as u32).to_be_bytes());
+            header.extend(dimensions.iter().flat_map(|id| id.to_be_bytes()));
+            header.extend(
+                [
+                    0x0cu32.to_be_bytes(),
+                    (attributes.len() as u32).to_be_bytes(),
+                ]
+                .concat(),
+            );
+            header.extend(attributes.concat());
+            header.extend(nc_type.to_be_bytes());
+            header.extend((data.len() as u32).to_be_bytes());
+            header.extend(begin.to_be_bytes());
+        }
+        header
+    };
+    let mut begin = header(&[0; 3]).len() as u32;
+    let begins: Vec<u32> = variables
+        .iter()
+        .map(|variable| {
+            begin += variable.4.len() as u32;
+            begin - variable.4.len() as u32
+        })
+        .collect();
+    let mut netcdf = header(&begins);
+    for variable in &variables {
+        netcdf.extend(variable.4);
+    }
+
+    // North first, with the missing value left out by a bitmap and the
+    // others packed in four bits from a reference value of 1.
+    let section = |number: u8, contents: &[u8]| {
+        let mut section = (contents.len() as u32 + 5).to_be_bytes().to_vec();
+        section.push(number);
+        section.extend(contents);
+        section
+    };
+    let mut grid = vec![0; 67];
+    let mut put =
+        |at: usize, value: u32| grid[at - 5..at - 1].copy_from_slice(&value.to_be_bytes());
+    put(6, 8);
+    put(30, 4);
+    put(34, 2);
+    put(42, u32::MAX);
+    put(46, 45_000_000);
+    put(55, 0x8000_0000 | 45_000_000);
+    put(59, 270_000_000);
+    put(63, 90_000_000);
+    put(67, 90_000_000);
+    let mut representation = 7u32.to_be_bytes().to_vec();
+    representation.extend([0, 0]);
+    representation.extend(1f32.to_be_bytes());
+    representation.extend([0, 0, 0, 0, 4, 0]);
+    let sections = [
+        section(1, &[0; 16]),
+        section(3, &grid),
+        section(4, &[0; 29]),
+        section(5, &representation),
+        section(6, &[0, 0b1101_1111]),
+        section(7, &[0x45, 0x70, 0x12, 0x30]),
+    ]
+    .concat();
+    let mut grib = b"GRIB\0\0\0\x02".to_vec();
+    grib.extend((sections.len() as u64 + 20).to_be_bytes());
+    grib.extend(sections);
+    grib.extend(b"7777");
+    vec![
+        ("field.nc", netcdf, "t2m"),
+        ("field.grib2", grib, "Temperature"),
+    ]
+}
+
+fn run_gridded_case(file_name: &str, data: &[u8], name: &str) -> Result<(), String> {
+    let path = write_input(file_name, data)?;
+    let field = Field::read(&path)?;
+    if field.name != name {
+        return Err(format!(
+            "the field is {:?} instead of {:?}",
+            field.name, name
+        ));
+    }
+    let image = loading::decode(&path, &Progress::default())?.to_rgba8();
+    if image.dimensions() != (4, 2) {
+        return Err(format!(
+            "drawn {:?} px large instead of 4 × 2",
+            image.dimensions()
+        ));
+    }
+    // The lowest and highest values are at the ends of the default ramp.
+    check_pixels(
+        &image,
+        &[
+            ((1, 1), [68, 1, 84, 255]),
+            ((0, 0), [253, 231, 37, 255]),
+            ((3, 0), [0; 4]),
+        ],
+    )
+}
+
+/// An all-sky image of 4 × 2 px at 90° per pixel, with right ascension
+/// increasing to the left as FITS images of the sky usually have it.
+fn fits_case() -> Vec<u8> {
+    let mut header: Vec<u8> = [
+        "SIMPLE  =                    T / conforms to FITS",
+        "BITPIX  =                  -32",
+        "NAXIS   =                    2",
+        "NAXIS1  =                    4",
+        "NAXIS2  =                    2",
+        "CTYPE1  = 'RA---CAR'",
+        "CTYPE2  = 'DEC--CAR'",
+        "CRPIX1  =                  2.5",
+        "CRPIX2  =                  1.5",
+        "CRVAL1  =                180.0",
+        "CRVAL2  =                  0.0",
+        "CDELT1  =                -90.0",
+        "CDELT2  =                 90.0",
+        "END",
+    ]
+    .iter()
+    .flat_map(|card| format!("{:80}", card).into_bytes())
+    .collect();
+    header.resize(2880, b' ');
+    // Bottom row first; the third value of the top row is blank.
+    for value in [0f32, 1., 2., 3., 4., 5., f32::NAN, 7.] {
+        header.extend(value.to_be_bytes());
+    }
+    header.resize(2 * 2880, 0);
+    header
+}
+
+fn run_fits_case(data: &[u8]) -> Result<(), String> {
+    let path = write_input("sky.fits", data)?;
+    let sky = fits::sky(&path).ok_or("no celestial coordinates found")?;
+    let kind = sky.projection.map(|projection| projection.kind());
+    if kind != Some(ProjectionKind::Equirectangular) || !sky.longitudes.west_positive {
+        return Err("projection or longitudes not taken from the coordinate system".into());
+    }
+    // A dry run reads its size as the conversion reads the image.
+    let equirectangular = ProjectionKind::Equirectangular.default_projection_data();
+    let conversion = batch::Conversion::for_input(path.clone(), &equirectangular, &equirectangular);
+    batch::plan(&conversion, Collision::Overwrite).map_err(|failure| failure.message)?;
+    let image = loading::decode(&path, &Progress::default())?.to_rgba8();
+    check_pixels(
+        &image,
+        &[
+            ((0, 1), [0, 0, 0, 255]),
+            ((3, 0), [255, 255, 255, 255]),
+            ((2, 0), [0; 4]),
+        ],
+    )
+}
+
+/// Checks that every HEALPix pixel contains its own center in ring order, and
+/// that the centers fall in distinct pixels in nested order.
+fn healpix_indices() -> Result<(), String> {
+    for nside in [1, 2, 4, 16] {
+        let mut nested = Vec::new();
+        for index in 0..12 * nside * nside {
+            let (lat, long) = healpix::center(nside, index);
+            let found = healpix::pixel(nside, healpix::Ordering::Ring, lat, long);
+            if found != index {
+                return Err(format!(
+                    "at nside {}, the center of {} is in {}",
+                    nside, index, found
+                ));
+            }
+            nested.push(healpix::pixel(nside, healpix::Ordering::Nested, lat, long));
+        }
+        nested.sort_unstable();
+        if !nested.iter().copied().eq(0..12 * nside * nside) {
+            return Err(format!("at nside {}, nested pixels are left out", nside));
+        }
+    }
+    Ok(())
+}
+
+/// Exports a map dark in the west and bright in the east as HEALPix and reads it back.
+fn healpix_round_trip() -> Result<(), String> {
+    let path = std::env::temp_dir()
+        .join("submaptive-self-test-inputs")
+        .join("map.fits");
+    std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+    let image = image::RgbaImage::from_fn(128, 64, |x, _| match x < 64 {
+        true => image::Rgba([0, 0, 0, 255]),
+        false => image::Rgba([255, 255, 255, 255]),
+    });
+    let projection = ProjectionKind::Equirectangular.default_projection_data();
+    healpix::write(&path, &image, &projection)?;
+    let read = loading::decode(&path, &Progress::default())?.to_rgba8();
+    let (width, height) = read.dimensions();
+    check_pixels(
+        &read,
+        &[
+            ((width / 4, height / 2), [68, 1, 84, 255]),
+            ((3 * width / 4, height / 2), [253, 231, 37, 255]),
+        ],
+    )
+}
+
+/// A TIFF with an overview, read in parts as if it were remote: the
+/// overview's blue is full, and the full image's red and green count pixels.
+fn remote_levels() -> Result<(), String> {
+    use tiff::encoder::colortype::RGB8;
+    let mut file = Vec::new();
+    let mut encoder = tiff::encoder::TiffEncoder::new(std::io::Cursor::new(&mut file))
+        .map_err(|e| e.to_string())?;
+    for (width, height, blue) in [(64, 32, 0), (32, 16, 255)] {
+        let scale = 64 / width;
+        let data: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).flat_map(move |x| [x * 4 * scale, y * 8 * scale, blue]))
+            .collect();
+        let mut image = encoder
+            .new_image::<RGB8>(width as u32, height as u32)
+            .map_err(|e| e.to_string())?;
+        if blue != 0 {
+            image
+                .encoder()
+                .write_tag(tiff::tags::Tag::NewSubfileType, 1u32)
+                .map_err(|e| e.to_string())?;
+        }
+        image.rows_per_strip(4).map_err(|e| e.to_string())?;
+        image.write_data(&data).map_err(|e| e.to_string())?;
+    }
+    let read = |max_side, region| {
+        let request = remote::Request {
+            url: "map.tif".into(),
+            max_side,
+            region,
+        };
+        remote::read_from(std::io::Cursor::new(&file), &request, &Progress::default())
+            .map(|(image, _)| image.to_rgba8())
+    };
+    let overview = read(
+        16,
+        remote::Region {
+            left: 0.5,
+            ..Default::default()
+        },
+    )?;
+    if overview.dimensions() != (16, 16) {
+        return Err(format!(
+            "the overview's half is {:?} px",
+            overview.dimensions()
+        ));
+    }
+    check_pixels(
+        &overview,
+        &[
+            ((0, 0), [128, 0, 255, 255]),
+            ((15, 15), [248, 240, 255, 255]),
+        ],
+    )?;
+    let full = read(
+        64,
+        remote::Region {
+            left: 0.5,
+            top: 0.5,
+            ..Default::default()
+        },
+    )?;
+    if full.dimensions() != (32, 16) {
+        return Err(format!(
+            "the full image's quarter is {:?} px",
+            full.dimensions()
+        ));
+    }
+    check_pixels(
+        &full,
+        &[((0, 0), [128, 128, 0, 255]), ((31, 15), [252, 248, 0, 255])],
+    )
+}
+
+/// Every point on the rim of the equal-area azimuthal disc is the antipode
+/// of its center, which is drawn on the rim.
+fn azimuthal_antipode() -> Result<(), String> {
+    use crate::projections::{LambertAzimuthalEqualArea, Spherical};
+    let projection = LambertAzimuthalEqualArea {
+        central_long: 10.,
+        center_lat: 52.,
+    };
+    let antipode = (std::f64::consts::PI, -52f64.to_radians());
+    for (x, y) in [(0., -2.), (2., 0.), (-1.2, 1.6)] {
+        let (long, lat) = projection
+            .inverse(x, y)
+            .ok_or(format!("nothing at ({}, {})", x, y))?;
+        if (long.abs() - antipode.0).abs() > 1e-9 || (lat - antipode.1).abs() > 1e-9 {
+            return Err(format!("({}, {}) is at {:?}", x, y, (long, lat)));
+        }
+    }
+    let (x, y) = projection.forward(antipode.0, antipode.1);
+    match (x.hypot(y) - 2.).abs() < 1e-6 {
+        true => Ok(()),
+        false => Err(format!("the antipode is drawn at ({}, {})", x, y)),
+    }
+}
+
+/// A credit stamped onto a transparent map, which only changes the corner.
+fn attribution_stamp() -> Result<(), String> {
+    let image = image::DynamicImage::ImageRgba8(image::RgbaImage::new(400, 200));
+    let stamped = attribution::stamp(image, "© Test Maps").to_rgba8();
+    let (width, height) = stamped.dimensions();
+    check_pixels(
+        &stamped,
+        &[
+            ((0, 0), [0, 0, 0, 0]),
+            ((width - 1, height - 1), [255, 255, 255, 191]),
+        ],
+    )?;
+    let text = stamped
+        .pixels()
+        .filter(|pixel| pixel.0[3] != 0 && pixel.0[0] < 128)
+        .count();
+    if text < 20 {
+        return Err(format!("only {} px of text", text));
+    }
+    match stamped
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[3] != 0)
+        .all(|(x, y, _)| x >= width / 2 && y >= height * 3 / 4)
+    {
+        true => Ok(()),
+        false => Err("the stamp reaches beyond the corner".into()),
+    }
+}
+
+/// A Mollweide map's mask covers its ellipse and leaves its corners out.
+fn validity_mask() -> Result<(), String> {
+    let mut encoded = Vec::new();
+    input()
+        .write_to(
+            &mut std::io::Cursor::new(&mut encoded),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| e.to_string())?;
+    let input = write_input("masked.png", &encoded)?;
+    let conversion = batch::Conversion {
+        use_sidecar: false,
+        validity_mask: true,
+        ..batch::Conversion::for_input(
+            input,
+            &ProjectionKind::Equirectangular.default_projection_data(),
+            &ProjectionKind::Mollweide.default_projection_data(),
+        )
+    };
+    batch::convert(&conversion, Collision::Overwrite, &Progress::default())
+        .map_err(|failure| failure.message)?;
+    let mask = image::open(crate::engine::mask_path(&conversion.output))
+        .map_err(|e| e.to_string())?
+        .to_luma8();
+    let (width, height) = mask.dimensions();
+    match (mask[(0, 0)][0], mask[(width / 2, height / 2)][0]) {
+        (0, 255) => Ok(()),
+        (corner, center) => Err(format!(
+            "{} in the corner and {} at the center",
+            corner, center
+        )),
+    }
+}
+
+/// A bundle unzipped and run again as a manifest makes the map it holds from
+/// its copy of the source, and finds its copy of the overlay.
+fn export_bundle() -> Result<(), String> {
+    let mut encoded = Vec::new();
+    input()
+        .write_to(
+            &mut std::io::Cursor::new(&mut encoded),
+            image::ImageOutputFormat::Png,
+        )
+        .map_err(|e| e.to_string())?;
+    let input = write_input("bundled.png", &encoded)?;
+    let overlay = write_input(
+        "bundled.geojson",
+        br#"{"type": "FeatureCollection", "features": []}"#,
+    )?;
+    let source = ProjectionKind::Equirectangular.default_projection_data();
+    let target = ProjectionKind::Mollweide.default_projection_data();
+    let sidecar = sidecar::Sidecar {
+        vector_overlays: vec![vectors::OverlaySettings {
+            path: overlay,
+            ..Default::default()
+        }],
+        ..Default::default()
+    };
+    let mut conversion = batch::Conversion::for_input(input.clone(), &source, &target);
+    conversion.use_sidecar = false;
+    batch::convert(&conversion, Collision::Overwrite, &Progress::default())
+        .map_err(|failure| failure.message)?;
+    let projected = image::open(&conversion.output).map_err(|e| e.to_string())?;
+    let archive = input.with_file_name("bundle.zip");
+    bundle::write(&archive, &projected, &input, &source, &target, &sidecar)?;
+    let directory = input.with_file_name("bundle");
+    let contents = std::fs::read(&archive).map_err(|e| e.to_string())?;
+    for (name, contents) in layered::unzip(&contents)? {
+        let path = directory.join(name);
+        std::fs::create_dir_all(path.parent().unwrap_or(&directory))
+            .and_then(|()| std::fs::write(path, contents))
+            .map_err(|e| e.to_string())?;
+    }
+    // The bundle has to make the map without the file it was made from.
+    std::fs::remove_file(&input).map_err(|e| e.to_string())?;
+    let manifest = batch::Manifest::load(&directory.join("manifest.json"))?;
+    let [conversion] = &manifest.conversions[..] else {
+        return Err(format!("{} conversions", manifest.conversions.len()));
+    };
+    batch::convert(conversion, Collision::Overwrite, &Progress::default())
+        .map_err(|failure| failure.message)?;
+    let open = |name| image::open(directory.join(name)).map_err(|e| e.to_string());
+    if open("map.png")? != open("regenerated.png")? {
+        return Err("the regenerated map differs".into());
+    }
+    let settings = sidecar::Sidecar::read(&directory.join("settings.json"))?;
+    match settings.vector_overlays[..] {
+        [ref overlay] if overlay.path.exists() => Ok(()),
+        _ => Err("the overlay isn't in the bundle".into()),
+    }
+}
+
+/// Renders every case and compares it with its golden image, or with `bless`
+/// replaces the golden images. Then checks that layered files come apart into
+/// their layers, that gridded data and images of the sky are drawn where they
+/// lie, that HEALPix maps are read as they are written, that the antipode of
+/// an azimuthal map is found on its rim, that remote TIFFs are read in the
+/// parts asked for, that attributions are stamped into the corner, that
+/// export bundles make their map again and that validity masks cover the
+/// projected map. Sampling, numbering and expressions are checked by the unit
+/// tests instead. The golden images are in `golden_directory`, if given.
+/// Returns the exit code.
+pub fn run(bless: bool, golden_directory: Option<PathBuf>) -> i32 {
+    let golden_directory = golden_directory.unwrap_or_else(default_golden_directory);
+    println!("Golden images in {}", golden_directory.display());
+    let mut failures = 0;
+    for (name, spec) in cases() {
+        match run_case(&name, &spec, &golden_directory, bless) {
+            Ok(result) => println!("{}: {}", name, result),
+            Err(e) => {
+                failures += 1;
+                println!("{}: FAILED, {}", name, e);
+            }
+        }
+    }
+    for case in layered_cases() {
+        match run_layered_case(&case) {
+            Ok(()) => println!("{}: ok", case.name),
+            Err(e) => {
+                failures += 1;
+                println!("{}: FAILED, {}", case.name, e);
+            }
+        }
+    }
+    for (file_name, data, name) in gridded_cases() {
+        match run_gridded_case(file_name, &data, name) {
+            Ok(()) => println!("{}: ok", file_name),
+            Err(e) => {
+                failures += 1;
+                println!("{}: FAILED, {}", file_name, e);
+            }
+        }
+    }
+    let checks: [(&str, Check); 8] = [
+        ("sky.fits", || run_fits_case(&fits_case())),
+        ("healpix indices", healpix_indices),
+        ("healpix round trip", healpix_round_trip),
+        ("azimuthal antipode", azimuthal_antipode),
+        ("remote levels", remote_levels),
+        ("attribution stamp", attribution_stamp),
+        ("export bundle", export_bundle),
+        ("validity mask", validity_mask),
+    ];
+    for (name, check) in checks {
+        match check() {
+            Ok(()) => println!("{}: ok", name),
+            Err(e) => {
+                failures += 1;
+                println!("{}: FAILED, {}", name, e);
+            }
+        }
+    }
+    (failures > 0) as i32
+}
@@ -0,0 +1,196 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::angles::{self, AngleUnit};
+use crate::appearance::{self, Appearance};
+use crate::cache::Cache;
+use crate::collisions::{self, Collision};
+use crate::dock::DockLayout;
+use crate::loading;
+use crate::network::{self, Credentials, Proxy};
+use crate::viewer::{self, Backdrop, MouseBindings};
+
+/// Application-wide preferences, stored in the user's configuration directory.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub cache_limit_mb: u64,
+    /// Most memory opening a source map may take. Larger maps are refused.
+    pub decode_memory_limit_mb: u64,
+    pub mouse: MouseBindings,
+    pub angle_unit: AngleUnit,
+    pub backdrop: Backdrop,
+    pub appearance: Appearance,
+    /// Ask the release feed for a newer version at startup. Off unless chosen.
+    pub check_for_updates: bool,
+    pub proxy: Proxy,
+    /// Serve remote files only from the download cache.
+    pub offline: bool,
+    /// Where the panels of the main window are.
+    pub dock: DockLayout,
+    /// What saving a map over an existing file does.
+    pub on_existing_file: Collision,
+    /// Save which pixels show something of the source next to exported maps.
+    pub export_validity_mask: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            cache_limit_mb: 2048,
+            decode_memory_limit_mb: loading::DEFAULT_DECODE_MEMORY / (1024 * 1024),
+            mouse: Default::default(),
+            angle_unit: Default::default(),
+            backdrop: Default::default(),
+            appearance: Default::default(),
+            check_for_updates: false,
+            proxy: Default::default(),
+            offline: false,
+            dock: Default::default(),
+            on_existing_file: Default::default(),
+            export_validity_mask: false,
+        }
+    }
+}
+
+/// A file next to the executable that turns on portable mode, like `--portable`.
+const PORTABLE_MARKER: &str = "portable.txt";
+
+static PORTABLE_DIRECTORY: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Keeps the configuration and cache in `submaptive-data` next to the
+/// executable, e.g. on a USB stick, if `flag` is set or the marker file
+/// exists. Must be called before anything is loaded.
+pub fn init_portable(flag: bool) {
+    let directory = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.parent().map(|directory| directory.to_path_buf()))
+        .filter(|directory| flag || directory.join(PORTABLE_MARKER).exists())
+        .map(|directory| directory.join("submaptive-data"));
+    let _ = PORTABLE_DIRECTORY.set(directory);
+}
+
+fn portable_directory() -> Option<&'static PathBuf> {
+    PORTABLE_DIRECTORY.get()?.as_ref()
+}
+
+pub fn config_directory() -> Option<PathBuf> {
+    match portable_directory() {
+        Some(directory) => Some(directory.join("config")),
+        None => dirs::config_dir().map(|directory| directory.join("submaptive")),
+    }
+}
+
+pub fn cache_directory() -> PathBuf {
+    match portable_directory() {
+        Some(directory) => directory.join("cache"),
+        None => dirs::cache_dir()
+            .unwrap_or_else(std::env::temp_dir)
+            .join("submaptive"),
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join("settings.json"))
+    }
+
+    /// The stored settings, or the defaults if there are none (or they are unreadable).
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn cache_limit_bytes(&self) -> u64 {
+        self.cache_limit_mb * 1024 * 1024
+    }
+
+    pub fn decode_memory_limit_bytes(&self) -> u64 {
+        self.decode_memory_limit_mb * 1024 * 1024
+    }
+}
+
+/// The settings window. Returns an error message if something went wrong.
+pub fn settings_window(
+    ctx: &egui::Context,
+    open: &mut bool,
+    settings: &mut Settings,
+    credentials: &mut Credentials,
+    cache: &Cache,
+) -> Option<String> {
+    let mut error = None;
+    egui::Window::new("Settings").open(open).show(ctx, |ui| {
+        if portable_directory().is_some() {
+            ui.label("Portable mode: settings and cache are kept next to the program.");
+            ui.separator();
+        }
+        ui.heading("Cache");
+        ui.label(format!("Location: {}", cache_directory().display()));
+        ui.add(
+            egui::Slider::new(&mut settings.cache_limit_mb, 64..=65536)
+                .logarithmic(true)
+                .suffix(" MB")
+                .text("Size limit"),
+        );
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "In use: {:.1} MB",
+                cache.size() as f64 / (1024. * 1024.)
+            ));
+            if ui.button("Clear cache").clicked() {
+                if let Err(e) = cache.clear() {
+                    error = Some(e.to_string());
+                }
+            }
+        });
+        ui.separator();
+        ui.heading("Opening maps");
+        ui.add(
+            egui::Slider::new(&mut settings.decode_memory_limit_mb, 256..=65536)
+                .logarithmic(true)
+                .suffix(" MB")
+                .text("Memory limit"),
+        );
+        ui.weak("Maps that would take more memory to open are refused.");
+        ui.separator();
+        ui.heading("Units");
+        angles::angle_unit_ui(ui, &mut settings.angle_unit);
+        ui.separator();
+        ui.heading("Saving");
+        collisions::collision_ui(ui, "Export collisions", &mut settings.on_existing_file);
+        ui.weak("Applies to exported maps, bundles and camera views.");
+        ui.separator();
+        ui.heading("Viewer");
+        viewer::backdrop_ui(ui, &mut settings.backdrop);
+        ui.separator();
+        ui.heading("Accessibility");
+        appearance::appearance_ui(ui, &mut settings.appearance);
+        ui.separator();
+        ui.heading("Mouse and touch");
+        viewer::mouse_bindings_ui(ui, &mut settings.mouse);
+        ui.separator();
+        ui.heading("Updates");
+        ui.checkbox(
+            &mut settings.check_for_updates,
+            "Check for a new version at startup",
+        );
+        ui.weak("This asks github.com for the latest release.");
+        ui.separator();
+        ui.heading("Network");
+        network::network_ui(ui, settings, credentials);
+    });
+    error
+}
@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::annotations::Annotation;
+use crate::attribution::Attribution;
+use crate::body::Body;
+use crate::color_keys::ColorKeys;
+use crate::dual_fisheye::DualFisheye;
+use crate::georeference::ControlPoint;
+use crate::graticule::Graticule;
+use crate::gridded::Coloring;
+use crate::leveling::Orientation;
+use crate::longitudes::Longitudes;
+use crate::mask::Mask;
+use crate::polar_fill::PolarFill;
+use crate::preprocess::Preprocessing;
+use crate::sampling::Sampling;
+use crate::vectors::OverlaySettings;
+
+/// The manual work done on one source image, stored next to it so that it is
+/// restored when the image is opened again.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sidecar {
+    pub control_points: Vec<ControlPoint>,
+    pub preprocessing: Preprocessing,
+    pub annotations: Vec<Annotation>,
+    pub mask: Mask,
+    pub color_keys: ColorKeys,
+    pub polar_fill: PolarFill,
+    pub orientation: Orientation,
+    pub dual_fisheye: Option<DualFisheye>,
+    /// How gridded data is drawn, if the source is any.
+    pub data_coloring: Coloring,
+    pub body: Body,
+    pub longitudes: Longitudes,
+    pub graticule: Graticule,
+    pub vector_overlays: Vec<OverlaySettings>,
+    pub attribution: Attribution,
+    pub sampling: Sampling,
+}
+
+impl Sidecar {
+    /// `map.png` keeps its sidecar in `map.png.submaptive.json`.
+    pub fn path_for(image_path: &Path) -> PathBuf {
+        let mut file_name = image_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".submaptive.json");
+        image_path.with_file_name(file_name)
+    }
+
+    /// Reads the sidecar of an image, or returns `None` if it doesn't have one.
+    pub fn load(image_path: &Path) -> Result<Option<Self>, String> {
+        let path = Self::path_for(image_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Self::read(&path).map(Some)
+    }
+
+    /// Reads a sidecar file wherever it is. Relative overlay paths are
+    /// relative to it.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut sidecar: Sidecar = serde_json::from_str(&contents)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for overlay in &mut sidecar.vector_overlays {
+            overlay.path = directory.join(&overlay.path);
+        }
+        Ok(sidecar)
+    }
+
+    pub fn save(&self, image_path: &Path) -> Result<(), String> {
+        let path = Self::path_for(image_path);
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
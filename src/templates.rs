@@ -0,0 +1,203 @@
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::batch::ProjectionSpec;
+use crate::settings::config_directory;
+use crate::sidecar::Sidecar;
+use crate::viewer::ViewerState;
+
+/// A starting point for maps that are made again and again: everything about
+/// a project that doesn't belong to one source image.
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Template {
+    pub source: ProjectionSpec,
+    pub target: ProjectionSpec,
+    pub viewer: ViewerState,
+    /// Overlays, export background, attribution and the like. What is tied
+    /// to the pixels of an image is left at its defaults.
+    pub settings: Sidecar,
+}
+
+impl Template {
+    pub fn new(
+        source: ProjectionSpec,
+        target: ProjectionSpec,
+        viewer: ViewerState,
+        sidecar: &Sidecar,
+    ) -> Self {
+        let mut template = Template {
+            source,
+            target,
+            viewer,
+            settings: sidecar.clone(),
+        };
+        template.settings = template.onto(&Sidecar::default());
+        template
+    }
+
+    /// `sidecar` with the template's settings in place of its own, except for
+    /// what is tied to the image.
+    pub fn onto(&self, sidecar: &Sidecar) -> Sidecar {
+        Sidecar {
+            control_points: sidecar.control_points.clone(),
+            preprocessing: sidecar.preprocessing,
+            annotations: sidecar.annotations.clone(),
+            mask: sidecar.mask.clone(),
+            polar_fill: sidecar.polar_fill,
+            orientation: sidecar.orientation,
+            dual_fisheye: sidecar.dual_fisheye,
+            ..self.settings.clone()
+        }
+    }
+
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = path(name)?;
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, name: &str) -> Result<(), String> {
+        let path = path(name)?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+fn directory() -> Option<PathBuf> {
+    config_directory().map(|directory| directory.join("templates"))
+}
+
+/// Names become file names, so they can't lead out of the directory.
+fn path(name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!("\"{}\" can't be the name of a template.", name));
+    }
+    let directory = directory().ok_or("No configuration directory available.")?;
+    Ok(directory.join(format!("{}.json", name)))
+}
+
+/// The names of the saved templates, sorted.
+pub fn list() -> Vec<String> {
+    let Some(entries) = directory().and_then(|directory| std::fs::read_dir(directory).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            match path.extension()? == "json" {
+                true => Some(path.file_stem()?.to_string_lossy().into_owned()),
+                false => None,
+            }
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    let path = path(name)?;
+    std::fs::remove_file(&path).map_err(|e| format!("Could not delete {}: {}", path.display(), e))
+}
+
+#[derive(Default)]
+pub struct TemplatesWindow {
+    pub open: bool,
+    /// The name to save the current project under.
+    name: String,
+    /// Read again whenever the window opens or a template is saved or deleted.
+    names: Option<Vec<String>>,
+}
+
+impl TemplatesWindow {
+    pub fn refresh(&mut self) {
+        self.names = None;
+    }
+}
+
+pub enum TemplateAction {
+    Save(String),
+    Apply(String),
+    Delete(String),
+    /// Stop applying the active template to newly opened images.
+    Clear,
+}
+
+/// `active` is the template that images without a sidecar of their own open
+/// with.
+pub fn templates_window(
+    ctx: &egui::Context,
+    window: &mut TemplatesWindow,
+    active: Option<&str>,
+) -> Option<TemplateAction> {
+    if !window.open {
+        window.refresh();
+        return None;
+    }
+    let mut action = None;
+    let names = window.names.get_or_insert_with(list);
+    egui::Window::new("Templates")
+        .open(&mut window.open)
+        .show(ctx, |ui| {
+            ui.label(
+                "Projections, overlays, view and export settings, without a source image. \
+                 An applied template stays active for maps opened without a sidecar.",
+            );
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.name).hint_text("Weekly weather map"),
+                );
+                if ui
+                    .add_enabled(
+                        !window.name.trim().is_empty(),
+                        egui::Button::new("Save current project"),
+                    )
+                    .clicked()
+                {
+                    action = Some(TemplateAction::Save(window.name.trim().to_string()));
+                }
+            });
+            ui.separator();
+            if names.is_empty() {
+                ui.label("No templates saved yet.");
+            }
+            egui::Grid::new("Templates").show(ui, |ui| {
+                for name in names.iter() {
+                    match Some(name.as_str()) == active {
+                        true => ui.strong(name),
+                        false => ui.label(name),
+                    };
+                    if ui.button("Apply").clicked() {
+                        action = Some(TemplateAction::Apply(name.clone()));
+                    }
+                    if ui.button("Delete").clicked() {
+                        action = Some(TemplateAction::Delete(name.clone()));
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(active) = active {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("New maps open with \"{}\".", active));
+                    if ui.button("Clear").clicked() {
+                        action = Some(TemplateAction::Clear);
+                    }
+                });
+            }
+        });
+    if matches!(
+        action,
+        Some(TemplateAction::Save(_) | TemplateAction::Delete(_))
+    ) {
+        window.refresh();
+    }
+    action
+}
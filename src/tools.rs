@@ -0,0 +1,182 @@
+use eframe::egui;
+
+use crate::color_keys;
+use crate::labels::{Labels, Priority};
+
+/// What clicking and dragging in the source view does. Exactly one tool is
+/// active at a time, so interactive features don't fight over the mouse.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Tool {
+    #[default]
+    Pan,
+    Measure,
+    PickCenter,
+    Annotate,
+    Mask,
+    Georeference,
+    Eyedropper,
+    Level,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 8] = [
+        Tool::Pan,
+        Tool::Measure,
+        Tool::PickCenter,
+        Tool::Annotate,
+        Tool::Mask,
+        Tool::Georeference,
+        Tool::Eyedropper,
+        Tool::Level,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tool::Pan => "Pan",
+            Tool::Measure => "Measure",
+            Tool::PickCenter => "Pick center",
+            Tool::Annotate => "Annotate",
+            Tool::Mask => "Mask",
+            Tool::Georeference => "Georeference",
+            Tool::Eyedropper => "Eyedropper",
+            Tool::Level => "Level",
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Tool::Pan => "Drag to pan, scroll to zoom.",
+            Tool::Measure => "Click two points to measure the distance between them.",
+            Tool::PickCenter => "Click a place to center the target projection on it.",
+            Tool::Annotate => "Click to place a note; edit its text under Annotations.",
+            Tool::Mask => {
+                "Click to add corners of an area to leave out; click the first corner to close it."
+            }
+            Tool::Georeference => "Click a known location to add a control point there.",
+            Tool::Eyedropper => "Click either view to sample a color.",
+            Tool::Level => "Click points along the horizon of a panorama to level it.",
+        }
+    }
+
+    pub fn cursor(&self) -> egui::CursorIcon {
+        match self {
+            Tool::Pan => egui::CursorIcon::Grab,
+            Tool::Annotate => egui::CursorIcon::Text,
+            _ => egui::CursorIcon::Crosshair,
+        }
+    }
+}
+
+/// What a color sampled with the eyedropper is used for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SampleTarget {
+    #[default]
+    NoData,
+    Background,
+    Annotation,
+}
+
+/// The active tool and the input it has collected so far.
+pub struct Tools {
+    pub active: Tool,
+    /// Up to two points picked with the measure tool, in source pixels.
+    pub measure: Vec<(f64, f64)>,
+    /// Corners of the mask area being drawn, in source pixels.
+    pub mask_draft: Vec<(f64, f64)>,
+    /// Points on the horizon of a panorama, in source pixels.
+    pub horizon: Vec<(f64, f64)>,
+    pub sampled: Option<[u8; 3]>,
+    pub sample_target: SampleTarget,
+    /// The color new annotations get.
+    pub annotation_color: [u8; 3],
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Tools {
+            active: Default::default(),
+            measure: Vec::new(),
+            mask_draft: Vec::new(),
+            horizon: Vec::new(),
+            sampled: None,
+            sample_target: Default::default(),
+            annotation_color: crate::annotations::DEFAULT_COLOR,
+        }
+    }
+}
+
+impl Tools {
+    pub fn add_measure_point(&mut self, pixel: (f64, f64)) {
+        if self.measure.len() == 2 {
+            self.measure.clear();
+        }
+        self.measure.push(pixel);
+    }
+}
+
+pub fn toolbar(ui: &mut egui::Ui, tools: &mut Tools) {
+    ui.horizontal(|ui| {
+        for tool in Tool::ALL {
+            ui.selectable_value(&mut tools.active, tool, tool.name())
+                .on_hover_text(tool.hint());
+        }
+        ui.separator();
+        ui.weak(tools.active.hint());
+    });
+    if tools.active == Tool::Eyedropper {
+        ui.horizontal(|ui| {
+            ui.label("Use sample as");
+            let target = &mut tools.sample_target;
+            ui.selectable_value(target, SampleTarget::NoData, "no-data color");
+            ui.selectable_value(target, SampleTarget::Background, "background fill");
+            ui.selectable_value(target, SampleTarget::Annotation, "annotation color");
+            if let Some(color) = tools.sampled {
+                ui.separator();
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(16., 16.), egui::Sense::hover());
+                ui.painter().rect_filled(
+                    rect,
+                    2.,
+                    egui::Color32::from_rgb(color[0], color[1], color[2]),
+                );
+                ui.monospace(color_keys::describe(color));
+            }
+        });
+    }
+}
+
+/// Draws the measured line into the image at `rect`, labelled with `label`.
+pub fn paint_measurement(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    tools: &Tools,
+    label: Option<String>,
+    labels: &mut Labels,
+) {
+    let to_screen = |(x, y): (f64, f64)| {
+        rect.min
+            + egui::vec2(
+                x as f32 / image_dimensions.0 as f32 * rect.width(),
+                y as f32 / image_dimensions.1 as f32 * rect.height(),
+            )
+    };
+    let points: Vec<_> = tools.measure.iter().copied().map(to_screen).collect();
+    let stroke = (2., egui::Color32::YELLOW);
+    for point in &points {
+        painter.circle_filled(*point, 3., egui::Color32::YELLOW);
+        labels.avoid(egui::Rect::from_center_size(*point, egui::vec2(6., 6.)));
+    }
+    if let [a, b] = points[..] {
+        painter.line_segment([a, b], stroke);
+        if let Some(label) = label {
+            labels.add(
+                a + (b - a) / 2.,
+                (egui::Align2::CENTER_BOTTOM, egui::vec2(0., -6.)),
+                label,
+                egui::FontId::default(),
+                egui::Color32::YELLOW,
+                Priority::Measurement,
+            );
+        }
+    }
+}
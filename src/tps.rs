@@ -0,0 +1,195 @@
+/// A two-dimensional thin-plate spline interpolating a set of control points.
+///
+/// The spline maps every source point exactly onto its target and bends as
+/// little as possible in between, which makes it a good model for the
+/// irregular distortions of scanned or hand-drawn maps.
+#[derive(Clone, Debug)]
+pub struct ThinPlateSpline {
+    sources: Vec<(f64, f64)>,
+    weights: Vec<(f64, f64)>,
+    affine: [(f64, f64); 3],
+    offset: (f64, f64),
+    scale: f64,
+}
+
+impl ThinPlateSpline {
+    /// Fits a spline through the given control points.
+    ///
+    /// Returns `None` if there are fewer than three points or the points are
+    /// degenerate (e.g. all on one line), in which case no unique spline exists.
+    pub fn fit(sources: &[(f64, f64)], targets: &[(f64, f64)]) -> Option<Self> {
+        assert_eq!(sources.len(), targets.len());
+        let n = sources.len();
+        if n < 3 {
+            return None;
+        }
+        // Normalize the source coordinates so the radial basis stays well
+        // conditioned regardless of whether we work in pixels or degrees.
+        let offset = (
+            sources.iter().map(|p| p.0).sum::<f64>() / n as f64,
+            sources.iter().map(|p| p.1).sum::<f64>() / n as f64,
+        );
+        let scale = sources
+            .iter()
+            .map(|p| (p.0 - offset.0).abs().max((p.1 - offset.1).abs()))
+            .fold(0.0, f64::max);
+        if scale == 0.0 {
+            return None;
+        }
+        let sources: Vec<_> = sources
+            .iter()
+            .map(|p| ((p.0 - offset.0) / scale, (p.1 - offset.1) / scale))
+            .collect();
+
+        let size = n + 3;
+        let mut matrix = vec![vec![0.0; size]; size];
+        for i in 0..n {
+            for j in 0..n {
+                matrix[i][j] = radial_basis(distance_squared(sources[i], sources[j]));
+            }
+            let row = [1.0, sources[i].0, sources[i].1];
+            for (k, value) in row.into_iter().enumerate() {
+                matrix[i][n + k] = value;
+                matrix[n + k][i] = value;
+            }
+        }
+        let mut rhs_x = vec![0.0; size];
+        let mut rhs_y = vec![0.0; size];
+        for (i, target) in targets.iter().enumerate() {
+            rhs_x[i] = target.0;
+            rhs_y[i] = target.1;
+        }
+        let solution_x = solve(matrix.clone(), rhs_x)?;
+        let solution_y = solve(matrix, rhs_y)?;
+
+        Some(ThinPlateSpline {
+            sources,
+            weights: (0..n).map(|i| (solution_x[i], solution_y[i])).collect(),
+            affine: [
+                (solution_x[n], solution_y[n]),
+                (solution_x[n + 1], solution_y[n + 1]),
+                (solution_x[n + 2], solution_y[n + 2]),
+            ],
+            offset,
+            scale,
+        })
+    }
+
+    pub fn apply(&self, point: (f64, f64)) -> (f64, f64) {
+        let point = (
+            (point.0 - self.offset.0) / self.scale,
+            (point.1 - self.offset.1) / self.scale,
+        );
+        let [constant, by_x, by_y] = self.affine;
+        let mut result = (
+            constant.0 + by_x.0 * point.0 + by_y.0 * point.1,
+            constant.1 + by_x.1 * point.0 + by_y.1 * point.1,
+        );
+        for (source, weight) in self.sources.iter().zip(&self.weights) {
+            let basis = radial_basis(distance_squared(*source, point));
+            result.0 += weight.0 * basis;
+            result.1 += weight.1 * basis;
+        }
+        result
+    }
+}
+
+fn distance_squared(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)
+}
+
+/// The thin-plate kernel r² ln r, written in terms of r² to avoid a square root.
+fn radial_basis(r_squared: f64) -> f64 {
+    if r_squared == 0.0 {
+        0.0
+    } else {
+        0.5 * r_squared * r_squared.ln()
+    }
+}
+
+/// Solves a dense linear system by Gaussian elimination with partial pivoting.
+fn solve(mut matrix: Vec<Vec<f64>>, mut rhs: Vec<f64>) -> Option<Vec<f64>> {
+    let size = rhs.len();
+    for column in 0..size {
+        let pivot = (column..size)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        for row in column + 1..size {
+            let factor = matrix[row][column] / matrix[column][column];
+            if factor == 0.0 {
+                continue;
+            }
+            let (upper, lower) = matrix.split_at_mut(row);
+            for (value, pivot_value) in lower[0][column..].iter_mut().zip(&upper[column][column..])
+            {
+                *value -= factor * pivot_value;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut solution = vec![0.0; size];
+    for row in (0..size).rev() {
+        let sum: f64 = (row + 1..size).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (rhs[row] - sum) / matrix[row][row];
+    }
+    Some(solution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: (f64, f64), expected: (f64, f64)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-9 && (actual.1 - expected.1).abs() < 1e-9,
+            "{:?} isn't {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn control_points_are_reproduced_exactly() {
+        let sources = [
+            (0., 0.),
+            (10., 0.),
+            (0., 10.),
+            (10., 10.),
+            (4., 7.),
+            (8., 2.),
+        ];
+        let targets = [
+            (3., 1.),
+            (14., -2.),
+            (1., 12.),
+            (15., 9.),
+            (6., 5.),
+            (9., 4.),
+        ];
+        let spline = ThinPlateSpline::fit(&sources, &targets).unwrap();
+        for (source, target) in sources.into_iter().zip(targets) {
+            assert_close(spline.apply(source), target);
+        }
+    }
+
+    #[test]
+    fn affine_maps_are_reproduced_exactly() {
+        let affine = |(x, y): (f64, f64)| (2. * x - 0.5 * y + 3., 0.25 * x + 1.5 * y - 7.);
+        let sources = [(-30., 5.), (12., -40.), (45., 60.), (0., 0.), (-10., 25.)];
+        let targets: Vec<_> = sources.into_iter().map(affine).collect();
+        let spline = ThinPlateSpline::fit(&sources, &targets).unwrap();
+        for point in [(0., 0.), (100., -80.), (-3.5, 17.25), (33., 33.)] {
+            assert_close(spline.apply(point), affine(point));
+        }
+    }
+
+    #[test]
+    fn points_on_a_line_have_no_spline() {
+        let sources = [(0., 0.), (1., 1.), (2., 2.)];
+        assert!(ThinPlateSpline::fit(&sources, &sources).is_none());
+    }
+}
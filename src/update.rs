@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use eframe::egui;
+
+use crate::jobs::Job;
+use crate::network;
+use crate::notifications::Log;
+
+/// The newest published release, as reported by GitHub.
+const RELEASE_FEED: &str = "https://api.github.com/repos/bjoern-j/submaptive-ui/releases/latest";
+const TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Debug)]
+pub struct Release {
+    pub version: String,
+    pub changelog: String,
+    pub url: String,
+}
+
+/// The numeric parts of a version such as `v1.2.3` or `1.2.3-beta`.
+fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(|c: char| !c.is_ascii_digit())
+        .take_while(|part| !part.is_empty())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+pub fn is_newer(candidate: &str, current: &str) -> bool {
+    version_numbers(candidate) > version_numbers(current)
+}
+
+fn fetch_latest() -> Result<Release, String> {
+    let agent = network::agent(TIMEOUT)?;
+    let body = network::get(&agent, RELEASE_FEED)
+        .set("Accept", "application/vnd.github+json")
+        .call()
+        .map_err(|e| e.to_string())?
+        .into_string()
+        .map_err(|e| e.to_string())?;
+    let release: serde_json::Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+    let text = |key: &str| release[key].as_str().unwrap_or_default().to_string();
+    let version = text("tag_name");
+    if version.is_empty() {
+        return Err("The release feed names no version.".to_string());
+    }
+    Ok(Release {
+        version,
+        changelog: text("body"),
+        url: text("html_url"),
+    })
+}
+
+/// Looks for a newer release in the background, if the user allowed it.
+#[derive(Default)]
+pub struct UpdateCheck {
+    job: Option<Job<Result<Release, String>>>,
+    started: bool,
+    pub available: Option<Release>,
+    pub window_open: bool,
+}
+
+impl UpdateCheck {
+    /// Starts the check once per run.
+    pub fn start(&mut self, ctx: &egui::Context) {
+        if self.started {
+            return;
+        }
+        self.started = true;
+        self.job = Some(Job::spawn(ctx, "Checking for updates", |send, _| {
+            send(fetch_latest())
+        }));
+    }
+
+    pub fn poll(&mut self, log: &mut Log) {
+        let Some(job) = &mut self.job else {
+            return;
+        };
+        for result in job.poll() {
+            match result {
+                Ok(release) if is_newer(&release.version, env!("CARGO_PKG_VERSION")) => {
+                    log.info(format!("Version {} is available.", release.version));
+                    self.available = Some(release);
+                }
+                Ok(_) => {}
+                // Being offline is no reason to show an error.
+                Err(e) => log.info(format!("Could not check for updates: {}", e)),
+            }
+        }
+        if job.is_finished() {
+            self.job = None;
+        }
+    }
+}
+
+pub fn update_window(ctx: &egui::Context, check: &mut UpdateCheck) {
+    let Some(release) = &check.available else {
+        return;
+    };
+    egui::Window::new(format!("Version {}", release.version))
+        .open(&mut check.window_open)
+        .show(ctx, |ui| {
+            ui.label(format!(
+                "You are running version {}.",
+                env!("CARGO_PKG_VERSION")
+            ));
+            if !release.url.is_empty() {
+                ui.hyperlink_to("Download", &release.url);
+            }
+            ui.separator();
+            egui::ScrollArea::vertical()
+                .max_height(300.)
+                .show(ui, |ui| {
+                    ui.label(&release.changelog);
+                });
+        });
+}
@@ -0,0 +1,559 @@
+use std::path::{Path, PathBuf};
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::crs::Crs;
+use crate::graticule::{self, densify, LineStyle};
+use crate::ProjectionData;
+
+/// Longitude and latitude, in degrees.
+pub type Coordinates = (f64, f64);
+
+/// A feature's geometry in geographic coordinates.
+#[derive(Clone, Debug)]
+pub enum Geometry {
+    Points(Vec<Coordinates>),
+    Lines(Vec<Vec<Coordinates>>),
+    /// The outer ring and any holes, each closed or not.
+    Polygon(Vec<Vec<Coordinates>>),
+}
+
+/// How an imported vector file is shown. The geometry itself is read from
+/// the file again whenever the source image is opened.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverlaySettings {
+    pub path: PathBuf,
+    pub visible: bool,
+    pub style: LineStyle,
+    /// How far (in points on screen) simplified lines may stray from the true ones.
+    pub tolerance: f32,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        OverlaySettings {
+            path: PathBuf::new(),
+            visible: true,
+            style: LineStyle {
+                color: [120, 220, 120],
+                ..Default::default()
+            },
+            tolerance: 0.5,
+        }
+    }
+}
+
+/// Lines in the pixel coordinates of an image.
+#[derive(Default)]
+struct Projected {
+    points: Vec<(f64, f64)>,
+    lines: Vec<Vec<(f64, f64)>>,
+}
+
+/// The projected (and simplified) geometry for one view.
+struct Cached {
+    key: String,
+    full: Projected,
+    tolerance: f64,
+    simplified: Projected,
+}
+
+pub struct VectorOverlay {
+    pub settings: OverlaySettings,
+    /// What the file's coordinates were in, before they were converted.
+    pub crs: Crs,
+    features: Vec<Geometry>,
+    /// At most one entry per view it is shown in.
+    cache: Vec<Cached>,
+}
+
+impl VectorOverlay {
+    pub fn load(settings: OverlaySettings) -> Result<Self, String> {
+        let (features, crs) = load_features(&settings.path)?;
+        Ok(VectorOverlay {
+            settings,
+            crs,
+            features,
+            cache: Vec::new(),
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.settings
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn project(&self, projection: &ProjectionData, image_dimensions: (u32, u32)) -> Projected {
+        let to_pixel = |(long, lat): Coordinates| {
+            let point = (wrap_long(long), lat.clamp(-90., 90.)).try_into().ok()?;
+            projection.pixel_at_point(&point, image_dimensions)
+        };
+        let mut projected = Projected::default();
+        for feature in &self.features {
+            match feature {
+                Geometry::Points(points) => {
+                    projected
+                        .points
+                        .extend(points.iter().copied().filter_map(to_pixel));
+                }
+                Geometry::Lines(lines) | Geometry::Polygon(lines) => {
+                    for line in lines {
+                        projected.lines.extend(project_line(line, &to_pixel));
+                    }
+                }
+            }
+        }
+        projected
+    }
+
+    /// The geometry for a view in `projection`, simplified to within `tolerance`
+    /// pixels. `key` identifies the projection and its parameters.
+    fn projected(
+        &mut self,
+        key: String,
+        projection: &ProjectionData,
+        image_dimensions: (u32, u32),
+        tolerance: f64,
+    ) -> &Projected {
+        let key = format!("{} {:?}", key, image_dimensions);
+        let index = self.cache.iter().position(|cached| cached.key == key);
+        let index = match index {
+            Some(index) => index,
+            None => {
+                // Two views at most, so the oldest entry can go.
+                if self.cache.len() >= 2 {
+                    self.cache.remove(0);
+                }
+                let full = self.project(projection, image_dimensions);
+                self.cache.push(Cached {
+                    key,
+                    simplified: simplify(&full, tolerance),
+                    full,
+                    tolerance,
+                });
+                self.cache.len() - 1
+            }
+        };
+        let cached = &mut self.cache[index];
+        if cached.tolerance != tolerance {
+            cached.simplified = simplify(&cached.full, tolerance);
+            cached.tolerance = tolerance;
+        }
+        &cached.simplified
+    }
+
+    /// All points of the overlay, unsimplified, in the pixel coordinates of
+    /// an image of `image_dimensions` in `projection`.
+    pub fn vertices(
+        &self,
+        projection: &ProjectionData,
+        image_dimensions: (u32, u32),
+    ) -> Vec<(f64, f64)> {
+        let projected = self.project(projection, image_dimensions);
+        projected
+            .points
+            .into_iter()
+            .chain(projected.lines.into_iter().flatten())
+            .collect()
+    }
+}
+
+fn wrap_long(long: f64) -> f64 {
+    if (-180.0..=180.).contains(&long) {
+        long
+    } else {
+        (long + 180.).rem_euclid(360.) - 180.
+    }
+}
+
+/// The point `t` of the way from `a` to `b`, going the short way around
+/// the antimeridian.
+fn interpolate(a: Coordinates, b: Coordinates, t: f64) -> Coordinates {
+    let long = a.0 + wrap_long(b.0 - a.0) * t;
+    (wrap_long(long), a.1 + (b.1 - a.1) * t)
+}
+
+/// Projects a line, cut into pieces where it leaves the projection or
+/// crosses a seam of the map (such as the antimeridian, or the edge a pole
+/// is stretched along), each reaching right up to the cut. Segments that
+/// curve in the projection get extra points.
+fn project_line(
+    line: &[Coordinates],
+    to_pixel: &dyn Fn(Coordinates) -> Option<(f64, f64)>,
+) -> Vec<Vec<(f64, f64)>> {
+    let Some(first) = line.first() else {
+        return Vec::new();
+    };
+    let to_position = |point| to_pixel(point).map(|(x, y)| egui::pos2(x as f32, y as f32));
+    let mut positions = vec![to_position(*first)];
+    for pair in line.windows(2) {
+        let along = |t: f64| to_position(interpolate(pair[0], pair[1], t));
+        densify(
+            &along,
+            egui::Rect::EVERYTHING,
+            (0., positions.last().copied().flatten()),
+            (1., along(1.)),
+            0,
+            &mut positions,
+        );
+    }
+    positions
+        .split(Option::is_none)
+        .filter(|piece| piece.len() > 1)
+        .map(|piece| {
+            piece
+                .iter()
+                .flatten()
+                .map(|position| (position.x as f64, position.y as f64))
+                .collect()
+        })
+        .collect()
+}
+
+fn simplify(projected: &Projected, tolerance: f64) -> Projected {
+    Projected {
+        points: projected.points.clone(),
+        lines: projected
+            .lines
+            .iter()
+            .map(|line| douglas_peucker(line, tolerance))
+            .collect(),
+    }
+}
+
+/// Leaves out the points of a line that are within `tolerance` of the
+/// simplified line, keeping its ends.
+fn douglas_peucker(line: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if line.len() < 3 || tolerance <= 0. {
+        return line.to_vec();
+    }
+    let mut keep = vec![false; line.len()];
+    keep[0] = true;
+    keep[line.len() - 1] = true;
+    // An explicit stack, since long lines would recurse too deep.
+    let mut stack = vec![(0, line.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let (a, b) = (line[first], line[last]);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = dx.hypot(dy);
+        let distance = |p: (f64, f64)| {
+            // Closed rings start and end at the same point.
+            if length == 0. {
+                (p.0 - a.0).hypot(p.1 - a.1)
+            } else {
+                ((p.0 - a.0) * dy - (p.1 - a.1) * dx).abs() / length
+            }
+        };
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance(line[i])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                stack.push((first, i));
+                stack.push((i, last));
+            }
+        }
+    }
+    line.iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+impl Geometry {
+    fn coordinates_mut(&mut self) -> impl Iterator<Item = &mut Coordinates> {
+        let lines = match self {
+            Geometry::Points(points) => std::slice::from_mut(points),
+            Geometry::Lines(lines) | Geometry::Polygon(lines) => &mut lines[..],
+        };
+        lines.iter_mut().flatten()
+    }
+}
+
+/// Reads GeoJSON (`.geojson`, `.json`) or ESRI shapefiles (`.shp`, with the
+/// coordinate system in the `.prj` next to it), in longitudes and latitudes.
+fn load_features(path: &Path) -> Result<(Vec<Geometry>, Crs), String> {
+    let (mut features, crs) = read_features(path)?;
+    match crs {
+        Some(crs) => {
+            for coordinates in features.iter_mut().flat_map(Geometry::coordinates_mut) {
+                *coordinates = crs.to_geographic(*coordinates);
+            }
+            Ok((features, crs))
+        }
+        None => {
+            let geographic = features
+                .iter_mut()
+                .flat_map(Geometry::coordinates_mut)
+                .all(|(long, lat)| (-360.0..=360.).contains(long) && (-90.0..=90.).contains(lat));
+            match geographic {
+                true => Ok((features, Crs::Geographic)),
+                false => Err(
+                    "The file doesn't say what coordinate system it uses, and its \
+                     coordinates aren't longitudes and latitudes."
+                        .to_string(),
+                ),
+            }
+        }
+    }
+}
+
+/// The features as they are in the file, and the coordinate system it names.
+fn read_features(path: &Path) -> Result<(Vec<Geometry>, Option<Crs>), String> {
+    let extension = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+    match extension.as_str() {
+        "shp" => {
+            let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+            let prj = path.with_extension("prj");
+            let crs = match std::fs::read_to_string(&prj) {
+                Ok(wkt) => Some(Crs::from_wkt(&wkt)?),
+                Err(_) => None,
+            };
+            Ok((read_shapefile(&bytes)?, crs))
+        }
+        "geojson" | "json" => {
+            let contents = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let value: serde_json::Value =
+                serde_json::from_str(&contents).map_err(|e| e.to_string())?;
+            let mut features = Vec::new();
+            read_geojson(&value, &mut features)?;
+            // Only older GeoJSON names a CRS; since RFC 7946 it is always WGS 84.
+            let crs = match value["crs"]["properties"]["name"].as_str() {
+                Some(name) => Crs::from_name(name)?,
+                None => Crs::Geographic,
+            };
+            Ok((features, Some(crs)))
+        }
+        _ => Err(format!(
+            "Can't read vector files of type \"{}\".",
+            extension
+        )),
+    }
+}
+
+fn read_geojson(value: &serde_json::Value, features: &mut Vec<Geometry>) -> Result<(), String> {
+    let position = |value: &serde_json::Value| -> Result<Coordinates, String> {
+        match value.as_array().map(|array| &array[..]) {
+            Some([long, lat, ..]) => long
+                .as_f64()
+                .zip(lat.as_f64())
+                .ok_or_else(|| "Invalid GeoJSON position.".to_string()),
+            _ => Err("Invalid GeoJSON position.".to_string()),
+        }
+    };
+    let list = |value: &serde_json::Value| -> Result<Vec<serde_json::Value>, String> {
+        value
+            .as_array()
+            .cloned()
+            .ok_or_else(|| "Invalid GeoJSON coordinates.".to_string())
+    };
+    let line = |value: &serde_json::Value| -> Result<Vec<Coordinates>, String> {
+        list(value)?.iter().map(position).collect()
+    };
+    let lines = |value: &serde_json::Value| -> Result<Vec<Vec<Coordinates>>, String> {
+        list(value)?.iter().map(line).collect()
+    };
+    let coordinates = &value["coordinates"];
+    match value["type"].as_str() {
+        Some("FeatureCollection") => {
+            for feature in list(&value["features"])? {
+                read_geojson(&feature, features)?;
+            }
+        }
+        // Features without a geometry are allowed.
+        Some("Feature") if value["geometry"].is_null() => {}
+        Some("Feature") => read_geojson(&value["geometry"], features)?,
+        Some("GeometryCollection") => {
+            for geometry in list(&value["geometries"])? {
+                read_geojson(&geometry, features)?;
+            }
+        }
+        Some("Point") => features.push(Geometry::Points(vec![position(coordinates)?])),
+        Some("MultiPoint") => features.push(Geometry::Points(line(coordinates)?)),
+        Some("LineString") => features.push(Geometry::Lines(vec![line(coordinates)?])),
+        Some("MultiLineString") => features.push(Geometry::Lines(lines(coordinates)?)),
+        Some("Polygon") => features.push(Geometry::Polygon(lines(coordinates)?)),
+        Some("MultiPolygon") => {
+            for polygon in list(coordinates)? {
+                features.push(Geometry::Polygon(lines(&polygon)?));
+            }
+        }
+        other => return Err(format!("Unknown GeoJSON type {:?}.", other)),
+    }
+    Ok(())
+}
+
+fn read_shapefile(bytes: &[u8]) -> Result<Vec<Geometry>, String> {
+    let truncated = || "The shapefile is truncated.".to_string();
+    let int = |bytes: &[u8], offset: usize, big_endian: bool| -> Result<usize, String> {
+        let word: [u8; 4] = bytes
+            .get(offset..offset + 4)
+            .ok_or_else(truncated)?
+            .try_into()
+            .unwrap();
+        let value = match big_endian {
+            true => i32::from_be_bytes(word),
+            false => i32::from_le_bytes(word),
+        };
+        usize::try_from(value).map_err(|_| "Invalid shapefile.".to_string())
+    };
+    let float = |bytes: &[u8], offset: usize| -> Result<f64, String> {
+        let word: [u8; 8] = bytes
+            .get(offset..offset + 8)
+            .ok_or_else(truncated)?
+            .try_into()
+            .unwrap();
+        Ok(f64::from_le_bytes(word))
+    };
+    let point = |bytes: &[u8], offset: usize| -> Result<Coordinates, String> {
+        Ok((float(bytes, offset)?, float(bytes, offset + 8)?))
+    };
+    if bytes.len() < 100 || int(bytes, 0, true)? != 9994 {
+        return Err("Not a shapefile.".to_string());
+    }
+    let mut features = Vec::new();
+    let mut offset = 100;
+    while offset + 8 <= bytes.len() {
+        // Lengths are counted in 16-bit words.
+        let length = int(bytes, offset + 4, true)? * 2;
+        let record = bytes
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(truncated)?;
+        offset += 8 + length;
+        let shape_type = int(record, 0, false)?;
+        // The Z and M variants (11–28) add values after the ones read here.
+        match shape_type % 10 {
+            0 => {}
+            1 => features.push(Geometry::Points(vec![point(record, 4)?])),
+            8 => {
+                let count = int(record, 36, false)?;
+                let points = (0..count)
+                    .map(|i| point(record, 40 + 16 * i))
+                    .collect::<Result<_, _>>()?;
+                features.push(Geometry::Points(points));
+            }
+            3 | 5 => {
+                let parts = int(record, 36, false)?;
+                let count = int(record, 40, false)?;
+                let points_start = 44 + 4 * parts;
+                let mut starts = (0..parts)
+                    .map(|i| int(record, 44 + 4 * i, false))
+                    .collect::<Result<Vec<_>, _>>()?;
+                starts.push(count);
+                let lines = starts
+                    .windows(2)
+                    .map(|range| {
+                        (range[0]..range[1].max(range[0]))
+                            .map(|i| point(record, points_start + 16 * i))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                features.push(match shape_type % 10 {
+                    3 => Geometry::Lines(lines),
+                    _ => Geometry::Polygon(lines),
+                });
+            }
+            _ => return Err(format!("Unsupported shape type {}.", shape_type)),
+        }
+    }
+    Ok(features)
+}
+
+/// Draws an overlay over a map in `projection` drawn into `rect`. `key`
+/// identifies the projection and its parameters.
+pub fn paint_vector_overlay(
+    painter: &egui::Painter,
+    rect: egui::Rect,
+    image_dimensions: (u32, u32),
+    projection: &ProjectionData,
+    key: String,
+    overlay: &mut VectorOverlay,
+) {
+    if !overlay.settings.visible {
+        return;
+    }
+    let pixels_per_point = image_dimensions.0 as f64 / rect.width() as f64;
+    // Rounded to quarter steps of a doubling, so that zooming doesn't
+    // simplify again on every frame.
+    let tolerance = overlay.settings.tolerance as f64 * pixels_per_point;
+    let tolerance = match tolerance > 0. {
+        true => 2f64.powf((tolerance.log2() * 4.).round() / 4.),
+        false => 0.,
+    };
+    let style = overlay.settings.style;
+    let projected = overlay.projected(key, projection, image_dimensions, tolerance);
+    let to_screen = |(x, y): (f64, f64)| {
+        rect.min
+            + egui::vec2(
+                x as f32 / image_dimensions.0 as f32 * rect.width(),
+                y as f32 / image_dimensions.1 as f32 * rect.height(),
+            )
+    };
+    let [r, g, b] = style.color;
+    for point in &projected.points {
+        painter.circle_filled(
+            to_screen(*point),
+            style.width + 1.5,
+            egui::Color32::from_rgb(r, g, b),
+        );
+    }
+    for line in &projected.lines {
+        let points: Vec<_> = line.iter().copied().map(to_screen).collect();
+        style.paint(painter, &points);
+    }
+}
+
+pub enum OverlayAction {
+    Import(PathBuf),
+}
+
+pub fn vector_overlays_ui(
+    ui: &mut egui::Ui,
+    overlays: &mut Vec<VectorOverlay>,
+) -> Option<OverlayAction> {
+    let mut action = None;
+    ui.collapsing("Vector overlays", |ui| {
+        if ui.button("Import GeoJSON or shapefile...").clicked() {
+            if let Some(path) = rfd::FileDialog::new()
+                .add_filter("Vector data", &["geojson", "json", "shp"])
+                .pick_file()
+            {
+                action = Some(OverlayAction::Import(path));
+            }
+        }
+        let mut removed = None;
+        for (index, overlay) in overlays.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    let name = overlay.name();
+                    ui.checkbox(&mut overlay.settings.visible, name);
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(index);
+                    }
+                });
+                ui.label(format!("Coordinates: {}", overlay.crs.name()));
+                graticule::line_style_ui(ui, &mut overlay.settings.style, "Style");
+                ui.add(
+                    egui::Slider::new(&mut overlay.settings.tolerance, 0.0..=10.)
+                        .suffix(" pt")
+                        .text("Simplification"),
+                );
+            });
+        }
+        if let Some(index) = removed {
+            overlays.remove(index);
+        }
+    });
+    action
+}
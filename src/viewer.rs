@@ -0,0 +1,291 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::config_directory;
+
+/// Height of the source view; its width follows the image's aspect ratio.
+const VIEW_HEIGHT: f32 = 400.;
+const MAX_ZOOM: f32 = 64.;
+
+/// How the source map is shown: the visible area and which overlays are drawn.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerState {
+    /// Magnification relative to fitting the whole image in the view.
+    pub zoom: f32,
+    /// The image point at the center of the view, as a fraction of the image size.
+    pub center: (f32, f32),
+    pub show_control_points: bool,
+    pub show_detected_lines: bool,
+    pub show_scale_bar: bool,
+    pub show_graticule: bool,
+}
+
+impl Default for ViewerState {
+    fn default() -> Self {
+        ViewerState {
+            zoom: 1.,
+            center: (0.5, 0.5),
+            show_control_points: true,
+            show_detected_lines: true,
+            show_scale_bar: true,
+            show_graticule: false,
+        }
+    }
+}
+
+/// What is shown behind the transparent parts of an image on screen. It
+/// doesn't affect exports, which have their own background.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Backdrop {
+    #[default]
+    Checkerboard,
+    Color([u8; 3]),
+}
+
+/// Side length of a checkerboard square, in points.
+const CHECKER_SIZE: f32 = 8.;
+
+/// `backdrop` filling `rect`, as far as it is visible within `clip`.
+pub fn backdrop_shape(rect: egui::Rect, clip: egui::Rect, backdrop: Backdrop) -> egui::Shape {
+    match backdrop {
+        Backdrop::Color([r, g, b]) => {
+            egui::Shape::rect_filled(rect, 0., egui::Color32::from_rgb(r, g, b))
+        }
+        Backdrop::Checkerboard => {
+            let visible = rect.intersect(clip);
+            if !visible.is_positive() {
+                return egui::Shape::Noop;
+            }
+            let mut mesh = egui::Mesh::default();
+            let first = ((visible.min - rect.min) / CHECKER_SIZE).floor();
+            let last = ((visible.max - rect.min) / CHECKER_SIZE).ceil();
+            for row in first.y as i32..last.y as i32 {
+                for column in first.x as i32..last.x as i32 {
+                    let color = match (row + column) % 2 {
+                        0 => egui::Color32::from_gray(204),
+                        _ => egui::Color32::from_gray(153),
+                    };
+                    let min = rect.min + egui::vec2(column as f32, row as f32) * CHECKER_SIZE;
+                    let square = egui::Rect::from_min_size(min, egui::Vec2::splat(CHECKER_SIZE));
+                    mesh.add_colored_rect(square.intersect(rect), color);
+                }
+            }
+            egui::Shape::mesh(mesh)
+        }
+    }
+}
+
+pub fn backdrop_ui(ui: &mut egui::Ui, backdrop: &mut Backdrop) {
+    ui.horizontal(|ui| {
+        ui.label("Behind transparency");
+        let is_color = matches!(backdrop, Backdrop::Color(_));
+        if ui.selectable_label(!is_color, "checkerboard").clicked() {
+            *backdrop = Backdrop::Checkerboard;
+        }
+        if ui.selectable_label(is_color, "color").clicked() && !is_color {
+            *backdrop = Backdrop::Color([255, 255, 255]);
+        }
+        if let Backdrop::Color(color) = backdrop {
+            ui.color_edit_button_srgb(color);
+        }
+    });
+}
+
+/// What plain scrolling (a mouse wheel or two fingers on a trackpad) does.
+/// Ctrl+scroll and pinching always zoom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScrollAction {
+    Zoom,
+    Pan,
+}
+
+/// How the mouse, trackpad and touchscreen move the view, adjustable in settings.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseBindings {
+    pub scroll: ScrollAction,
+    /// Dragging with the middle button pans with any tool.
+    pub middle_drag_pans: bool,
+    /// Dragging while holding space pans with any tool.
+    pub space_drag_pans: bool,
+    pub zoom_speed: f32,
+}
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        MouseBindings {
+            scroll: ScrollAction::Zoom,
+            middle_drag_pans: true,
+            space_drag_pans: true,
+            zoom_speed: 1.,
+        }
+    }
+}
+
+impl MouseBindings {
+    /// Whether the primary button pans rather than using the active tool.
+    pub fn pan_held(&self, ui: &egui::Ui) -> bool {
+        self.space_drag_pans && ui.input(|input| input.key_down(egui::Key::Space))
+    }
+}
+
+pub fn mouse_bindings_ui(ui: &mut egui::Ui, bindings: &mut MouseBindings) {
+    ui.horizontal(|ui| {
+        ui.label("Scrolling");
+        ui.selectable_value(&mut bindings.scroll, ScrollAction::Zoom, "zooms");
+        ui.selectable_value(&mut bindings.scroll, ScrollAction::Pan, "pans");
+    });
+    ui.checkbox(&mut bindings.middle_drag_pans, "Middle-drag pans");
+    ui.checkbox(&mut bindings.space_drag_pans, "Space+drag pans");
+    ui.add(
+        egui::Slider::new(&mut bindings.zoom_speed, 0.25..=4.)
+            .logarithmic(true)
+            .text("Zoom speed"),
+    );
+}
+
+impl ViewerState {
+    /// Where the whole image lies on screen for the given view, which it may
+    /// extend far beyond when zoomed in.
+    pub fn image_rect(&self, view: egui::Rect) -> egui::Rect {
+        let size = view.size() * self.zoom;
+        egui::Rect::from_min_size(
+            view.center() - egui::vec2(self.center.0 * size.x, self.center.1 * size.y),
+            size,
+        )
+    }
+
+    /// Zooms by `factor`, keeping the image point under `anchor` where it is.
+    fn zoom_by(&mut self, factor: f32, anchor: egui::Pos2, view: egui::Rect) {
+        let offset = |zoom: f32| (anchor - view.center()) / (view.size() * zoom);
+        let before = offset(self.zoom);
+        self.zoom = (self.zoom * factor).clamp(1., MAX_ZOOM);
+        let after = offset(self.zoom);
+        self.center.0 += before.x - after.x;
+        self.center.1 += before.y - after.y;
+        self.clamp_center();
+    }
+
+    fn pan_by(&mut self, delta: egui::Vec2, view: egui::Rect) {
+        let delta = delta / (view.size() * self.zoom);
+        self.center.0 -= delta.x;
+        self.center.1 -= delta.y;
+        self.clamp_center();
+    }
+
+    fn clamp_center(&mut self) {
+        self.center = (self.center.0.clamp(0., 1.), self.center.1.clamp(0., 1.));
+    }
+}
+
+/// Shows the texture zoomed and panned according to `state` and `bindings`,
+/// over `backdrop`.
+/// Dragging with the primary button only pans if `pan` is set, since other
+/// tools use it themselves. Returns the response of the view and the rect the
+/// whole image occupies, for mapping image pixels to the screen.
+pub fn image_view(
+    ui: &mut egui::Ui,
+    state: &mut ViewerState,
+    bindings: &MouseBindings,
+    texture: egui::TextureId,
+    image_dimensions: (u32, u32),
+    pan: bool,
+    backdrop: Backdrop,
+) -> (egui::Response, egui::Rect) {
+    let aspect = image_dimensions.0 as f32 / image_dimensions.1 as f32;
+    let (view, response) = ui.allocate_exact_size(
+        egui::vec2(VIEW_HEIGHT * aspect, VIEW_HEIGHT),
+        egui::Sense::click_and_drag(),
+    );
+    let primary_pans = pan || bindings.pan_held(ui);
+    if (primary_pans && response.dragged_by(egui::PointerButton::Primary))
+        || (bindings.middle_drag_pans && response.dragged_by(egui::PointerButton::Middle))
+    {
+        state.pan_by(response.drag_delta(), view);
+    }
+    if let Some(pointer) = response.hover_pos() {
+        let (scroll, mut zoom, touch) = ui.input(|input| {
+            (
+                input.scroll_delta,
+                input.zoom_delta(),
+                input.multi_touch().map(|touch| touch.translation_delta),
+            )
+        });
+        match bindings.scroll {
+            ScrollAction::Zoom => zoom *= (scroll.y / 200.).exp(),
+            ScrollAction::Pan => state.pan_by(scroll, view),
+        }
+        // Two fingers on a touchscreen pan the view.
+        if let Some(translation) = touch {
+            state.pan_by(translation, view);
+        }
+        if zoom != 1. {
+            state.zoom_by(zoom.powf(bindings.zoom_speed), pointer, view);
+        }
+    }
+    let image_rect = state.image_rect(view);
+    let painter = ui.painter_at(view);
+    painter.add(backdrop_shape(image_rect, view, backdrop));
+    painter.image(
+        texture,
+        image_rect,
+        egui::Rect::from_min_max(egui::pos2(0., 0.), egui::pos2(1., 1.)),
+        egui::Color32::WHITE,
+    );
+    (response, image_rect)
+}
+
+pub fn viewer_toolbar(ui: &mut egui::Ui, state: &mut ViewerState) {
+    ui.horizontal(|ui| {
+        if ui.button("Fit").clicked() {
+            state.zoom = 1.;
+            state.center = (0.5, 0.5);
+        }
+        ui.label(format!("{:.0}%", state.zoom * 100.));
+        ui.checkbox(&mut state.show_control_points, "Control points");
+        ui.checkbox(&mut state.show_detected_lines, "Detected lines");
+        ui.checkbox(&mut state.show_scale_bar, "Scale bar");
+        ui.checkbox(&mut state.show_graticule, "Graticule");
+    });
+}
+
+/// Viewer states of previously opened images, so that reopening one returns
+/// to the area the user was working on.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Sessions {
+    views: HashMap<String, ViewerState>,
+}
+
+impl Sessions {
+    fn path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join("sessions.json"))
+    }
+
+    /// The stored sessions, or none if they are missing or unreadable.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    pub fn get(&self, key: &str) -> ViewerState {
+        self.views.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, key: String, state: ViewerState) {
+        self.views.insert(key, state);
+    }
+}
@@ -0,0 +1,155 @@
+use eframe::egui;
+
+use crate::angles::{AngleUnit, AngleWidget};
+use crate::leveling::{sample_direction, Vector};
+
+/// Height of the preview in the virtual camera window.
+const PREVIEW_HEIGHT: f32 = 360.;
+
+/// A normal, rectilinear camera looking out from the center of an
+/// equirectangular panorama.
+pub struct VirtualCamera {
+    /// Where the camera looks, in degrees: yaw along the horizon, pitch above it.
+    pub yaw: f64,
+    pub pitch: f64,
+    /// The horizontal field of view, in degrees.
+    pub fov: f64,
+    pub width: u32,
+    pub height: u32,
+    pub open: bool,
+    /// The panorama the preview is rendered from, usually an overview.
+    pub preview_source: Option<image::RgbaImage>,
+    preview: Option<egui::TextureHandle>,
+    rendered: Option<(f64, f64, f64, u32, u32)>,
+}
+
+impl Default for VirtualCamera {
+    fn default() -> Self {
+        VirtualCamera {
+            yaw: 0.,
+            pitch: 0.,
+            fov: 90.,
+            width: 1920,
+            height: 1080,
+            open: false,
+            preview_source: None,
+            preview: None,
+            rendered: None,
+        }
+    }
+}
+
+pub enum CameraAction {
+    /// Render the view from the full-resolution panorama and save it.
+    Export,
+}
+
+impl VirtualCamera {
+    /// Renders what the camera sees into an image of `width` × `height` pixels.
+    pub fn render(&self, panorama: &image::RgbaImage, width: u32, height: u32) -> image::RgbaImage {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        let forward = direction_at(yaw, pitch);
+        let right: Vector = [-yaw.sin(), yaw.cos(), 0.];
+        let up: Vector = [
+            -pitch.sin() * yaw.cos(),
+            -pitch.sin() * yaw.sin(),
+            pitch.cos(),
+        ];
+        let half_width = (self.fov.to_radians() / 2.).tan();
+        let half_height = half_width * height as f64 / width as f64;
+        image::RgbaImage::from_fn(width, height, |x, y| {
+            let u = (2. * (x as f64 + 0.5) / width as f64 - 1.) * half_width;
+            let v = (1. - 2. * (y as f64 + 0.5) / height as f64) * half_height;
+            let ray = std::array::from_fn(|i| forward[i] + u * right[i] + v * up[i]);
+            sample_direction(panorama, ray)
+        })
+    }
+}
+
+/// Yaw zero looks at the center of the panorama.
+fn direction_at(yaw: f64, pitch: f64) -> Vector {
+    [
+        pitch.cos() * yaw.cos(),
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+    ]
+}
+
+pub fn virtual_camera_window(
+    ctx: &egui::Context,
+    camera: &mut VirtualCamera,
+    unit: AngleUnit,
+) -> Option<CameraAction> {
+    let mut action = None;
+    let mut open = camera.open;
+    egui::Window::new("Virtual camera")
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.label("Looks around an equirectangular panorama with a normal lens.");
+            ui.add(
+                egui::Slider::new(&mut camera.yaw, -180.0..=180.)
+                    .angle(unit)
+                    .text("Yaw"),
+            );
+            ui.add(
+                egui::Slider::new(&mut camera.pitch, -90.0..=90.)
+                    .angle(unit)
+                    .text("Pitch"),
+            );
+            ui.add(
+                egui::Slider::new(&mut camera.fov, 10.0..=150.)
+                    .angle(unit)
+                    .text("Field of view"),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Output size");
+                ui.add(egui::DragValue::new(&mut camera.width).clamp_range(16..=16384));
+                ui.label("×");
+                ui.add(egui::DragValue::new(&mut camera.height).clamp_range(16..=16384));
+            });
+            let Some(source) = &camera.preview_source else {
+                ui.label("Load a source panorama first.");
+                return;
+            };
+            let aspect = camera.width as f32 / camera.height as f32;
+            let size = egui::vec2(PREVIEW_HEIGHT * aspect, PREVIEW_HEIGHT);
+            let state = (
+                camera.yaw,
+                camera.pitch,
+                camera.fov,
+                camera.width,
+                camera.height,
+            );
+            if camera.rendered != Some(state) || camera.preview.is_none() {
+                let preview = camera.render(source, size.x as u32, size.y as u32);
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [preview.width() as usize, preview.height() as usize],
+                    preview.as_raw(),
+                );
+                camera.preview =
+                    Some(ctx.load_texture("Virtual camera", image, Default::default()));
+                camera.rendered = Some(state);
+            }
+            let texture = camera.preview.as_ref().unwrap().id();
+            let response = ui.add(egui::Image::new(texture, size).sense(egui::Sense::drag()));
+            // Dragging turns the camera as if grabbing the scene.
+            if response.dragged() {
+                let degrees_per_point = camera.fov / size.x as f64;
+                let delta = response.drag_delta();
+                camera.yaw = (camera.yaw - delta.x as f64 * degrees_per_point + 540.)
+                    .rem_euclid(360.)
+                    - 180.;
+                camera.pitch = (camera.pitch + delta.y as f64 * degrees_per_point).clamp(-90., 90.);
+            }
+            if response.hovered() {
+                let zoom =
+                    ui.input(|input| input.zoom_delta() * (input.scroll_delta.y / 200.).exp());
+                camera.fov = (camera.fov / zoom as f64).clamp(10., 150.);
+            }
+            if ui.button("Export view...").clicked() {
+                action = Some(CameraAction::Export);
+            }
+        });
+    camera.open = open;
+    action
+}
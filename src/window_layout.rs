@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::config_directory;
+
+/// Restored windows are never smaller than this, in points.
+const MIN_WINDOW_SIZE: egui::Vec2 = egui::vec2(640., 400.);
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct WindowLayout {
+    /// The outer top left corner, relative to the first display.
+    pub position: Option<(f32, f32)>,
+    /// The inner size while not maximized.
+    pub size: (f32, f32),
+    pub maximized: bool,
+}
+
+impl WindowLayout {
+    /// The layout fitted onto a monitor of the given size.
+    fn fitted(&self, monitor_size: Option<egui::Vec2>) -> Self {
+        let mut size = egui::vec2(self.size.0, self.size.1).max(MIN_WINDOW_SIZE);
+        if let Some(monitor_size) = monitor_size {
+            size = size.min(monitor_size);
+        }
+        WindowLayout {
+            size: (size.x, size.y),
+            ..*self
+        }
+    }
+}
+
+/// The window layout for each monitor configuration it was used on, so that
+/// plugging in or removing a display doesn't leave the window tiny or off-screen.
+#[derive(Default, Serialize, Deserialize)]
+pub struct WindowLayouts {
+    layouts: HashMap<String, WindowLayout>,
+    /// The configuration the application was last closed on.
+    last_monitor: Option<String>,
+    /// Whether the layout for the current configuration was applied yet.
+    #[serde(skip)]
+    restored: bool,
+}
+
+/// Only the size of the current monitor is known, so that stands in for the
+/// whole configuration.
+fn monitor_key(monitor_size: egui::Vec2) -> String {
+    format!("{}x{}", monitor_size.x.round(), monitor_size.y.round())
+}
+
+impl WindowLayouts {
+    fn path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join("window.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// Opens the window as it was last closed. The monitor is only known once
+    /// the window exists, so `update` corrects this if the displays changed.
+    pub fn native_options(&self) -> eframe::NativeOptions {
+        let mut options = eframe::NativeOptions::default();
+        let last = self
+            .last_monitor
+            .as_ref()
+            .and_then(|key| self.layouts.get(key));
+        if let Some(layout) = last {
+            let layout = layout.fitted(None);
+            options.initial_window_pos = layout.position.map(|(x, y)| egui::pos2(x, y));
+            options.initial_window_size = Some(egui::vec2(layout.size.0, layout.size.1));
+            options.maximized = layout.maximized;
+        }
+        options
+    }
+
+    /// Restores the layout for the current monitor on the first frame, and
+    /// after that keeps track of where the user puts the window.
+    pub fn update(&mut self, frame: &mut eframe::Frame) {
+        let info = frame.info().window_info;
+        let Some(monitor_size) = info.monitor_size else {
+            return;
+        };
+        let key = monitor_key(monitor_size);
+        if !self.restored {
+            self.restored = true;
+            if self.last_monitor.as_ref() != Some(&key) {
+                match self.layouts.get(&key) {
+                    Some(layout) => {
+                        let layout = layout.fitted(Some(monitor_size));
+                        if let Some((x, y)) = layout.position {
+                            frame.set_window_pos(egui::pos2(x, y));
+                        }
+                        frame.set_window_size(egui::vec2(layout.size.0, layout.size.1));
+                        frame.set_maximized(layout.maximized);
+                    }
+                    // Nothing is known about these displays, so at least make
+                    // sure the window is on one of them.
+                    None => frame.set_centered(),
+                }
+            }
+            self.last_monitor = Some(key);
+            return;
+        }
+        if info.minimized || info.fullscreen {
+            return;
+        }
+        let previous = self.layouts.get(&key).copied();
+        let layout = if info.maximized {
+            // Keep the size to return to when the window is restored.
+            WindowLayout {
+                maximized: true,
+                ..previous.unwrap_or(WindowLayout {
+                    position: None,
+                    size: (info.size.x, info.size.y),
+                    maximized: true,
+                })
+            }
+        } else {
+            WindowLayout {
+                position: info.position.map(|position| (position.x, position.y)),
+                size: (info.size.x, info.size.y),
+                maximized: false,
+            }
+        };
+        self.layouts.insert(key.clone(), layout);
+        self.last_monitor = Some(key);
+    }
+}
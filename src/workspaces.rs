@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::dock::{Dock, DockLayout, PanelKind};
+use crate::settings::config_directory;
+
+/// The sections of the panels, by title.
+const SECTIONS: [&str; 18] = [
+    "Photo corrections",
+    "Dual fisheye",
+    "Gridded data",
+    "Longitudes",
+    "Graticule",
+    "Distortion",
+    "Georeferencing",
+    "Annotations",
+    "Masks",
+    "No-data and background",
+    "Vector overlays",
+    "Layers",
+    "Batch",
+    "Leveling",
+    "Polar caps",
+    "Match against reference",
+    "Alignment check",
+    "Attribution",
+];
+
+/// How the user interface is laid out for one kind of work, such as
+/// georeferencing or preparing an export.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspace {
+    pub dock: DockLayout,
+    /// The width of the side docks and the height of the bottom one, in
+    /// points, by dock id.
+    pub dock_sizes: BTreeMap<String, f32>,
+    /// Whether each section of the panels is expanded, by title.
+    pub sections: BTreeMap<String, bool>,
+    pub virtual_camera_open: bool,
+    pub overlays: Overlays,
+}
+
+/// What is drawn over the source image.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overlays {
+    pub control_points: bool,
+    pub detected_lines: bool,
+    pub scale_bar: bool,
+    pub graticule: bool,
+    /// Vector overlays by file, as they may not all be open.
+    pub vectors: BTreeMap<PathBuf, bool>,
+}
+
+/// The dock sizes and sections of the panels as they are now. `panel_ids`
+/// are the ids of the panels' `Ui`s, which the sections are kept under.
+pub fn capture(
+    ctx: &egui::Context,
+    panel_ids: &HashMap<PanelKind, egui::Id>,
+    workspace: &mut Workspace,
+) {
+    workspace.dock_sizes = [Dock::Left, Dock::Right, Dock::Bottom]
+        .into_iter()
+        .filter_map(|dock| {
+            let state = egui::containers::panel::PanelState::load(ctx, egui::Id::new(dock.id()?))?;
+            let size = match dock {
+                Dock::Bottom => state.size().y,
+                _ => state.size().x,
+            };
+            Some((dock.id()?.to_string(), size))
+        })
+        .collect();
+    workspace.sections = SECTIONS
+        .iter()
+        .filter_map(|title| {
+            let state = panel_ids.values().find_map(|id| {
+                egui::collapsing_header::CollapsingState::load(ctx, id.with(egui::Id::new(title)))
+            })?;
+            Some((title.to_string(), state.is_open()))
+        })
+        .collect();
+}
+
+/// Sizes the docks and expands and collapses the sections of the panels as
+/// the workspace has them, from the next frame on.
+pub fn restore(
+    ctx: &egui::Context,
+    panel_ids: &HashMap<PanelKind, egui::Id>,
+    workspace: &Workspace,
+) {
+    for (title, open) in &workspace.sections {
+        for id in panel_ids.values() {
+            let id = id.with(egui::Id::new(title));
+            if let Some(mut state) = egui::collapsing_header::CollapsingState::load(ctx, id) {
+                state.set_open(*open);
+                state.store(ctx);
+            }
+        }
+    }
+    for (id, size) in &workspace.dock_sizes {
+        // Side panels take the width and the bottom one the height.
+        let rect = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(*size, *size));
+        ctx.data_mut(|data| {
+            data.insert_persisted(
+                egui::Id::new(id.as_str()),
+                egui::containers::panel::PanelState { rect },
+            )
+        });
+    }
+    ctx.request_repaint();
+}
+
+pub enum WorkspaceAction {
+    Save(String),
+    Restore(String),
+}
+
+/// The named workspaces, stored in the user's configuration directory.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Workspaces {
+    pub saved: BTreeMap<String, Workspace>,
+    /// The name to save the current layout under.
+    #[serde(skip)]
+    name: String,
+}
+
+impl Workspaces {
+    fn path() -> Option<PathBuf> {
+        config_directory().map(|directory| directory.join("workspaces.json"))
+    }
+
+    /// The stored workspaces, or none if they are missing or unreadable.
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("No configuration directory available.")?;
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        let contents = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, contents)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+}
+
+/// Switches between the workspaces and saves the current layout as one.
+pub fn workspace_menu(
+    ui: &mut egui::Ui,
+    workspaces: &mut Workspaces,
+) -> Result<Option<WorkspaceAction>, String> {
+    let mut action = None;
+    let mut result = Ok(());
+    ui.menu_button("Workspace", |ui| {
+        let mut deleted = None;
+        for name in workspaces.saved.keys() {
+            ui.horizontal(|ui| {
+                if ui.button(name).clicked() {
+                    action = Some(WorkspaceAction::Restore(name.clone()));
+                    ui.close_menu();
+                }
+                if ui.small_button("✖").clicked() {
+                    deleted = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = deleted {
+            workspaces.saved.remove(&name);
+            result = workspaces.save();
+        }
+        if !workspaces.saved.is_empty() {
+            ui.separator();
+        }
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut workspaces.name)
+                    .hint_text("Georeferencing")
+                    .desired_width(120.),
+            );
+            let name = workspaces.name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Save current"))
+                .clicked()
+            {
+                action = Some(WorkspaceAction::Save(name.to_string()));
+                workspaces.name.clear();
+                ui.close_menu();
+            }
+        });
+    });
+    result.map(|()| action)
+}